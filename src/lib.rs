@@ -1,55 +1,226 @@
+#[path = "pdf2/annotations.rs"]
+pub mod annotations;
+#[path = "pdf2/content.rs"]
+pub mod content;
+#[path = "pdf2/editor.rs"]
+pub mod editor;
+#[path = "pdf2/encoding.rs"]
+pub mod encoding;
 #[path = "pdf2/encryption.rs"]
 pub mod encryption;
+#[path = "pdf2/font.rs"]
+pub mod font;
+#[path = "pdf2/font_registry.rs"]
+pub mod font_registry;
+#[path = "pdf2/forms.rs"]
+pub mod forms;
 #[path = "pdf2/generator.rs"]
 pub mod generator;
+#[path = "pdf2/images.rs"]
+pub mod images;
+#[path = "pdf2/layout.rs"]
+pub mod layout;
+#[path = "pdf2/links.rs"]
+pub mod links;
+#[path = "pdf2/matrix.rs"]
+pub mod matrix;
+#[path = "pdf2/merge.rs"]
+pub mod merge;
+#[path = "pdf2/metadata.rs"]
+pub mod metadata;
+#[path = "pdf2/names.rs"]
+pub mod names;
+#[path = "pdf2/outline.rs"]
+pub mod outline;
+#[path = "pdf2/page_selection.rs"]
+pub mod page_selection;
 #[path = "pdf2/parser.rs"]
 pub mod parser;
+#[path = "pdf2/pyfile.rs"]
+pub mod pyfile;
+#[path = "pdf2/split.rs"]
+pub mod split;
 #[path = "pdf2/structure.rs"]
 pub mod structure;
+#[path = "pdf2/subset.rs"]
+pub mod subset;
+#[path = "pdf2/tables.rs"]
+pub mod tables;
 
+#[cfg(test)]
+#[path = "pdf2/test_annotations.rs"]
+pub mod test_annotations;
+#[cfg(test)]
+#[path = "pdf2/test_content.rs"]
+pub mod test_content;
+#[cfg(test)]
+#[path = "pdf2/test_editor.rs"]
+pub mod test_editor;
+#[cfg(test)]
+#[path = "pdf2/test_encoding.rs"]
+pub mod test_encoding;
 #[cfg(test)]
 #[path = "pdf2/test_encryption.rs"]
 pub mod test_encryption;
 #[cfg(test)]
+#[path = "pdf2/test_font.rs"]
+pub mod test_font;
+#[cfg(test)]
+#[path = "pdf2/test_font_registry.rs"]
+pub mod test_font_registry;
+#[cfg(test)]
+#[path = "pdf2/test_forms.rs"]
+pub mod test_forms;
+#[cfg(test)]
 #[path = "pdf2/test_generator.rs"]
 pub mod test_generator;
 #[cfg(test)]
+#[path = "pdf2/test_images.rs"]
+pub mod test_images;
+#[cfg(test)]
+#[path = "pdf2/test_layout.rs"]
+pub mod test_layout;
+#[cfg(test)]
+#[path = "pdf2/test_links.rs"]
+pub mod test_links;
+#[cfg(test)]
+#[path = "pdf2/test_matrix.rs"]
+pub mod test_matrix;
+#[cfg(test)]
+#[path = "pdf2/test_merge.rs"]
+pub mod test_merge;
+#[cfg(test)]
+#[path = "pdf2/test_metadata.rs"]
+pub mod test_metadata;
+#[cfg(test)]
+#[path = "pdf2/test_outline.rs"]
+pub mod test_outline;
+#[cfg(test)]
+#[path = "pdf2/test_page_selection.rs"]
+pub mod test_page_selection;
+#[cfg(test)]
 #[path = "pdf2/test_parser.rs"]
 pub mod test_parser;
 #[cfg(test)]
+#[path = "pdf2/test_pyfile.rs"]
+pub mod test_pyfile;
+#[cfg(test)]
+#[path = "pdf2/test_split.rs"]
+pub mod test_split;
+#[cfg(test)]
 #[path = "pdf2/test_structure.rs"]
 pub mod test_structure;
+#[cfg(test)]
+#[path = "pdf2/test_subset.rs"]
+pub mod test_subset;
+#[cfg(test)]
+#[path = "pdf2/test_tables.rs"]
+pub mod test_tables;
+#[cfg(test)]
+#[path = "pdf2/test_utils.rs"]
+pub mod test_utils;
 
 use pyo3::prelude::*;
-use std::path::Path;
+use pyo3::types::PyBytes;
 
-use structure::{Document, Image, Page, TextBlock};
+use font_registry::FontRegistry;
+use generator::GenerateError;
+use parser::ParseError;
+use pyfile::PdfFile;
+use structure::{
+    Annotation, CheckBox, Column, Destination, Document, Dropdown, FieldWidget, FormField, Image,
+    Link, Metadata, OutlineItem, Page, PageContent, Paragraph, PathSegment, RadioButton,
+    RadioGroup, Rect, Shape, SignatureField, Table, TableCell, TextBlock, TextField, TextLine,
+    Word,
+};
 
 #[pyfunction]
-fn parse(path_str: String) -> PyResult<Document> {
-    let path = Path::new(&path_str);
+#[pyo3(signature = (path_str, password=None))]
+fn parse(path_str: PdfFile, password: Option<String>) -> PyResult<Document> {
     // Here, we map the custom Rust error to a PyErr.
-    parser::parse_pdf(path).map_err(|e| {
+    path_str
+        .read()
+        .map_err(ParseError::from)
+        .and_then(|bytes| parser::parse_bytes(&bytes, password.as_deref()))
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to parse PDF: {}", e))
+        })
+}
+
+#[pyfunction]
+#[pyo3(signature = (data, password=None))]
+fn parse_bytes(data: &[u8], password: Option<String>) -> PyResult<Document> {
+    parser::parse_bytes(data, password.as_deref()).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to parse PDF: {}", e))
     })
 }
 
 #[pyfunction]
-fn generate(doc: &Document, path_str: String) -> PyResult<()> {
-    let path = Path::new(&path_str);
-    generator::generate_pdf(doc, path)
+#[pyo3(signature = (doc, path_str, fonts=None))]
+fn generate(doc: &Document, path_str: PdfFile, fonts: Option<&FontRegistry>) -> PyResult<()> {
+    let default_fonts = FontRegistry::default();
+    generator::generate_to_bytes(doc, fonts.unwrap_or(&default_fonts))
+        .and_then(|bytes| path_str.write(&bytes).map_err(GenerateError::from))
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyNotImplementedError, _>(format!("{}", e)))
 }
 
+#[pyfunction]
+#[pyo3(signature = (doc, fonts=None))]
+fn generate_to_bytes<'py>(
+    py: Python<'py>,
+    doc: &Document,
+    fonts: Option<&FontRegistry>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let default_fonts = FontRegistry::default();
+    let bytes = generator::generate_to_bytes(doc, fonts.unwrap_or(&default_fonts))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyNotImplementedError, _>(format!("{}", e)))?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 fn pdf2(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_bytes, m)?)?;
     m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_to_bytes, m)?)?;
     m.add_function(wrap_pyfunction!(encryption::encrypt_pdf, m)?)?;
+    m.add_function(wrap_pyfunction!(encryption::py_encrypt_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(encryption::decrypt_pdf, m)?)?;
+    m.add_function(wrap_pyfunction!(merge::merge, m)?)?;
+    m.add_function(wrap_pyfunction!(split::split, m)?)?;
+    m.add_function(wrap_pyfunction!(split::py_extract_pages, m)?)?;
+    m.add_function(wrap_pyfunction!(forms::py_fill_form, m)?)?;
+    m.add_function(wrap_pyfunction!(forms::py_fill_form_bytes, m)?)?;
     m.add_class::<Document>()?;
     m.add_class::<Page>()?;
     m.add_class::<TextBlock>()?;
     m.add_class::<Image>()?;
+    m.add_class::<Rect>()?;
+    m.add_class::<PathSegment>()?;
+    m.add_class::<Shape>()?;
+    m.add_class::<PageContent>()?;
+    m.add_class::<Word>()?;
+    m.add_class::<TextLine>()?;
+    m.add_class::<Paragraph>()?;
+    m.add_class::<Column>()?;
+    m.add_class::<Table>()?;
+    m.add_class::<TableCell>()?;
+    m.add_class::<Link>()?;
+    m.add_class::<Annotation>()?;
+    m.add_class::<Metadata>()?;
+    m.add_class::<Destination>()?;
+    m.add_class::<OutlineItem>()?;
+    m.add_class::<FormField>()?;
+    m.add_class::<FieldWidget>()?;
+    m.add_class::<TextField>()?;
+    m.add_class::<CheckBox>()?;
+    m.add_class::<RadioButton>()?;
+    m.add_class::<RadioGroup>()?;
+    m.add_class::<Dropdown>()?;
+    m.add_class::<SignatureField>()?;
+    m.add_class::<FontRegistry>()?;
+    m.add_class::<encryption::DocumentPermissions>()?;
+    m.add_class::<editor::PdfEditor>()?;
     Ok(())
 }
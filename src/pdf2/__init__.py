@@ -10,9 +10,44 @@ from ._core import (
     Page,
     TextBlock,
     Image,
+    Rect,
+    PathSegment,
+    Shape,
+    PageContent,
+    Word,
+    TextLine,
+    Paragraph,
+    Column,
+    Table,
+    TableCell,
+    Link,
+    Annotation,
+    Metadata,
+    Destination,
+    OutlineItem,
+    FormField,
+    FieldWidget,
+    TextField,
+    CheckBox,
+    RadioButton,
+    RadioGroup,
+    Dropdown,
+    SignatureField,
+    FontRegistry,
+    DocumentPermissions,
+    PdfEditor,
     parse,
+    parse_bytes,
     generate,
+    generate_to_bytes,
     encrypt_pdf,
+    encrypt_bytes,
+    decrypt_pdf,
+    merge,
+    split,
+    extract_pages,
+    fill_form,
+    fill_form_bytes,
 )
 
 __version__ = "0.1.0"
@@ -21,7 +56,42 @@ __all__ = [
     "Page",
     "TextBlock",
     "Image",
+    "Rect",
+    "PathSegment",
+    "Shape",
+    "PageContent",
+    "Word",
+    "TextLine",
+    "Paragraph",
+    "Column",
+    "Table",
+    "TableCell",
+    "Link",
+    "Annotation",
+    "Metadata",
+    "Destination",
+    "OutlineItem",
+    "FormField",
+    "FieldWidget",
+    "TextField",
+    "CheckBox",
+    "RadioButton",
+    "RadioGroup",
+    "Dropdown",
+    "SignatureField",
+    "FontRegistry",
+    "DocumentPermissions",
+    "PdfEditor",
     "parse",
+    "parse_bytes",
     "generate",
+    "generate_to_bytes",
     "encrypt_pdf",
+    "encrypt_bytes",
+    "decrypt_pdf",
+    "merge",
+    "split",
+    "extract_pages",
+    "fill_form",
+    "fill_form_bytes",
 ]
@@ -4,7 +4,12 @@ Type stubs for pdf2._core module.
 This file provides type information for the Rust-based core module.
 """
 
-from typing import List
+import os
+from typing import BinaryIO, Dict, List, Optional, Tuple, Union
+
+PathOrFile = Union[str, os.PathLike[str], BinaryIO]
+"""A file path, or a binary file object: one opened with ``"rb"`` for input
+or ``"wb"`` for output, or an ``io.BytesIO``."""
 
 class TextBlock:
     """Represents a single text block with its content and position."""
@@ -13,11 +18,64 @@ class TextBlock:
     x: float
     y: float
     font_size: float
+    font_name: Optional[str]
+    """Name of a font in the ``FontRegistry`` passed to ``generate``; text
+    without one, or naming an unregistered font, is drawn in Helvetica.
+    The parser sets it to the family name of the font used."""
+    width: float
+    """Advance width of the text as drawn, set by the parser."""
+    base_font: Optional[str]
+    """The font's ``BaseFont``, including any subset prefix; set by the
+    parser."""
+    embedded: bool
+    """Whether the font program is embedded; set by the parser."""
+    bold: bool
+    """Bold and italic text uses the registered ``"<font_name>-Bold"``,
+    ``"-Italic"`` or ``"-BoldItalic"`` font when there is one, the matching
+    Helvetica face for the built-in font, and is emboldened or slanted
+    otherwise."""
+    italic: bool
+    fill_color: List[float]
+    """1 (gray), 3 (RGB) or 4 (CMYK) components; empty means black."""
+    stroke_color: List[float]
+    char_spacing: float
+    word_spacing: float
+    horizontal_scaling: float
+    """Percentage of the normal glyph width."""
+    rise: float
+    """Baseline offset, already included in ``y``."""
+    render_mode: int
+    """PDF text rendering mode: 0 fill, 1 stroke, 2 fill and stroke,
+    3 invisible, and 4 to 7 the same adding the text to the clip."""
 
-    def __init__(self, text: str, x: float, y: float, font_size: float) -> None: ...
+    def __init__(
+        self,
+        text: str,
+        x: float,
+        y: float,
+        font_size: float,
+        font_name: Optional[str] = None,
+        *,
+        bold: bool = False,
+        italic: bool = False,
+        fill_color: Optional[List[float]] = None,
+        stroke_color: Optional[List[float]] = None,
+        char_spacing: float = 0.0,
+        word_spacing: float = 0.0,
+        horizontal_scaling: float = 100.0,
+        rise: float = 0.0,
+        render_mode: int = 0,
+    ) -> None: ...
 
 class Image:
-    """Represents an image with its data and position."""
+    """Represents an image with its data and position.
+
+    ``data`` holds a complete image file in ``format``: ``"jpeg"`` and
+    ``"jp2"`` images are extracted unchanged, other pixel data is re-encoded
+    as ``"png"``. When generating, ``"jpeg"``, ``"png"`` and raw 8-bit
+    ``"rgb"``/``"gray"`` pixels (sized by ``pixel_width`` and
+    ``pixel_height``) are supported.
+    """
 
     x: float
     y: float
@@ -25,72 +83,1008 @@ class Image:
     height: float
     data: bytes
     format: str
+    pixel_width: int
+    pixel_height: int
+
+    def __init__(
+        self,
+        x: float,
+        y: float,
+        width: float,
+        height: float,
+        data: bytes,
+        format: str,
+        pixel_width: int = 0,
+        pixel_height: int = 0,
+    ) -> None: ...
+
+class Rect:
+    """A rectangle in PDF user space, from (x0, y0) to (x1, y1)."""
+
+    x0: float
+    y0: float
+    x1: float
+    y1: float
+
+    def __init__(self, x0: float, y0: float, x1: float, y1: float) -> None: ...
+    @property
+    def width(self) -> float: ...
+    @property
+    def height(self) -> float: ...
+
+class PathSegment:
+    """One step of a path. ``kind`` is ``"move"`` or ``"line"`` with one
+    point, ``"curve"`` with a cubic Bézier curve's two control points and its
+    end point, or ``"close"``, which joins the subpath back to its start."""
+
+    kind: str
+    points: List[Tuple[float, float]]
+
+    def __init__(
+        self, kind: str, points: Optional[List[Tuple[float, float]]] = None
+    ) -> None: ...
+
+class Shape:
+    """A vector path, such as a table rule, an underline or a filled box, in
+    page coordinates.
+
+    A shape is filled when it has a ``fill_color`` and stroked when it has a
+    ``stroke_color``; colours are 1 (gray), 3 (RGB) or 4 (CMYK) components
+    from 0 to 1.
+    """
+
+    segments: List[PathSegment]
+    fill_color: Optional[List[float]]
+    stroke_color: Optional[List[float]]
+    line_width: float
+    dash: List[float]
+    """Lengths of alternating dashes and gaps; empty for a solid line."""
+    dash_phase: float
+    line_cap: str
+    """``"butt"``, ``"round"`` or ``"square"``."""
+    line_join: str
+    """``"miter"``, ``"round"`` or ``"bevel"``."""
+    even_odd: bool
+    """Whether the fill uses the even-odd rule rather than nonzero winding."""
+
+    def __init__(
+        self,
+        segments: List[PathSegment],
+        *,
+        fill_color: Optional[List[float]] = None,
+        stroke_color: Optional[List[float]] = None,
+        line_width: float = 1.0,
+        dash: Optional[List[float]] = None,
+        dash_phase: float = 0.0,
+        line_cap: Optional[str] = None,
+        line_join: Optional[str] = None,
+        even_odd: bool = False,
+    ) -> None: ...
+    @staticmethod
+    def line(
+        x0: float,
+        y0: float,
+        x1: float,
+        y1: float,
+        line_width: float = 1.0,
+        color: Optional[List[float]] = None,
+    ) -> Shape:
+        """A straight line, stroked in black unless a colour is given."""
+        ...
+    @staticmethod
+    def rectangle(
+        rect: Rect,
+        line_width: float = 1.0,
+        color: Optional[List[float]] = None,
+        fill_color: Optional[List[float]] = None,
+    ) -> Shape:
+        """The outline of ``rect``, stroked in black unless a colour is
+        given, and filled if ``fill_color`` is given."""
+        ...
+    def bounds(self) -> Rect:
+        """The smallest rectangle holding every point of the path, control
+        points included."""
+        ...
+
+PageElement = Union[TextBlock, Image, Shape]
+
+class PageContent:
+    """An element drawn on a page. Each kind is a subclass holding its
+    element, so contents can be told apart with ``isinstance`` or ``match``::
+
+        match item:
+            case PageContent.Text(block):
+                print(block.text)
+    """
+
+    @property
+    def kind(self) -> str:
+        """``"text"``, ``"image"`` or ``"shape"``."""
+        ...
+    @property
+    def value(self) -> PageElement:
+        """The element held."""
+        ...
+
+    class Text(PageContent):
+        _0: TextBlock
+        def __init__(self, _0: TextBlock) -> None: ...
+
+    class Image(PageContent):
+        _0: Image
+        def __init__(self, _0: Image) -> None: ...
+
+    class Shape(PageContent):
+        _0: Shape
+        def __init__(self, _0: Shape) -> None: ...
+
+class Link:
+    """A clickable area of a page, leading to a URI, a place in the document,
+    or a named destination.
+
+    When parsing, named destinations are also resolved into ``destination``.
+    When generating, ``uri`` takes precedence over ``named_destination``,
+    which takes precedence over ``destination``; a link with both of the
+    latter defines the name as leading to ``destination``.
+    """
+
+    rect: Rect
+    uri: Optional[str]
+    destination: Optional[Destination]
+    named_destination: Optional[str]
+
+    def __init__(
+        self,
+        rect: Rect,
+        uri: Optional[str] = None,
+        destination: Optional[Destination] = None,
+        named_destination: Optional[str] = None,
+    ) -> None: ...
+
+class Annotation:
+    """A review annotation.
+
+    ``kind`` is one of ``"text"`` (a sticky note), ``"highlight"``,
+    ``"underline"``, ``"strikeout"``, ``"squiggly"``, ``"freetext"``,
+    ``"ink"``, ``"square"``, ``"circle"`` or ``"stamp"``. Colours are 1 (gray),
+    3 (RGB) or 4 (CMYK) components from 0 to 1; dates are ISO 8601 strings.
+    """
+
+    kind: str
+    rect: Rect
+    """Where the annotation is drawn. When generating, an empty rectangle is
+    replaced by the bounds of ``quads`` or ``ink``."""
+    contents: Optional[str]
+    """The note's text, or the text a free text annotation shows."""
+    author: Optional[str]
+    created: Optional[str]
+    modified: Optional[str]
+    color: List[float]
+    interior_color: List[float]
+    """The fill colour of squares and circles."""
+    border_width: Optional[float]
+    quads: List[Rect]
+    """The areas text markup covers, one per line of text."""
+    text: Optional[str]
+    """The page text under ``quads``, found by the parser. Positions within a
+    text run are estimated, so the ends may be off by a character."""
+    ink: List[List[Tuple[float, float]]]
+    """The strokes of an ink annotation, as lists of points."""
+    icon: Optional[str]
+    """The icon of a note (such as ``"Comment"``) or stamp (such as
+    ``"Approved"``)."""
+
+    def __init__(
+        self,
+        kind: str,
+        rect: Optional[Rect] = None,
+        contents: Optional[str] = None,
+        author: Optional[str] = None,
+        *,
+        created: Optional[str] = None,
+        modified: Optional[str] = None,
+        color: Optional[List[float]] = None,
+        interior_color: Optional[List[float]] = None,
+        border_width: Optional[float] = None,
+        quads: Optional[List[Rect]] = None,
+        ink: Optional[List[List[Tuple[float, float]]]] = None,
+        icon: Optional[str] = None,
+    ) -> None: ...
+
+class TextField:
+    """A fillable text input.
+
+    Names may be dotted (``"person.name"``) to group fields under a common
+    parent; a name cannot be both a field and such a parent.
+    """
+
+    name: str
+    rect: Rect
+    value: Optional[str]
+    """The initial text, which resetting the form restores."""
+    multiline: bool
+    max_length: Optional[int]
+    font_size: float
+    """Text size in points; 0 fits the text to the field."""
+    required: bool
+    read_only: bool
+
+    def __init__(
+        self,
+        name: str,
+        rect: Rect,
+        value: Optional[str] = None,
+        multiline: bool = False,
+        max_length: Optional[int] = None,
+        font_size: float = 0.0,
+        required: bool = False,
+        read_only: bool = False,
+    ) -> None: ...
+
+class CheckBox:
+    """A checkbox, whose value is ``export_value`` when checked and ``"Off"``
+    otherwise."""
+
+    name: str
+    rect: Rect
+    checked: bool
+    export_value: str
+    required: bool
+    read_only: bool
 
     def __init__(
-        self, x: float, y: float, width: float, height: float, data: bytes, format: str
+        self,
+        name: str,
+        rect: Rect,
+        checked: bool = False,
+        export_value: str = "Yes",
+        required: bool = False,
+        read_only: bool = False,
     ) -> None: ...
 
+class RadioButton:
+    """One button of a radio group: the value the group takes when it is
+    selected, and where it is drawn."""
+
+    value: str
+    rect: Rect
+
+    def __init__(self, value: str, rect: Rect) -> None: ...
+
+class RadioGroup:
+    """A group of radio buttons of which at most one is selected."""
+
+    name: str
+    buttons: List[RadioButton]
+    selected: Optional[str]
+    """The value of the initially selected button."""
+    required: bool
+    read_only: bool
+
+    def __init__(
+        self,
+        name: str,
+        buttons: List[RadioButton],
+        selected: Optional[str] = None,
+        required: bool = False,
+        read_only: bool = False,
+    ) -> None: ...
+
+class Dropdown:
+    """A drop-down list (combo box)."""
+
+    name: str
+    rect: Rect
+    options: List[str]
+    value: Optional[str]
+    """The initially selected option."""
+    editable: bool
+    """Whether text other than the options can be typed in."""
+    font_size: float
+    required: bool
+    read_only: bool
+
+    def __init__(
+        self,
+        name: str,
+        rect: Rect,
+        options: List[str],
+        value: Optional[str] = None,
+        editable: bool = False,
+        font_size: float = 0.0,
+        required: bool = False,
+        read_only: bool = False,
+    ) -> None: ...
+
+class SignatureField:
+    """An unsigned signature field, which viewers offer to sign."""
+
+    name: str
+    rect: Rect
+    required: bool
+
+    def __init__(self, name: str, rect: Rect, required: bool = False) -> None: ...
+
+class Word:
+    """A word found by layout analysis, with the bounding box of its glyphs."""
+
+    text: str
+    bbox: Rect
+    font_size: float
+
+class TextLine:
+    """Words sharing a baseline, left to right."""
+
+    words: List[Word]
+    bbox: Rect
+
+    def text(self) -> str:
+        """The words separated by spaces."""
+        ...
+
+class Paragraph:
+    """Consecutive lines of one block of text, top to bottom."""
+
+    lines: List[TextLine]
+    bbox: Rect
+
+    def text(self) -> str:
+        """The lines separated by newlines."""
+        ...
+
+class Column:
+    """Paragraphs read one after the other. Parts of a page spanning several
+    columns, such as a title above them, form columns of their own."""
+
+    paragraphs: List[Paragraph]
+    bbox: Rect
+
+    def text(self) -> str:
+        """The paragraphs separated by blank lines."""
+        ...
+
+class TableCell:
+    """A cell of a ``Table``, covering ``row_span`` rows and ``column_span``
+    columns from ``row`` and ``column``, counted from the top left."""
+
+    row: int
+    column: int
+    row_span: int
+    column_span: int
+    bbox: Rect
+    text: str
+    """The cell's lines of text, joined by spaces."""
+
+class Table:
+    """A table found on a page, from its ruling lines or from the alignment
+    of its text.
+
+    ``row_edges`` are the y coordinates of the row boundaries from top to
+    bottom, and ``column_edges`` the x coordinates of the column boundaries
+    from left to right. Every position of the grid is covered by exactly one
+    of ``cells``.
+    """
+
+    bbox: Rect
+    row_edges: List[float]
+    column_edges: List[float]
+    cells: List[TableCell]
+
+    @property
+    def row_count(self) -> int: ...
+    @property
+    def column_count(self) -> int: ...
+    def to_list(self) -> List[List[str]]:
+        """The text of each row, cell by cell. A merged cell's text is placed
+        at its top left position, and the positions it also covers are
+        empty."""
+        ...
+    def to_csv(self) -> str:
+        """The rows of ``to_list`` as CSV, quoting fields as in RFC 4180."""
+        ...
+
 class Page:
-    """Represents a single page in the document."""
+    """Represents a single page in the document.
+
+    ``width`` and ``height`` are the size of the media box. Box attributes are
+    ``None`` when the entry is absent from the file.
+    """
 
     width: float
     height: float
+    rotation: int
+    media_box: Optional[Rect]
+    crop_box: Optional[Rect]
+    bleed_box: Optional[Rect]
+    trim_box: Optional[Rect]
+    art_box: Optional[Rect]
+    contents: List[PageContent]
+    """Text, images and shapes in painting order, so that later elements are
+    drawn over earlier ones."""
     text_blocks: List[TextBlock]
+    """The text blocks of ``contents``. Assigning a list replaces them there
+    in place: each new block takes the place of an old one, and extra blocks
+    are added at the end. ``images`` and ``shapes`` work the same way."""
     images: List[Image]
+    shapes: List[Shape]
+    links: List[Link]
+    annotations: List[Annotation]
+    text_fields: List[TextField]
+    checkboxes: List[CheckBox]
+    radio_groups: List[RadioGroup]
+    dropdowns: List[Dropdown]
+    signature_fields: List[SignatureField]
+    """Fillable fields written by ``generate``. Parsed forms are read into
+    ``Document.form_fields`` instead."""
 
     def __init__(
         self,
         width: float,
         height: float,
-        text_blocks: List[TextBlock],
-        images: List[Image],
+        text_blocks: Optional[List[TextBlock]] = None,
+        images: Optional[List[Image]] = None,
+        links: Optional[List[Link]] = None,
+        *,
+        annotations: Optional[List[Annotation]] = None,
+        shapes: Optional[List[Shape]] = None,
+        contents: Optional[List[PageContent]] = None,
+        text_fields: Optional[List[TextField]] = None,
+        checkboxes: Optional[List[CheckBox]] = None,
+        radio_groups: Optional[List[RadioGroup]] = None,
+        dropdowns: Optional[List[Dropdown]] = None,
+        signature_fields: Optional[List[SignatureField]] = None,
+    ) -> None:
+        """Contents are ordered with ``images`` at the bottom, then
+        ``shapes``, then ``text_blocks``, then ``contents`` on top."""
+        ...
+    def visible_box(self) -> Rect:
+        """The crop box, or the media box if there is none."""
+        ...
+    def display_size(self) -> Tuple[float, float]:
+        """Size of the visible area as displayed, accounting for rotation."""
+        ...
+    def columns(self) -> List[Column]:
+        """Groups the characters of the text blocks into words, lines,
+        paragraphs and columns, in reading order. Text is assumed to run
+        horizontally."""
+        ...
+    def text(self) -> str:
+        """The text of the page in reading order, with paragraphs separated
+        by blank lines."""
+        ...
+    def tables(self) -> List[Table]:
+        """Finds the tables of the page, top to bottom: grids of ruling
+        lines, where a missing inner border merges cells, and runs of text
+        rows lining up in columns."""
+        ...
+
+class Metadata:
+    """Document metadata, stored in both the Info dictionary and the XMP
+    packet of generated files. When parsing, XMP values take precedence over
+    Info entries.
+
+    Dates are ISO 8601 strings such as ``"2024-03-01T12:30:00+01:00"``;
+    attributes are ``None`` when the entry is absent.
+    """
+
+    title: Optional[str]
+    author: Optional[str]
+    subject: Optional[str]
+    keywords: Optional[str]
+    creator: Optional[str]
+    """The application that created the original document."""
+    producer: Optional[str]
+    """The application that wrote the PDF."""
+    creation_date: Optional[str]
+    mod_date: Optional[str]
+    custom: Dict[str, str]
+    """Info dictionary entries other than the standard ones."""
+
+    def __init__(
+        self,
+        title: Optional[str] = None,
+        author: Optional[str] = None,
+        subject: Optional[str] = None,
+        keywords: Optional[str] = None,
+        creator: Optional[str] = None,
+        producer: Optional[str] = None,
+        creation_date: Optional[str] = None,
+        mod_date: Optional[str] = None,
+        custom: Optional[Dict[str, str]] = None,
     ) -> None: ...
+    def is_empty(self) -> bool:
+        """Whether no entry is set."""
+        ...
+
+class Destination:
+    """A place that a bookmark or link leads to: a page, and how to show it.
+
+    ``kind`` is the PDF fit type, which decides the coordinates used:
+    ``"XYZ"`` (``left``, ``top`` and ``zoom``; ``None`` keeps the viewer's
+    current value), ``"Fit"``, ``"FitH"`` (``top``), ``"FitV"`` (``left``),
+    ``"FitR"`` (``left``, ``bottom``, ``right`` and ``top``), and ``"FitB"``,
+    ``"FitBH"`` and ``"FitBV"``, which fit the page's content instead.
+    """
+
+    page: int
+    """Index of the target page in ``Document.pages``."""
+    kind: str
+    left: Optional[float]
+    top: Optional[float]
+    right: Optional[float]
+    bottom: Optional[float]
+    zoom: Optional[float]
+
+    def __init__(
+        self,
+        page: int,
+        kind: str = "Fit",
+        left: Optional[float] = None,
+        top: Optional[float] = None,
+        right: Optional[float] = None,
+        bottom: Optional[float] = None,
+        zoom: Optional[float] = None,
+    ) -> None: ...
+
+class OutlineItem:
+    """A bookmark in the document outline, with its nested bookmarks."""
+
+    title: str
+    destination: Optional[Destination]
+    """``None`` for entries that lead nowhere in the document, such as
+    headings or web links."""
+    open: bool
+    """Whether the children are shown when the outline is first displayed."""
+    children: List[OutlineItem]
+
+    def __init__(
+        self,
+        title: str,
+        destination: Optional[Destination] = None,
+        children: Optional[List[OutlineItem]] = None,
+        open: bool = False,
+    ) -> None: ...
+
+class FieldWidget:
+    """Where a form field appears on a page."""
+
+    page: Optional[int]
+    """Index of the page in ``Document.pages``, if the widget is on one."""
+    rect: Rect
+    on_state: Optional[str]
+    """For checkboxes and radio buttons, the value the field takes when this
+    widget is selected."""
+
+class FormField:
+    """An interactive form (AcroForm) field."""
+
+    name: str
+    """The fully qualified name, such as ``"person.address.city"``."""
+    field_type: str
+    """``"text"``, ``"checkbox"``, ``"radio"``, ``"choice"``,
+    ``"signature"`` or ``"button"`` (a push button)."""
+    value: Optional[str]
+    """The text of a text field, the selected option of a choice field, or the
+    state of a checkbox or radio button (``"Off"`` when not selected)."""
+    values: List[str]
+    """All selected options of a list box that allows several; otherwise the
+    value alone."""
+    default_value: Optional[str]
+    options: List[str]
+    """The options of a choice field, or the on states of a checkbox's or
+    radio group's widgets."""
+    option_labels: List[str]
+    """The text shown for each option, which may differ from its value."""
+    flags: int
+    """The field flags (``/Ff``) as defined by the PDF specification."""
+    widgets: List[FieldWidget]
+    @property
+    def read_only(self) -> bool: ...
+    @property
+    def required(self) -> bool: ...
 
 class Document:
     """Represents the entire PDF document."""
 
     pages: List[Page]
+    metadata: Metadata
+    outline: List[OutlineItem]
+    """The top-level bookmarks."""
+    form_fields: List[FormField]
+    """The interactive form's fields, in the order the form lists them."""
 
-    def __init__(self, pages: List[Page]) -> None: ...
+    def __init__(
+        self,
+        pages: List[Page],
+        metadata: Optional[Metadata] = None,
+        outline: Optional[List[OutlineItem]] = None,
+    ) -> None: ...
 
-def parse(path_str: str) -> Document:
+def parse(path_str: PathOrFile, password: Optional[str] = None) -> Document:
     """Parse a PDF file and return a Document object.
 
     Args:
-        path_str: Path to the PDF file to parse
+        path_str: Path to the PDF file to parse, or a binary file object to
+            read it from
+        password: User or owner password for encrypted files. Without it,
+            encrypted files only open if their user password is empty.
 
     Returns:
         Document object containing the parsed PDF data
 
     Raises:
-        ValueError: If the PDF file cannot be parsed
+        ValueError: If the PDF file cannot be parsed or the password is wrong
     """
     ...
 
-def generate(doc: Document, path_str: str) -> None:
+def parse_bytes(data: bytes, password: Optional[str] = None) -> Document:
+    """Parse a PDF held in memory; see ``parse``."""
+    ...
+
+class FontRegistry:
+    """TrueType/OpenType fonts available to ``generate``, by name.
+
+    Fonts with TrueType outlines are embedded as a subset of the glyphs
+    used, and fonts with CFF outlines are embedded whole. Both get a
+    ToUnicode map so that text can be copied from the generated PDF.
+    """
+
+    def __init__(self) -> None: ...
+    def register(self, name: str, path: str, index: int = 0) -> None:
+        """Register the font file at ``path`` under ``name``.
+
+        Args:
+            index: The font to use from a TTC/OTC collection
+
+        Raises:
+            ValueError: If the file cannot be read or is not a font, or if
+                ``index`` is out of range
+        """
+        ...
+
+    def register_bytes(self, name: str, data: bytes, index: int = 0) -> None:
+        """Register font file contents under ``name``.
+
+        Args:
+            index: The font to use from a TTC/OTC collection
+
+        Raises:
+            ValueError: If the data is not a font, or if ``index`` is out of
+                range
+        """
+        ...
+
+    def names(self) -> List[str]: ...
+    def __contains__(self, name: str) -> bool: ...
+    def __len__(self) -> int: ...
+
+def generate(
+    doc: Document, path_str: PathOrFile, fonts: Optional[FontRegistry] = None
+) -> None:
     """Generate a PDF file from a Document object.
 
+    Each page is written with its own size, boxes and rotation, and its
+    contents are drawn in order, each text block at its own position and
+    font size in Helvetica. Images are scaled to their rectangle, and
+    identical images are stored once.
+    Links become link annotations, and review annotations are written with
+    appearance streams (text Helvetica cannot show is left to the viewer). Form fields become an interactive form, with
+    appearances showing their initial values; values Helvetica cannot show
+    are left for the viewer to draw. The document's metadata is written to both the Info dictionary
+    and an XMP packet, and its outline as bookmarks that the viewer shows
+    when the file is opened.
+
     Args:
         doc: Document object to generate PDF from
-        path_str: Path where the PDF file should be saved
+        path_str: Path where the PDF file should be saved, or a binary file
+            object to write it to
+        fonts: Fonts that text blocks can select with ``font_name``
 
     Raises:
         NotImplementedError: If PDF generation fails
     """
     ...
 
+def generate_to_bytes(doc: Document, fonts: Optional[FontRegistry] = None) -> bytes:
+    """Generate a PDF and return its contents; see ``generate``."""
+    ...
+
+class DocumentPermissions:
+    """What a user who opens an encrypted document with the user password may
+    do. The owner password always grants everything."""
+
+    print: bool
+    print_high_quality: bool
+    """Print at full quality; requires ``print``."""
+    modify: bool
+    copy: bool
+    annotate: bool
+    """Add or modify annotations, and fill in form fields."""
+    fill_forms: bool
+    """Fill in existing form fields, even if ``annotate`` is off."""
+    accessibility: bool
+    """Extract text and graphics for assistive technology."""
+    assemble: bool
+    """Insert, rotate or delete pages and create bookmarks."""
+
+    def __init__(
+        self,
+        print: bool = True,
+        print_high_quality: bool = True,
+        modify: bool = True,
+        copy: bool = True,
+        annotate: bool = True,
+        fill_forms: bool = True,
+        accessibility: bool = True,
+        assemble: bool = True,
+    ) -> None: ...
+    @staticmethod
+    def all() -> DocumentPermissions: ...
+    @staticmethod
+    def none() -> DocumentPermissions: ...
+
 def encrypt_pdf(
-    input_path: str, output_path: str, user_password: str, owner_password: str | None
+    input_path: PathOrFile,
+    output_path: PathOrFile,
+    user_password: str,
+    owner_password: str | None = None,
+    permissions: Optional[DocumentPermissions] = None,
+    algorithm: str = "aes-128",
+    encrypt_metadata: bool = True,
 ) -> None:
     """Encrypt a PDF file.
 
     Args:
-        input_path: Path to the PDF file to encrypt
-        output_path: Path where the encrypted PDF file should be saved
+        input_path: Path to the PDF file to encrypt, or a file object
+        output_path: Path where the encrypted PDF file should be saved, or a
+            file object
         user_password: User password for the PDF file
         owner_password: Owner password for the PDF file. If not provided, the user password is used.
+        permissions: Permissions granted with the user password; all by
+            default. Restricting them requires a distinct owner password.
+        algorithm: ``"aes-128"`` (V4, for legacy readers) or ``"aes-256"``
+            (V5 revision 6, ISO 32000-2; the file is marked as PDF 2.0)
+        encrypt_metadata: Whether the document's XMP metadata stream is
+            encrypted too
 
     Raises:
-        ValueError: If the PDF file cannot be encrypted
+        ValueError: If the PDF file cannot be encrypted or the permissions
+            are invalid
     """
     ...
+
+def encrypt_bytes(
+    data: bytes,
+    user_password: str,
+    owner_password: str | None = None,
+    permissions: Optional[DocumentPermissions] = None,
+    algorithm: str = "aes-128",
+    encrypt_metadata: bool = True,
+) -> bytes:
+    """Encrypt a PDF held in memory and return the encrypted file; see
+    ``encrypt_pdf``."""
+    ...
+
+def decrypt_pdf(
+    input_path: PathOrFile, output_path: PathOrFile, password: str
+) -> None:
+    """Write a decrypted copy of a PDF file.
+
+    RC4 (40 and 128 bit), AES-128 and AES-256 encrypted files are supported.
+    Files that are not encrypted are copied unchanged.
+
+    Args:
+        input_path: Path to the encrypted PDF file, or a file object
+        output_path: Path where the decrypted PDF file should be saved, or a
+            file object
+        password: User or owner password of the file
+
+    Raises:
+        ValueError: If the file cannot be read or the password is wrong
+    """
+    ...
+
+def merge(
+    inputs: List[Union[PathOrFile, Tuple[PathOrFile, str]]], output_path: PathOrFile
+) -> None:
+    """Merge PDF files into one.
+
+    Each input is a path or file object, or a ``(file, pages)`` tuple taking
+    only the given pages, such as ``"1-3,7,10-"``. Page numbers start at 1,
+    either end of a range may be left open, and ``"5-3"`` takes pages 5, 4
+    and 3 in that order.
+
+    Identical fonts and images are stored once. Top-level bookmarks of every
+    input are kept, except those leading only to pages that were left out,
+    and named destinations that clash with an earlier input's are renamed.
+    The first input's metadata is kept.
+
+    Args:
+        inputs: Files to merge, in order, with optional page selections
+        output_path: Path where the merged PDF file should be saved, or a
+            file object
+
+    Raises:
+        ValueError: If an input cannot be read or a page selection is invalid
+    """
+    ...
+
+def split(
+    input_path: PathOrFile,
+    output_template: str,
+    *,
+    ranges: Optional[List[str]] = None,
+    every: Optional[int] = None,
+    bookmark_level: Optional[int] = None,
+    blank_separators: bool = False,
+) -> List[str]:
+    """Split a PDF file into several files.
+
+    Exactly one way of splitting must be given:
+
+    - ``ranges``: one file per page selection, such as ``["1-3", "4-"]``
+    - ``every``: files of this many pages; the last one may be shorter
+    - ``bookmark_level``: a file starting at each bookmark of this depth, 1
+      being the top-level bookmarks; pages before the first one form a file
+      of their own
+    - ``blank_separators``: files separated by blank pages, which are left out
+
+    Each file keeps only the resources, bookmarks and named destinations of
+    its own pages.
+
+    Args:
+        input_path: Path to the PDF file, or a file object
+        output_template: Path of the output files, with ``{}`` standing for
+            the file number. Numbers start at 1 and are zero-padded so the
+            files sort in order, e.g. ``"part-{}.pdf"``
+        ranges: Page selections, one per file
+        every: Pages per file
+        bookmark_level: Depth of the bookmarks starting each file
+        blank_separators: Split at blank pages
+
+    Returns:
+        The paths of the files written, in order
+
+    Raises:
+        ValueError: If the input cannot be read, the options are invalid or a
+            page selection is out of range
+    """
+    ...
+
+def extract_pages(input_path: PathOrFile, output_path: PathOrFile, pages: str) -> None:
+    """Write a selection of a PDF file's pages to a new file.
+
+    Only the resources, bookmarks and named destinations of the selected
+    pages are kept.
+
+    Args:
+        input_path: Path to the PDF file, or a file object
+        output_path: Path where the new PDF file should be saved, or a file
+            object
+        pages: Pages to take, such as ``"1-3,7,10-"``
+
+    Raises:
+        ValueError: If the input cannot be read or the selection is invalid
+    """
+    ...
+
+FieldValue = Union[str, bool, int, float, List[str]]
+
+def fill_form(
+    input_path: PathOrFile,
+    output_path: PathOrFile,
+    values: Union[Dict[str, FieldValue], str],
+    *,
+    need_appearances: bool = False,
+    flatten: bool = False,
+    password: Optional[str] = None,
+) -> None:
+    """Fill in the interactive form of a PDF file.
+
+    Fields are named by their fully qualified name, as in
+    ``FormField.name``. Text fields take a string (numbers are written as
+    text), choice fields an option or, for list boxes allowing several, a
+    list of options, checkboxes ``True``/``False`` or a state name, and
+    radio groups the state name of the button to select (``"Off"`` or
+    ``False`` for none). The appearance streams of the filled fields are
+    regenerated so that every viewer shows the new values. A field whose
+    font cannot show its new value keeps its appearance, and viewers are
+    asked to regenerate it.
+
+    Args:
+        input_path: Path to the PDF file, or a file object
+        output_path: Path where the filled PDF file should be saved, or a
+            file object
+        values: Mapping of field names to values, or a JSON object string
+        need_appearances: Also ask viewers to regenerate all appearances
+        flatten: Draw every field into the page content and remove the form,
+            so that the values can no longer be edited
+        password: Password for encrypted files; the output is not encrypted
+
+    Raises:
+        ValueError: If the input cannot be read, a field does not exist, a
+            value does not suit its field, or a field to flatten has a value
+            its font cannot show
+    """
+    ...
+
+def fill_form_bytes(
+    data: bytes,
+    values: Union[Dict[str, FieldValue], str],
+    *,
+    need_appearances: bool = False,
+    flatten: bool = False,
+    password: Optional[str] = None,
+) -> bytes:
+    """Fill in the form of a PDF held in memory and return the filled file;
+    see ``fill_form``."""
+    ...
+
+class PdfEditor:
+    """An existing PDF whose pages are being edited.
+
+    Pages are addressed by 0-based index. Content is never re-rendered:
+    edits only change the page tree and page dictionaries, and the result
+    is written with ``save`` or ``to_bytes``.
+
+    Raises:
+        ValueError: From every method, if the file cannot be read, an index
+            is out of range or an argument is invalid
+    """
+
+    page_count: int
+
+    def __init__(self, input_path: PathOrFile, password: Optional[str] = None) -> None:
+        """Open a PDF file, decrypting it with ``password`` if it is encrypted."""
+        ...
+
+    @staticmethod
+    def from_bytes(data: bytes, password: Optional[str] = None) -> PdfEditor:
+        """Open PDF file contents for editing."""
+        ...
+
+    def __len__(self) -> int: ...
+    def rotation(self, index: int) -> int:
+        """The clockwise rotation of a page: 0, 90, 180 or 270."""
+        ...
+
+    def rotate(self, pages: List[int], degrees: int) -> None:
+        """Rotate pages clockwise by a multiple of 90 degrees, which may be
+        negative."""
+        ...
+
+    def reorder(self, order: List[int]) -> None:
+        """Put the pages in a new order: ``order[i]`` is the current index of
+        the page that becomes page ``i``. Every page must appear exactly
+        once."""
+        ...
+
+    def delete(self, pages: List[int]) -> None:
+        """Delete pages. Links and bookmarks leading to them are left without
+        a target page."""
+        ...
+
+    def insert_blank(self, index: int, width: float, height: float) -> None:
+        """Insert an empty page of the given size in points so that it becomes
+        page ``index``; ``index`` may equal the page count to append."""
+        ...
+
+    def duplicate(self, index: int, to: Optional[int] = None) -> None:
+        """Copy a page so that the copy becomes page ``to``, by default right
+        after the original. The copy shares the original's content and
+        resources; its annotations are copied, except form field widgets."""
+        ...
+
+    def add_annotation(self, page: int, annotation: Annotation) -> None:
+        """Add an annotation to a page, after its existing annotations. It is
+        written with an appearance stream, so every viewer draws it alike,
+        unless it shows text Helvetica cannot, which the viewer draws."""
+        ...
+
+    def save(self, output_path: PathOrFile) -> None:
+        """Write the edited document to a path or file object."""
+        ...
+
+    def to_bytes(self) -> bytes:
+        """The edited document as PDF file contents."""
+        ...
@@ -0,0 +1,575 @@
+//! Review annotations (ISO 32000-1, 12.5.6): sticky notes, text markup,
+//! free text, ink drawings, squares and circles, and stamps.
+
+use crate::encoding::BaseEncoding;
+use crate::generator::color_operation;
+use crate::metadata::{decode_text_string, encode_text_string, iso_to_pdf_date, pdf_date_to_iso};
+use crate::parser::rect_from_object;
+use crate::structure::{Annotation, Rect, TextBlock};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+
+/// Annotation subtypes in the model, with the names `Annotation::kind`
+/// uses for them.
+const KINDS: [(&[u8], &str); 10] = [
+    (b"Text", "text"),
+    (b"Highlight", "highlight"),
+    (b"Underline", "underline"),
+    (b"StrikeOut", "strikeout"),
+    (b"Squiggly", "squiggly"),
+    (b"FreeText", "freetext"),
+    (b"Ink", "ink"),
+    (b"Square", "square"),
+    (b"Circle", "circle"),
+    (b"Stamp", "stamp"),
+];
+
+/// The kinds that mark up text and carry `/QuadPoints`.
+const MARKUP: [&str; 4] = ["highlight", "underline", "strikeout", "squiggly"];
+
+/// Size of the text free text annotations and stamps are drawn in.
+const TEXT_SIZE: f32 = 12.0;
+
+/// The annotations of page `page_id`, with the text under text markup
+/// looked up in the page's `text_blocks`. Links, form widgets and other
+/// subtypes the model has no kind for are skipped.
+pub fn read_annotations(
+    doc: &lopdf::Document,
+    page_id: ObjectId,
+    text_blocks: &[&TextBlock],
+) -> Vec<Annotation> {
+    let annotations = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Annots").ok())
+        .and_then(|a| doc.dereference(a).ok())
+        .and_then(|(_, a)| a.as_array().ok());
+    let mut result = Vec::new();
+    for annotation in annotations.into_iter().flatten() {
+        let Ok((_, Object::Dictionary(dict))) = doc.dereference(annotation) else {
+            continue;
+        };
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name).ok();
+        let Some(&(_, kind)) = KINDS.iter().find(|(name, _)| Some(*name) == subtype) else {
+            continue;
+        };
+        let Some(rect) = dict
+            .get(b"Rect")
+            .ok()
+            .and_then(|r| rect_from_object(doc, r))
+        else {
+            continue;
+        };
+        let numbers = |key: &[u8]| -> Vec<f32> {
+            dict.get(key)
+                .ok()
+                .and_then(|o| doc.dereference(o).ok())
+                .and_then(|(_, o)| o.as_array().ok())
+                .map(|values| values.iter().filter_map(|v| v.as_float().ok()).collect())
+                .unwrap_or_default()
+        };
+        let text = |key: &[u8]| {
+            dict.get(key)
+                .ok()
+                .and_then(|o| doc.dereference(o).ok())
+                .and_then(|(_, o)| o.as_str().ok())
+                .map(decode_text_string)
+        };
+
+        let quads: Vec<Rect> = numbers(b"QuadPoints")
+            .chunks_exact(8)
+            .map(|quad| {
+                let xs = [quad[0], quad[2], quad[4], quad[6]];
+                let ys = [quad[1], quad[3], quad[5], quad[7]];
+                Rect::new(
+                    xs.into_iter().fold(f32::MAX, f32::min),
+                    ys.into_iter().fold(f32::MAX, f32::min),
+                    xs.into_iter().fold(f32::MIN, f32::max),
+                    ys.into_iter().fold(f32::MIN, f32::max),
+                )
+            })
+            .collect();
+        let ink = dict
+            .get(b"InkList")
+            .ok()
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_array().ok())
+            .map(|strokes| {
+                strokes
+                    .iter()
+                    .filter_map(|stroke| doc.dereference(stroke).ok()?.1.as_array().ok())
+                    .map(|stroke| {
+                        let values: Vec<f32> =
+                            stroke.iter().filter_map(|v| v.as_float().ok()).collect();
+                        values.chunks_exact(2).map(|p| (p[0], p[1])).collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let border_width = dict
+            .get(b"BS")
+            .ok()
+            .and_then(|bs| doc.dereference(bs).ok())
+            .and_then(|(_, bs)| bs.as_dict().ok()?.get(b"W").ok()?.as_float().ok())
+            .or_else(|| numbers(b"Border").get(2).copied());
+
+        result.push(Annotation {
+            kind: kind.to_string(),
+            rect,
+            contents: text(b"Contents"),
+            author: text(b"T"),
+            created: text(b"CreationDate").and_then(|d| pdf_date_to_iso(&d)),
+            modified: text(b"M").and_then(|d| pdf_date_to_iso(&d)),
+            color: numbers(b"C"),
+            interior_color: numbers(b"IC"),
+            border_width,
+            text: if MARKUP.contains(&kind) {
+                covered_text(text_blocks, &quads)
+            } else {
+                None
+            },
+            quads,
+            ink,
+            icon: dict
+                .get(b"Name")
+                .and_then(Object::as_name)
+                .ok()
+                .map(|name| String::from_utf8_lossy(name).into_owned()),
+        });
+    }
+    result
+}
+
+/// The text of `blocks` under `quads`. Characters count as covered when
+/// their middle lies inside a quad.
+fn covered_text(blocks: &[&TextBlock], quads: &[Rect]) -> Option<String> {
+    let mut lines = Vec::new();
+    for quad in quads {
+        // Covered parts of blocks, with their start and end x.
+        let mut parts: Vec<(f32, f32, String)> = Vec::new();
+        for block in blocks {
+            let middle = block.y + block.font_size * 0.3;
+            if middle < quad.y0 || middle > quad.y1 {
+                continue;
+            }
+            let mut covered = String::new();
+            let (mut start, mut end) = (f32::MAX, f32::MIN);
+            for (ch, x, advance) in block.char_positions() {
+                let center = x + advance / 2.0;
+                if center >= quad.x0 && center <= quad.x1 {
+                    covered.push(ch);
+                    start = start.min(x);
+                    end = end.max(x + advance);
+                }
+            }
+            if !covered.is_empty() {
+                parts.push((start, end, covered));
+            }
+        }
+        parts.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut line = String::new();
+        let mut previous_end = None;
+        for (start, end, text) in parts {
+            if let Some(previous_end) = previous_end {
+                // Separate runs with a visible gap between them.
+                let gap = start - previous_end;
+                if gap > quad.height() * 0.15 && !line.ends_with(' ') && !text.starts_with(' ') {
+                    line.push(' ');
+                }
+            }
+            line.push_str(&text);
+            previous_end = Some(end);
+        }
+        let line = line.trim();
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// The rectangle an annotation is drawn in: its own, or the bounds of its
+/// quads or ink strokes (widened by the line width) when it has none.
+fn annotation_rect(annotation: &Annotation) -> Option<Rect> {
+    let rect = annotation.rect.normalized();
+    if rect.width() > 0.0 && rect.height() > 0.0 {
+        return Some(rect);
+    }
+    let mut points: Vec<(f32, f32)> = annotation
+        .quads
+        .iter()
+        .flat_map(|q| [(q.x0, q.y0), (q.x1, q.y1)])
+        .collect();
+    points.extend(annotation.ink.iter().flatten());
+    let margin = if annotation.ink.is_empty() {
+        0.0
+    } else {
+        annotation.border_width.unwrap_or(1.0)
+    };
+    let (first, rest) = points.split_first()?;
+    let mut bounds = Rect::new(first.0, first.1, first.0, first.1);
+    for &(x, y) in rest {
+        bounds = Rect::new(
+            bounds.x0.min(x),
+            bounds.y0.min(y),
+            bounds.x1.max(x),
+            bounds.y1.max(y),
+        );
+    }
+    Some(Rect::new(
+        bounds.x0 - margin,
+        bounds.y0 - margin,
+        bounds.x1 + margin,
+        bounds.y1 + margin,
+    ))
+}
+
+fn rect_array(rect: &Rect) -> Object {
+    vec![
+        rect.x0.into(),
+        rect.y0.into(),
+        rect.x1.into(),
+        rect.y1.into(),
+    ]
+    .into()
+}
+
+fn number_array(values: &[f32]) -> Object {
+    values
+        .iter()
+        .map(|&v| Object::from(v))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Writes the annotation dictionary for `annotation` on the page `page_id`
+/// to `pdf`, with an appearance stream so that every viewer shows it the
+/// same way, and returns its id. Text that Helvetica cannot show is left
+/// for the viewer to draw from `/Contents` and `/DA`.
+pub fn add_annotation(
+    pdf: &mut lopdf::Document,
+    annotation: &Annotation,
+    page_id: ObjectId,
+) -> Result<ObjectId, String> {
+    let kind = annotation.kind.as_str();
+    let Some(&(subtype, _)) = KINDS.iter().find(|(_, name)| *name == kind) else {
+        let kinds: Vec<&str> = KINDS.iter().map(|(_, name)| *name).collect();
+        return Err(format!(
+            "unknown annotation kind '{}', expected one of {}",
+            kind,
+            kinds.join(", ")
+        ));
+    };
+    let markup = MARKUP.contains(&kind);
+    if markup && annotation.quads.is_empty() && annotation.rect.width() == 0.0 {
+        return Err(format!("{} annotation has no quads or rect", kind));
+    }
+    if kind == "ink" && annotation.ink.iter().all(Vec::is_empty) {
+        return Err("ink annotation has no strokes".to_string());
+    }
+    for color in [&annotation.color, &annotation.interior_color] {
+        if !matches!(color.len(), 0 | 1 | 3 | 4) {
+            return Err(format!(
+                "colours have 1, 3 or 4 components, not {}",
+                color.len()
+            ));
+        }
+    }
+    let rect = annotation_rect(annotation)
+        .ok_or_else(|| format!("{} annotation has an empty rect", kind))?;
+
+    let mut dict = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Annot".to_vec())),
+        ("Subtype", Object::Name(subtype.to_vec())),
+        ("Rect", rect_array(&rect)),
+        ("P", page_id.into()),
+        ("F", 4.into()),
+    ]);
+    if let Some(contents) = &annotation.contents {
+        dict.set("Contents", encode_text_string(contents));
+    }
+    if let Some(author) = &annotation.author {
+        dict.set("T", encode_text_string(author));
+    }
+    for (key, date) in [
+        ("CreationDate", &annotation.created),
+        ("M", &annotation.modified),
+    ] {
+        if let Some(date) = date {
+            let pdf_date =
+                iso_to_pdf_date(date).ok_or_else(|| format!("invalid date '{}'", date))?;
+            dict.set(key, Object::string_literal(pdf_date));
+        }
+    }
+
+    // Markup defaults to a yellow highlight or a red line, like most viewers.
+    let color = match (annotation.color.is_empty(), kind) {
+        (false, _) => annotation.color.clone(),
+        (true, "highlight") => vec![1.0, 1.0, 0.0],
+        (true, "text") => vec![1.0, 0.85, 0.0],
+        (true, _) => vec![1.0, 0.0, 0.0],
+    };
+    dict.set("C", number_array(&color));
+    if !annotation.interior_color.is_empty() {
+        dict.set("IC", number_array(&annotation.interior_color));
+    }
+    let width = annotation.border_width.unwrap_or(1.0);
+    if annotation.border_width.is_some() || matches!(kind, "ink" | "square" | "circle") {
+        dict.set("BS", Dictionary::from_iter(vec![("W", width.into())]));
+    }
+    let quads = if annotation.quads.is_empty() {
+        vec![rect]
+    } else {
+        annotation.quads.iter().map(Rect::normalized).collect()
+    };
+    if markup {
+        // Each quad as its top left, top right, bottom left and bottom
+        // right corners, the order viewers expect.
+        let points: Vec<f32> = quads
+            .iter()
+            .flat_map(|q| [q.x0, q.y1, q.x1, q.y1, q.x0, q.y0, q.x1, q.y0])
+            .collect();
+        dict.set("QuadPoints", number_array(&points));
+    }
+    if kind == "ink" {
+        let strokes: Vec<Object> = annotation
+            .ink
+            .iter()
+            .map(|stroke| {
+                let points: Vec<f32> = stroke.iter().flat_map(|&(x, y)| [x, y]).collect();
+                number_array(&points)
+            })
+            .collect();
+        dict.set("InkList", strokes);
+    }
+    match (kind, &annotation.icon) {
+        ("text" | "stamp", Some(icon)) => dict.set("Name", Object::Name(icon.as_bytes().to_vec())),
+        ("stamp", None) => dict.set("Name", Object::Name(b"Draft".to_vec())),
+        _ => {}
+    }
+    if kind == "freetext" {
+        dict.set(
+            "DA",
+            Object::string_literal(format!("/Helv {} Tf 0 g", TEXT_SIZE)),
+        );
+    }
+
+    // Notes are drawn as an icon each viewer chooses.
+    if kind != "text" {
+        if let Some(stream) = appearance(annotation, rect, &quads, &color, width) {
+            let stream_id = pdf.add_object(stream);
+            dict.set("AP", Dictionary::from_iter(vec![("N", stream_id.into())]));
+        }
+    }
+    Ok(pdf.add_object(dict))
+}
+
+/// The appearance stream of a non-note annotation drawn in `rect`. Its
+/// bounding box is `rect` itself, so it draws in page coordinates. Returns
+/// `None` if the annotation shows text that Helvetica cannot, which is left
+/// for the viewer to draw.
+fn appearance(
+    annotation: &Annotation,
+    rect: Rect,
+    quads: &[Rect],
+    color: &[f32],
+    width: f32,
+) -> Option<Stream> {
+    let mut resources = Dictionary::new();
+    let mut operations = vec![Operation::new("q", vec![])];
+    operations.extend(color_operation(color, true));
+    operations.extend(color_operation(color, false));
+    let helvetica = || {
+        Dictionary::from_iter(vec![(
+            "Helv",
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Font".to_vec())),
+                ("Subtype", Object::Name(b"Type1".to_vec())),
+                ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+                ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+            ])
+            .into(),
+        )])
+    };
+    let text_lines = |text: &str, x: f32, top: f32| -> Option<Vec<Operation>> {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["Helv".into(), TEXT_SIZE.into()]),
+        ];
+        for (index, line) in text.lines().enumerate() {
+            let y = top - TEXT_SIZE * (index as f32 + 1.0);
+            operations.push(Operation::new(
+                "Tm",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()],
+            ));
+            let encoded = line
+                .chars()
+                .map(|ch| BaseEncoding::WinAnsi.encode(ch))
+                .collect::<Option<Vec<u8>>>()?;
+            operations.push(Operation::new("Tj", vec![Object::string_literal(encoded)]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        Some(operations)
+    };
+
+    match annotation.kind.as_str() {
+        "highlight" => {
+            // Multiply blending keeps the text under the highlight legible.
+            resources.set(
+                "ExtGState",
+                Dictionary::from_iter(vec![(
+                    "GS0",
+                    Dictionary::from_iter(vec![("BM", Object::Name(b"Multiply".to_vec()))]).into(),
+                )]),
+            );
+            operations.push(Operation::new("gs", vec!["GS0".into()]));
+            for quad in quads {
+                operations.push(Operation::new(
+                    "re",
+                    vec![
+                        quad.x0.into(),
+                        quad.y0.into(),
+                        quad.width().into(),
+                        quad.height().into(),
+                    ],
+                ));
+            }
+            operations.push(Operation::new("f", vec![]));
+        }
+        kind @ ("underline" | "strikeout" | "squiggly") => {
+            for quad in quads {
+                let thickness = (quad.height() / 14.0).max(0.5);
+                let y = if kind == "strikeout" {
+                    quad.y0 + quad.height() * 0.4
+                } else {
+                    quad.y0 + thickness
+                };
+                operations.extend([
+                    Operation::new("w", vec![thickness.into()]),
+                    Operation::new("m", vec![quad.x0.into(), y.into()]),
+                ]);
+                if kind == "squiggly" {
+                    let step = thickness * 2.0;
+                    let mut x = quad.x0;
+                    let mut up = true;
+                    while x < quad.x1 {
+                        x = (x + step).min(quad.x1);
+                        let dy = if up { step } else { 0.0 };
+                        operations.push(Operation::new("l", vec![x.into(), (y + dy).into()]));
+                        up = !up;
+                    }
+                } else {
+                    operations.push(Operation::new("l", vec![quad.x1.into(), y.into()]));
+                }
+                operations.push(Operation::new("S", vec![]));
+            }
+        }
+        "ink" => {
+            operations.extend([
+                Operation::new("w", vec![width.into()]),
+                Operation::new("J", vec![1.into()]),
+                Operation::new("j", vec![1.into()]),
+            ]);
+            for stroke in &annotation.ink {
+                for (index, &(x, y)) in stroke.iter().enumerate() {
+                    let operator = if index == 0 { "m" } else { "l" };
+                    operations.push(Operation::new(operator, vec![x.into(), y.into()]));
+                }
+                operations.push(Operation::new("S", vec![]));
+            }
+        }
+        kind @ ("square" | "circle") => {
+            let inner = Rect::new(
+                rect.x0 + width / 2.0,
+                rect.y0 + width / 2.0,
+                rect.x1 - width / 2.0,
+                rect.y1 - width / 2.0,
+            );
+            operations.push(Operation::new("w", vec![width.into()]));
+            let filled = color_operation(&annotation.interior_color, true);
+            let fill = filled.is_some();
+            operations.extend(filled);
+            if kind == "square" {
+                operations.push(Operation::new(
+                    "re",
+                    vec![
+                        inner.x0.into(),
+                        inner.y0.into(),
+                        inner.width().into(),
+                        inner.height().into(),
+                    ],
+                ));
+            } else {
+                operations.extend(ellipse(inner));
+            }
+            operations.push(Operation::new(if fill { "B" } else { "S" }, vec![]));
+        }
+        "freetext" => {
+            resources.set("Font", helvetica());
+            // Free text is black, framed in the annotation's colour.
+            operations.push(Operation::new(
+                "re",
+                vec![
+                    rect.x0.into(),
+                    rect.y0.into(),
+                    rect.width().into(),
+                    rect.height().into(),
+                ],
+            ));
+            operations.push(Operation::new("S", vec![]));
+            operations.push(Operation::new("g", vec![0.into()]));
+            let text = annotation.contents.as_deref().unwrap_or_default();
+            operations.extend(text_lines(text, rect.x0 + 2.0, rect.y1 - 2.0)?);
+        }
+        "stamp" => {
+            resources.set("Font", helvetica());
+            let label = annotation.icon.as_deref().unwrap_or("Draft").to_uppercase();
+            operations.extend([
+                Operation::new("w", vec![2.into()]),
+                Operation::new(
+                    "re",
+                    vec![
+                        (rect.x0 + 1.0).into(),
+                        (rect.y0 + 1.0).into(),
+                        (rect.width() - 2.0).into(),
+                        (rect.height() - 2.0).into(),
+                    ],
+                ),
+                Operation::new("S", vec![]),
+            ]);
+            let top = rect.y0 + (rect.height() + TEXT_SIZE) / 2.0 + TEXT_SIZE * 0.25;
+            operations.extend(text_lines(&label, rect.x0 + 6.0, top)?);
+        }
+        _ => {}
+    }
+    operations.push(Operation::new("Q", vec![]));
+
+    let content = Content { operations }.encode().unwrap_or_default();
+    Some(Stream::new(
+        Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Form".to_vec())),
+            ("BBox", rect_array(&rect)),
+            ("Resources", resources.into()),
+        ]),
+        content,
+    ))
+}
+
+/// An ellipse inscribed in `rect`, as four Bézier curves.
+fn ellipse(rect: Rect) -> Vec<Operation> {
+    // The control point distance that best approximates a quarter circle.
+    const KAPPA: f32 = 0.552_284_8;
+    let (cx, cy) = ((rect.x0 + rect.x1) / 2.0, (rect.y0 + rect.y1) / 2.0);
+    let (rx, ry) = (rect.width() / 2.0, rect.height() / 2.0);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let curve = |points: [f32; 6]| Operation::new("c", points.iter().map(|&v| v.into()).collect());
+    vec![
+        Operation::new("m", vec![(cx + rx).into(), cy.into()]),
+        curve([cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry]),
+        curve([cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy]),
+        curve([cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry]),
+        curve([cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy]),
+        Operation::new("h", vec![]),
+    ]
+}
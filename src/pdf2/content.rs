@@ -0,0 +1,846 @@
+use crate::font::PdfFont;
+use crate::images::{expand_inline_dictionary, extract_image};
+use crate::matrix::Matrix;
+use crate::structure::{Image, PageContent, PathSegment, Shape, TextBlock, LINE_CAPS, LINE_JOINS};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
+
+/// Form XObjects may reference each other; stop descending past this depth.
+const MAX_FORM_DEPTH: usize = 12;
+
+/// A `TJ` adjustment larger than this (in thousandths of an em) is treated
+/// as a word break and rendered as a space.
+const TJ_SPACE_THRESHOLD: f32 = 200.0;
+
+#[derive(Debug, Clone)]
+struct TextState {
+    font: Option<Rc<PdfFont>>,
+    font_size: f32,
+    char_spacing: f32,
+    word_spacing: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+    rise: f32,
+    render_mode: u8,
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        TextState {
+            font: None,
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+            render_mode: 0,
+        }
+    }
+}
+
+/// A colour and the number of components its colour space has, or `None`
+/// for colour spaces whose colours cannot be expressed as gray, RGB or
+/// CMYK (patterns, separations, indexed spaces); colours set in those are
+/// ignored.
+#[derive(Debug, Clone)]
+struct Color {
+    components: Option<usize>,
+    value: Vec<f32>,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color {
+            components: Some(1),
+            value: vec![0.0],
+        }
+    }
+}
+
+impl Color {
+    /// Selects a colour space with `components` components, whose initial
+    /// colour is black.
+    fn set_space(&mut self, components: Option<usize>) {
+        self.components = components;
+        match components {
+            Some(4) => self.value = vec![0.0, 0.0, 0.0, 1.0],
+            Some(n) => self.value = vec![0.0; n],
+            None => {}
+        }
+    }
+
+    fn set(&mut self, values: Vec<f32>) {
+        if Some(values.len()) == self.components {
+            self.value = values;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    text: TextState,
+    line_width: f32,
+    line_cap: usize,
+    line_join: usize,
+    dash: Vec<f32>,
+    dash_phase: f32,
+    fill: Color,
+    stroke: Color,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        GraphicsState {
+            ctm: Matrix::default(),
+            text: TextState::default(),
+            line_width: 1.0,
+            line_cap: 0,
+            line_join: 0,
+            dash: Vec::new(),
+            dash_phase: 0.0,
+            fill: Color::default(),
+            stroke: Color::default(),
+        }
+    }
+}
+
+/// Walks content streams, tracking the graphics and text state, and
+/// collects the elements drawn on the page in painting order.
+pub struct ContentInterpreter<'a> {
+    doc: &'a Document,
+    fonts: HashMap<ObjectId, Rc<PdfFont>>,
+    images: HashMap<ObjectId, Option<(Vec<u8>, String)>>,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    text_matrix: Matrix,
+    text_line_matrix: Matrix,
+    /// The path under construction, in page coordinates.
+    path: Vec<PathSegment>,
+    depth: usize,
+    contents: Vec<PageContent>,
+}
+
+fn operand_numbers(operands: &[Object]) -> Vec<f32> {
+    operands.iter().filter_map(|o| o.as_float().ok()).collect()
+}
+
+impl<'a> ContentInterpreter<'a> {
+    pub fn new(doc: &'a Document) -> Self {
+        ContentInterpreter {
+            doc,
+            fonts: HashMap::new(),
+            images: HashMap::new(),
+            state: GraphicsState::default(),
+            stack: Vec::new(),
+            text_matrix: Matrix::identity(),
+            text_line_matrix: Matrix::identity(),
+            path: Vec::new(),
+            depth: 0,
+            contents: Vec::new(),
+        }
+    }
+
+    /// Interprets a page's (decoded) content stream against its resources
+    /// and returns everything it draws.
+    pub fn run(mut self, content: &[u8], resources: Option<&'a Dictionary>) -> Vec<PageContent> {
+        self.interpret(content, resources);
+        self.contents
+    }
+
+    fn interpret(&mut self, content: &[u8], resources: Option<&'a Dictionary>) {
+        // lopdf cannot parse inline images that use filters or named colour
+        // spaces, and fails the whole stream when it meets one, so they are
+        // cut out and handled separately.
+        for segment in split_inline_images(content) {
+            match segment {
+                Segment::Operations(data) => {
+                    let Ok(content) = Content::decode(data) else {
+                        continue;
+                    };
+                    for operation in &content.operations {
+                        self.execute(operation, resources);
+                    }
+                }
+                Segment::InlineImage { dict, data } => {
+                    self.draw_inline_image(resources, dict, data);
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, object: &'a Object) -> &'a Object {
+        self.doc
+            .dereference(object)
+            .map(|(_, o)| o)
+            .unwrap_or(object)
+    }
+
+    /// Looks up `/category /name` in a resource dictionary, returning the
+    /// referenced object and its id when it is an indirect object.
+    fn resource(
+        &self,
+        resources: Option<&'a Dictionary>,
+        category: &[u8],
+        name: &[u8],
+    ) -> Option<(Option<ObjectId>, &'a Object)> {
+        let category = self
+            .resolve(resources?.get(category).ok()?)
+            .as_dict()
+            .ok()?;
+        let entry = category.get(name).ok()?;
+        self.doc.dereference(entry).ok()
+    }
+
+    fn font(&mut self, resources: Option<&'a Dictionary>, name: &[u8]) -> Option<Rc<PdfFont>> {
+        let (id, object) = self.resource(resources, b"Font", name)?;
+        if let Some(font) = id.and_then(|id| self.fonts.get(&id)) {
+            return Some(font.clone());
+        }
+        let font = Rc::new(PdfFont::from_dict(self.doc, object.as_dict().ok()?));
+        if let Some(id) = id {
+            self.fonts.insert(id, font.clone());
+        }
+        Some(font)
+    }
+
+    fn execute(&mut self, operation: &Operation, resources: Option<&'a Dictionary>) {
+        let operands = &operation.operands;
+        match operation.operator.as_str() {
+            "q" => self.stack.push(self.state.clone()),
+            "Q" => {
+                if let Some(state) = self.stack.pop() {
+                    self.state = state;
+                }
+            }
+            "cm" => {
+                if let Some(m) = Matrix::from_slice(&operand_numbers(operands)) {
+                    self.state.ctm = m.multiply(&self.state.ctm);
+                }
+            }
+            "BT" => {
+                self.text_matrix = Matrix::identity();
+                self.text_line_matrix = Matrix::identity();
+            }
+            "Tf" => {
+                if let [name, size] = operands.as_slice() {
+                    self.state.text.font_size = size.as_float().unwrap_or(0.0);
+                    if let Ok(name) = name.as_name() {
+                        self.state.text.font = self.font(resources, name);
+                    }
+                }
+            }
+            "Tc" => self.state.text.char_spacing = first_number(operands),
+            "Tw" => self.state.text.word_spacing = first_number(operands),
+            "Tz" => self.state.text.horizontal_scaling = first_number(operands) / 100.0,
+            "TL" => self.state.text.leading = first_number(operands),
+            "Ts" => self.state.text.rise = first_number(operands),
+            "Tr" => self.state.text.render_mode = first_number(operands).clamp(0.0, 7.0) as u8,
+            "Td" => {
+                if let [tx, ty] = operand_numbers(operands).as_slice() {
+                    self.next_line(*tx, *ty);
+                }
+            }
+            "TD" => {
+                if let [tx, ty] = operand_numbers(operands).as_slice() {
+                    self.state.text.leading = -ty;
+                    self.next_line(*tx, *ty);
+                }
+            }
+            "Tm" => {
+                if let Some(m) = Matrix::from_slice(&operand_numbers(operands)) {
+                    self.text_matrix = m;
+                    self.text_line_matrix = m;
+                }
+            }
+            "T*" => self.next_line(0.0, -self.state.text.leading),
+            "Tj" => {
+                if let Some(Ok(bytes)) = operands.first().map(Object::as_str) {
+                    self.show_text(&[TextItem::Text(bytes)]);
+                }
+            }
+            "'" => {
+                self.next_line(0.0, -self.state.text.leading);
+                if let Some(Ok(bytes)) = operands.first().map(Object::as_str) {
+                    self.show_text(&[TextItem::Text(bytes)]);
+                }
+            }
+            "\"" => {
+                if let [aw, ac, text] = operands.as_slice() {
+                    self.state.text.word_spacing = aw.as_float().unwrap_or(0.0);
+                    self.state.text.char_spacing = ac.as_float().unwrap_or(0.0);
+                    self.next_line(0.0, -self.state.text.leading);
+                    if let Ok(bytes) = text.as_str() {
+                        self.show_text(&[TextItem::Text(bytes)]);
+                    }
+                }
+            }
+            "TJ" => {
+                if let Some(Ok(items)) = operands.first().map(Object::as_array) {
+                    let items: Vec<TextItem> = items
+                        .iter()
+                        .filter_map(|item| match item {
+                            Object::String(bytes, _) => Some(TextItem::Text(bytes)),
+                            other => other.as_float().ok().map(TextItem::Adjust),
+                        })
+                        .collect();
+                    self.show_text(&items);
+                }
+            }
+            "Do" => {
+                if let Some(Ok(name)) = operands.first().map(Object::as_name) {
+                    self.draw_xobject(resources, name);
+                }
+            }
+            "w" => self.state.line_width = first_number(operands),
+            "J" => self.state.line_cap = first_number(operands) as usize,
+            "j" => self.state.line_join = first_number(operands) as usize,
+            "d" => {
+                if let [Object::Array(dash), phase] = operands.as_slice() {
+                    self.state.dash = operand_numbers(dash);
+                    self.state.dash_phase = phase.as_float().unwrap_or(0.0);
+                }
+            }
+            "g" | "rg" | "k" => {
+                let values = operand_numbers(operands);
+                self.state.fill.set_space(Some(values.len()));
+                self.state.fill.set(values);
+            }
+            "G" | "RG" | "K" => {
+                let values = operand_numbers(operands);
+                self.state.stroke.set_space(Some(values.len()));
+                self.state.stroke.set(values);
+            }
+            "cs" | "CS" => {
+                let components = operands
+                    .first()
+                    .and_then(|o| o.as_name().ok())
+                    .and_then(|name| self.color_space_components(resources, name));
+                match operation.operator.as_str() {
+                    "cs" => self.state.fill.set_space(components),
+                    _ => self.state.stroke.set_space(components),
+                }
+            }
+            // Pattern colours end in a name, and are skipped by `set` as
+            // pattern spaces have no component count.
+            "sc" | "scn" => self.state.fill.set(operand_numbers(operands)),
+            "SC" | "SCN" => self.state.stroke.set(operand_numbers(operands)),
+            "m" => {
+                if let [x, y] = operand_numbers(operands).as_slice() {
+                    let point = self.state.ctm.transform_point(*x, *y);
+                    self.path.push(PathSegment::move_to(point.0, point.1));
+                }
+            }
+            "l" => {
+                if let [x, y] = operand_numbers(operands).as_slice() {
+                    let point = self.state.ctm.transform_point(*x, *y);
+                    self.path.push(PathSegment::line_to(point.0, point.1));
+                }
+            }
+            "c" | "v" | "y" => {
+                let numbers = operand_numbers(operands);
+                let points: Vec<(f32, f32)> = numbers
+                    .chunks_exact(2)
+                    .map(|p| self.state.ctm.transform_point(p[0], p[1]))
+                    .collect();
+                let current = self.current_point();
+                let curve = match (operation.operator.as_str(), points.as_slice()) {
+                    ("c", [c1, c2, end]) => Some((*c1, *c2, *end)),
+                    // `v` starts at the current point, `y` ends at its end.
+                    ("v", [c2, end]) => current.map(|c1| (c1, *c2, *end)),
+                    ("y", [c1, end]) => Some((*c1, *end, *end)),
+                    _ => None,
+                };
+                if let Some((c1, c2, end)) = curve {
+                    self.path.push(PathSegment::curve_to(c1, c2, end));
+                }
+            }
+            "h" => self.path.push(PathSegment::close()),
+            "re" => {
+                if let [x, y, width, height] = operand_numbers(operands).as_slice() {
+                    let ctm = &self.state.ctm;
+                    let corners = [
+                        ctm.transform_point(*x, *y),
+                        ctm.transform_point(x + width, *y),
+                        ctm.transform_point(x + width, y + height),
+                        ctm.transform_point(*x, y + height),
+                    ];
+                    self.path
+                        .push(PathSegment::move_to(corners[0].0, corners[0].1));
+                    for (x, y) in &corners[1..] {
+                        self.path.push(PathSegment::line_to(*x, *y));
+                    }
+                    self.path.push(PathSegment::close());
+                }
+            }
+            "S" => self.paint(false, true, false, false),
+            "s" => self.paint(true, true, false, false),
+            "f" | "F" => self.paint(false, false, true, false),
+            "f*" => self.paint(false, false, true, true),
+            "B" => self.paint(false, true, true, false),
+            "B*" => self.paint(false, true, true, true),
+            "b" => self.paint(true, true, true, false),
+            "b*" => self.paint(true, true, true, true),
+            "n" => self.path.clear(),
+            _ => {}
+        }
+    }
+
+    /// The number of components of the colour space `name`, or `None` if
+    /// its colours are not gray, RGB or CMYK.
+    fn color_space_components(
+        &self,
+        resources: Option<&'a Dictionary>,
+        name: &[u8],
+    ) -> Option<usize> {
+        match name {
+            b"DeviceGray" | b"CalGray" => return Some(1),
+            b"DeviceRGB" | b"CalRGB" => return Some(3),
+            b"DeviceCMYK" => return Some(4),
+            _ => {}
+        }
+        let (_, space) = self.resource(resources, b"ColorSpace", name)?;
+        let space = space.as_array().ok()?;
+        match space.first()?.as_name().ok()? {
+            b"CalGray" => Some(1),
+            b"CalRGB" => Some(3),
+            b"ICCBased" => {
+                let profile = self.resolve(space.get(1)?).as_stream().ok()?;
+                let components = profile.dict.get(b"N").ok()?.as_i64().ok()?;
+                matches!(components, 1 | 3 | 4).then_some(components as usize)
+            }
+            _ => None,
+        }
+    }
+
+    /// The last point of the path under construction.
+    fn current_point(&self) -> Option<(f32, f32)> {
+        let mut start = None;
+        let mut current = None;
+        for segment in &self.path {
+            match segment.kind.as_str() {
+                "close" => current = start,
+                kind => {
+                    current = segment.points.last().copied();
+                    if kind == "move" {
+                        start = current;
+                    }
+                }
+            }
+        }
+        current
+    }
+
+    /// Ends the path under construction, recording it as a `Shape` painted
+    /// with the current graphics state.
+    fn paint(&mut self, close: bool, stroke: bool, fill: bool, even_odd: bool) {
+        let mut segments = std::mem::take(&mut self.path);
+        if close {
+            segments.push(PathSegment::close());
+        }
+        if segments
+            .iter()
+            .all(|s| s.kind == "move" || s.kind == "close")
+        {
+            return;
+        }
+        // Line widths and dashes are in user space; scale them to the page.
+        let ctm = &self.state.ctm;
+        let scale = (ctm.horizontal_scale() + ctm.vertical_scale()) / 2.0;
+        let state = &self.state;
+        self.contents.push(PageContent::Shape(Shape {
+            segments,
+            fill_color: fill.then(|| state.fill.value.clone()),
+            stroke_color: stroke.then(|| state.stroke.value.clone()),
+            line_width: state.line_width * scale,
+            dash: state.dash.iter().map(|d| d * scale).collect(),
+            dash_phase: state.dash_phase * scale,
+            line_cap: LINE_CAPS
+                .get(state.line_cap)
+                .unwrap_or(&LINE_CAPS[0])
+                .to_string(),
+            line_join: LINE_JOINS
+                .get(state.line_join)
+                .unwrap_or(&LINE_JOINS[0])
+                .to_string(),
+            even_odd,
+        }));
+    }
+
+    fn next_line(&mut self, tx: f32, ty: f32) {
+        self.text_line_matrix = Matrix::translate(tx, ty).multiply(&self.text_line_matrix);
+        self.text_matrix = self.text_line_matrix;
+    }
+
+    /// Shows a text run (the operand of one `Tj`/`TJ`/`'`/`"`), advancing the
+    /// text matrix glyph by glyph, and records it as a single `TextBlock`.
+    fn show_text(&mut self, items: &[TextItem]) {
+        let Some(font) = self.state.text.font.clone() else {
+            return;
+        };
+        let ts = &self.state.text;
+        let origin = Matrix::translate(0.0, ts.rise)
+            .multiply(&self.text_matrix)
+            .multiply(&self.state.ctm);
+        let (x, y) = origin.transform_point(0.0, 0.0);
+        // Text space lengths in points.
+        let scale = origin.vertical_scale();
+        let font_size = ts.font_size * scale;
+
+        // Distance from the start of the run to the current text position.
+        let (rise, ctm) = (ts.rise, self.state.ctm);
+        let offset = |text_matrix: &Matrix| {
+            let (px, py) = Matrix::translate(0.0, rise)
+                .multiply(text_matrix)
+                .multiply(&ctm)
+                .transform_point(0.0, 0.0);
+            (px - x).hypot(py - y)
+        };
+
+        let mut text = String::new();
+        let mut advances: Vec<f32> = Vec::new();
+        for item in items {
+            match item {
+                TextItem::Text(bytes) => {
+                    for glyph in font.decode(bytes) {
+                        let start = offset(&self.text_matrix);
+                        let mut advance = glyph.width * ts.font_size + ts.char_spacing;
+                        if glyph.is_space {
+                            advance += ts.word_spacing;
+                        }
+                        self.text_matrix = Matrix::translate(advance * ts.horizontal_scaling, 0.0)
+                            .multiply(&self.text_matrix);
+                        let advance = offset(&self.text_matrix) - start;
+                        // Ligatures share their glyph's advance; unmapped
+                        // glyphs widen the character before them.
+                        let count = glyph.text.chars().count();
+                        if count == 0 {
+                            if let Some(last) = advances.last_mut() {
+                                *last += advance;
+                            }
+                        }
+                        text.push_str(&glyph.text);
+                        advances.extend(std::iter::repeat_n(advance / count.max(1) as f32, count));
+                    }
+                }
+                TextItem::Adjust(amount) => {
+                    let start = offset(&self.text_matrix);
+                    let advance = -amount / 1000.0 * ts.font_size * ts.horizontal_scaling;
+                    self.text_matrix = Matrix::translate(advance, 0.0).multiply(&self.text_matrix);
+                    let advance = offset(&self.text_matrix) - start;
+                    if *amount < -TJ_SPACE_THRESHOLD && !text.is_empty() && !text.ends_with(' ') {
+                        text.push(' ');
+                        advances.push(advance);
+                    } else if let Some(last) = advances.last_mut() {
+                        *last += advance;
+                    }
+                }
+            }
+        }
+
+        if !text.trim().is_empty() {
+            self.contents.push(PageContent::Text(TextBlock {
+                text,
+                x,
+                y,
+                font_size,
+                font_name: Some(font.family().to_string()),
+                width: offset(&self.text_matrix),
+                base_font: Some(font.base_font.clone()),
+                embedded: font.embedded,
+                bold: font.bold,
+                italic: font.italic,
+                fill_color: self.state.fill.value.clone(),
+                stroke_color: self.state.stroke.value.clone(),
+                char_spacing: ts.char_spacing * scale,
+                word_spacing: ts.word_spacing * scale,
+                horizontal_scaling: ts.horizontal_scaling * 100.0,
+                rise: ts.rise * scale,
+                render_mode: ts.render_mode,
+                char_advances: advances,
+            }));
+        }
+    }
+
+    /// Records an image painted into the unit square of the current CTM.
+    fn push_image(&mut self, decoded: Option<(Vec<u8>, String)>) {
+        let Some((data, format)) = decoded else {
+            return;
+        };
+        let ctm = &self.state.ctm;
+        let corners = [
+            ctm.transform_point(0.0, 0.0),
+            ctm.transform_point(1.0, 0.0),
+            ctm.transform_point(0.0, 1.0),
+            ctm.transform_point(1.0, 1.0),
+        ];
+        let min_x = corners.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|p| p.0)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let max_y = corners
+            .iter()
+            .map(|p| p.1)
+            .fold(f32::NEG_INFINITY, f32::max);
+        self.contents.push(PageContent::Image(Image {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+            data,
+            format,
+            ..Default::default()
+        }));
+    }
+
+    fn draw_inline_image(&mut self, resources: Option<&'a Dictionary>, dict: &[u8], data: &[u8]) {
+        let Some(dict) = inline_image_dictionary(dict) else {
+            return;
+        };
+        let decoded = extract_image(self.doc, &dict, data, resources);
+        self.push_image(decoded);
+    }
+
+    fn draw_xobject(&mut self, resources: Option<&'a Dictionary>, name: &[u8]) {
+        let Some((id, object)) = self.resource(resources, b"XObject", name) else {
+            return;
+        };
+        let Ok(stream) = object.as_stream() else {
+            return;
+        };
+        match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Image") => {
+                let decoded = match id.and_then(|id| self.images.get(&id)) {
+                    Some(cached) => cached.clone(),
+                    None => {
+                        let decoded =
+                            extract_image(self.doc, &stream.dict, &stream.content, resources);
+                        if let Some(id) = id {
+                            self.images.insert(id, decoded.clone());
+                        }
+                        decoded
+                    }
+                };
+                self.push_image(decoded);
+                return;
+            }
+            Ok(b"Form") if self.depth < MAX_FORM_DEPTH => {}
+            _ => return,
+        }
+        let Ok(data) = stream.get_plain_content() else {
+            return;
+        };
+
+        let form_resources = stream
+            .dict
+            .get(b"Resources")
+            .ok()
+            .map(|o| self.resolve(o))
+            .and_then(|o| o.as_dict().ok())
+            .or(resources);
+
+        let saved = (self.state.clone(), self.stack.len());
+        if let Some(m) = stream
+            .dict
+            .get(b"Matrix")
+            .ok()
+            .map(|o| self.resolve(o))
+            .and_then(|o| o.as_array().ok())
+            .and_then(|a| Matrix::from_slice(&operand_numbers(a)))
+        {
+            self.state.ctm = m.multiply(&self.state.ctm);
+        }
+        self.depth += 1;
+        self.interpret(&data, form_resources);
+        self.depth -= 1;
+        self.stack.truncate(saved.1);
+        self.state = saved.0;
+    }
+}
+
+/// Parses the dictionary of an inline image, between `BI` and `ID`, into
+/// its XObject form.
+fn inline_image_dictionary(dict: &[u8]) -> Option<Dictionary> {
+    // The dictionary is parsed as the operands of a dummy operator.
+    let mut source = dict.to_vec();
+    source.extend_from_slice(b" BI");
+    let operands = Content::decode(&source)
+        .ok()?
+        .operations
+        .into_iter()
+        .next()?
+        .operands;
+    let mut raw = Dictionary::new();
+    for pair in operands.chunks_exact(2) {
+        if let Ok(key) = pair[0].as_name() {
+            raw.set(key.to_vec(), pair[1].clone());
+        }
+    }
+    Some(expand_inline_dictionary(&raw))
+}
+
+/// The named resources a content stream uses, and whether it paints
+/// anything.
+#[derive(Debug, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// The names used from each resource category (`Font`, `XObject`, ...).
+    pub names: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    /// Whether the stream shows text or paints paths, shadings or images.
+    pub paints: bool,
+}
+
+impl ResourceUsage {
+    fn add(&mut self, category: &[u8], name: Option<&Object>) {
+        if let Some(Ok(name)) = name.map(Object::as_name) {
+            self.names
+                .entry(category.to_vec())
+                .or_default()
+                .insert(name.to_vec());
+        }
+    }
+}
+
+/// Scans a decoded content stream for the resources it uses. Returns `None`
+/// when part of the stream cannot be parsed, as what it uses is then
+/// unknown.
+pub fn resource_usage(content: &[u8]) -> Option<ResourceUsage> {
+    let mut usage = ResourceUsage::default();
+    for segment in split_inline_images(content) {
+        match segment {
+            Segment::Operations(data) => {
+                for operation in Content::decode(data).ok()?.operations {
+                    let operands = &operation.operands;
+                    match operation.operator.as_str() {
+                        "Tf" => usage.add(b"Font", operands.first()),
+                        "gs" => usage.add(b"ExtGState", operands.first()),
+                        "cs" | "CS" => usage.add(b"ColorSpace", operands.first()),
+                        "scn" | "SCN" => usage.add(b"Pattern", operands.last()),
+                        "BDC" | "DP" => usage.add(b"Properties", operands.get(1)),
+                        "Do" => {
+                            usage.add(b"XObject", operands.first());
+                            usage.paints = true;
+                        }
+                        "sh" => {
+                            usage.add(b"Shading", operands.first());
+                            usage.paints = true;
+                        }
+                        "Tj" | "TJ" | "'" | "\"" | "S" | "s" | "f" | "F" | "f*" | "B" | "B*"
+                        | "b" | "b*" => usage.paints = true,
+                        _ => {}
+                    }
+                }
+            }
+            Segment::InlineImage { dict, .. } => {
+                usage.paints = true;
+                // A named colour space, possibly the base of an indexed one.
+                match inline_image_dictionary(dict)?.get(b"ColorSpace") {
+                    Ok(Object::Array(items)) => {
+                        for item in items {
+                            usage.add(b"ColorSpace", Some(item));
+                        }
+                    }
+                    other => usage.add(b"ColorSpace", other.ok()),
+                }
+            }
+        }
+    }
+    Some(usage)
+}
+
+enum Segment<'b> {
+    Operations(&'b [u8]),
+    InlineImage { dict: &'b [u8], data: &'b [u8] },
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || b"()<>[]{}/%".contains(&byte)
+}
+
+/// Whether `keyword` occurs at `pos` as a complete token, rather than as
+/// part of a name such as `/BI`.
+fn keyword_at(content: &[u8], pos: usize, keyword: &[u8]) -> bool {
+    content[pos..].starts_with(keyword)
+        && (pos == 0 || is_delimiter(content[pos - 1]) && content[pos - 1] != b'/')
+        && content
+            .get(pos + keyword.len())
+            .is_none_or(|&b| is_delimiter(b))
+}
+
+/// Splits a content stream into operator runs and inline images
+/// (`BI <dict> ID <data> EI`), skipping over strings and comments so that
+/// their contents are never mistaken for keywords.
+fn split_inline_images(content: &[u8]) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'(' => {
+                let mut depth = 0;
+                while i < content.len() {
+                    match content[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'B' if keyword_at(content, i, b"BI") => {
+                let dict_start = i + 2;
+                let Some(id_pos) =
+                    (dict_start..content.len()).find(|&p| keyword_at(content, p, b"ID"))
+                else {
+                    break;
+                };
+                // A single whitespace byte separates `ID` from the data.
+                let data_start = (id_pos + 3).min(content.len());
+                let data_end = (data_start..content.len())
+                    .find(|&p| {
+                        content[p - 1].is_ascii_whitespace() && keyword_at(content, p, b"EI")
+                    })
+                    .unwrap_or(content.len());
+                segments.push(Segment::Operations(&content[start..i]));
+                let data = &content[data_start..data_end.saturating_sub(1).max(data_start)];
+                segments.push(Segment::InlineImage {
+                    dict: &content[dict_start..id_pos],
+                    data,
+                });
+                i = (data_end + 2).min(content.len());
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    segments.push(Segment::Operations(&content[start..]));
+    segments
+}
+
+enum TextItem<'b> {
+    Text(&'b [u8]),
+    Adjust(f32),
+}
+
+fn first_number(operands: &[Object]) -> f32 {
+    operands
+        .first()
+        .and_then(|o| o.as_float().ok())
+        .unwrap_or(0.0)
+}
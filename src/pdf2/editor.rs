@@ -0,0 +1,424 @@
+//! Editing the pages of an existing PDF in place: rotating, reordering,
+//! deleting, inserting blank pages, duplicating and annotating. Page content
+//! is never re-rendered; only the page tree, page dictionaries and
+//! annotations change.
+//!
+//! The page tree is flattened when a document is opened: every page is
+//! made a direct child of the root `/Pages` node, with the attributes it
+//! inherited copied onto it, so that pages can be moved freely.
+
+use crate::annotations;
+use crate::encryption::load_document;
+use crate::parser::{inherited_attribute, INHERITABLE};
+use crate::pyfile::PdfFile;
+use crate::structure::Annotation;
+use lopdf::{Dictionary, Object, ObjectId};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to load PDF: {0}")]
+    Load(String),
+    #[error("Invalid page tree: {0}")]
+    PageTree(String),
+    #[error("page index {index} is out of range (the document has {count} pages)")]
+    PageIndex { index: usize, count: usize },
+    #[error("rotation must be a multiple of 90 degrees, not {0}")]
+    Rotation(i32),
+    #[error("invalid page order: {0}")]
+    Order(String),
+    #[error("invalid page size {width} x {height}")]
+    PageSize { width: f32, height: f32 },
+    #[error("invalid annotation: {0}")]
+    Annotation(String),
+}
+
+/// An open PDF whose pages are being edited. Pages are addressed by 0-based
+/// index.
+#[derive(Debug)]
+#[pyclass]
+pub struct PdfEditor {
+    doc: lopdf::Document,
+    pages_id: ObjectId,
+    /// The pages in order, mirroring the root node's `/Kids`.
+    pages: Vec<ObjectId>,
+}
+
+impl PdfEditor {
+    /// Opens the PDF file contents `bytes`, decrypting them with `password`
+    /// if they are encrypted.
+    pub fn load(bytes: &[u8], password: Option<&str>) -> Result<Self, EditError> {
+        let doc = load_document(bytes, password).map_err(|e| EditError::Load(e.to_string()))?;
+        Self::from_document(doc)
+    }
+
+    pub fn open(path: &Path, password: Option<&str>) -> Result<Self, EditError> {
+        Self::load(&std::fs::read(path)?, password)
+    }
+
+    /// Takes over `doc`, flattening its page tree.
+    pub fn from_document(mut doc: lopdf::Document) -> Result<Self, EditError> {
+        let pages_id = doc
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Pages"))
+            .and_then(Object::as_reference)
+            .map_err(|e| EditError::PageTree(e.to_string()))?;
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+
+        for &id in &pages {
+            let inherited: Vec<(&[u8], Object)> = INHERITABLE
+                .iter()
+                .filter_map(|&key| Some((key, inherited_attribute(&doc, id, key)?.clone())))
+                .collect();
+            let page = doc
+                .get_dictionary_mut(id)
+                .map_err(|e| EditError::PageTree(e.to_string()))?;
+            for (key, value) in inherited {
+                page.set(key, value);
+            }
+            page.set("Parent", pages_id);
+        }
+        let nodes = page_tree_nodes(&doc, pages_id);
+        for id in nodes {
+            doc.objects.remove(&id);
+        }
+        let root = doc
+            .get_dictionary_mut(pages_id)
+            .map_err(|e| EditError::PageTree(e.to_string()))?;
+        for key in INHERITABLE {
+            root.remove(key);
+        }
+
+        let mut editor = PdfEditor {
+            doc,
+            pages_id,
+            pages,
+        };
+        editor.update_page_tree();
+        Ok(editor)
+    }
+
+    /// The edited document. Objects that are no longer used, such as the
+    /// contents of deleted pages, are dropped.
+    pub fn into_document(mut self) -> lopdf::Document {
+        self.doc.prune_objects();
+        self.doc
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn page_id(&self, index: usize) -> Result<ObjectId, EditError> {
+        self.pages.get(index).copied().ok_or(EditError::PageIndex {
+            index,
+            count: self.pages.len(),
+        })
+    }
+
+    fn check_indices(&self, indices: &[usize]) -> Result<(), EditError> {
+        indices
+            .iter()
+            .try_for_each(|&index| self.page_id(index).map(|_| ()))
+    }
+
+    fn update_page_tree(&mut self) {
+        let count = self.pages.len() as i64;
+        let kids: Vec<Object> = self.pages.iter().map(|&id| id.into()).collect();
+        if let Ok(root) = self.doc.get_dictionary_mut(self.pages_id) {
+            root.set("Kids", kids);
+            root.set("Count", count);
+        }
+    }
+
+    /// The clockwise rotation of page `index`: 0, 90, 180 or 270.
+    pub fn rotation(&self, index: usize) -> Result<i32, EditError> {
+        let page = self
+            .doc
+            .get_dictionary(self.page_id(index)?)
+            .map_err(|e| EditError::PageTree(e.to_string()))?;
+        let rotation = page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0);
+        Ok(rotation.rem_euclid(360) as i32)
+    }
+
+    /// Rotates the pages at `indices` clockwise by `degrees`, a multiple of
+    /// 90 that may be negative.
+    pub fn rotate(&mut self, indices: &[usize], degrees: i32) -> Result<(), EditError> {
+        if degrees % 90 != 0 {
+            return Err(EditError::Rotation(degrees));
+        }
+        self.check_indices(indices)?;
+        for &index in indices.iter().collect::<HashSet<_>>() {
+            let rotation = (self.rotation(index)? + degrees.rem_euclid(360)).rem_euclid(360);
+            if let Ok(page) = self.doc.get_dictionary_mut(self.pages[index]) {
+                page.set("Rotate", rotation as i64);
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts the pages in a new order: `order[i]` is the current index of
+    /// the page that becomes page `i`. Every page must appear exactly once.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<(), EditError> {
+        self.check_indices(order)?;
+        let distinct: HashSet<usize> = order.iter().copied().collect();
+        if order.len() != self.pages.len() || distinct.len() != order.len() {
+            return Err(EditError::Order(format!(
+                "expected each of the {} pages exactly once",
+                self.pages.len()
+            )));
+        }
+        self.pages = order.iter().map(|&index| self.pages[index]).collect();
+        self.update_page_tree();
+        Ok(())
+    }
+
+    /// Deletes the pages at `indices`. Links and bookmarks leading to them
+    /// are left without a target page.
+    pub fn delete(&mut self, indices: &[usize]) -> Result<(), EditError> {
+        self.check_indices(indices)?;
+        let deleted: HashSet<ObjectId> = indices.iter().map(|&index| self.pages[index]).collect();
+        self.pages.retain(|id| !deleted.contains(id));
+        self.update_page_tree();
+        for id in &deleted {
+            self.doc.objects.remove(id);
+        }
+        self.doc.traverse_objects(|object| {
+            if let Object::Reference(id) = object {
+                if deleted.contains(id) {
+                    *object = Object::Null;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Inserts an empty `width` x `height` page (in points) so that it
+    /// becomes page `index`; `index` may equal the page count to append.
+    pub fn insert_blank(&mut self, index: usize, width: f32, height: f32) -> Result<(), EditError> {
+        if index > self.pages.len() {
+            return Err(EditError::PageIndex {
+                index,
+                count: self.pages.len(),
+            });
+        }
+        if !(width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite()) {
+            return Err(EditError::PageSize { width, height });
+        }
+        let page = self.doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", self.pages_id.into()),
+            (
+                "MediaBox",
+                vec![0.into(), 0.into(), width.into(), height.into()].into(),
+            ),
+            ("Resources", Dictionary::new().into()),
+        ]));
+        self.pages.insert(index, page);
+        self.update_page_tree();
+        Ok(())
+    }
+
+    /// Copies page `index` so that the copy becomes page `to`, by default
+    /// right after the original. The copy shares the original's content
+    /// and resources. Its annotations are copied too, except form field
+    /// widgets, which belong to a single page.
+    pub fn duplicate(&mut self, index: usize, to: Option<usize>) -> Result<(), EditError> {
+        let original = self.page_id(index)?;
+        let to = to.unwrap_or(index + 1);
+        if to > self.pages.len() {
+            return Err(EditError::PageIndex {
+                index: to,
+                count: self.pages.len(),
+            });
+        }
+        let mut page = self
+            .doc
+            .get_dictionary(original)
+            .map_err(|e| EditError::PageTree(e.to_string()))?
+            .clone();
+        let copy = self.doc.new_object_id();
+
+        let annotations = page
+            .get(b"Annots")
+            .ok()
+            .and_then(|a| self.doc.dereference(a).ok())
+            .and_then(|(_, a)| a.as_array().ok())
+            .cloned();
+        if let Some(annotations) = annotations {
+            let mut copies = Vec::new();
+            for annotation in &annotations {
+                let Ok((_, Object::Dictionary(annotation))) = self.doc.dereference(annotation)
+                else {
+                    continue;
+                };
+                if annotation.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget") {
+                    continue;
+                }
+                let mut annotation = annotation.clone();
+                if annotation.has(b"P") {
+                    annotation.set("P", copy);
+                }
+                copies.push(self.doc.add_object(annotation).into());
+            }
+            page.set("Annots", copies);
+        }
+
+        self.doc.objects.insert(copy, page.into());
+        self.pages.insert(to, copy);
+        self.update_page_tree();
+        Ok(())
+    }
+
+    /// Adds `annotation` to page `index`, drawn with its own appearance
+    /// stream, after the annotations the page already has.
+    pub fn add_annotation(
+        &mut self,
+        index: usize,
+        annotation: &Annotation,
+    ) -> Result<(), EditError> {
+        let page_id = self.page_id(index)?;
+        let mut annotations = self
+            .doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+            .and_then(|a| self.doc.dereference(a).ok())
+            .and_then(|(_, a)| a.as_array().ok())
+            .cloned()
+            .unwrap_or_default();
+        let id = annotations::add_annotation(&mut self.doc, annotation, page_id)
+            .map_err(EditError::Annotation)?;
+        annotations.push(id.into());
+        self.doc
+            .get_dictionary_mut(page_id)
+            .map_err(|e| EditError::PageTree(e.to_string()))?
+            .set("Annots", annotations);
+        Ok(())
+    }
+
+    /// The edited file's contents.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EditError> {
+        let mut doc = self.doc.clone();
+        doc.prune_objects();
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// The intermediate nodes of the page tree below `root`.
+fn page_tree_nodes(doc: &lopdf::Document, root: ObjectId) -> Vec<ObjectId> {
+    let mut nodes = Vec::new();
+    let mut visited = HashSet::from([root]);
+    let mut queue = vec![root];
+    while let Some(id) = queue.pop() {
+        let Ok(node) = doc.get_dictionary(id) else {
+            continue;
+        };
+        let kids = node.get(b"Kids").and_then(Object::as_array);
+        for kid in kids.into_iter().flatten() {
+            let Ok(kid) = kid.as_reference() else {
+                continue;
+            };
+            let is_node = doc
+                .get_dictionary(kid)
+                .and_then(|d| d.get(b"Type"))
+                .and_then(Object::as_name)
+                .is_ok_and(|t| t == b"Pages");
+            if is_node && visited.insert(kid) {
+                nodes.push(kid);
+                queue.push(kid);
+            }
+        }
+    }
+    nodes
+}
+
+fn edit_error(e: impl std::fmt::Display) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to edit PDF: {}", e))
+}
+
+#[pymethods]
+impl PdfEditor {
+    /// Opens a PDF file for editing, decrypting it with `password` if it is
+    /// encrypted.
+    #[new]
+    #[pyo3(signature = (input_path, password=None))]
+    fn py_new(input_path: PdfFile, password: Option<&str>) -> PyResult<Self> {
+        let bytes = input_path.read().map_err(edit_error)?;
+        Self::load(&bytes, password).map_err(edit_error)
+    }
+
+    /// Opens PDF file contents for editing.
+    #[staticmethod]
+    #[pyo3(signature = (data, password=None))]
+    fn from_bytes(data: &[u8], password: Option<&str>) -> PyResult<Self> {
+        Self::load(data, password).map_err(edit_error)
+    }
+
+    #[getter(page_count)]
+    fn py_page_count(&self) -> usize {
+        self.page_count()
+    }
+
+    fn __len__(&self) -> usize {
+        self.page_count()
+    }
+
+    #[pyo3(name = "rotation")]
+    fn py_rotation(&self, index: usize) -> PyResult<i32> {
+        self.rotation(index).map_err(edit_error)
+    }
+
+    #[pyo3(name = "rotate")]
+    fn py_rotate(&mut self, pages: Vec<usize>, degrees: i32) -> PyResult<()> {
+        self.rotate(&pages, degrees).map_err(edit_error)
+    }
+
+    #[pyo3(name = "reorder")]
+    fn py_reorder(&mut self, order: Vec<usize>) -> PyResult<()> {
+        self.reorder(&order).map_err(edit_error)
+    }
+
+    #[pyo3(name = "delete")]
+    fn py_delete(&mut self, pages: Vec<usize>) -> PyResult<()> {
+        self.delete(&pages).map_err(edit_error)
+    }
+
+    #[pyo3(name = "insert_blank")]
+    fn py_insert_blank(&mut self, index: usize, width: f32, height: f32) -> PyResult<()> {
+        self.insert_blank(index, width, height).map_err(edit_error)
+    }
+
+    #[pyo3(name = "duplicate", signature = (index, to=None))]
+    fn py_duplicate(&mut self, index: usize, to: Option<usize>) -> PyResult<()> {
+        self.duplicate(index, to).map_err(edit_error)
+    }
+
+    #[pyo3(name = "add_annotation")]
+    fn py_add_annotation(&mut self, page: usize, annotation: Annotation) -> PyResult<()> {
+        self.add_annotation(page, &annotation).map_err(edit_error)
+    }
+
+    /// Writes the edited document to `output_path`.
+    fn save(&self, output_path: PdfFile) -> PyResult<()> {
+        let bytes = self.to_bytes().map_err(edit_error)?;
+        output_path.write(&bytes).map_err(edit_error)
+    }
+
+    #[pyo3(name = "to_bytes")]
+    fn py_to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self.to_bytes().map_err(edit_error)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PdfEditor(page_count={})", self.page_count())
+    }
+}
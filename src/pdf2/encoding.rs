@@ -0,0 +1,452 @@
+//! Single-byte font encodings and glyph names used when a simple font has
+//! no `/ToUnicode` CMap.
+
+/// The base encodings a simple font's `/Encoding` entry can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseEncoding {
+    Standard,
+    WinAnsi,
+    MacRoman,
+    /// PDFDocEncoding, used by text strings outside content streams
+    /// (document information, outlines, form fields); never a font encoding.
+    PdfDoc,
+    /// No known mapping; codes are taken as Latin-1 code points.
+    Identity,
+}
+
+impl BaseEncoding {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"StandardEncoding" => Some(BaseEncoding::Standard),
+            b"WinAnsiEncoding" => Some(BaseEncoding::WinAnsi),
+            b"MacRomanEncoding" => Some(BaseEncoding::MacRoman),
+            _ => None,
+        }
+    }
+
+    pub fn decode(&self, code: u8) -> Option<char> {
+        match self {
+            BaseEncoding::Standard => standard(code),
+            BaseEncoding::WinAnsi => win_ansi(code),
+            BaseEncoding::MacRoman => mac_roman(code),
+            BaseEncoding::PdfDoc => pdf_doc(code),
+            BaseEncoding::Identity => Some(code as char),
+        }
+    }
+
+    /// The code that `decode`s to `ch`, preferring the lowest one.
+    pub fn encode(&self, ch: char) -> Option<u8> {
+        (0..=255u8).find(|&code| self.decode(code) == Some(ch))
+    }
+}
+
+const WIN_ANSI_80: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+const MAC_ROMAN_80: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü\
+†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø\
+¿¡¬√ƒ≈∆«»…\u{a0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄¤‹›ﬁﬂ\
+‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{f8ff}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ";
+
+fn win_ansi(code: u8) -> Option<char> {
+    match code {
+        0x20..=0x7E => Some(code as char),
+        0x80..=0x9F => WIN_ANSI_80[(code - 0x80) as usize],
+        0xAD => Some('-'),
+        0xA0..=0xFF => Some(code as char),
+        _ => None,
+    }
+}
+
+const PDF_DOC_18: &str = "˘ˇˆ˙˝˛˚˜";
+
+const PDF_DOC_80: &str = "•†‡…—–ƒ⁄‹›−‰„“”‘’‚™ﬁﬂŁŒŠŸŽıłœšž";
+
+fn pdf_doc(code: u8) -> Option<char> {
+    match code {
+        0x18..=0x1F => PDF_DOC_18.chars().nth((code - 0x18) as usize),
+        0x80..=0x9E => PDF_DOC_80.chars().nth((code - 0x80) as usize),
+        0xA0 => Some('€'),
+        0x09 | 0x0A | 0x0D | 0x20..=0x7E | 0xA1..=0xFF => Some(code as char),
+        _ => None,
+    }
+}
+
+fn mac_roman(code: u8) -> Option<char> {
+    match code {
+        0x20..=0x7E => Some(code as char),
+        0x80..=0xFF => MAC_ROMAN_80.chars().nth((code - 0x80) as usize),
+        _ => None,
+    }
+}
+
+fn standard(code: u8) -> Option<char> {
+    let c = match code {
+        0x27 => '’',
+        0x60 => '‘',
+        0x20..=0x7E => code as char,
+        0xA1 => '¡',
+        0xA2 => '¢',
+        0xA3 => '£',
+        0xA4 => '⁄',
+        0xA5 => '¥',
+        0xA6 => 'ƒ',
+        0xA7 => '§',
+        0xA8 => '¤',
+        0xA9 => '\'',
+        0xAA => '“',
+        0xAB => '«',
+        0xAC => '‹',
+        0xAD => '›',
+        0xAE => 'ﬁ',
+        0xAF => 'ﬂ',
+        0xB1 => '–',
+        0xB2 => '†',
+        0xB3 => '‡',
+        0xB4 => '·',
+        0xB6 => '¶',
+        0xB7 => '•',
+        0xB8 => '‚',
+        0xB9 => '„',
+        0xBA => '”',
+        0xBB => '»',
+        0xBC => '…',
+        0xBD => '‰',
+        0xBF => '¿',
+        0xC1 => '`',
+        0xC2 => '´',
+        0xC3 => 'ˆ',
+        0xC4 => '˜',
+        0xC5 => '¯',
+        0xC6 => '˘',
+        0xC7 => '˙',
+        0xC8 => '¨',
+        0xCA => '˚',
+        0xCB => '¸',
+        0xCD => '˝',
+        0xCE => '˛',
+        0xCF => 'ˇ',
+        0xD0 => '—',
+        0xE1 => 'Æ',
+        0xE3 => 'ª',
+        0xE8 => 'Ł',
+        0xE9 => 'Ø',
+        0xEA => 'Œ',
+        0xEB => 'º',
+        0xF1 => 'æ',
+        0xF5 => 'ı',
+        0xF8 => 'ł',
+        0xF9 => 'ø',
+        0xFA => 'œ',
+        0xFB => 'ß',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Glyph names from the Adobe Glyph List that commonly appear in
+/// `/Differences` arrays. Single ASCII letters are handled separately.
+const GLYPH_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("exclam", '!'),
+    ("quotedbl", '"'),
+    ("numbersign", '#'),
+    ("dollar", '$'),
+    ("percent", '%'),
+    ("ampersand", '&'),
+    ("quotesingle", '\''),
+    ("parenleft", '('),
+    ("parenright", ')'),
+    ("asterisk", '*'),
+    ("plus", '+'),
+    ("comma", ','),
+    ("hyphen", '-'),
+    ("minus", '−'),
+    ("period", '.'),
+    ("slash", '/'),
+    ("zero", '0'),
+    ("one", '1'),
+    ("two", '2'),
+    ("three", '3'),
+    ("four", '4'),
+    ("five", '5'),
+    ("six", '6'),
+    ("seven", '7'),
+    ("eight", '8'),
+    ("nine", '9'),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("less", '<'),
+    ("equal", '='),
+    ("greater", '>'),
+    ("question", '?'),
+    ("at", '@'),
+    ("bracketleft", '['),
+    ("backslash", '\\'),
+    ("bracketright", ']'),
+    ("asciicircum", '^'),
+    ("underscore", '_'),
+    ("grave", '`'),
+    ("braceleft", '{'),
+    ("bar", '|'),
+    ("braceright", '}'),
+    ("asciitilde", '~'),
+    ("bullet", '•'),
+    ("endash", '–'),
+    ("emdash", '—'),
+    ("quoteleft", '‘'),
+    ("quoteright", '’'),
+    ("quotedblleft", '“'),
+    ("quotedblright", '”'),
+    ("quotesinglbase", '‚'),
+    ("quotedblbase", '„'),
+    ("guillemotleft", '«'),
+    ("guillemotright", '»'),
+    ("guilsinglleft", '‹'),
+    ("guilsinglright", '›'),
+    ("ellipsis", '…'),
+    ("dagger", '†'),
+    ("daggerdbl", '‡'),
+    ("perthousand", '‰'),
+    ("trademark", '™'),
+    ("copyright", '©'),
+    ("registered", '®'),
+    ("degree", '°'),
+    ("section", '§'),
+    ("paragraph", '¶'),
+    ("periodcentered", '·'),
+    ("exclamdown", '¡'),
+    ("questiondown", '¿'),
+    ("cent", '¢'),
+    ("sterling", '£'),
+    ("yen", '¥'),
+    ("Euro", '€'),
+    ("currency", '¤'),
+    ("florin", 'ƒ'),
+    ("brokenbar", '¦'),
+    ("dieresis", '¨'),
+    ("macron", '¯'),
+    ("acute", '´'),
+    ("cedilla", '¸'),
+    ("circumflex", 'ˆ'),
+    ("tilde", '˜'),
+    ("ordfeminine", 'ª'),
+    ("ordmasculine", 'º'),
+    ("logicalnot", '¬'),
+    ("plusminus", '±'),
+    ("multiply", '×'),
+    ("divide", '÷'),
+    ("mu", 'µ'),
+    ("onehalf", '½'),
+    ("onequarter", '¼'),
+    ("threequarters", '¾'),
+    ("onesuperior", '¹'),
+    ("twosuperior", '²'),
+    ("threesuperior", '³'),
+    ("fi", 'ﬁ'),
+    ("fl", 'ﬂ'),
+    ("ff", 'ﬀ'),
+    ("ffi", 'ﬃ'),
+    ("ffl", 'ﬄ'),
+    ("dotlessi", 'ı'),
+    ("germandbls", 'ß'),
+    ("AE", 'Æ'),
+    ("ae", 'æ'),
+    ("OE", 'Œ'),
+    ("oe", 'œ'),
+    ("Oslash", 'Ø'),
+    ("oslash", 'ø'),
+    ("Lslash", 'Ł'),
+    ("lslash", 'ł'),
+    ("Eth", 'Ð'),
+    ("eth", 'ð'),
+    ("Thorn", 'Þ'),
+    ("thorn", 'þ'),
+    ("Scaron", 'Š'),
+    ("scaron", 'š'),
+    ("Zcaron", 'Ž'),
+    ("zcaron", 'ž'),
+    ("Ydieresis", 'Ÿ'),
+    ("nbspace", '\u{a0}'),
+    ("sfthyphen", '\u{ad}'),
+];
+
+/// Accent suffixes used by the composite Latin-1 glyph names
+/// (`Aacute`, `ntilde`, ...), paired with the combining mark they add.
+const ACCENTS: &[(&str, char)] = &[
+    ("grave", '\u{300}'),
+    ("acute", '\u{301}'),
+    ("circumflex", '\u{302}'),
+    ("tilde", '\u{303}'),
+    ("dieresis", '\u{308}'),
+    ("ring", '\u{30a}'),
+    ("cedilla", '\u{327}'),
+    ("caron", '\u{30c}'),
+];
+
+const COMPOSED: &[(char, char, char)] = &[
+    ('A', '\u{300}', 'À'),
+    ('A', '\u{301}', 'Á'),
+    ('A', '\u{302}', 'Â'),
+    ('A', '\u{303}', 'Ã'),
+    ('A', '\u{308}', 'Ä'),
+    ('A', '\u{30a}', 'Å'),
+    ('C', '\u{327}', 'Ç'),
+    ('E', '\u{300}', 'È'),
+    ('E', '\u{301}', 'É'),
+    ('E', '\u{302}', 'Ê'),
+    ('E', '\u{308}', 'Ë'),
+    ('I', '\u{300}', 'Ì'),
+    ('I', '\u{301}', 'Í'),
+    ('I', '\u{302}', 'Î'),
+    ('I', '\u{308}', 'Ï'),
+    ('N', '\u{303}', 'Ñ'),
+    ('O', '\u{300}', 'Ò'),
+    ('O', '\u{301}', 'Ó'),
+    ('O', '\u{302}', 'Ô'),
+    ('O', '\u{303}', 'Õ'),
+    ('O', '\u{308}', 'Ö'),
+    ('U', '\u{300}', 'Ù'),
+    ('U', '\u{301}', 'Ú'),
+    ('U', '\u{302}', 'Û'),
+    ('U', '\u{308}', 'Ü'),
+    ('Y', '\u{301}', 'Ý'),
+    ('a', '\u{300}', 'à'),
+    ('a', '\u{301}', 'á'),
+    ('a', '\u{302}', 'â'),
+    ('a', '\u{303}', 'ã'),
+    ('a', '\u{308}', 'ä'),
+    ('a', '\u{30a}', 'å'),
+    ('c', '\u{327}', 'ç'),
+    ('e', '\u{300}', 'è'),
+    ('e', '\u{301}', 'é'),
+    ('e', '\u{302}', 'ê'),
+    ('e', '\u{308}', 'ë'),
+    ('i', '\u{300}', 'ì'),
+    ('i', '\u{301}', 'í'),
+    ('i', '\u{302}', 'î'),
+    ('i', '\u{308}', 'ï'),
+    ('n', '\u{303}', 'ñ'),
+    ('o', '\u{300}', 'ò'),
+    ('o', '\u{301}', 'ó'),
+    ('o', '\u{302}', 'ô'),
+    ('o', '\u{303}', 'õ'),
+    ('o', '\u{308}', 'ö'),
+    ('u', '\u{300}', 'ù'),
+    ('u', '\u{301}', 'ú'),
+    ('u', '\u{302}', 'û'),
+    ('u', '\u{308}', 'ü'),
+    ('y', '\u{301}', 'ý'),
+    ('y', '\u{308}', 'ÿ'),
+    ('S', '\u{30c}', 'Š'),
+    ('s', '\u{30c}', 'š'),
+    ('Z', '\u{30c}', 'Ž'),
+    ('z', '\u{30c}', 'ž'),
+];
+
+/// Maps a glyph name to the text it represents.
+///
+/// Handles `uniXXXX` / `uXXXX` names, plain ASCII letters, the common
+/// Adobe Glyph List names and accented Latin letters such as `eacute`.
+pub fn glyph_name_to_string(name: &str) -> Option<String> {
+    // Suffixes such as `.sc` or `.alt` name variants of the same character.
+    let name = name.split('.').next().unwrap_or(name);
+    if name.len() == 1 && name.as_bytes()[0].is_ascii_alphabetic() {
+        return Some(name.to_string());
+    }
+    if let Some(hex) = name.strip_prefix("uni") {
+        if hex.len() >= 4 && hex.len() % 4 == 0 {
+            let units: Option<Vec<u16>> = (0..hex.len())
+                .step_by(4)
+                .map(|i| u16::from_str_radix(&hex[i..i + 4], 16).ok())
+                .collect();
+            return units.and_then(|u| String::from_utf16(&u).ok());
+        }
+    }
+    if let Some(hex) = name.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                return Some(c.to_string());
+            }
+        }
+    }
+    if let Some((_, c)) = GLYPH_NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(c.to_string());
+    }
+    for (suffix, mark) in ACCENTS {
+        if let Some(base) = name.strip_suffix(suffix) {
+            let mut chars = base.chars();
+            if let (Some(b), None) = (chars.next(), chars.next()) {
+                if let Some((_, _, composed)) =
+                    COMPOSED.iter().find(|(cb, cm, _)| *cb == b && cm == mark)
+                {
+                    return Some(composed.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Advance widths (in 1/1000 em) of the printable ASCII range in Helvetica.
+///
+/// Used as a fallback for the non-embedded standard 14 fonts, which are
+/// allowed to omit `/Widths`.
+pub const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Returns the width of `code` in one of the standard 14 fonts, or `None`
+/// if `base_font` is not a standard font we have metrics for.
+pub fn standard_font_width(base_font: &str, code: u8) -> Option<f32> {
+    let base_font = base_font.split('+').next_back().unwrap_or(base_font);
+    if base_font.starts_with("Courier") {
+        return Some(600.0);
+    }
+    if base_font.starts_with("Helvetica")
+        || base_font.starts_with("Arial")
+        || base_font.starts_with("Times")
+    {
+        return Some(match code {
+            0x20..=0x7E => HELVETICA_WIDTHS[(code - 0x20) as usize] as f32,
+            _ => 556.0,
+        });
+    }
+    None
+}
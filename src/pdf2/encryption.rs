@@ -1,50 +1,532 @@
-use lopdf::encryption::crypt_filters::{Aes128CryptFilter, CryptFilter};
-use lopdf::{Document, EncryptionState, EncryptionVersion, Permissions};
+use crate::pyfile::PdfFile;
+use lopdf::encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter};
+use lopdf::encryption::{decrypt_object, PasswordAlgorithm};
+use lopdf::xref::XrefEntry;
+use lopdf::{
+    Document, EncryptionState, EncryptionVersion, Object, ObjectId, ObjectStream, Permissions,
+    Reader,
+};
+use md5::{Digest, Md5};
 use pyo3::prelude::*;
-use std::collections::BTreeMap;
+use pyo3::types::PyBytes;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
+#[derive(Debug, thiserror::Error)]
+pub enum DecryptError {
+    #[error("Failed to load PDF: {0}")]
+    Load(String),
+    #[error("Incorrect or missing password")]
+    Password,
+    #[error("Failed to decrypt PDF: {0}")]
+    Decrypt(String),
+}
+
+/// Loads a PDF, decrypting it with `password` (user or owner) if it is
+/// encrypted. Without a password, the empty user password is tried.
+pub fn load_document(bytes: &[u8], password: Option<&str>) -> Result<Document, DecryptError> {
+    let mut doc = Document::load_mem(bytes).map_err(|e| DecryptError::Load(e.to_string()))?;
+    let Ok(encrypt_id) = doc.trailer.get(b"Encrypt").and_then(Object::as_reference) else {
+        return Ok(doc);
+    };
+
+    let algorithm =
+        PasswordAlgorithm::try_from(&doc).map_err(|e| DecryptError::Decrypt(e.to_string()))?;
+    let password = algorithm
+        .sanitize_password(password.unwrap_or(""))
+        .map_err(|_| DecryptError::Password)?;
+    let key_password = if algorithm
+        .authenticate_user_password(&doc, &password)
+        .is_ok()
+    {
+        password
+    } else if algorithm
+        .authenticate_owner_password(&doc, &password)
+        .is_ok()
+    {
+        user_password_from_owner(&doc, &password).unwrap_or(password)
+    } else {
+        return Err(DecryptError::Password);
+    };
+    // lopdf loads and decrypts the objects itself only when the empty user
+    // password opens the file.
+    if doc.encryption_state.is_none() {
+        let state = EncryptionState::decode(&doc, &key_password)
+            .map_err(|e| DecryptError::Decrypt(e.to_string()))?;
+        read_encrypted_objects(&mut doc, bytes, &state, encrypt_id);
+    }
+    doc.trailer.remove(b"Encrypt");
+    doc.objects.remove(&encrypt_id);
+    doc.encryption_state = None;
+    Ok(doc)
+}
+
+/// Reads the objects of an encrypted file into `doc`, decrypting each one,
+/// then adds the objects of its object streams, which can only be unpacked
+/// once the streams are decrypted.
+fn read_encrypted_objects(
+    doc: &mut Document,
+    bytes: &[u8],
+    state: &EncryptionState,
+    encrypt_id: ObjectId,
+) {
+    // Offsets count from the header, as lopdf's own reader does.
+    let start = bytes.windows(5).position(|w| w == b"%PDF-").unwrap_or(0);
+    let mut reader = Reader {
+        buffer: &bytes[start..],
+        document: Document::new(),
+        encryption_state: None,
+        raw_objects: BTreeMap::new(),
+    };
+    reader.document.reference_table = doc.reference_table.clone();
+    for (&number, entry) in &doc.reference_table.entries {
+        let XrefEntry::Normal { generation, .. } = *entry else {
+            continue;
+        };
+        let id = (number, generation);
+        if id == encrypt_id {
+            continue;
+        }
+        if let Ok(mut object) = reader.get_object(id, &mut HashSet::new()) {
+            // Like lopdf, keep objects with strings that fail to decrypt.
+            let _ = decrypt_object(state, id, &mut object);
+            doc.objects.insert(id, object);
+        }
+    }
+
+    let mut compressed = Vec::new();
+    for object in doc.objects.values_mut() {
+        if let Ok(stream) = object.as_stream_mut() {
+            if stream.dict.has_type(b"ObjStm") {
+                compressed.extend(
+                    ObjectStream::new(stream)
+                        .map(|s| s.objects)
+                        .unwrap_or_default(),
+                );
+            }
+        }
+    }
+    // Objects stored directly take precedence over compressed copies.
+    for (id, object) in compressed {
+        doc.objects.entry(id).or_insert(object);
+    }
+}
+
+/// Padding for passwords of revision 4 and earlier (ISO 32000-2, 7.6.4.3.2).
+const PAD_BYTES: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// Recovers the padded user password from the owner password for security
+/// handlers of revision 4 and earlier (Algorithm 7), since the file key is
+/// derived from the user password. Later revisions derive it from either.
+fn user_password_from_owner(doc: &Document, owner_password: &[u8]) -> Option<Vec<u8>> {
+    let dict = doc.get_encrypted().ok()?;
+    let revision = dict.get(b"R").and_then(Object::as_i64).ok()?;
+    if revision > 4 {
+        return None;
+    }
+    let owner_value = dict.get(b"O").and_then(Object::as_str).ok()?;
+    let length = dict.get(b"Length").and_then(Object::as_i64).unwrap_or(40) as usize;
+    let n = if revision >= 3 {
+        (length / 8).min(16)
+    } else {
+        5
+    };
+
+    let len = owner_password.len().min(32);
+    let mut hasher = Md5::new();
+    hasher.update(&owner_password[..len]);
+    hasher.update(&PAD_BYTES[..32 - len]);
+    let mut hash = hasher.finalize();
+    if revision >= 3 {
+        for _ in 0..50 {
+            hash = Md5::digest(hash);
+        }
+    }
+    let key = &hash[..n];
+
+    if revision == 2 {
+        return Some(rc4(key, owner_value));
+    }
+    let mut result = owner_value.to_vec();
+    for i in (0..=19u8).rev() {
+        let round_key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        result = rc4(&round_key, &result);
+    }
+    Some(result)
+}
+
+/// Writes a decrypted copy of `input_path` to `output_path`. Files that are
+/// not encrypted are copied unchanged.
 #[pyfunction]
-pub fn encrypt_pdf(
-    input_path: String,
-    output_path: String,
-    user_password: String,
-    owner_password: Option<String>,
-) -> PyResult<()> {
-    let mut doc = Document::load(&input_path).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to load PDF: {}", e))
+pub fn decrypt_pdf(input_path: PdfFile, output_path: PdfFile, password: String) -> PyResult<()> {
+    let bytes = input_path.read().map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to read PDF: {}", e))
     })?;
+    let mut doc = load_document(&bytes, Some(&password))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("{}", e)))?;
 
-    let owner_pwd = owner_password.as_deref().unwrap_or(&user_password);
-
-    let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
-    let encryption_version = EncryptionVersion::V4 {
-        document: &doc,
-        encrypt_metadata: true,
-        crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
-        stream_filter: b"StdCF".to_vec(),
-        string_filter: b"StdCF".to_vec(),
-        owner_password: owner_pwd,
-        user_password: &user_password,
-        permissions: Permissions::all(),
+    let save_error = |e: &dyn std::fmt::Display| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Failed to save decrypted PDF: {}",
+            e
+        ))
     };
+    let mut decrypted = Vec::new();
+    doc.save_to(&mut decrypted).map_err(|e| save_error(&e))?;
+    output_path.write(&decrypted).map_err(|e| save_error(&e))
+}
+
+/// What a user who opens an encrypted document with the user password may
+/// do. The owner password always grants everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[pyclass]
+pub struct DocumentPermissions {
+    #[pyo3(get, set)]
+    pub print: bool,
+    /// Print at full quality; without it printing is limited to a
+    /// low-resolution rendering. Requires `print`.
+    #[pyo3(get, set)]
+    pub print_high_quality: bool,
+    #[pyo3(get, set)]
+    pub modify: bool,
+    #[pyo3(get, set)]
+    pub copy: bool,
+    /// Add or modify annotations, and fill in form fields.
+    #[pyo3(get, set)]
+    pub annotate: bool,
+    /// Fill in existing form fields, even if `annotate` is off.
+    #[pyo3(get, set)]
+    pub fill_forms: bool,
+    /// Extract text and graphics for assistive technology.
+    #[pyo3(get, set)]
+    pub accessibility: bool,
+    /// Insert, rotate or delete pages and create bookmarks, even if
+    /// `modify` is off.
+    #[pyo3(get, set)]
+    pub assemble: bool,
+}
+
+impl Default for DocumentPermissions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl DocumentPermissions {
+    /// Rejects combinations a reader cannot honour.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.print_high_quality && !self.print {
+            return Err("print_high_quality requires print".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        *self == Self::all()
+    }
+
+    /// The flags written to the encryption dictionary's `/P` entry.
+    pub fn to_flags(&self) -> Permissions {
+        let mut flags = Permissions::empty();
+        let bits = [
+            (self.print, Permissions::PRINTABLE),
+            (
+                self.print_high_quality,
+                Permissions::PRINTABLE_IN_HIGH_QUALITY,
+            ),
+            (self.modify, Permissions::MODIFIABLE),
+            (self.copy, Permissions::COPYABLE),
+            (self.annotate, Permissions::ANNOTABLE),
+            (self.fill_forms, Permissions::FILLABLE),
+            (self.accessibility, Permissions::COPYABLE_FOR_ACCESSIBILITY),
+            (self.assemble, Permissions::ASSEMBLABLE),
+        ];
+        for (allowed, flag) in bits {
+            flags.set(flag, allowed);
+        }
+        flags
+    }
+}
 
-    let state = EncryptionState::try_from(encryption_version).map_err(|e| {
+#[pymethods]
+impl DocumentPermissions {
+    #[new]
+    #[pyo3(signature = (
+        print=true,
+        print_high_quality=true,
+        modify=true,
+        copy=true,
+        annotate=true,
+        fill_forms=true,
+        accessibility=true,
+        assemble=true,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        print: bool,
+        print_high_quality: bool,
+        modify: bool,
+        copy: bool,
+        annotate: bool,
+        fill_forms: bool,
+        accessibility: bool,
+        assemble: bool,
+    ) -> Self {
+        DocumentPermissions {
+            print,
+            print_high_quality,
+            modify,
+            copy,
+            annotate,
+            fill_forms,
+            accessibility,
+            assemble,
+        }
+    }
+
+    /// Every permission granted.
+    #[staticmethod]
+    pub fn all() -> Self {
+        Self::new(true, true, true, true, true, true, true, true)
+    }
+
+    /// Every permission withheld.
+    #[staticmethod]
+    pub fn none() -> Self {
+        Self::new(false, false, false, false, false, false, false, false)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DocumentPermissions(print={}, print_high_quality={}, modify={}, copy={}, \
+             annotate={}, fill_forms={}, accessibility={}, assemble={})",
+            self.print,
+            self.print_high_quality,
+            self.modify,
+            self.copy,
+            self.annotate,
+            self.fill_forms,
+            self.accessibility,
+            self.assemble
+        )
+    }
+}
+
+/// The security handlers `encrypt_pdf` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncryptionAlgorithm {
+    /// AES-128 (V4, R4), readable by PDF 1.6 readers.
+    #[default]
+    Aes128,
+    /// AES-256 (V5, R6) as defined by ISO 32000-2.
+    Aes256,
+}
+
+impl EncryptionAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "aes-128" | "aes128" => Some(EncryptionAlgorithm::Aes128),
+            "aes-256" | "aes256" => Some(EncryptionAlgorithm::Aes256),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptError {
+    #[error("Invalid permissions: {0}")]
+    Permissions(String),
+    #[error("Failed to load PDF: {0}")]
+    Load(String),
+    #[error("Failed to encrypt PDF: {0}")]
+    Encrypt(String),
+    #[error("Failed to save encrypted PDF: {0}")]
+    Save(String),
+}
+
+/// How `encrypt_bytes` protects a document.
+#[derive(Debug, Clone)]
+pub struct EncryptOptions<'a> {
+    pub user_password: &'a str,
+    /// Defaults to the user password.
+    pub owner_password: Option<&'a str>,
+    pub permissions: DocumentPermissions,
+    pub algorithm: EncryptionAlgorithm,
+    pub encrypt_metadata: bool,
+}
+
+/// Encrypts the PDF file contents `data`, returning the encrypted file.
+pub fn encrypt_bytes(data: &[u8], options: &EncryptOptions) -> Result<Vec<u8>, EncryptError> {
+    let permissions = options.permissions;
+    permissions.validate().map_err(EncryptError::Permissions)?;
+    let user_password = options.user_password;
+    let owner_pwd = options.owner_password.unwrap_or(user_password);
+    if !permissions.is_unrestricted() && owner_pwd == user_password {
+        // Anyone with the user password would also be the owner.
+        return Err(EncryptError::Permissions(
+            "restricting permissions requires an owner password \
+             different from the user password"
+                .to_string(),
+        ));
+    }
+
+    let mut doc = Document::load_mem(data).map_err(|e| EncryptError::Load(e.to_string()))?;
+
+    let file_encryption_key: [u8; 32] = rand::random();
+    let encryption_version = match options.algorithm {
+        EncryptionAlgorithm::Aes128 => {
+            let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+            EncryptionVersion::V4 {
+                document: &doc,
+                encrypt_metadata: options.encrypt_metadata,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password: owner_pwd,
+                user_password,
+                permissions: permissions.to_flags(),
+            }
+        }
+        EncryptionAlgorithm::Aes256 => {
+            let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+            EncryptionVersion::V5 {
+                encrypt_metadata: options.encrypt_metadata,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+                file_encryption_key: &file_encryption_key,
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password: owner_pwd,
+                user_password,
+                permissions: permissions.to_flags(),
+            }
+        }
+    };
+
+    let state = EncryptionState::try_from(encryption_version)
+        .map_err(|e| EncryptError::Encrypt(format!("failed to create encryption state: {}", e)))?;
+    doc.encrypt(&state)
+        .map_err(|e| EncryptError::Encrypt(e.to_string()))?;
+    if options.algorithm == EncryptionAlgorithm::Aes256 && doc.version.as_str() < "2.0" {
+        doc.version = "2.0".to_string();
+    }
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)
+        .map_err(|e| EncryptError::Save(e.to_string()))?;
+    Ok(output)
+}
+
+/// Builds the `encrypt_bytes` options from the arguments of the Python
+/// functions.
+fn encrypt_options<'a>(
+    user_password: &'a str,
+    owner_password: Option<&'a str>,
+    permissions: Option<DocumentPermissions>,
+    algorithm: &str,
+    encrypt_metadata: bool,
+) -> PyResult<EncryptOptions<'a>> {
+    let algorithm = EncryptionAlgorithm::from_name(algorithm).ok_or_else(|| {
         PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-            "Failed to create encryption state: {}",
-            e
+            "Unknown encryption algorithm '{}', expected 'aes-128' or 'aes-256'",
+            algorithm
         ))
     })?;
-    doc.encrypt(&state).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to encrypt PDF: {}", e))
-    })?;
+    Ok(EncryptOptions {
+        user_password,
+        owner_password,
+        permissions: permissions.unwrap_or_default(),
+        algorithm,
+        encrypt_metadata,
+    })
+}
 
-    doc.save(&output_path).map_err(|e| {
+#[pyfunction]
+#[pyo3(signature = (
+    input_path,
+    output_path,
+    user_password,
+    owner_password=None,
+    permissions=None,
+    algorithm="aes-128",
+    encrypt_metadata=true,
+))]
+pub fn encrypt_pdf(
+    input_path: PdfFile,
+    output_path: PdfFile,
+    user_password: String,
+    owner_password: Option<String>,
+    permissions: Option<DocumentPermissions>,
+    algorithm: &str,
+    encrypt_metadata: bool,
+) -> PyResult<()> {
+    let options = encrypt_options(
+        &user_password,
+        owner_password.as_deref(),
+        permissions,
+        algorithm,
+        encrypt_metadata,
+    )?;
+    let data = input_path.read().map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to load PDF: {}", e))
+    })?;
+    let encrypted = encrypt_bytes(&data, &options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    output_path.write(&encrypted).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
             "Failed to save encrypted PDF: {}",
             e
         ))
-    })?;
+    })
+}
 
-    Ok(())
+/// Like `encrypt_pdf`, taking and returning the file contents.
+#[pyfunction]
+#[pyo3(name = "encrypt_bytes", signature = (
+    data,
+    user_password,
+    owner_password=None,
+    permissions=None,
+    algorithm="aes-128",
+    encrypt_metadata=true,
+))]
+pub fn py_encrypt_bytes<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    user_password: String,
+    owner_password: Option<String>,
+    permissions: Option<DocumentPermissions>,
+    algorithm: &str,
+    encrypt_metadata: bool,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let options = encrypt_options(
+        &user_password,
+        owner_password.as_deref(),
+        permissions,
+        algorithm,
+        encrypt_metadata,
+    )?;
+    let encrypted = encrypt_bytes(data, &options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    Ok(PyBytes::new(py, &encrypted))
 }
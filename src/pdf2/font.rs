@@ -0,0 +1,816 @@
+use crate::encoding::{glyph_name_to_string, standard_font_width, BaseEncoding};
+use lopdf::{Dictionary, Document, Object};
+use std::collections::HashMap;
+
+/// A single character code decoded from a shown string.
+#[derive(Debug, Clone)]
+pub struct Glyph {
+    /// The Unicode text the code maps to; empty if it could not be mapped.
+    pub text: String,
+    /// Horizontal displacement in text space units (already divided by 1000,
+    /// or scaled by the `/FontMatrix` for Type 3 fonts).
+    pub width: f32,
+    /// Whether the code is the single-byte code 32, to which word spacing
+    /// (`Tw`) applies.
+    pub is_space: bool,
+}
+
+/// `(byte length, low, high)` of a `codespacerange` entry.
+type Codespace = (usize, u32, u32);
+
+/// `(byte length, low, high, first CID)` of a `cidrange` or `notdefrange`
+/// entry.
+type CidRange = (usize, u32, u32, u32);
+
+/// A parsed `/ToUnicode` CMap.
+#[derive(Debug, Default, Clone)]
+pub struct ToUnicode {
+    codespaces: Vec<Codespace>,
+    map: HashMap<(usize, u32), String>,
+}
+
+impl ToUnicode {
+    pub fn parse(data: &[u8]) -> Self {
+        let tokens = cmap_tokens(data);
+        let mut cmap = ToUnicode::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i] {
+                CMapToken::Keyword(k) if k == "begincodespacerange" => {
+                    i += 1;
+                    while let (Some(CMapToken::Hex(lo)), Some(CMapToken::Hex(hi))) =
+                        (tokens.get(i), tokens.get(i + 1))
+                    {
+                        cmap.codespaces.push((lo.len(), be_value(lo), be_value(hi)));
+                        i += 2;
+                    }
+                }
+                CMapToken::Keyword(k) if k == "beginbfchar" => {
+                    i += 1;
+                    while let (Some(CMapToken::Hex(src)), Some(dst)) =
+                        (tokens.get(i), tokens.get(i + 1))
+                    {
+                        if let CMapToken::Hex(dst) = dst {
+                            cmap.map.insert((src.len(), be_value(src)), utf16_be(dst));
+                        }
+                        i += 2;
+                    }
+                }
+                CMapToken::Keyword(k) if k == "beginbfrange" => {
+                    i += 1;
+                    while let (Some(CMapToken::Hex(lo)), Some(CMapToken::Hex(hi)), Some(dst)) =
+                        (tokens.get(i), tokens.get(i + 1), tokens.get(i + 2))
+                    {
+                        let (len, lo, hi) = (lo.len(), be_value(lo), be_value(hi));
+                        match dst {
+                            CMapToken::Hex(start) => {
+                                // Only the last byte of the destination is incremented.
+                                for (offset, code) in
+                                    (lo..=hi.min(lo.saturating_add(0xFFFF))).enumerate()
+                                {
+                                    let mut target = start.clone();
+                                    if let Some(last) = target.last_mut() {
+                                        *last = last.wrapping_add(offset as u8);
+                                    }
+                                    cmap.map.insert((len, code), utf16_be(&target));
+                                }
+                            }
+                            CMapToken::Array(targets) => {
+                                for (code, target) in (lo..=hi).zip(targets) {
+                                    cmap.map.insert((len, code), utf16_be(target));
+                                }
+                            }
+                            _ => {}
+                        }
+                        i += 3;
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        cmap
+    }
+
+    /// Length in bytes of the code starting at `bytes[0]`, according to the
+    /// codespace ranges, or `None` if the CMap declares none.
+    fn code_length(&self, bytes: &[u8]) -> Option<usize> {
+        codespace_length(&self.codespaces, bytes)
+    }
+
+    pub fn get(&self, len: usize, code: u32) -> Option<&str> {
+        self.map.get(&(len, code)).map(String::as_str)
+    }
+
+    /// Every mapped code as `(byte length, code, text)`, in no particular
+    /// order.
+    pub fn entries(&self) -> impl Iterator<Item = (usize, u32, &str)> {
+        self.map
+            .iter()
+            .map(|(&(len, code), text)| (len, code, text.as_str()))
+    }
+}
+
+/// Length in bytes of the code starting at `bytes[0]` according to
+/// `codespaces`, or `None` if there are none or none match.
+fn codespace_length(codespaces: &[Codespace], bytes: &[u8]) -> Option<usize> {
+    if codespaces.is_empty() {
+        return None;
+    }
+    for len in 1..=4.min(bytes.len()) {
+        let value = be_value(&bytes[..len]);
+        if codespaces
+            .iter()
+            .any(|&(l, lo, hi)| l == len && (lo..=hi).contains(&value))
+        {
+            return Some(len);
+        }
+    }
+    None
+}
+
+/// Codespaces of the predefined CMaps (ISO 32000-1, 9.7.5.2), by the
+/// character encoding part of their names.
+const PREDEFINED_CODESPACES: [(&str, &[Codespace]); 9] = [
+    (
+        "RKSJ",
+        &[
+            (1, 0x00, 0x80),
+            (1, 0xA0, 0xDF),
+            (2, 0x8140, 0x9FFC),
+            (2, 0xE040, 0xFCFC),
+        ],
+    ),
+    ("UCS2", &[(2, 0x0000, 0xFFFF)]),
+    (
+        "UTF16",
+        &[
+            (2, 0x0000, 0xD7FF),
+            (2, 0xE000, 0xFFFF),
+            (4, 0xD800_DC00, 0xDBFF_DFFF),
+        ],
+    ),
+    (
+        "UTF8",
+        &[
+            (1, 0x00, 0x7F),
+            (2, 0xC080, 0xDFBF),
+            (3, 0xE0_8080, 0xEF_BFBF),
+            (4, 0xF080_8080, 0xF7BF_BFBF),
+        ],
+    ),
+    ("UTF32", &[(4, 0x0000_0000, 0x0010_FFFF)]),
+    ("GBK", &[(1, 0x00, 0x80), (2, 0x8140, 0xFEFE)]),
+    ("B5", &[(1, 0x00, 0x80), (2, 0xA140, 0xFEFE)]),
+    ("UHC", &[(1, 0x00, 0x80), (2, 0x8141, 0xFEFE)]),
+    (
+        "EUC",
+        &[(1, 0x00, 0x80), (2, 0x8EA0, 0x8EDF), (2, 0xA1A1, 0xFEFE)],
+    ),
+];
+
+/// A composite font's `/Encoding` CMap, which splits shown strings into
+/// character codes and maps each code to a CID.
+#[derive(Debug, Clone)]
+enum CidCMap {
+    /// `Identity-H`/`Identity-V`: two-byte codes that are their own CIDs.
+    Identity,
+    /// A predefined CMap other than Identity. Their code to CID tables are
+    /// not bundled, so only the codespace is known; it is empty for names
+    /// that are not recognised.
+    Predefined(Vec<Codespace>),
+    /// A CMap stream embedded in the file.
+    Embedded {
+        codespaces: Vec<Codespace>,
+        ranges: Vec<CidRange>,
+        chars: HashMap<(usize, u32), u32>,
+        /// `notdefrange` entries, used for codes that are not mapped.
+        notdef: Vec<CidRange>,
+        /// The CMap named by `usecmap`, which supplies the mappings this
+        /// one does not have.
+        parent: Option<Box<CidCMap>>,
+    },
+}
+
+impl CidCMap {
+    fn from_name(name: &[u8]) -> Self {
+        let name = String::from_utf8_lossy(name);
+        if name.starts_with("Identity-") {
+            return CidCMap::Identity;
+        }
+        let codespaces = name
+            .split('-')
+            .find_map(|part| {
+                PREDEFINED_CODESPACES
+                    .iter()
+                    .find(|(encoding, _)| part.ends_with(encoding))
+            })
+            .map_or_else(Vec::new, |(_, codespaces)| codespaces.to_vec());
+        CidCMap::Predefined(codespaces)
+    }
+
+    fn parse(data: &[u8], parent: Option<CidCMap>) -> Self {
+        let tokens = cmap_tokens(data);
+        let mut codespaces = Vec::new();
+        let mut ranges = Vec::new();
+        let mut chars = HashMap::new();
+        let mut notdef = Vec::new();
+        let mut parent = parent.map(Box::new);
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i] {
+                CMapToken::Keyword(k) if k == "begincodespacerange" => {
+                    i += 1;
+                    while let (Some(CMapToken::Hex(lo)), Some(CMapToken::Hex(hi))) =
+                        (tokens.get(i), tokens.get(i + 1))
+                    {
+                        codespaces.push((lo.len(), be_value(lo), be_value(hi)));
+                        i += 2;
+                    }
+                }
+                CMapToken::Keyword(k) if k == "begincidrange" || k == "beginnotdefrange" => {
+                    let target = if k == "begincidrange" {
+                        &mut ranges
+                    } else {
+                        &mut notdef
+                    };
+                    i += 1;
+                    while let (
+                        Some(CMapToken::Hex(lo)),
+                        Some(CMapToken::Hex(hi)),
+                        Some(CMapToken::Int(cid)),
+                    ) = (tokens.get(i), tokens.get(i + 1), tokens.get(i + 2))
+                    {
+                        target.push((lo.len(), be_value(lo), be_value(hi), *cid));
+                        i += 3;
+                    }
+                }
+                CMapToken::Keyword(k) if k == "begincidchar" => {
+                    i += 1;
+                    while let (Some(CMapToken::Hex(code)), Some(CMapToken::Int(cid))) =
+                        (tokens.get(i), tokens.get(i + 1))
+                    {
+                        chars.insert((code.len(), be_value(code)), *cid);
+                        i += 2;
+                    }
+                }
+                CMapToken::Name(name) if matches!(tokens.get(i + 1), Some(CMapToken::Keyword(k)) if k == "usecmap") =>
+                {
+                    parent = Some(Box::new(CidCMap::from_name(name.as_bytes())));
+                    i += 2;
+                }
+                _ => i += 1,
+            }
+        }
+        CidCMap::Embedded {
+            codespaces,
+            ranges,
+            chars,
+            notdef,
+            parent,
+        }
+    }
+
+    /// Length in bytes of the code starting at `bytes[0]`, or `None` if the
+    /// CMap's codespace is not known.
+    fn code_length(&self, bytes: &[u8]) -> Option<usize> {
+        match self {
+            CidCMap::Identity => Some(2),
+            CidCMap::Predefined(codespaces) => codespace_length(codespaces, bytes),
+            CidCMap::Embedded {
+                codespaces, parent, ..
+            } => codespace_length(codespaces, bytes)
+                .or_else(|| parent.as_ref().and_then(|p| p.code_length(bytes))),
+        }
+    }
+
+    /// The CID of `code`, or `None` if it cannot be known without the
+    /// tables of a predefined CMap.
+    fn cid(&self, len: usize, code: u32) -> Option<u32> {
+        let find = |ranges: &[CidRange]| {
+            ranges
+                .iter()
+                .find(|&&(l, lo, hi, _)| l == len && (lo..=hi).contains(&code))
+                .copied()
+        };
+        match self {
+            CidCMap::Identity => Some(code),
+            CidCMap::Predefined(_) => None,
+            CidCMap::Embedded {
+                ranges,
+                chars,
+                notdef,
+                parent,
+                ..
+            } => chars
+                .get(&(len, code))
+                .copied()
+                .or_else(|| find(ranges).and_then(|(_, lo, _, cid)| cid.checked_add(code - lo)))
+                .or_else(|| parent.as_ref().and_then(|p| p.cid(len, code)))
+                .or_else(|| find(notdef).map(|(_, _, _, cid)| cid))
+                // Codes that are not mapped select CID 0, unless the parent
+                // CMap's tables are unknown.
+                .or(parent.is_none().then_some(0)),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum CMapToken {
+    Hex(Vec<u8>),
+    Array(Vec<Vec<u8>>),
+    Keyword(String),
+    Name(String),
+    Int(u32),
+}
+
+fn cmap_tokens(data: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut array: Option<Vec<Vec<u8>>> = None;
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'<' if data.get(i + 1) != Some(&b'<') => {
+                let end = data[i..].iter().position(|&b| b == b'>').map(|p| i + p);
+                let end = end.unwrap_or(data.len());
+                let hex = parse_hex(&data[i + 1..end]);
+                match array.as_mut() {
+                    Some(items) => items.push(hex),
+                    None => tokens.push(CMapToken::Hex(hex)),
+                }
+                i = end + 1;
+            }
+            b'[' => {
+                array = Some(Vec::new());
+                i += 1;
+            }
+            b']' => {
+                if let Some(items) = array.take() {
+                    tokens.push(CMapToken::Array(items));
+                }
+                i += 1;
+            }
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'/' => {
+                let start = i + 1;
+                i = start;
+                while i < data.len()
+                    && !data[i].is_ascii_whitespace()
+                    && !b"()<>[]{}/%".contains(&data[i])
+                {
+                    i += 1;
+                }
+                tokens.push(CMapToken::Name(
+                    String::from_utf8_lossy(&data[start..i]).into_owned(),
+                ));
+            }
+            b if b.is_ascii_digit() => {
+                let start = i;
+                while i < data.len() && data[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if let Ok(value) = std::str::from_utf8(&data[start..i]).unwrap_or("").parse() {
+                    tokens.push(CMapToken::Int(value));
+                }
+            }
+            b if b.is_ascii_alphabetic() => {
+                let start = i;
+                while i < data.len() && data[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                tokens.push(CMapToken::Keyword(
+                    String::from_utf8_lossy(&data[start..i]).into_owned(),
+                ));
+            }
+            _ => i += 1,
+        }
+    }
+    tokens
+}
+
+fn parse_hex(digits: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = digits
+        .iter()
+        .filter_map(|&b| (b as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn be_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+}
+
+fn utf16_be(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| ((pair[0] as u16) << 8) | pair.get(1).copied().unwrap_or(0) as u16)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[derive(Debug, Clone)]
+enum FontKind {
+    /// Type1, TrueType, MMType1 and Type3 fonts: one byte per code.
+    Simple {
+        encoding: BaseEncoding,
+        differences: HashMap<u8, String>,
+        first_char: u32,
+        widths: Vec<f32>,
+    },
+    /// Type0 composite fonts, whose codes are split and mapped to CIDs by
+    /// their `/Encoding` CMap.
+    Composite {
+        cmap: CidCMap,
+        /// Set for the predefined `Uni*-UCS2-*` / `Uni*-UTF16-*` CMaps, whose
+        /// codes are UTF-16BE already.
+        unicode_cmap: bool,
+        default_width: f32,
+        widths: HashMap<u32, f32>,
+    },
+}
+
+/// A font resource, reduced to what is needed to turn shown strings into
+/// text and glyph advances.
+#[derive(Debug, Clone)]
+pub struct PdfFont {
+    pub base_font: String,
+    /// Whether the font descriptor carries a font program.
+    pub embedded: bool,
+    pub bold: bool,
+    pub italic: bool,
+    kind: FontKind,
+    to_unicode: Option<ToUnicode>,
+    /// Scale from glyph space to text space (1/1000 except for Type 3).
+    glyph_scale: f32,
+}
+
+/// Font descriptor flags (ISO 32000-1, 9.8.2).
+const ITALIC: i64 = 1 << 6;
+const FORCE_BOLD: i64 = 1 << 18;
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, o)| o).unwrap_or(object)
+}
+
+fn number(object: &Object) -> Option<f32> {
+    object.as_float().ok()
+}
+
+impl PdfFont {
+    pub fn from_dict(doc: &Document, dict: &Dictionary) -> Self {
+        let base_font = dict
+            .get(b"BaseFont")
+            .and_then(Object::as_name)
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+            .unwrap_or_default();
+        let subtype = dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .unwrap_or(b"");
+        let to_unicode = dict
+            .get(b"ToUnicode")
+            .ok()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_stream().ok())
+            .and_then(|s| s.get_plain_content().ok())
+            .map(|data| ToUnicode::parse(&data));
+
+        let glyph_scale = if subtype == b"Type3" {
+            dict.get(b"FontMatrix")
+                .ok()
+                .map(|o| resolve(doc, o))
+                .and_then(|o| o.as_array().ok())
+                .and_then(|a| a.first().and_then(number))
+                .unwrap_or(0.001)
+        } else {
+            0.001
+        };
+
+        let kind = if subtype == b"Type0" {
+            Self::composite_kind(doc, dict)
+        } else {
+            Self::simple_kind(doc, dict, subtype)
+        };
+
+        let descriptor = Self::descriptor(doc, dict, subtype);
+        let flags = descriptor
+            .and_then(|d| d.get(b"Flags").and_then(Object::as_i64).ok())
+            .unwrap_or(0);
+        let weight = descriptor
+            .and_then(|d| d.get(b"FontWeight").ok())
+            .and_then(number)
+            .unwrap_or(0.0);
+        let italic_angle = descriptor
+            .and_then(|d| d.get(b"ItalicAngle").ok())
+            .and_then(number)
+            .unwrap_or(0.0);
+        let embedded = descriptor.is_some_and(|d| {
+            [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+                .iter()
+                .any(|key| d.has(key))
+        });
+        let style = base_font
+            .split_once(['-', ','])
+            .map_or("", |(_, style)| style);
+        let bold = flags & FORCE_BOLD != 0
+            || weight >= 600.0
+            || ["Bold", "Black", "Heavy", "Semibold", "Demi"]
+                .iter()
+                .any(|s| style.contains(s));
+        let italic = flags & ITALIC != 0
+            || italic_angle != 0.0
+            || ["Italic", "Oblique"].iter().any(|s| style.contains(s));
+
+        PdfFont {
+            base_font,
+            embedded,
+            bold,
+            italic,
+            kind,
+            to_unicode,
+            glyph_scale,
+        }
+    }
+
+    /// The font descriptor of a simple font, or of a composite font's
+    /// descendant.
+    fn descriptor<'a>(
+        doc: &'a Document,
+        dict: &'a Dictionary,
+        subtype: &[u8],
+    ) -> Option<&'a Dictionary> {
+        let font = if subtype == b"Type0" {
+            dict.get(b"DescendantFonts")
+                .ok()
+                .map(|o| resolve(doc, o))
+                .and_then(|o| o.as_array().ok())
+                .and_then(|a| a.first())
+                .map(|o| resolve(doc, o))
+                .and_then(|o| o.as_dict().ok())?
+        } else {
+            dict
+        };
+        font.get(b"FontDescriptor")
+            .ok()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_dict().ok())
+    }
+
+    /// The font's family name: its `BaseFont` without a subset prefix or
+    /// a `-Bold`/`,Italic` style suffix.
+    pub fn family(&self) -> &str {
+        let name = match self.base_font.split_once('+') {
+            Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => {
+                rest
+            }
+            _ => &self.base_font,
+        };
+        name.split(['-', ',']).next().unwrap_or(name)
+    }
+
+    fn simple_kind(doc: &Document, dict: &Dictionary, subtype: &[u8]) -> FontKind {
+        let symbolic = dict
+            .get(b"FontDescriptor")
+            .ok()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_dict().ok())
+            .and_then(|d| d.get(b"Flags").and_then(Object::as_i64).ok())
+            .map(|flags| flags & 4 != 0)
+            .unwrap_or(false);
+        let mut encoding = if subtype == b"TrueType" {
+            BaseEncoding::WinAnsi
+        } else if symbolic {
+            BaseEncoding::Identity
+        } else {
+            BaseEncoding::Standard
+        };
+        let mut differences = HashMap::new();
+        match dict.get(b"Encoding").ok().map(|o| resolve(doc, o)) {
+            Some(Object::Name(name)) => {
+                if let Some(base) = BaseEncoding::from_name(name) {
+                    encoding = base;
+                }
+            }
+            Some(Object::Dictionary(enc)) => {
+                if let Some(base) = enc
+                    .get(b"BaseEncoding")
+                    .and_then(Object::as_name)
+                    .ok()
+                    .and_then(BaseEncoding::from_name)
+                {
+                    encoding = base;
+                }
+                if let Ok(diffs) = enc.get(b"Differences").and_then(Object::as_array) {
+                    let mut code = 0u32;
+                    for item in diffs {
+                        match item {
+                            Object::Integer(start) => code = *start as u32,
+                            Object::Name(name) => {
+                                if code <= 0xFF {
+                                    differences.insert(
+                                        code as u8,
+                                        String::from_utf8_lossy(name).into_owned(),
+                                    );
+                                }
+                                code += 1;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        let first_char = dict
+            .get(b"FirstChar")
+            .and_then(Object::as_i64)
+            .unwrap_or(0)
+            .max(0) as u32;
+        let widths = dict
+            .get(b"Widths")
+            .ok()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_array().ok())
+            .map(|a| {
+                a.iter()
+                    .map(|w| number(resolve(doc, w)).unwrap_or(0.0))
+                    .collect()
+            })
+            .unwrap_or_default();
+        FontKind::Simple {
+            encoding,
+            differences,
+            first_char,
+            widths,
+        }
+    }
+
+    fn composite_kind(doc: &Document, dict: &Dictionary) -> FontKind {
+        let encoding = dict.get(b"Encoding").ok().map(|o| resolve(doc, o));
+        let unicode_cmap = match encoding.and_then(|o| o.as_name().ok()) {
+            Some(name) => {
+                let name = String::from_utf8_lossy(name);
+                name.starts_with("Uni") && (name.contains("UCS2") || name.contains("UTF16"))
+            }
+            None => false,
+        };
+        let cmap = match encoding {
+            Some(Object::Name(name)) => CidCMap::from_name(name),
+            Some(Object::Stream(stream)) => match stream.get_plain_content() {
+                Ok(data) => {
+                    let parent = stream
+                        .dict
+                        .get(b"UseCMap")
+                        .and_then(Object::as_name)
+                        .ok()
+                        .map(CidCMap::from_name);
+                    CidCMap::parse(&data, parent)
+                }
+                Err(_) => CidCMap::Identity,
+            },
+            _ => CidCMap::Identity,
+        };
+        let descendant = dict
+            .get(b"DescendantFonts")
+            .ok()
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_array().ok())
+            .and_then(|a| a.first())
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_dict().ok());
+        let default_width = descendant
+            .and_then(|d| d.get(b"DW").ok())
+            .and_then(number)
+            .unwrap_or(1000.0);
+        let mut widths = HashMap::new();
+        if let Some(w) = descendant
+            .and_then(|d| d.get(b"W").ok())
+            .map(|o| resolve(doc, o))
+            .and_then(|o| o.as_array().ok())
+        {
+            let mut i = 0;
+            while i < w.len() {
+                // CIDs outside the u32 range are skipped with their widths.
+                let first = u32::try_from(resolve(doc, &w[i]).as_i64().unwrap_or(0)).ok();
+                match w.get(i + 1).map(|o| resolve(doc, o)) {
+                    Some(Object::Array(list)) => {
+                        for (offset, width) in list.iter().enumerate() {
+                            let cid = first.and_then(|f| f.checked_add(offset.try_into().ok()?));
+                            if let (Some(cid), Some(width)) = (cid, number(resolve(doc, width))) {
+                                widths.insert(cid, width);
+                            }
+                        }
+                        i += 2;
+                    }
+                    Some(last) => {
+                        let last = u32::try_from(last.as_i64().unwrap_or(0)).ok();
+                        let width = w.get(i + 2).and_then(number).unwrap_or(default_width);
+                        if let (Some(first), Some(last)) = (first, last) {
+                            for cid in first..=last.min(first.saturating_add(0xFFFF)) {
+                                widths.insert(cid, width);
+                            }
+                        }
+                        i += 3;
+                    }
+                    None => break,
+                }
+            }
+        }
+        FontKind::Composite {
+            cmap,
+            unicode_cmap,
+            default_width,
+            widths,
+        }
+    }
+
+    /// Splits a shown string into character codes and decodes each one.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let len = match &self.kind {
+                FontKind::Simple { .. } => 1,
+                // Without a known codespace, the ToUnicode CMap's is the
+                // best guess.
+                FontKind::Composite { cmap, .. } => cmap
+                    .code_length(&bytes[i..])
+                    .or_else(|| {
+                        self.to_unicode
+                            .as_ref()
+                            .and_then(|cmap| cmap.code_length(&bytes[i..]))
+                    })
+                    .unwrap_or(2)
+                    .min(bytes.len() - i),
+            };
+            let code = be_value(&bytes[i..i + len]);
+            glyphs.push(self.glyph(len, code));
+            i += len;
+        }
+        glyphs
+    }
+
+    fn glyph(&self, len: usize, code: u32) -> Glyph {
+        let mapped = self
+            .to_unicode
+            .as_ref()
+            .and_then(|cmap| cmap.get(len, code))
+            .map(str::to_string);
+        match &self.kind {
+            FontKind::Simple {
+                encoding,
+                differences,
+                first_char,
+                widths,
+            } => {
+                let byte = code as u8;
+                let text = mapped.unwrap_or_else(|| {
+                    differences
+                        .get(&byte)
+                        .and_then(|name| glyph_name_to_string(name))
+                        .or_else(|| encoding.decode(byte).map(String::from))
+                        .unwrap_or_default()
+                });
+                let width = code
+                    .checked_sub(*first_char)
+                    .and_then(|index| widths.get(index as usize).copied())
+                    .or_else(|| standard_font_width(&self.base_font, byte))
+                    .unwrap_or(500.0);
+                Glyph {
+                    text,
+                    width: width * self.glyph_scale,
+                    is_space: len == 1 && code == 32,
+                }
+            }
+            FontKind::Composite {
+                cmap,
+                unicode_cmap,
+                default_width,
+                widths,
+            } => {
+                let text = mapped.unwrap_or_else(|| {
+                    if *unicode_cmap {
+                        utf16_be(&code.to_be_bytes()[4 - len..])
+                    } else {
+                        String::new()
+                    }
+                });
+                let width = cmap
+                    .cid(len, code)
+                    .and_then(|cid| widths.get(&cid).copied())
+                    .unwrap_or(*default_width);
+                Glyph {
+                    text,
+                    width: width * self.glyph_scale,
+                    is_space: len == 1 && code == 32,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! TrueType/OpenType fonts registered for text generation, and their
+//! embedding as Type0 fonts with Identity-H encoding.
+//!
+//! Fonts with TrueType outlines are embedded as a subset of the glyphs used;
+//! OpenType fonts with CFF outlines are embedded whole.
+
+use crate::subset::{collection_face, is_truetype, subset_truetype};
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+use ttf_parser::{name_id, Face, GlyphId};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FontError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid font: {0}")]
+    Invalid(String),
+}
+
+/// Fonts available to `TextBlock::font_name` when generating a PDF, keyed
+/// by the name callers register them under.
+#[derive(Debug, Clone, Default)]
+#[pyclass]
+pub struct FontRegistry {
+    fonts: BTreeMap<String, Arc<Vec<u8>>>,
+}
+
+impl FontRegistry {
+    /// Registers the TTF/OTF file at `path` under `name`, or font `index` of
+    /// a TTC/OTC collection.
+    pub fn register_file(&mut self, name: &str, path: &Path, index: u32) -> Result<(), FontError> {
+        self.register_data(name, std::fs::read(path)?, index)
+    }
+
+    /// Registers font file contents under `name`, replacing any font
+    /// registered under the same name. For a collection, font `index` is
+    /// extracted and registered on its own; otherwise `index` must be 0.
+    pub fn register_data(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+        index: u32,
+    ) -> Result<(), FontError> {
+        let data = if data.starts_with(b"ttcf") {
+            collection_face(&data, index).map_err(FontError::Invalid)?
+        } else if index != 0 {
+            return Err(FontError::Invalid(format!(
+                "font index {} given for a file that is not a collection",
+                index
+            )));
+        } else {
+            data
+        };
+        Face::parse(&data, 0).map_err(|e| FontError::Invalid(e.to_string()))?;
+        self.fonts.insert(name.to_string(), Arc::new(data));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.fonts.get(name).map(|data| data.as_slice())
+    }
+
+    /// The registry's own copy of `name`, if it is registered.
+    pub(crate) fn key(&self, name: &str) -> Option<&str> {
+        self.fonts.get_key_value(name).map(|(key, _)| key.as_str())
+    }
+}
+
+#[pymethods]
+impl FontRegistry {
+    #[new]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the TTF/OTF file at `path` under `name`, or font `index` of
+    /// a TTC/OTC collection.
+    #[pyo3(signature = (name, path, index=0))]
+    fn register(&mut self, name: String, path: String, index: u32) -> PyResult<()> {
+        self.register_file(&name, Path::new(&path), index)
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Failed to register font: {}",
+                    e
+                ))
+            })
+    }
+
+    /// Registers TTF/OTF/TTC/OTC file contents under `name`.
+    #[pyo3(signature = (name, data, index=0))]
+    fn register_bytes(&mut self, name: String, data: Vec<u8>, index: u32) -> PyResult<()> {
+        self.register_data(&name, data, index).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Failed to register font: {}",
+                e
+            ))
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.fonts.keys().cloned().collect()
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.fonts.contains_key(name)
+    }
+
+    fn __len__(&self) -> usize {
+        self.fonts.len()
+    }
+}
+
+/// A registered font being used by a generated document. Text is encoded as
+/// two-byte CIDs, which are the glyph ids except in CID-keyed CFF fonts, and
+/// the glyphs used are remembered so that TrueType fonts can be subset.
+pub struct EmbeddedFont<'a> {
+    name: &'a str,
+    data: &'a [u8],
+    face: Face<'a>,
+    /// Glyph id to the text it was used for, for the ToUnicode CMap.
+    glyphs: BTreeMap<u16, String>,
+}
+
+impl<'a> EmbeddedFont<'a> {
+    pub fn new(name: &'a str, data: &'a [u8]) -> Result<Self, String> {
+        let face = Face::parse(data, 0).map_err(|e| e.to_string())?;
+        Ok(EmbeddedFont {
+            name,
+            data,
+            face,
+            glyphs: BTreeMap::new(),
+        })
+    }
+
+    /// Encodes `text` as CIDs. Characters the font has no glyph for are
+    /// drawn as `.notdef`.
+    pub fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for ch in text.chars() {
+            let id = self.face.glyph_index(ch).map_or(0, |g| g.0);
+            if id != 0 {
+                self.glyphs.entry(id).or_insert_with(|| ch.to_string());
+            }
+            bytes.extend_from_slice(&self.cid(id).to_be_bytes());
+        }
+        bytes
+    }
+
+    /// The CID of glyph `id`. CID-keyed CFF fonts have their own mapping;
+    /// in other fonts the CID is the glyph id.
+    fn cid(&self, id: u16) -> u16 {
+        self.face
+            .tables()
+            .cff
+            .and_then(|cff| cff.glyph_cid(GlyphId(id)))
+            .unwrap_or(id)
+    }
+
+    /// Converts a length in font units to PDF glyph space.
+    fn scale(&self, value: f32) -> f32 {
+        (value * 1000.0 / self.face.units_per_em() as f32).round()
+    }
+
+    fn postscript_name(&self) -> String {
+        let name = self
+            .face
+            .names()
+            .into_iter()
+            .find(|n| n.name_id == name_id::POST_SCRIPT_NAME)
+            .and_then(|n| n.to_string())
+            .unwrap_or_else(|| self.name.to_string());
+        name.chars()
+            .filter(|c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(*c))
+            .collect()
+    }
+
+    /// Six uppercase letters identifying this subset (ISO 32000-1, 9.6.4).
+    fn subset_tag(&self) -> String {
+        let hash = self.glyphs.keys().fold(0x811C_9DC5u32, |hash, id| {
+            (hash ^ *id as u32).wrapping_mul(0x0100_0193)
+        });
+        (0..6)
+            .map(|i| (b'A' + ((hash >> (i * 5)) % 26) as u8) as char)
+            .collect()
+    }
+
+    /// The `/W` array: runs of consecutive CIDs with their widths.
+    fn widths(&self) -> Vec<Object> {
+        let cids: BTreeMap<u16, u16> = self.glyphs.keys().map(|&id| (self.cid(id), id)).collect();
+        let mut array = Vec::new();
+        let mut run: Option<(u16, Vec<Object>)> = None;
+        for (&cid, &id) in &cids {
+            let advance = self.face.glyph_hor_advance(GlyphId(id)).unwrap_or(0);
+            let width = Object::Real(self.scale(advance as f32));
+            match &mut run {
+                Some((start, widths)) if *start as usize + widths.len() == cid as usize => {
+                    widths.push(width)
+                }
+                _ => {
+                    if let Some((start, widths)) = run.take() {
+                        array.push((start as i64).into());
+                        array.push(widths.into());
+                    }
+                    run = Some((cid, vec![width]));
+                }
+            }
+        }
+        if let Some((start, widths)) = run {
+            array.push((start as i64).into());
+            array.push(widths.into());
+        }
+        array
+    }
+
+    fn to_unicode(&self) -> Vec<u8> {
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        let entries: Vec<_> = self
+            .glyphs
+            .iter()
+            .map(|(&id, text)| (self.cid(id), text))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .collect();
+        // At most 100 entries are allowed per bfchar block.
+        for chunk in entries.chunks(100) {
+            let _ = writeln!(cmap, "{} beginbfchar", chunk.len());
+            for (id, text) in chunk {
+                let utf16: String = text.encode_utf16().map(|u| format!("{:04X}", u)).collect();
+                let _ = writeln!(cmap, "<{:04X}> <{}>", id, utf16);
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap.into_bytes()
+    }
+
+    /// Writes the font objects, using `font_id` for the Type0 font that
+    /// page resources refer to.
+    pub fn write(&self, pdf: &mut lopdf::Document, font_id: ObjectId) -> Result<(), String> {
+        let truetype = is_truetype(self.data);
+        let (base_font, font_file) = if truetype {
+            let used: BTreeSet<u16> = self.glyphs.keys().copied().collect();
+            let subset = subset_truetype(self.data, &used)?;
+            let stream = Stream::new(
+                Dictionary::from_iter(vec![("Length1", (subset.len() as i64).into())]),
+                subset,
+            );
+            let base_font = format!("{}+{}", self.subset_tag(), self.postscript_name());
+            (base_font, ("FontFile2", stream))
+        } else {
+            // CFF outlines are embedded whole.
+            let stream = Stream::new(
+                Dictionary::from_iter(vec![("Subtype", Object::Name(b"OpenType".to_vec()))]),
+                self.data.to_vec(),
+            );
+            (self.postscript_name(), ("FontFile3", stream))
+        };
+        let font_file_id = pdf.add_object(font_file.1);
+
+        let bbox = self.face.global_bounding_box();
+        let ascent = self.scale(self.face.ascender() as f32);
+        let mut flags = 32; // Nonsymbolic
+        if self.face.is_monospaced() {
+            flags |= 1;
+        }
+        if self.face.is_italic() {
+            flags |= 64;
+        }
+        let descriptor_id = pdf.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"FontDescriptor".to_vec())),
+            ("FontName", Object::Name(base_font.as_bytes().to_vec())),
+            ("Flags", flags.into()),
+            (
+                "FontBBox",
+                vec![
+                    Object::Real(self.scale(bbox.x_min as f32)),
+                    Object::Real(self.scale(bbox.y_min as f32)),
+                    Object::Real(self.scale(bbox.x_max as f32)),
+                    Object::Real(self.scale(bbox.y_max as f32)),
+                ]
+                .into(),
+            ),
+            ("ItalicAngle", Object::Real(self.face.italic_angle())),
+            ("Ascent", Object::Real(ascent)),
+            (
+                "Descent",
+                Object::Real(self.scale(self.face.descender() as f32)),
+            ),
+            (
+                "CapHeight",
+                Object::Real(
+                    self.face
+                        .capital_height()
+                        .map_or(ascent, |h| self.scale(h as f32)),
+                ),
+            ),
+            ("StemV", 80.into()),
+            (font_file.0, font_file_id.into()),
+        ]));
+
+        let subtype: &[u8] = if truetype {
+            b"CIDFontType2"
+        } else {
+            b"CIDFontType0"
+        };
+        let mut cid_font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(subtype.to_vec())),
+            ("BaseFont", Object::Name(base_font.as_bytes().to_vec())),
+            (
+                "CIDSystemInfo",
+                Dictionary::from_iter(vec![
+                    ("Registry", Object::string_literal("Adobe")),
+                    ("Ordering", Object::string_literal("Identity")),
+                    ("Supplement", 0.into()),
+                ])
+                .into(),
+            ),
+            ("FontDescriptor", descriptor_id.into()),
+            ("W", self.widths().into()),
+        ]);
+        if truetype {
+            cid_font.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+        }
+        let cid_font_id = pdf.add_object(cid_font);
+        let to_unicode_id = pdf.add_object(Stream::new(Dictionary::new(), self.to_unicode()));
+
+        pdf.objects.insert(
+            font_id,
+            Object::Dictionary(Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Font".to_vec())),
+                ("Subtype", Object::Name(b"Type0".to_vec())),
+                ("BaseFont", Object::Name(base_font.into_bytes())),
+                ("Encoding", Object::Name(b"Identity-H".to_vec())),
+                ("DescendantFonts", vec![cid_font_id.into()].into()),
+                ("ToUnicode", to_unicode_id.into()),
+            ])),
+        );
+        Ok(())
+    }
+}
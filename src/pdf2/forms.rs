@@ -0,0 +1,1704 @@
+//! Interactive forms (ISO 32000-1, 12.7): the field tree under the
+//! catalog's `/AcroForm` dictionary and the widgets that show each field,
+//! read into `FormField`s, filled in, and flattened into page content.
+
+use crate::encoding::{standard_font_width, BaseEncoding};
+use crate::encryption::load_document;
+use crate::font::{PdfFont, ToUnicode};
+use crate::matrix::Matrix;
+use crate::metadata::{decode_text_string, encode_text_string};
+use crate::parser::{inherited_attribute, rect_from_object};
+use crate::pyfile::PdfFile;
+use crate::structure::{
+    FieldWidget, FormField, Page, Rect, FIELD_COMBO, FIELD_EDIT, FIELD_MULTILINE,
+    FIELD_MULTI_SELECT, FIELD_NO_TOGGLE_TO_OFF, FIELD_PASSWORD, FIELD_PUSHBUTTON, FIELD_RADIO,
+    FIELD_READ_ONLY, FIELD_REQUIRED,
+};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyFloat, PyInt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Field attributes that terminal fields inherit from their ancestors.
+const INHERITABLE_FIELD_KEYS: [&[u8]; 8] =
+    [b"FT", b"Ff", b"V", b"DV", b"Opt", b"DA", b"Q", b"MaxLen"];
+
+/// Guards walks over the field tree against cycles and runaway depth.
+const MAX_FIELD_DEPTH: usize = 64;
+
+/// The catalog's `/AcroForm` dictionary.
+pub(crate) fn acroform(doc: &lopdf::Document) -> Option<&Dictionary> {
+    let form = doc.catalog().ok()?.get(b"AcroForm").ok()?;
+    doc.dereference(form).ok()?.1.as_dict().ok()
+}
+
+/// Reads the terminal fields of the form of `doc`, looking pages up in
+/// `page_indices` (page id to index).
+pub fn read_form_fields(
+    doc: &lopdf::Document,
+    page_indices: &HashMap<ObjectId, usize>,
+) -> Vec<FormField> {
+    field_nodes(doc, page_indices)
+        .into_iter()
+        .map(|node| node.field)
+        .collect()
+}
+
+/// A terminal field with the objects it was read from.
+struct FieldNode {
+    field: FormField,
+    /// The field dictionary, where the value is set.
+    id: Option<ObjectId>,
+    /// The widget annotations, in the order of `field.widgets`.
+    widget_ids: Vec<Option<ObjectId>>,
+    /// The field's own and inherited attributes.
+    attributes: Dictionary,
+}
+
+fn field_nodes(doc: &lopdf::Document, page_indices: &HashMap<ObjectId, usize>) -> Vec<FieldNode> {
+    let Some(fields) = acroform(doc)
+        .and_then(|form| form.get(b"Fields").ok())
+        .and_then(|f| doc.dereference(f).ok())
+        .and_then(|(_, f)| f.as_array().ok())
+    else {
+        return Vec::new();
+    };
+
+    // Widgets without a /P entry are found through the pages' /Annots.
+    let mut widget_pages = HashMap::new();
+    for (&page_id, &index) in page_indices {
+        let annotations = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+            .and_then(|a| doc.dereference(a).ok())
+            .and_then(|(_, a)| a.as_array().ok());
+        for annotation in annotations.into_iter().flatten() {
+            if let Ok(id) = annotation.as_reference() {
+                widget_pages.insert(id, index);
+            }
+        }
+    }
+
+    let mut reader = FieldReader {
+        doc,
+        page_indices,
+        widget_pages,
+        visited: HashSet::new(),
+        fields: Vec::new(),
+    };
+    for field in fields {
+        reader.read(field, &Dictionary::new(), "", 0);
+    }
+    reader.fields
+}
+
+struct FieldReader<'a> {
+    doc: &'a lopdf::Document,
+    page_indices: &'a HashMap<ObjectId, usize>,
+    widget_pages: HashMap<ObjectId, usize>,
+    visited: HashSet<ObjectId>,
+    fields: Vec<FieldNode>,
+}
+
+impl FieldReader<'_> {
+    fn read(&mut self, node: &Object, inherited: &Dictionary, parent_name: &str, depth: usize) {
+        if depth > MAX_FIELD_DEPTH {
+            return;
+        }
+        if let Ok(id) = node.as_reference() {
+            if !self.visited.insert(id) {
+                return;
+            }
+        }
+        let Ok((_, Object::Dictionary(dict))) = self.doc.dereference(node) else {
+            return;
+        };
+
+        let mut attributes = inherited.clone();
+        for key in INHERITABLE_FIELD_KEYS {
+            if let Ok(value) = dict.get(key) {
+                attributes.set(key, value.clone());
+            }
+        }
+        let name = match self.text(dict.get(b"T").ok()) {
+            Some(partial) if parent_name.is_empty() => partial,
+            Some(partial) => format!("{}.{}", parent_name, partial),
+            None => parent_name.to_string(),
+        };
+
+        // Kids are either fields, which have a partial name or kids of
+        // their own, or the widgets of this field.
+        let kids = dict
+            .get(b"Kids")
+            .ok()
+            .and_then(|k| self.doc.dereference(k).ok())
+            .and_then(|(_, k)| k.as_array().ok());
+        let (field_kids, widget_kids): (Vec<&Object>, Vec<&Object>) =
+            kids.into_iter().flatten().partition(|kid| {
+                self.doc
+                    .dereference(kid)
+                    .ok()
+                    .and_then(|(_, k)| k.as_dict().ok())
+                    .is_some_and(|k| k.has(b"T") || k.has(b"Kids"))
+            });
+        if !field_kids.is_empty() {
+            for kid in field_kids {
+                self.read(kid, &attributes, &name, depth + 1);
+            }
+            return;
+        }
+
+        let widgets: Vec<(Option<ObjectId>, &Dictionary)> = if widget_kids.is_empty() {
+            // A field with a single widget may be merged with it.
+            if dict.has(b"Rect") {
+                vec![(node.as_reference().ok(), dict)]
+            } else {
+                Vec::new()
+            }
+        } else {
+            widget_kids
+                .into_iter()
+                .filter_map(|kid| {
+                    let (_, widget) = self.doc.dereference(kid).ok()?;
+                    Some((kid.as_reference().ok(), widget.as_dict().ok()?))
+                })
+                .collect()
+        };
+        let (field, widget_ids) = self.field(name, &attributes, &widgets);
+        self.fields.push(FieldNode {
+            field,
+            id: node.as_reference().ok(),
+            widget_ids,
+            attributes,
+        });
+    }
+
+    fn field(
+        &self,
+        name: String,
+        attributes: &Dictionary,
+        widgets: &[(Option<ObjectId>, &Dictionary)],
+    ) -> (FormField, Vec<Option<ObjectId>>) {
+        let flags = attributes
+            .get(b"Ff")
+            .and_then(Object::as_i64)
+            .map_or(0, |ff| ff as u32);
+        let field_type = match attributes.get(b"FT").and_then(Object::as_name) {
+            Ok(b"Tx") => "text",
+            Ok(b"Ch") => "choice",
+            Ok(b"Sig") => "signature",
+            Ok(b"Btn") if flags & FIELD_PUSHBUTTON != 0 => "button",
+            Ok(b"Btn") if flags & FIELD_RADIO != 0 => "radio",
+            Ok(b"Btn") => "checkbox",
+            _ => "",
+        };
+
+        let (widgets, widget_ids): (Vec<FieldWidget>, Vec<Option<ObjectId>>) = widgets
+            .iter()
+            .filter_map(|&(id, widget)| {
+                let rect = rect_from_object(self.doc, widget.get(b"Rect").ok()?)?;
+                let page = widget
+                    .get(b"P")
+                    .and_then(Object::as_reference)
+                    .ok()
+                    .and_then(|p| self.page_indices.get(&p))
+                    .or_else(|| id.and_then(|id| self.widget_pages.get(&id)))
+                    .copied();
+                let on_state = match field_type {
+                    "checkbox" | "radio" => on_state(self.doc, widget),
+                    _ => None,
+                };
+                let widget = FieldWidget {
+                    page,
+                    rect,
+                    on_state,
+                };
+                Some((widget, id))
+            })
+            .unzip();
+
+        // Choice options are either text strings or [export, label] pairs;
+        // button options give the export value of each widget in order.
+        let mut options = Vec::new();
+        let mut option_labels = Vec::new();
+        let opt = attributes
+            .get(b"Opt")
+            .ok()
+            .and_then(|o| self.doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_array().ok());
+        match field_type {
+            "checkbox" | "radio" => {
+                for widget in &widgets {
+                    if let Some(state) = &widget.on_state {
+                        if !options.contains(state) {
+                            options.push(state.clone());
+                        }
+                    }
+                }
+                let labels: Vec<String> = opt
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|o| self.text(Some(o)))
+                    .collect();
+                option_labels = if labels.len() == options.len() {
+                    labels
+                } else {
+                    options.clone()
+                };
+            }
+            _ => {
+                for option in opt.into_iter().flatten() {
+                    let option = self.doc.dereference(option).map(|(_, o)| o);
+                    let (value, label) = match option {
+                        Ok(Object::Array(pair)) => {
+                            (self.text(pair.first()), self.text(pair.get(1)))
+                        }
+                        Ok(other) => (self.text(Some(other)), None),
+                        Err(_) => (None, None),
+                    };
+                    if let Some(value) = value {
+                        option_labels.push(label.unwrap_or_else(|| value.clone()));
+                        options.push(value);
+                    }
+                }
+            }
+        }
+
+        let values = self.values(attributes.get(b"V").ok());
+        let field = FormField {
+            name,
+            field_type: field_type.to_string(),
+            value: values.first().cloned(),
+            values,
+            default_value: self.values(attributes.get(b"DV").ok()).into_iter().next(),
+            options,
+            option_labels,
+            flags,
+            widgets,
+        };
+        (field, widget_ids)
+    }
+
+    /// A field value as a list: names, text strings, or an array of them
+    /// for list boxes with several options selected.
+    fn values(&self, value: Option<&Object>) -> Vec<String> {
+        match value.and_then(|v| self.doc.dereference(v).ok()) {
+            Some((_, Object::Array(items))) => items
+                .iter()
+                .filter_map(|item| self.text(Some(item)))
+                .collect(),
+            Some((_, other)) => self.text(Some(other)).into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn text(&self, object: Option<&Object>) -> Option<String> {
+        match self.doc.dereference(object?).ok()?.1 {
+            Object::String(bytes, _) => Some(decode_text_string(bytes)),
+            Object::Name(name) => Some(String::from_utf8_lossy(name).into_owned()),
+            _ => None,
+        }
+    }
+}
+
+/// The appearance state a checkbox or radio button widget shows when it is
+/// selected: the key of its normal appearances other than `Off`.
+pub(crate) fn on_state(doc: &lopdf::Document, widget: &Dictionary) -> Option<String> {
+    let appearances = widget.get(b"AP").ok()?;
+    let appearances = doc.dereference(appearances).ok()?.1.as_dict().ok()?;
+    let normal = doc.dereference(appearances.get(b"N").ok()?).ok()?.1;
+    normal
+        .as_dict()
+        .ok()?
+        .iter()
+        .map(|(state, _)| state)
+        .find(|state| state.as_slice() != b"Off")
+        .map(|state| String::from_utf8_lossy(state).into_owned())
+}
+
+/// A value to fill a field with.
+#[derive(Debug, Clone, PartialEq, FromPyObject)]
+pub enum FieldValue {
+    /// Checks or clears a checkbox, or clears a radio group.
+    Checked(bool),
+    /// The text of a text field, the option to select in a choice field, or
+    /// the state of a checkbox or radio group.
+    Text(String),
+    /// The options to select in a list box.
+    Choices(Vec<String>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormError {
+    #[error("Failed to load PDF: {0}")]
+    Load(String),
+    #[error("PDF has no interactive form")]
+    NoForm,
+    #[error("No form field named '{0}'")]
+    UnknownField(String),
+    #[error("Invalid value for field '{name}': {message}")]
+    Value { name: String, message: String },
+    #[error("Failed to save PDF: {0}")]
+    Save(String),
+}
+
+/// How `fill_form` finishes the form.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FillOptions {
+    /// Sets `/NeedAppearances` so that viewers regenerate the appearances
+    /// themselves.
+    pub need_appearances: bool,
+    /// Draws every field into its page's content and removes the form.
+    pub flatten: bool,
+}
+
+/// Sets the fields named in `values` and regenerates their appearance
+/// streams, then finishes the form as `options` asks. Widgets whose font
+/// cannot show the new value keep their appearance and `/NeedAppearances`
+/// is set so that viewers redraw them; flattening them is an error.
+pub fn fill_form(
+    doc: &mut lopdf::Document,
+    values: &BTreeMap<String, FieldValue>,
+    options: FillOptions,
+) -> Result<(), FormError> {
+    if acroform(doc).is_none() {
+        return if values.is_empty() {
+            Ok(())
+        } else {
+            Err(FormError::NoForm)
+        };
+    }
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let page_indices: HashMap<ObjectId, usize> =
+        pages.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut nodes = field_nodes(doc, &page_indices);
+    let mut need_appearances = options.need_appearances;
+
+    for (name, value) in values {
+        let node = nodes
+            .iter_mut()
+            .find(|node| node.field.name == *name)
+            .ok_or_else(|| FormError::UnknownField(name.clone()))?;
+        set_value(doc, node, value).map_err(|message| FormError::Value {
+            name: name.clone(),
+            message,
+        })?;
+        if let Err(message) = write_appearances(doc, node) {
+            if options.flatten {
+                return Err(FormError::Value {
+                    name: name.clone(),
+                    message,
+                });
+            }
+            need_appearances = true;
+        }
+    }
+
+    if need_appearances {
+        if let Some(form) = acroform_mut(doc) {
+            form.set("NeedAppearances", true);
+        }
+    }
+    if options.flatten {
+        flatten(doc, &nodes, &pages)?;
+    }
+    Ok(())
+}
+
+/// Like `fill_form`, taking and returning the file contents.
+pub fn fill_form_bytes(
+    data: &[u8],
+    password: Option<&str>,
+    values: &BTreeMap<String, FieldValue>,
+    options: FillOptions,
+) -> Result<Vec<u8>, FormError> {
+    let mut doc = load_document(data, password).map_err(|e| FormError::Load(e.to_string()))?;
+    fill_form(&mut doc, values, options)?;
+    let mut output = Vec::new();
+    doc.save_to(&mut output)
+        .map_err(|e| FormError::Save(e.to_string()))?;
+    Ok(output)
+}
+
+fn acroform_mut(doc: &mut lopdf::Document) -> Option<&mut Dictionary> {
+    match doc.catalog().ok()?.get(b"AcroForm").ok()? {
+        Object::Reference(id) => {
+            let id = *id;
+            doc.get_dictionary_mut(id).ok()
+        }
+        _ => doc
+            .catalog_mut()
+            .ok()?
+            .get_mut(b"AcroForm")
+            .ok()?
+            .as_dict_mut()
+            .ok(),
+    }
+}
+
+/// Checks `value` against the field, then writes it to the field and, for
+/// checkboxes and radio buttons, selects the matching widgets.
+fn set_value(
+    doc: &mut lopdf::Document,
+    node: &mut FieldNode,
+    value: &FieldValue,
+) -> Result<(), String> {
+    let field = &mut node.field;
+    let is_button = matches!(field.field_type.as_str(), "checkbox" | "radio");
+    let values = match (field.field_type.as_str(), value) {
+        ("text", FieldValue::Text(text)) => {
+            let max_len = node.attributes.get(b"MaxLen").and_then(Object::as_i64);
+            if let Ok(max_len) = max_len {
+                if text.chars().count() as i64 > max_len {
+                    return Err(format!("longer than {} characters", max_len));
+                }
+            }
+            vec![text.clone()]
+        }
+        ("choice", FieldValue::Text(option)) => vec![option.clone()],
+        ("choice", FieldValue::Choices(options))
+            if options.len() <= 1 || field.flags & FIELD_MULTI_SELECT != 0 =>
+        {
+            options.clone()
+        }
+        ("checkbox", FieldValue::Checked(true)) => {
+            vec![field.options.first().cloned().unwrap_or("Yes".to_string())]
+        }
+        ("checkbox" | "radio", FieldValue::Checked(false)) => vec!["Off".to_string()],
+        ("checkbox" | "radio", FieldValue::Text(state)) => vec![state.clone()],
+        ("signature" | "button", _) => {
+            return Err(format!("{} fields cannot be filled", field.field_type))
+        }
+        ("radio", FieldValue::Checked(true)) => {
+            return Err("select a radio button by its state name".to_string())
+        }
+        ("", _) => return Err("the field has no type".to_string()),
+        (field_type, _) => return Err(format!("unsupported value for a {} field", field_type)),
+    };
+
+    let free_text = field.field_type == "choice" && field.flags & FIELD_EDIT != 0;
+    if !field.options.is_empty() && !free_text {
+        if let Some(unknown) = values
+            .iter()
+            .find(|v| !(field.options.contains(v) || is_button && v.as_str() == "Off"))
+        {
+            return Err(format!(
+                "'{}' is not one of {}",
+                unknown,
+                field.options.join(", ")
+            ));
+        }
+    }
+    let id = node
+        .id
+        .ok_or_else(|| "the field is not an indirect object".to_string())?;
+
+    let object = if is_button {
+        Object::Name(values[0].as_bytes().to_vec())
+    } else if values.len() == 1 {
+        encode_text_string(&values[0])
+    } else {
+        Object::Array(values.iter().map(|v| encode_text_string(v)).collect())
+    };
+    let list_box = field.field_type == "choice" && field.flags & FIELD_COMBO == 0;
+    let dict = doc
+        .get_dictionary_mut(id)
+        .map_err(|e| format!("the field cannot be written: {}", e))?;
+    dict.set("V", object);
+    if list_box {
+        // The indices of the selected options, which list boxes with
+        // repeated options need.
+        let indices: Vec<Object> = field
+            .options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| values.contains(option))
+            .map(|(index, _)| (index as i64).into())
+            .collect();
+        dict.set("I", indices);
+    }
+
+    if is_button {
+        let state = &values[0];
+        for (widget, &widget_id) in field.widgets.iter_mut().zip(&node.widget_ids) {
+            // A checkbox widget without appearances gets generated ones for
+            // the state being set.
+            if widget.on_state.is_none() && field.field_type == "checkbox" && state != "Off" {
+                widget.on_state = Some(state.clone());
+            }
+            let selected = widget.on_state.as_ref() == Some(state);
+            if let Some(widget_id) = widget_id {
+                if let Ok(dict) = doc.get_dictionary_mut(widget_id) {
+                    let shown = if selected { state.as_str() } else { "Off" };
+                    dict.set("AS", Object::Name(shown.as_bytes().to_vec()));
+                }
+            }
+        }
+    }
+    field.value = values.first().cloned();
+    field.values = values;
+    Ok(())
+}
+
+/// Regenerates the appearance streams of the widgets of `node`. Checkboxes
+/// and radio buttons keep their appearances, which show states rather than
+/// values, and only get generated ones when they have none for a state.
+/// Widgets whose font cannot show the value keep their appearance, and the
+/// reason is returned once the others are written.
+fn write_appearances(doc: &mut lopdf::Document, node: &FieldNode) -> Result<(), String> {
+    let mut result = Ok(());
+    for (widget, &widget_id) in node.field.widgets.iter().zip(&node.widget_ids) {
+        let Some(widget_id) = widget_id else {
+            continue;
+        };
+        let normal = match node.field.field_type.as_str() {
+            "text" | "choice" => {
+                match text_appearance(doc, &node.field, &node.attributes, widget.rect) {
+                    Ok(stream) => Object::Reference(doc.add_object(stream)),
+                    Err(message) => {
+                        result = Err(message);
+                        continue;
+                    }
+                }
+            }
+            "checkbox" | "radio" => {
+                let Some(on) = &widget.on_state else {
+                    continue;
+                };
+                let existing = doc
+                    .get_dictionary(widget_id)
+                    .ok()
+                    .and_then(|widget| on_state(doc, widget));
+                if existing.as_ref() == Some(on) {
+                    continue;
+                }
+                let radio = node.field.field_type == "radio";
+                let on_stream = doc.add_object(button_appearance(widget.rect, radio));
+                let off_stream =
+                    doc.add_object(form_xobject(widget.rect, Dictionary::new(), vec![]));
+                Object::Dictionary(Dictionary::from_iter(vec![
+                    (on.as_str(), on_stream.into()),
+                    ("Off", off_stream.into()),
+                ]))
+            }
+            _ => continue,
+        };
+        if let Ok(dict) = doc.get_dictionary_mut(widget_id) {
+            dict.set("AP", Dictionary::from_iter(vec![("N", normal)]));
+        }
+    }
+    result
+}
+
+/// Space left between a field's border and its text.
+const PADDING: f32 = 2.0;
+
+/// A form XObject of the size of `rect`, drawing `operations`.
+fn form_xobject(rect: Rect, resources: Dictionary, operations: Vec<Operation>) -> Stream {
+    let content = Content { operations }.encode().unwrap_or_default();
+    Stream::new(
+        Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Form".to_vec())),
+            (
+                "BBox",
+                vec![
+                    0.into(),
+                    0.into(),
+                    rect.width().into(),
+                    rect.height().into(),
+                ]
+                .into(),
+            ),
+            ("Resources", resources.into()),
+        ]),
+        content,
+    )
+}
+
+/// The "on" appearance of a checkbox (a check mark) or radio button (a
+/// dot), drawn with ZapfDingbats.
+fn button_appearance(rect: Rect, radio: bool) -> Stream {
+    let (glyph, width) = if radio { (b"l", 791.0) } else { (b"4", 846.0) };
+    let size = rect.width().min(rect.height()) * 0.8;
+    let x = (rect.width() - size * width / 1000.0) / 2.0;
+    let y = (rect.height() - size * 0.7) / 2.0;
+    let font = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Font".to_vec())),
+        ("Subtype", Object::Name(b"Type1".to_vec())),
+        ("BaseFont", Object::Name(b"ZapfDingbats".to_vec())),
+    ]);
+    let resources = Dictionary::from_iter(vec![(
+        "Font",
+        Dictionary::from_iter(vec![("ZaDb", font.into())]).into(),
+    )]);
+    let operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("BT", vec![]),
+        Operation::new("g", vec![0.into()]),
+        Operation::new("Tf", vec!["ZaDb".into(), size.into()]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+        Operation::new("Tj", vec![Object::string_literal(glyph.to_vec())]),
+        Operation::new("ET", vec![]),
+        Operation::new("Q", vec![]),
+    ];
+    form_xobject(rect, resources, operations)
+}
+
+/// A font that appearance text is set in.
+struct AppearanceFont {
+    resource: Vec<u8>,
+    font: Object,
+    base_font: String,
+    kind: AppearanceEncoding,
+}
+
+/// How an `AppearanceFont` turns characters into codes.
+enum AppearanceEncoding {
+    Simple {
+        encoding: BaseEncoding,
+        first_char: i64,
+        widths: Vec<f32>,
+    },
+    /// A composite font, written with the code its ToUnicode CMap gives
+    /// each character.
+    Composite {
+        codes: HashMap<char, Vec<u8>>,
+        font: Box<PdfFont>,
+    },
+}
+
+impl AppearanceFont {
+    /// The font named `resource` in the form's default resources, if it is
+    /// a simple font or a composite font with a ToUnicode CMap, or else
+    /// Helvetica.
+    fn find(doc: &lopdf::Document, resource: &[u8]) -> Self {
+        let font = acroform(doc)
+            .and_then(|form| form.get(b"DR").ok())
+            .and_then(|dr| doc.dereference(dr).ok())
+            .and_then(|(_, dr)| dr.as_dict().ok()?.get(b"Font").ok())
+            .and_then(|fonts| doc.dereference(fonts).ok())
+            .and_then(|(_, fonts)| fonts.as_dict().ok()?.get(resource).ok());
+        let dict = font
+            .and_then(|f| doc.dereference(f).ok())
+            .and_then(|(_, f)| f.as_dict().ok());
+        let kind = dict.and_then(
+            |dict| match dict.get(b"Subtype").and_then(Object::as_name) {
+                Ok(b"Type1" | b"TrueType") => Some(Self::simple(doc, dict)),
+                Ok(b"Type0") => Self::composite(doc, dict),
+                _ => None,
+            },
+        );
+        let (Some(font), Some(dict), Some(kind)) = (font, dict, kind) else {
+            return AppearanceFont {
+                resource: b"Helv".to_vec(),
+                font: Object::Dictionary(Dictionary::from_iter(vec![
+                    ("Type", Object::Name(b"Font".to_vec())),
+                    ("Subtype", Object::Name(b"Type1".to_vec())),
+                    ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+                    ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+                ])),
+                base_font: "Helvetica".to_string(),
+                kind: AppearanceEncoding::Simple {
+                    encoding: BaseEncoding::WinAnsi,
+                    first_char: 0,
+                    widths: Vec::new(),
+                },
+            };
+        };
+        AppearanceFont {
+            resource: resource.to_vec(),
+            font: font.clone(),
+            base_font: dict
+                .get(b"BaseFont")
+                .and_then(Object::as_name)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default(),
+            kind,
+        }
+    }
+
+    fn simple(doc: &lopdf::Document, dict: &Dictionary) -> AppearanceEncoding {
+        let encoding = match dict.get(b"Encoding").map(|e| doc.dereference(e)) {
+            Ok(Ok((_, Object::Name(name)))) => BaseEncoding::from_name(name),
+            Ok(Ok((_, Object::Dictionary(encoding)))) => encoding
+                .get(b"BaseEncoding")
+                .and_then(Object::as_name)
+                .ok()
+                .and_then(BaseEncoding::from_name),
+            _ => None,
+        };
+        let widths = dict
+            .get(b"Widths")
+            .ok()
+            .and_then(|w| doc.dereference(w).ok())
+            .and_then(|(_, w)| w.as_array().ok())
+            .map(|widths| {
+                widths
+                    .iter()
+                    .map(|w| {
+                        doc.dereference(w)
+                            .ok()
+                            .and_then(|(_, w)| w.as_float().ok())
+                            .unwrap_or(0.0)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        AppearanceEncoding::Simple {
+            encoding: encoding.unwrap_or(BaseEncoding::Standard),
+            first_char: dict.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0),
+            widths,
+        }
+    }
+
+    /// The encoding of a composite font, which is only known from its
+    /// ToUnicode CMap.
+    fn composite(doc: &lopdf::Document, dict: &Dictionary) -> Option<AppearanceEncoding> {
+        let (_, to_unicode) = doc.dereference(dict.get(b"ToUnicode").ok()?).ok()?;
+        let to_unicode = ToUnicode::parse(&to_unicode.as_stream().ok()?.get_plain_content().ok()?);
+        let mut codes: HashMap<char, Vec<u8>> = HashMap::new();
+        for (len, code, text) in to_unicode.entries() {
+            let mut chars = text.chars();
+            let (Some(ch), None) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            let bytes = code.to_be_bytes()[4 - len.min(4)..].to_vec();
+            // The lowest code wins when several show the same character.
+            codes
+                .entry(ch)
+                .and_modify(|existing| *existing = existing.clone().min(bytes.clone()))
+                .or_insert(bytes);
+        }
+        if codes.is_empty() {
+            return None;
+        }
+        Some(AppearanceEncoding::Composite {
+            codes,
+            font: Box::new(PdfFont::from_dict(doc, dict)),
+        })
+    }
+
+    /// The code of `ch`, if the font can show it.
+    fn code(&self, ch: char) -> Option<Vec<u8>> {
+        match &self.kind {
+            AppearanceEncoding::Simple { encoding, .. } => encoding.encode(ch).map(|c| vec![c]),
+            AppearanceEncoding::Composite { codes, .. } => codes.get(&ch).cloned(),
+        }
+    }
+
+    /// Encodes `text`, or returns why the font cannot show it.
+    fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::with_capacity(text.len());
+        for ch in text.chars() {
+            let code = self
+                .code(ch)
+                .ok_or_else(|| format!("the font '{}' cannot show '{}'", self.base_font, ch))?;
+            bytes.extend(code);
+        }
+        Ok(bytes)
+    }
+
+    /// The width of `text` at size 1, leaving out characters the font
+    /// cannot show.
+    fn width(&self, text: &str) -> f32 {
+        text.chars()
+            .filter_map(|ch| self.code(ch))
+            .map(|code| match &self.kind {
+                AppearanceEncoding::Simple {
+                    first_char, widths, ..
+                } => {
+                    let index = code[0] as i64 - first_char;
+                    let width = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| widths.get(index))
+                        .copied()
+                        .filter(|&w| w > 0.0);
+                    width
+                        .or_else(|| standard_font_width(&self.base_font, code[0]))
+                        .unwrap_or(556.0)
+                        / 1000.0
+                }
+                AppearanceEncoding::Composite { font, .. } => {
+                    font.decode(&code).iter().map(|glyph| glyph.width).sum()
+                }
+            })
+            .sum()
+    }
+}
+
+/// The appearance of a text or choice field widget of size `rect`, set as
+/// the field's default appearance string (`/DA`) asks, or why its font
+/// cannot show the text.
+fn text_appearance(
+    doc: &lopdf::Document,
+    field: &FormField,
+    attributes: &Dictionary,
+    rect: Rect,
+) -> Result<Stream, String> {
+    let default_appearance = attributes
+        .get(b"DA")
+        .ok()
+        .or_else(|| acroform(doc)?.get(b"DA").ok())
+        .and_then(|da| doc.dereference(da).ok())
+        .and_then(|(_, da)| da.as_str().ok())
+        .and_then(|da| Content::decode(da).ok())
+        .map(|content| content.operations)
+        .unwrap_or_default();
+    let mut font_resource = b"Helv".to_vec();
+    let mut size = 0.0;
+    let mut colour = Vec::new();
+    for operation in default_appearance {
+        match operation.operator.as_str() {
+            "Tf" => {
+                if let [Object::Name(name), font_size] = operation.operands.as_slice() {
+                    font_resource = name.clone();
+                    size = font_size.as_float().unwrap_or(0.0);
+                }
+            }
+            "g" | "rg" | "k" => colour.push(operation),
+            _ => {}
+        }
+    }
+    let font = AppearanceFont::find(doc, &font_resource);
+
+    // Each line with whether it is a selected list box option.
+    let list_box = field.field_type == "choice" && field.flags & FIELD_COMBO == 0;
+    let multiline = field.field_type == "text" && field.flags & FIELD_MULTILINE != 0;
+    let value = field.value.clone().unwrap_or_default();
+    let mut lines: Vec<(String, bool)> = if list_box {
+        field
+            .options
+            .iter()
+            .zip(&field.option_labels)
+            .map(|(option, label)| (label.clone(), field.values.contains(option)))
+            .collect()
+    } else if field.field_type == "choice" {
+        let label = field
+            .options
+            .iter()
+            .position(|option| *option == value)
+            .and_then(|index| field.option_labels.get(index))
+            .cloned();
+        vec![(label.unwrap_or(value), false)]
+    } else if field.flags & FIELD_PASSWORD != 0 {
+        vec![("*".repeat(value.chars().count()), false)]
+    } else {
+        value
+            .lines()
+            .map(|line| (line.to_string(), false))
+            .collect()
+    };
+
+    let (width, height) = (rect.width(), rect.height());
+    let available = width - 2.0 * PADDING;
+    if size <= 0.0 {
+        size = if list_box || multiline {
+            12.0
+        } else {
+            let widest = lines
+                .iter()
+                .map(|(line, _)| font.width(line))
+                .fold(0.0, f32::max);
+            let fit = if widest > 0.0 {
+                available / widest
+            } else {
+                f32::MAX
+            };
+            ((height - 2.0 * PADDING) / 1.15).min(fit).max(4.0)
+        };
+    }
+    if multiline {
+        lines = lines
+            .into_iter()
+            .flat_map(|(line, _)| wrap(&font, &line, available / size))
+            .map(|line| (line, false))
+            .collect();
+    }
+
+    let quadding = attributes.get(b"Q").and_then(Object::as_i64).unwrap_or(0);
+    let line_height = size * 1.15;
+    let mut operations = vec![
+        Operation::new("BMC", vec!["Tx".into()]),
+        Operation::new("q", vec![]),
+    ];
+    if list_box {
+        for (index, (_, selected)) in lines.iter().enumerate() {
+            if *selected {
+                let top = height - PADDING - line_height * index as f32;
+                operations.push(Operation::new(
+                    "rg",
+                    vec![0.6.into(), 0.75.into(), 0.86.into()],
+                ));
+                operations.push(Operation::new(
+                    "re",
+                    vec![
+                        0.into(),
+                        (top - line_height).into(),
+                        width.into(),
+                        line_height.into(),
+                    ],
+                ));
+                operations.push(Operation::new("f", vec![]));
+            }
+        }
+    }
+    operations.extend([
+        Operation::new(
+            "re",
+            vec![
+                (PADDING / 2.0).into(),
+                (PADDING / 2.0).into(),
+                (width - PADDING).into(),
+                (height - PADDING).into(),
+            ],
+        ),
+        Operation::new("W", vec![]),
+        Operation::new("n", vec![]),
+        Operation::new("BT", vec![]),
+    ]);
+    operations.extend(colour);
+    operations.push(Operation::new(
+        "Tf",
+        vec![Object::Name(font.resource.clone()), size.into()],
+    ));
+    for (index, (line, _)) in lines.iter().enumerate() {
+        let y = if list_box || multiline {
+            height - PADDING - line_height * index as f32 - size * 0.9
+        } else {
+            (height - size) / 2.0 + size * 0.22
+        };
+        let line_width = font.width(line) * size;
+        let x = match quadding {
+            1 => (width - line_width) / 2.0,
+            2 => width - PADDING - line_width,
+            _ => PADDING,
+        };
+        operations.push(Operation::new(
+            "Tm",
+            vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()],
+        ));
+        operations.push(Operation::new(
+            "Tj",
+            vec![Object::string_literal(font.encode(line)?)],
+        ));
+    }
+    operations.extend([
+        Operation::new("ET", vec![]),
+        Operation::new("Q", vec![]),
+        Operation::new("EMC", vec![]),
+    ]);
+
+    let resources = Dictionary::from_iter(vec![(
+        "Font",
+        Dictionary::from_iter(vec![(font.resource.clone(), font.font.clone())]).into(),
+    )]);
+    Ok(form_xobject(rect, resources, operations))
+}
+
+/// Breaks `text` into lines no wider than `max_width` (at size 1), at
+/// spaces where possible.
+fn wrap(font: &AppearanceFont, text: &str, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if font.width(&candidate) <= max_width || line.is_empty() {
+            line = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Annotation flags (`/F`) that keep a widget from being shown.
+const HIDDEN_ANNOTATION: i64 = 1 << 1 | 1 << 5;
+
+/// Draws the appearance of every widget into its page's content, removes
+/// the widgets, and removes the form. Fails, before changing any page, if a
+/// widget has no appearance and its font cannot show the field's value.
+fn flatten(
+    doc: &mut lopdf::Document,
+    nodes: &[FieldNode],
+    pages: &[ObjectId],
+) -> Result<(), FormError> {
+    let mut drawings: BTreeMap<usize, Vec<(ObjectId, Matrix)>> = BTreeMap::new();
+    let mut widgets = HashSet::new();
+    for node in nodes {
+        for (widget, &widget_id) in node.field.widgets.iter().zip(&node.widget_ids) {
+            let Some(widget_id) = widget_id else {
+                continue;
+            };
+            widgets.insert(widget_id);
+            let Some(page) = widget.page else {
+                continue;
+            };
+            let Ok(dict) = doc.get_dictionary(widget_id) else {
+                continue;
+            };
+            if dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) & HIDDEN_ANNOTATION != 0 {
+                continue;
+            }
+            let appearance = match normal_appearance(doc, dict) {
+                Some(appearance) => appearance,
+                None if matches!(node.field.field_type.as_str(), "text" | "choice") => {
+                    let stream = text_appearance(doc, &node.field, &node.attributes, widget.rect)
+                        .map_err(|message| FormError::Value {
+                        name: node.field.name.clone(),
+                        message,
+                    })?;
+                    doc.add_object(stream)
+                }
+                None => continue,
+            };
+            if let Some(matrix) = placement(doc, appearance, widget.rect) {
+                drawings.entry(page).or_default().push((appearance, matrix));
+            }
+        }
+    }
+
+    for (index, &page_id) in pages.iter().enumerate() {
+        remove_annotations(doc, page_id, &widgets);
+        if let Some(drawn) = drawings.remove(&index) {
+            draw_on_page(doc, page_id, drawn);
+        }
+    }
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"AcroForm");
+    }
+    doc.prune_objects();
+    Ok(())
+}
+
+/// The id of the appearance stream a widget shows, choosing by `/AS`
+/// among several states.
+fn normal_appearance(doc: &lopdf::Document, widget: &Dictionary) -> Option<ObjectId> {
+    let appearances = doc
+        .dereference(widget.get(b"AP").ok()?)
+        .ok()?
+        .1
+        .as_dict()
+        .ok()?;
+    let normal = appearances.get(b"N").ok()?;
+    let normal = match doc.dereference(normal).ok()? {
+        (_, Object::Dictionary(states)) => {
+            let state = widget.get(b"AS").and_then(Object::as_name).ok()?;
+            states.get(state).ok()?
+        }
+        _ => normal,
+    };
+    let id = normal.as_reference().ok()?;
+    doc.get_object(id).ok()?.as_stream().ok()?;
+    Some(id)
+}
+
+/// The matrix that maps the appearance stream `id` onto `rect`
+/// (ISO 32000-1, 12.5.5).
+fn placement(doc: &lopdf::Document, id: ObjectId, rect: Rect) -> Option<Matrix> {
+    let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+    let bbox = rect_from_object(doc, stream.dict.get(b"BBox").ok()?)?;
+    let matrix = stream
+        .dict
+        .get(b"Matrix")
+        .ok()
+        .and_then(|m| m.as_array().ok())
+        .map(|m| {
+            m.iter()
+                .filter_map(|v| v.as_float().ok())
+                .collect::<Vec<f32>>()
+        })
+        .and_then(|m| Matrix::from_slice(&m))
+        .unwrap_or_default();
+    let corners = [
+        matrix.transform_point(bbox.x0, bbox.y0),
+        matrix.transform_point(bbox.x1, bbox.y0),
+        matrix.transform_point(bbox.x0, bbox.y1),
+        matrix.transform_point(bbox.x1, bbox.y1),
+    ];
+    let x0 = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min);
+    let x1 = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max);
+    let y0 = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min);
+    let y1 = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max);
+    if x1 - x0 <= 0.0 || y1 - y0 <= 0.0 {
+        return None;
+    }
+    let (sx, sy) = (rect.width() / (x1 - x0), rect.height() / (y1 - y0));
+    Some(Matrix::new(
+        sx,
+        0.0,
+        0.0,
+        sy,
+        rect.x0 - sx * x0,
+        rect.y0 - sy * y0,
+    ))
+}
+
+fn remove_annotations(doc: &mut lopdf::Document, page_id: ObjectId, removed: &HashSet<ObjectId>) {
+    let Some(annotations) = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Annots").ok())
+        .and_then(|a| doc.dereference(a).ok())
+        .and_then(|(_, a)| a.as_array().ok())
+    else {
+        return;
+    };
+    let kept: Vec<Object> = annotations
+        .iter()
+        .filter(|a| !a.as_reference().is_ok_and(|id| removed.contains(&id)))
+        .cloned()
+        .collect();
+    if let Ok(page) = doc.get_dictionary_mut(page_id) {
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", kept);
+        }
+    }
+}
+
+/// Draws the form XObjects `drawn` after the page's content, which is
+/// wrapped in `q`/`Q` so that it cannot move them.
+fn draw_on_page(doc: &mut lopdf::Document, page_id: ObjectId, drawn: Vec<(ObjectId, Matrix)>) {
+    let mut resources = inherited_attribute(doc, page_id, b"Resources")
+        .and_then(|r| r.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let mut xobjects = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|x| doc.dereference(x).ok())
+        .and_then(|(_, x)| x.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let mut operations = vec![Operation::new("Q", vec![])];
+    let mut counter = 0;
+    for (appearance, matrix) in drawn {
+        let name = loop {
+            counter += 1;
+            let name = format!("Fld{}", counter);
+            if !xobjects.has(name.as_bytes()) {
+                break name;
+            }
+        };
+        xobjects.set(name.clone(), appearance);
+        operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new(
+                "cm",
+                [matrix.a, matrix.b, matrix.c, matrix.d, matrix.e, matrix.f]
+                    .into_iter()
+                    .map(Object::from)
+                    .collect(),
+            ),
+            Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+            Operation::new("Q", vec![]),
+        ]);
+    }
+    resources.set("XObject", xobjects);
+
+    let mut contents: Vec<Object> = match doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Contents").ok())
+    {
+        Some(Object::Array(parts)) => parts.clone(),
+        Some(Object::Reference(id)) => match doc.get_object(*id) {
+            Ok(Object::Array(parts)) => parts.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        _ => Vec::new(),
+    };
+    let save = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let content = Content { operations }.encode().unwrap_or_default();
+    let draw = doc.add_object(Stream::new(Dictionary::new(), content));
+    contents.insert(0, save.into());
+    contents.push(draw.into());
+    if let Ok(page) = doc.get_dictionary_mut(page_id) {
+        page.set("Contents", contents);
+        page.set("Resources", resources);
+    }
+}
+
+/// Writes the form fields of generated pages, creating the parent fields
+/// that dotted names imply.
+#[derive(Default)]
+pub(crate) struct FieldWriter {
+    /// The fields without a parent, for the form's `/Fields`.
+    roots: Vec<ObjectId>,
+    /// Field ids by fully qualified name, with whether the field is
+    /// terminal rather than a parent.
+    fields: HashMap<String, (ObjectId, bool)>,
+    /// Set when a field's value cannot be shown in Helvetica, so that its
+    /// appearance is left to the viewer.
+    need_appearances: bool,
+}
+
+/// The default appearance of generated text: Helvetica in black, of
+/// `font_size` points or fitted to the field if 0.
+fn default_appearance(font_size: f32) -> Object {
+    Object::string_literal(format!("/Helv {} Tf 0 g", font_size))
+}
+
+fn field_flags(required: bool, read_only: bool) -> u32 {
+    let mut flags = 0;
+    if required {
+        flags |= FIELD_REQUIRED;
+    }
+    if read_only {
+        flags |= FIELD_READ_ONLY;
+    }
+    flags
+}
+
+/// A printed widget annotation at `rect` on the page `page_id`.
+fn widget_dictionary(rect: Rect, page_id: ObjectId) -> Dictionary {
+    let rect = rect.normalized();
+    Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Annot".to_vec())),
+        ("Subtype", Object::Name(b"Widget".to_vec())),
+        (
+            "Rect",
+            vec![
+                rect.x0.into(),
+                rect.y0.into(),
+                rect.x1.into(),
+                rect.y1.into(),
+            ]
+            .into(),
+        ),
+        ("P", page_id.into()),
+        ("F", 4.into()),
+    ])
+}
+
+/// Appearances of a checkbox or radio button widget showing `on` when
+/// selected, with the button's caption for viewers that redraw it.
+fn button_appearances(
+    pdf: &mut lopdf::Document,
+    widget: &mut Dictionary,
+    rect: Rect,
+    on: &str,
+    radio: bool,
+) {
+    let rect = rect.normalized();
+    let on_stream = pdf.add_object(button_appearance(rect, radio));
+    let off_stream = pdf.add_object(form_xobject(rect, Dictionary::new(), vec![]));
+    let normal = Dictionary::from_iter(vec![(on, on_stream.into()), ("Off", off_stream.into())]);
+    widget.set("AP", Dictionary::from_iter(vec![("N", normal.into())]));
+    let caption = if radio { "l" } else { "4" };
+    widget.set(
+        "MK",
+        Dictionary::from_iter(vec![("CA", Object::string_literal(caption))]),
+    );
+}
+
+impl FieldWriter {
+    /// Writes the fields of `page`, whose id is `page_id`, and returns the
+    /// ids of their widget annotations.
+    pub fn write_page(
+        &mut self,
+        pdf: &mut lopdf::Document,
+        page: &Page,
+        page_id: ObjectId,
+    ) -> Result<Vec<ObjectId>, String> {
+        let mut widgets = Vec::new();
+        for field in &page.text_fields {
+            let mut flags = field_flags(field.required, field.read_only);
+            if field.multiline {
+                flags |= FIELD_MULTILINE;
+            }
+            let mut dict = widget_dictionary(field.rect, page_id);
+            dict.set("FT", Object::Name(b"Tx".to_vec()));
+            dict.set("Ff", flags as i64);
+            dict.set("DA", default_appearance(field.font_size));
+            if let Some(max_length) = field.max_length {
+                dict.set("MaxLen", max_length as i64);
+            }
+            if let Some(value) = &field.value {
+                if field
+                    .max_length
+                    .is_some_and(|max| value.chars().count() > max as usize)
+                {
+                    return Err(format!(
+                        "form field '{}': value is longer than its maximum length",
+                        field.name
+                    ));
+                }
+                dict.set("V", encode_text_string(value));
+                dict.set("DV", encode_text_string(value));
+            }
+            let appearance = FormField {
+                field_type: "text".to_string(),
+                value: field.value.clone(),
+                values: field.value.iter().cloned().collect(),
+                flags,
+                ..Default::default()
+            };
+            self.set_appearance(pdf, &appearance, &mut dict, field.rect);
+            widgets.push(self.add_field(pdf, &field.name, dict)?);
+        }
+
+        for field in &page.checkboxes {
+            let on = field.export_value.as_str();
+            if on.is_empty() || on == "Off" {
+                return Err(format!(
+                    "form field '{}': export value must not be empty or 'Off'",
+                    field.name
+                ));
+            }
+            let state = Object::Name(if field.checked { on } else { "Off" }.as_bytes().to_vec());
+            let mut dict = widget_dictionary(field.rect, page_id);
+            dict.set("FT", Object::Name(b"Btn".to_vec()));
+            dict.set("Ff", field_flags(field.required, field.read_only) as i64);
+            dict.set("V", state.clone());
+            dict.set("DV", state.clone());
+            dict.set("AS", state);
+            button_appearances(pdf, &mut dict, field.rect, on, false);
+            widgets.push(self.add_field(pdf, &field.name, dict)?);
+        }
+
+        for group in &page.radio_groups {
+            if group.buttons.is_empty() {
+                return Err(format!(
+                    "form field '{}': radio group has no buttons",
+                    group.name
+                ));
+            }
+            if let Some(selected) = &group.selected {
+                if !group.buttons.iter().any(|b| b.value == *selected) {
+                    return Err(format!(
+                        "form field '{}': no button has the value '{}'",
+                        group.name, selected
+                    ));
+                }
+            }
+            if let Some(button) = group
+                .buttons
+                .iter()
+                .find(|b| b.value.is_empty() || b.value == "Off")
+            {
+                return Err(format!(
+                    "form field '{}': button value '{}' must not be empty or 'Off'",
+                    group.name, button.value
+                ));
+            }
+            let selected = group.selected.as_deref().unwrap_or("Off");
+            let flags =
+                field_flags(group.required, group.read_only) | FIELD_RADIO | FIELD_NO_TOGGLE_TO_OFF;
+            let dict = Dictionary::from_iter(vec![
+                ("FT", Object::Name(b"Btn".to_vec())),
+                ("Ff", (flags as i64).into()),
+                ("V", Object::Name(selected.as_bytes().to_vec())),
+                ("DV", Object::Name(selected.as_bytes().to_vec())),
+                ("Kids", Object::Array(vec![])),
+            ]);
+            let group_id = self.add_field(pdf, &group.name, dict)?;
+            for button in &group.buttons {
+                let state = if button.value == selected {
+                    button.value.as_str()
+                } else {
+                    "Off"
+                };
+                let mut widget = widget_dictionary(button.rect, page_id);
+                widget.set("Parent", group_id);
+                widget.set("AS", Object::Name(state.as_bytes().to_vec()));
+                button_appearances(pdf, &mut widget, button.rect, &button.value, true);
+                let widget_id = pdf.add_object(widget);
+                self.link(pdf, Some(group_id), widget_id);
+                widgets.push(widget_id);
+            }
+        }
+
+        for field in &page.dropdowns {
+            if let Some(value) = &field.value {
+                if !field.editable && !field.options.contains(value) {
+                    return Err(format!(
+                        "form field '{}': '{}' is not one of its options",
+                        field.name, value
+                    ));
+                }
+            }
+            let mut flags = field_flags(field.required, field.read_only) | FIELD_COMBO;
+            if field.editable {
+                flags |= FIELD_EDIT;
+            }
+            let mut dict = widget_dictionary(field.rect, page_id);
+            dict.set("FT", Object::Name(b"Ch".to_vec()));
+            dict.set("Ff", flags as i64);
+            dict.set("DA", default_appearance(field.font_size));
+            dict.set(
+                "Opt",
+                field
+                    .options
+                    .iter()
+                    .map(|option| encode_text_string(option))
+                    .collect::<Vec<Object>>(),
+            );
+            if let Some(value) = &field.value {
+                dict.set("V", encode_text_string(value));
+                dict.set("DV", encode_text_string(value));
+            }
+            let appearance = FormField {
+                field_type: "choice".to_string(),
+                value: field.value.clone(),
+                values: field.value.iter().cloned().collect(),
+                options: field.options.clone(),
+                option_labels: field.options.clone(),
+                flags,
+                ..Default::default()
+            };
+            self.set_appearance(pdf, &appearance, &mut dict, field.rect);
+            widgets.push(self.add_field(pdf, &field.name, dict)?);
+        }
+
+        for field in &page.signature_fields {
+            let rect = field.rect.normalized();
+            let mut dict = widget_dictionary(rect, page_id);
+            dict.set("FT", Object::Name(b"Sig".to_vec()));
+            dict.set("Ff", field_flags(field.required, false) as i64);
+            // A line to sign on, as placeholders usually show.
+            let y = rect.height() * 0.25;
+            let line = vec![
+                Operation::new("w", vec![0.5.into()]),
+                Operation::new("G", vec![0.into()]),
+                Operation::new("m", vec![PADDING.into(), y.into()]),
+                Operation::new("l", vec![(rect.width() - PADDING).into(), y.into()]),
+                Operation::new("S", vec![]),
+            ];
+            let stream_id = pdf.add_object(form_xobject(rect, Dictionary::new(), line));
+            dict.set("AP", Dictionary::from_iter(vec![("N", stream_id.into())]));
+            widgets.push(self.add_field(pdf, &field.name, dict)?);
+        }
+        Ok(widgets)
+    }
+
+    /// Gives the text or choice field widget `dict` an appearance showing
+    /// `field`, or marks the form as needing appearances if Helvetica
+    /// cannot show its text.
+    fn set_appearance(
+        &mut self,
+        pdf: &mut lopdf::Document,
+        field: &FormField,
+        dict: &mut Dictionary,
+        rect: Rect,
+    ) {
+        match text_appearance(pdf, field, dict, rect.normalized()) {
+            Ok(stream) => {
+                let stream_id = pdf.add_object(stream);
+                dict.set("AP", Dictionary::from_iter(vec![("N", stream_id.into())]));
+            }
+            Err(_) => self.need_appearances = true,
+        }
+    }
+
+    /// The `/AcroForm` dictionary, or `None` if no fields were written.
+    pub fn finish(self, pdf: &mut lopdf::Document) -> Option<Dictionary> {
+        if self.roots.is_empty() {
+            return None;
+        }
+        let helvetica = pdf.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]));
+        let dingbats = pdf.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"ZapfDingbats".to_vec())),
+        ]));
+        let fonts =
+            Dictionary::from_iter(vec![("Helv", helvetica.into()), ("ZaDb", dingbats.into())]);
+        let roots: Vec<Object> = self.roots.into_iter().map(Object::from).collect();
+        let mut form = Dictionary::from_iter(vec![
+            ("Fields", roots.into()),
+            ("DA", default_appearance(0.0)),
+            (
+                "DR",
+                Dictionary::from_iter(vec![("Font", fonts.into())]).into(),
+            ),
+        ]);
+        if self.need_appearances {
+            form.set("NeedAppearances", true);
+        }
+        Some(form)
+    }
+
+    /// Adds the terminal field `dict` named `name` under the parents its
+    /// name implies, and returns its id.
+    fn add_field(
+        &mut self,
+        pdf: &mut lopdf::Document,
+        name: &str,
+        dict: Dictionary,
+    ) -> Result<ObjectId, String> {
+        if self.fields.contains_key(name) {
+            return Err(format!("duplicate form field name '{}'", name));
+        }
+        self.add_node(pdf, name, dict, true)
+    }
+
+    fn add_node(
+        &mut self,
+        pdf: &mut lopdf::Document,
+        name: &str,
+        mut dict: Dictionary,
+        terminal: bool,
+    ) -> Result<ObjectId, String> {
+        if name.split('.').any(str::is_empty) {
+            return Err(format!("invalid form field name '{}'", name));
+        }
+        let (parent, partial) = match name.rsplit_once('.') {
+            Some((parent, partial)) => (Some(self.parent(pdf, parent)?), partial),
+            None => (None, name),
+        };
+        dict.set("T", encode_text_string(partial));
+        if let Some(parent) = parent {
+            dict.set("Parent", parent);
+        }
+        let id = pdf.add_object(dict);
+        self.link(pdf, parent, id);
+        self.fields.insert(name.to_string(), (id, terminal));
+        Ok(id)
+    }
+
+    /// The parent field named `name`, created if needed.
+    fn parent(&mut self, pdf: &mut lopdf::Document, name: &str) -> Result<ObjectId, String> {
+        match self.fields.get(name) {
+            Some(&(id, false)) => Ok(id),
+            Some(&(_, true)) => Err(format!(
+                "form field '{}' cannot also be the parent of other fields",
+                name
+            )),
+            None => {
+                let dict = Dictionary::from_iter(vec![("Kids", Object::Array(vec![]))]);
+                self.add_node(pdf, name, dict, false)
+            }
+        }
+    }
+
+    fn link(&mut self, pdf: &mut lopdf::Document, parent: Option<ObjectId>, id: ObjectId) {
+        let Some(parent) = parent else {
+            self.roots.push(id);
+            return;
+        };
+        let kids = pdf
+            .get_dictionary_mut(parent)
+            .and_then(|parent| parent.get_mut(b"Kids"))
+            .and_then(Object::as_array_mut);
+        if let Ok(kids) = kids {
+            kids.push(id.into());
+        }
+    }
+}
+
+/// Reads the values to fill in: a mapping of field names to values, or a
+/// JSON object as a string. Numbers are filled in as text.
+fn field_values(values: &Bound<'_, PyAny>) -> PyResult<BTreeMap<String, FieldValue>> {
+    let values = match values.extract::<String>() {
+        Ok(json) => values
+            .py()
+            .import("json")?
+            .call_method1("loads", (json,))
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Failed to read field values: {}",
+                    e
+                ))
+            })?,
+        Err(_) => values.clone(),
+    };
+    let values = values.downcast::<PyDict>().map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Field values must be a mapping of field names to values",
+        )
+    })?;
+    let mut fields = BTreeMap::new();
+    for (name, value) in values.iter() {
+        let name: String = name.extract()?;
+        let value = match value.extract::<FieldValue>() {
+            Ok(value) => value,
+            Err(_) if value.is_instance_of::<PyInt>() || value.is_instance_of::<PyFloat>() => {
+                FieldValue::Text(value.str()?.to_string())
+            }
+            Err(_) => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Invalid value for field '{}': expected a string, bool or list of strings",
+                    name
+                )))
+            }
+        };
+        fields.insert(name, value);
+    }
+    Ok(fields)
+}
+
+#[pyfunction]
+#[pyo3(name = "fill_form", signature = (
+    input_path,
+    output_path,
+    values,
+    *,
+    need_appearances=false,
+    flatten=false,
+    password=None,
+))]
+pub fn py_fill_form(
+    input_path: PdfFile,
+    output_path: PdfFile,
+    values: &Bound<'_, PyAny>,
+    need_appearances: bool,
+    flatten: bool,
+    password: Option<&str>,
+) -> PyResult<()> {
+    let values = field_values(values)?;
+    let data = input_path.read().map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to load PDF: {}", e))
+    })?;
+    let options = FillOptions {
+        need_appearances,
+        flatten,
+    };
+    let filled = fill_form_bytes(&data, password, &values, options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    output_path.write(&filled).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to save PDF: {}", e))
+    })
+}
+
+/// Like `fill_form`, taking and returning the file contents.
+#[pyfunction]
+#[pyo3(name = "fill_form_bytes", signature = (
+    data,
+    values,
+    *,
+    need_appearances=false,
+    flatten=false,
+    password=None,
+))]
+pub fn py_fill_form_bytes<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    values: &Bound<'py, PyAny>,
+    need_appearances: bool,
+    flatten: bool,
+    password: Option<&str>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let values = field_values(values)?;
+    let options = FillOptions {
+        need_appearances,
+        flatten,
+    };
+    let filled = fill_form_bytes(data, password, &values, options)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    Ok(PyBytes::new(py, &filled))
+}
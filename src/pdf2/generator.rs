@@ -1,7 +1,18 @@
-use crate::structure::Document;
-use printpdf::{Mm, PdfDocument};
-use std::fs::File;
-use std::io::BufWriter;
+use crate::annotations::add_annotation;
+use crate::encoding::BaseEncoding;
+use crate::font_registry::{EmbeddedFont, FontRegistry};
+use crate::forms::FieldWriter;
+use crate::images::embed_image;
+use crate::links::link_annotation;
+use crate::metadata::{info_dictionary, xmp_stream};
+use crate::names::{destination_array, name_tree};
+use crate::outline::write_outline;
+use crate::structure::{
+    Document, Image, Page, PageContent, Rect, Shape, TextBlock, LINE_CAPS, LINE_JOINS,
+};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Object, ObjectId, Stream, StringFormat};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 #[derive(Debug, thiserror::Error)]
@@ -12,27 +23,591 @@ pub enum GenerateError {
     Pdf(String),
 }
 
+/// Resource and base font names of the built-in Helvetica faces used for
+/// text blocks, indexed by `bold as usize | (italic as usize) << 1`.
+const STANDARD_FONTS: [(&str, &str); 4] = [
+    ("F1", "Helvetica"),
+    ("F1B", "Helvetica-Bold"),
+    ("F1I", "Helvetica-Oblique"),
+    ("F1BI", "Helvetica-BoldOblique"),
+];
+
+/// Horizontal shear used to slant registered fonts without an italic face.
+const SYNTHETIC_SLANT: f32 = 0.2;
+
+/// Stroke width, relative to the font size, used to embolden registered
+/// fonts without a bold face.
+const SYNTHETIC_BOLD: f32 = 1.0 / 30.0;
+
+/// Writes `doc` as a PDF with one page per `Page`, its contents drawn in
+/// order, each at its own position and size.
 pub fn generate_pdf(doc: &Document, path: &Path) -> Result<(), GenerateError> {
-    let (pdf_doc, page1, layer1) =
-        PdfDocument::new("Generated PDF", Mm(210.0), Mm(297.0), "Layer 1");
-    let current_layer = pdf_doc.get_page(page1).get_layer(layer1);
-    let font = pdf_doc
-        .add_builtin_font(printpdf::BuiltinFont::Helvetica)
-        .unwrap();
-
-    let full_text = doc
-        .pages
-        .iter()
-        .flat_map(|p| p.text_blocks.iter())
-        .map(|tb| tb.text.as_str())
-        .collect::<Vec<&str>>()
-        .join("\n");
-
-    current_layer.use_text(full_text, 12.0, Mm(10.0), Mm(280.0), &font);
-
-    pdf_doc
-        .save(&mut BufWriter::new(File::create(path)?))
-        .map_err(|e| GenerateError::Pdf(e.to_string()))?;
+    generate_pdf_with_fonts(doc, &FontRegistry::default(), path)
+}
 
+/// Like `generate_pdf`, drawing text blocks whose `font_name` is registered
+/// in `fonts` with that font, embedded as a subset.
+pub fn generate_pdf_with_fonts(
+    doc: &Document,
+    fonts: &FontRegistry,
+    path: &Path,
+) -> Result<(), GenerateError> {
+    let mut pdf = build_document(doc, fonts)?;
+    pdf.save(path)?;
     Ok(())
 }
+
+/// Like `generate_pdf_with_fonts`, returning the PDF file contents instead
+/// of writing them to a file.
+pub fn generate_to_bytes(doc: &Document, fonts: &FontRegistry) -> Result<Vec<u8>, GenerateError> {
+    let mut pdf = build_document(doc, fonts)?;
+    let mut bytes = Vec::new();
+    pdf.save_to(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Builds the lopdf document for `doc` without writing it.
+pub(crate) fn build_document(
+    doc: &Document,
+    fonts: &FontRegistry,
+) -> Result<lopdf::Document, GenerateError> {
+    let mut writer = Writer::new(fonts);
+    let mut pages = Vec::with_capacity(doc.pages.len());
+    for page in &doc.pages {
+        pages.push(writer.write_page(page)?);
+    }
+    writer.finish(pages, doc)
+}
+
+/// Images are shared between pages when their format, pixel size and bytes
+/// are all equal.
+type ImageKey<'d> = (&'d str, u32, u32, &'d [u8]);
+
+/// A registered font used in the document, with the id reserved for its
+/// font dictionary and its resource name.
+struct FontUse<'d> {
+    name: &'d str,
+    id: ObjectId,
+    resource: String,
+    font: EmbeddedFont<'d>,
+}
+
+/// Accumulates the objects of a generated document.
+struct Writer<'d> {
+    pdf: lopdf::Document,
+    pages_id: ObjectId,
+    standard_fonts: [Option<ObjectId>; 4],
+    images: HashMap<ImageKey<'d>, ObjectId>,
+    registry: &'d FontRegistry,
+    fonts: Vec<FontUse<'d>>,
+    font_indices: HashMap<&'d str, usize>,
+}
+
+impl<'d> Writer<'d> {
+    fn new(registry: &'d FontRegistry) -> Self {
+        let mut pdf = lopdf::Document::with_version("1.7");
+        let pages_id = pdf.new_object_id();
+        Writer {
+            pdf,
+            pages_id,
+            standard_fonts: [None; 4],
+            images: HashMap::new(),
+            registry,
+            fonts: Vec::new(),
+            font_indices: HashMap::new(),
+        }
+    }
+
+    fn finish(
+        mut self,
+        pages: Vec<ObjectId>,
+        doc: &Document,
+    ) -> Result<lopdf::Document, GenerateError> {
+        for font in &self.fonts {
+            font.font.write(&mut self.pdf, font.id).map_err(|e| {
+                GenerateError::Pdf(format!("Failed to embed font '{}': {}", font.name, e))
+            })?;
+        }
+        let count = pages.len() as i64;
+        let kids: Vec<Object> = pages.iter().map(|&id| id.into()).collect();
+        self.pdf.objects.insert(
+            self.pages_id,
+            Object::Dictionary(Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", kids.into()),
+                ("Count", count.into()),
+            ])),
+        );
+        let mut catalog = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", self.pages_id.into()),
+        ]);
+        let mut destinations = BTreeMap::new();
+        let mut fields = FieldWriter::default();
+        for (page, &page_id) in doc.pages.iter().zip(&pages) {
+            if page.links.is_empty() && page.annotations.is_empty() && !page.has_fields() {
+                continue;
+            }
+            let mut annotations = Vec::with_capacity(page.links.len());
+            for link in &page.links {
+                let annotation = link_annotation(link, &pages).map_err(GenerateError::Pdf)?;
+                annotations.push(self.pdf.add_object(annotation).into());
+                if let (Some(name), Some(destination)) =
+                    (&link.named_destination, &link.destination)
+                {
+                    if !destinations.contains_key(name.as_bytes()) {
+                        let array =
+                            destination_array(destination, &pages).map_err(GenerateError::Pdf)?;
+                        destinations.insert(name.as_bytes().to_vec(), array);
+                    }
+                }
+            }
+            for annotation in &page.annotations {
+                let id = add_annotation(&mut self.pdf, annotation, page_id)
+                    .map_err(GenerateError::Pdf)?;
+                annotations.push(id.into());
+            }
+            let widgets = fields
+                .write_page(&mut self.pdf, page, page_id)
+                .map_err(GenerateError::Pdf)?;
+            annotations.extend(widgets.into_iter().map(Object::from));
+            if let Ok(page) = self.pdf.get_dictionary_mut(page_id) {
+                page.set("Annots", annotations);
+            }
+        }
+        if let Some(form) = fields.finish(&mut self.pdf) {
+            catalog.set("AcroForm", self.pdf.add_object(form));
+        }
+        if !destinations.is_empty() {
+            let dests_id = self
+                .pdf
+                .add_object(name_tree(destinations.into_iter().collect()));
+            catalog.set(
+                "Names",
+                Dictionary::from_iter(vec![("Dests", dests_id.into())]),
+            );
+        }
+        if !doc.outline.is_empty() {
+            let outlines_id =
+                write_outline(&mut self.pdf, &doc.outline, &pages).map_err(GenerateError::Pdf)?;
+            catalog.set("Outlines", outlines_id);
+            catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+        }
+        let metadata = &doc.metadata;
+        if !metadata.is_empty() {
+            let info_id = self.pdf.add_object(info_dictionary(metadata));
+            self.pdf.trailer.set("Info", info_id);
+            catalog.set("Metadata", self.pdf.add_object(xmp_stream(metadata)));
+        }
+        let catalog_id = self.pdf.add_object(catalog);
+        self.pdf.trailer.set("Root", catalog_id);
+        self.pdf.compress();
+        Ok(self.pdf)
+    }
+
+    /// Returns the index into `fonts` of the registered font `name`, or
+    /// `None` if it is not registered.
+    fn font_use(&mut self, name: &'d str) -> Result<Option<usize>, GenerateError> {
+        if let Some(&index) = self.font_indices.get(name) {
+            return Ok(Some(index));
+        }
+        let Some(data) = self.registry.get(name) else {
+            return Ok(None);
+        };
+        let font = EmbeddedFont::new(name, data)
+            .map_err(|e| GenerateError::Pdf(format!("Failed to load font '{}': {}", name, e)))?;
+        let index = self.fonts.len();
+        self.fonts.push(FontUse {
+            name,
+            id: self.pdf.new_object_id(),
+            resource: format!("F{}", index + 2),
+            font,
+        });
+        self.font_indices.insert(name, index);
+        Ok(Some(index))
+    }
+
+    /// Returns the id of the built-in font at `index` in `STANDARD_FONTS`,
+    /// adding its dictionary on first use.
+    fn standard_font(&mut self, index: usize) -> ObjectId {
+        if let Some(id) = self.standard_fonts[index] {
+            return id;
+        }
+        let id = self.pdf.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            (
+                "BaseFont",
+                Object::Name(STANDARD_FONTS[index].1.as_bytes().to_vec()),
+            ),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]));
+        self.standard_fonts[index] = Some(id);
+        id
+    }
+
+    /// Returns the registered font to draw `block` in and whether it has to
+    /// be emboldened and slanted by hand: the `-Bold`, `-Italic` or
+    /// `-BoldItalic` face of `font_name` when one is registered, else
+    /// `font_name` itself.
+    fn registered_font(
+        &mut self,
+        block: &'d TextBlock,
+    ) -> Result<Option<(usize, bool)>, GenerateError> {
+        let Some(name) = block.font_name.as_deref() else {
+            return Ok(None);
+        };
+        let suffix = match (block.bold, block.italic) {
+            (false, false) => None,
+            (true, false) => Some("-Bold"),
+            (false, true) => Some("-Italic"),
+            (true, true) => Some("-BoldItalic"),
+        };
+        let registry = self.registry;
+        if let Some(styled) = suffix.and_then(|s| registry.key(&format!("{}{}", name, s))) {
+            return Ok(self.font_use(styled)?.map(|index| (index, false)));
+        }
+        Ok(self.font_use(name)?.map(|index| (index, suffix.is_some())))
+    }
+
+    /// Text showing operations for `block`, registering the font it uses
+    /// in `page_fonts`.
+    fn text_operations(
+        &mut self,
+        block: &'d TextBlock,
+        page_fonts: &mut Dictionary,
+    ) -> Result<Vec<Operation>, GenerateError> {
+        let (resource, text, synthetic) = match self.registered_font(block)? {
+            Some((index, synthetic)) => {
+                let used = &mut self.fonts[index];
+                page_fonts.set(used.resource.as_str(), used.id);
+                let bytes = used.font.encode(&block.text);
+                (
+                    used.resource.clone(),
+                    Object::String(bytes, StringFormat::Hexadecimal),
+                    synthetic,
+                )
+            }
+            None => {
+                let index = block.bold as usize | (block.italic as usize) << 1;
+                let resource = STANDARD_FONTS[index].0;
+                page_fonts.set(resource, self.standard_font(index));
+                (
+                    resource.to_string(),
+                    Object::String(encode_win_ansi(&block.text), StringFormat::Literal),
+                    false,
+                )
+            }
+        };
+        text_operations(&resource, text, block, synthetic)
+    }
+
+    /// Returns the XObject for `image`, embedding it on first use.
+    fn image_xobject(&mut self, image: &'d Image) -> Result<ObjectId, GenerateError> {
+        let key = (
+            image.format.as_str(),
+            image.pixel_width,
+            image.pixel_height,
+            image.data.as_slice(),
+        );
+        if let Some(&id) = self.images.get(&key) {
+            return Ok(id);
+        }
+        let embedded = embed_image(
+            &image.format,
+            &image.data,
+            image.pixel_width,
+            image.pixel_height,
+        )
+        .map_err(|e| GenerateError::Pdf(format!("Failed to embed image: {}", e)))?;
+        let mut stream = embedded.stream;
+        if let Some(mask) = embedded.soft_mask {
+            stream.dict.set("SMask", self.pdf.add_object(mask));
+        }
+        let id = self.pdf.add_object(stream);
+        self.images.insert(key, id);
+        Ok(id)
+    }
+
+    fn write_page(&mut self, page: &'d Page) -> Result<ObjectId, GenerateError> {
+        let mut operations = Vec::new();
+        let mut xobjects = Dictionary::new();
+        let mut page_fonts = Dictionary::new();
+        for item in &page.contents {
+            match item {
+                PageContent::Text(block) => {
+                    operations.extend(self.text_operations(block, &mut page_fonts)?);
+                }
+                PageContent::Image(image) => {
+                    let name = format!("Im{}", xobjects.len() + 1);
+                    xobjects.set(name.as_str(), self.image_xobject(image)?);
+                    operations.extend(image_operations(&name, image));
+                }
+                PageContent::Shape(shape) => operations.extend(shape_operations(shape)?),
+            }
+        }
+        let content = Content { operations }
+            .encode()
+            .map_err(|e| GenerateError::Pdf(e.to_string()))?;
+        let content_id = self.pdf.add_object(Stream::new(Dictionary::new(), content));
+
+        let mut resources = Dictionary::new();
+        if !page_fonts.is_empty() {
+            resources.set("Font", page_fonts);
+        }
+        if !xobjects.is_empty() {
+            resources.set("XObject", xobjects);
+        }
+        let media_box = page.media_box.unwrap_or(Rect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: page.width,
+            y1: page.height,
+        });
+        let mut dict = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", self.pages_id.into()),
+            ("MediaBox", rect_object(&media_box)),
+            ("Contents", content_id.into()),
+            ("Resources", resources.into()),
+        ]);
+        let boxes = [
+            ("CropBox", page.crop_box),
+            ("BleedBox", page.bleed_box),
+            ("TrimBox", page.trim_box),
+            ("ArtBox", page.art_box),
+        ];
+        for (key, rect) in boxes {
+            if let Some(rect) = rect {
+                dict.set(key, rect_object(&rect));
+            }
+        }
+        if page.rotation != 0 {
+            dict.set("Rotate", page.rotation as i64);
+        }
+        Ok(self.pdf.add_object(dict))
+    }
+}
+
+fn image_operations(name: &str, image: &Image) -> Vec<Operation> {
+    vec![
+        Operation::new("q", vec![]),
+        Operation::new(
+            "cm",
+            vec![
+                image.width.into(),
+                0.into(),
+                0.into(),
+                image.height.into(),
+                image.x.into(),
+                image.y.into(),
+            ],
+        ),
+        Operation::new("Do", vec![Object::Name(name.as_bytes().to_vec())]),
+        Operation::new("Q", vec![]),
+    ]
+}
+
+/// Operations painting `shape`, in a saved graphics state so that its
+/// colours and line style do not leak into what is drawn after it.
+fn shape_operations(shape: &Shape) -> Result<Vec<Operation>, GenerateError> {
+    let mut operations = vec![Operation::new("q", vec![])];
+    for (color, fill) in [(&shape.fill_color, true), (&shape.stroke_color, false)] {
+        if let Some(color) = color {
+            let operation = color_operation(color, fill).ok_or_else(|| {
+                GenerateError::Pdf(format!(
+                    "colours have 1, 3 or 4 components, not {}",
+                    color.len()
+                ))
+            })?;
+            operations.push(operation);
+        }
+    }
+    if shape.stroke_color.is_some() {
+        let style = |names: &[&str; 3], name: &str, what: &str| {
+            names.iter().position(|n| *n == name).ok_or_else(|| {
+                GenerateError::Pdf(format!(
+                    "unknown line {} '{}', expected one of {}",
+                    what,
+                    name,
+                    names.join(", ")
+                ))
+            })
+        };
+        let cap = style(&LINE_CAPS, &shape.line_cap, "cap")?;
+        let join = style(&LINE_JOINS, &shape.line_join, "join")?;
+        operations.push(Operation::new("w", vec![shape.line_width.into()]));
+        if cap != 0 {
+            operations.push(Operation::new("J", vec![(cap as i64).into()]));
+        }
+        if join != 0 {
+            operations.push(Operation::new("j", vec![(join as i64).into()]));
+        }
+        if !shape.dash.is_empty() {
+            let dash: Vec<Object> = shape.dash.iter().map(|&d| d.into()).collect();
+            operations.push(Operation::new(
+                "d",
+                vec![dash.into(), shape.dash_phase.into()],
+            ));
+        }
+    }
+    for segment in &shape.segments {
+        let operator = match (segment.kind.as_str(), segment.points.len()) {
+            ("move", 1) => "m",
+            ("line", 1) => "l",
+            ("curve", 3) => "c",
+            ("close", 0) => "h",
+            (kind, count) => {
+                return Err(GenerateError::Pdf(format!(
+                    "invalid path segment '{}' with {} points",
+                    kind, count
+                )))
+            }
+        };
+        let operands = segment
+            .points
+            .iter()
+            .flat_map(|&(x, y)| [x.into(), y.into()])
+            .collect();
+        operations.push(Operation::new(operator, operands));
+    }
+    let paint = match (
+        shape.fill_color.is_some(),
+        shape.stroke_color.is_some(),
+        shape.even_odd,
+    ) {
+        (true, true, false) => "B",
+        (true, true, true) => "B*",
+        (true, false, false) => "f",
+        (true, false, true) => "f*",
+        (false, true, _) => "S",
+        // Neither filled nor stroked: nothing to see.
+        (false, false, _) => "n",
+    };
+    operations.push(Operation::new(paint, vec![]));
+    operations.push(Operation::new("Q", vec![]));
+    Ok(operations)
+}
+
+/// The operator that sets `color` for stroking or, with `fill`, filling.
+pub(crate) fn color_operation(color: &[f32], fill: bool) -> Option<Operation> {
+    let operator = match (color.len(), fill) {
+        (1, true) => "g",
+        (1, false) => "G",
+        (3, true) => "rg",
+        (3, false) => "RG",
+        (4, true) => "k",
+        (4, false) => "K",
+        _ => return None,
+    };
+    Some(Operation::new(
+        operator,
+        color.iter().map(|&c| c.into()).collect(),
+    ))
+}
+
+/// Operations drawing `block` in the font resource `font`, emboldening
+/// and slanting it when `synthetic` is set. Styles other than the defaults
+/// are set inside `q`/`Q` so that they do not leak into later contents.
+fn text_operations(
+    font: &str,
+    text: Object,
+    block: &TextBlock,
+    synthetic: bool,
+) -> Result<Vec<Operation>, GenerateError> {
+    let mut style = Vec::new();
+    for (color, fill) in [(&block.fill_color, true), (&block.stroke_color, false)] {
+        if !color.is_empty() {
+            style.push(color_operation(color, fill).ok_or_else(|| {
+                GenerateError::Pdf(format!(
+                    "Text colours need 1, 3 or 4 components, got {}",
+                    color.len()
+                ))
+            })?);
+        }
+    }
+    if block.render_mode > 7 {
+        return Err(GenerateError::Pdf(format!(
+            "Unknown text render mode: {}",
+            block.render_mode
+        )));
+    }
+    let mut render_mode = block.render_mode;
+    let embolden = synthetic && block.bold && render_mode == 0;
+    if embolden {
+        render_mode = 2;
+        style.push(Operation::new(
+            "w",
+            vec![(block.font_size * SYNTHETIC_BOLD).into()],
+        ));
+        if block.stroke_color.is_empty() && !block.fill_color.is_empty() {
+            style.extend(color_operation(&block.fill_color, false));
+        }
+    }
+    let text_state = [
+        ("Tc", block.char_spacing, 0.0),
+        ("Tw", block.word_spacing, 0.0),
+        ("Tz", block.horizontal_scaling, 100.0),
+        ("Ts", block.rise, 0.0),
+        ("Tr", render_mode as f32, 0.0),
+    ];
+    for (operator, value, default) in text_state {
+        if value != default {
+            style.push(Operation::new(operator, vec![value.into()]));
+        }
+    }
+    let slant = if synthetic && block.italic {
+        SYNTHETIC_SLANT
+    } else {
+        0.0
+    };
+    let styled = !style.is_empty();
+    let mut operations = Vec::with_capacity(style.len() + 7);
+    if styled {
+        operations.push(Operation::new("q", vec![]));
+    }
+    operations.push(Operation::new("BT", vec![]));
+    operations.extend(style);
+    operations.push(Operation::new(
+        "Tf",
+        vec![
+            Object::Name(font.as_bytes().to_vec()),
+            block.font_size.into(),
+        ],
+    ));
+    // `y` includes the rise, which `Ts` adds back.
+    operations.push(Operation::new(
+        "Tm",
+        vec![
+            1.into(),
+            0.into(),
+            slant.into(),
+            1.into(),
+            block.x.into(),
+            (block.y - block.rise).into(),
+        ],
+    ));
+    operations.push(Operation::new("Tj", vec![text]));
+    operations.push(Operation::new("ET", vec![]));
+    if styled {
+        operations.push(Operation::new("Q", vec![]));
+    }
+    Ok(operations)
+}
+
+/// Encodes `text` for the built-in font; characters outside WinAnsi become `?`.
+fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| BaseEncoding::WinAnsi.encode(ch).unwrap_or(b'?'))
+        .collect()
+}
+
+fn rect_object(rect: &Rect) -> Object {
+    vec![
+        rect.x0.into(),
+        rect.y0.into(),
+        rect.x1.into(),
+        rect.y1.into(),
+    ]
+    .into()
+}
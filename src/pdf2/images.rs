@@ -0,0 +1,786 @@
+//! Decoding of image XObjects and inline images into standalone files,
+//! a minimal PNG encoder for re-encoding raw pixel data, and the reverse
+//! conversion of image files into XObjects for the generator.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lopdf::{Dictionary, Document, Object, Stream};
+use std::io::{Read, Write};
+
+/// Abbreviations allowed in inline image dictionaries (ISO 32000-1, 8.9.7).
+const INLINE_KEYS: &[(&[u8], &str)] = &[
+    (b"W", "Width"),
+    (b"H", "Height"),
+    (b"BPC", "BitsPerComponent"),
+    (b"CS", "ColorSpace"),
+    (b"F", "Filter"),
+    (b"DP", "DecodeParms"),
+    (b"IM", "ImageMask"),
+    (b"D", "Decode"),
+    (b"I", "Interpolate"),
+    (b"L", "Length"),
+];
+
+const INLINE_NAMES: &[(&[u8], &str)] = &[
+    (b"G", "DeviceGray"),
+    (b"RGB", "DeviceRGB"),
+    (b"CMYK", "DeviceCMYK"),
+    (b"I", "Indexed"),
+    (b"AHx", "ASCIIHexDecode"),
+    (b"A85", "ASCII85Decode"),
+    (b"LZW", "LZWDecode"),
+    (b"Fl", "FlateDecode"),
+    (b"RL", "RunLengthDecode"),
+    (b"CCF", "CCITTFaxDecode"),
+    (b"DCT", "DCTDecode"),
+];
+
+fn expand_inline_name(object: Object) -> Object {
+    match object {
+        Object::Name(name) => Object::Name(
+            INLINE_NAMES
+                .iter()
+                .find(|(short, _)| *short == name.as_slice())
+                .map(|(_, long)| long.as_bytes().to_vec())
+                .unwrap_or(name),
+        ),
+        Object::Array(items) => Object::Array(items.into_iter().map(expand_inline_name).collect()),
+        other => other,
+    }
+}
+
+/// Rewrites an inline image dictionary with abbreviated keys and names
+/// (`/W`, `/CS /RGB`, `/F /Fl`, ...) into the equivalent XObject form.
+pub fn expand_inline_dictionary(dict: &Dictionary) -> Dictionary {
+    let mut expanded = Dictionary::new();
+    for (key, value) in dict.iter() {
+        let key = INLINE_KEYS
+            .iter()
+            .find(|(short, _)| *short == key.as_slice())
+            .map(|(_, long)| long.as_bytes().to_vec())
+            .unwrap_or_else(|| key.clone());
+        let value = match key.as_slice() {
+            b"ColorSpace" | b"Filter" => expand_inline_name(value.clone()),
+            _ => value.clone(),
+        };
+        expanded.set(key, value);
+    }
+    expanded
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, o)| o).unwrap_or(object)
+}
+
+fn filter_names(doc: &Document, dict: &Dictionary) -> Vec<Vec<u8>> {
+    match dict.get(b"Filter").map(|o| resolve(doc, o)) {
+        Ok(Object::Name(name)) => vec![name.clone()],
+        Ok(Object::Array(names)) => names
+            .iter()
+            .filter_map(|n| resolve(doc, n).as_name().ok().map(<[u8]>::to_vec))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn filter_params<'a>(doc: &'a Document, dict: &'a Dictionary, index: usize) -> Option<&'a Object> {
+    match dict.get(b"DecodeParms").map(|o| resolve(doc, o)) {
+        Ok(Object::Array(params)) => params.get(index).map(|p| resolve(doc, p)),
+        Ok(params @ Object::Dictionary(_)) if index == 0 => Some(params),
+        _ => None,
+    }
+}
+
+fn decode_ascii_hex(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|&&b| b != b'>')
+        .filter_map(|&b| (b as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn decode_run_length(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let length = data[i];
+        i += 1;
+        match length {
+            128 => break,
+            0..=127 => {
+                let end = (i + length as usize + 1).min(data.len());
+                output.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            _ => {
+                if let Some(&byte) = data.get(i) {
+                    output.extend(std::iter::repeat_n(byte, 257 - length as usize));
+                }
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// The outcome of applying a stream's filters.
+enum Decoded {
+    /// Fully decoded sample data.
+    Samples(Vec<u8>),
+    /// Data still encoded with an image codec we pass through as a file
+    /// (`jpeg`, `jp2`, ...), or cannot decode (`jbig2`, `ccitt`).
+    Encoded(Vec<u8>, &'static str),
+}
+
+fn apply_filters(doc: &Document, dict: &Dictionary, data: &[u8]) -> Option<Decoded> {
+    let mut data = data.to_vec();
+    for (index, filter) in filter_names(doc, dict).iter().enumerate() {
+        data = match filter.as_slice() {
+            b"DCTDecode" => return Some(Decoded::Encoded(data, "jpeg")),
+            b"JPXDecode" => return Some(Decoded::Encoded(data, "jp2")),
+            b"JBIG2Decode" => return Some(Decoded::Encoded(data, "jbig2")),
+            b"CCITTFaxDecode" => return Some(Decoded::Encoded(data, "ccitt")),
+            b"ASCIIHexDecode" => decode_ascii_hex(&data),
+            b"RunLengthDecode" => decode_run_length(&data),
+            b"FlateDecode" | b"LZWDecode" | b"ASCII85Decode" => {
+                let mut single = Dictionary::new();
+                single.set("Filter", Object::Name(filter.clone()));
+                if let Some(params) = filter_params(doc, dict, index) {
+                    single.set("DecodeParms", params.clone());
+                }
+                Stream::new(single, data).decompressed_content().ok()?
+            }
+            _ => return None,
+        };
+    }
+    Some(Decoded::Samples(data))
+}
+
+/// A colour space reduced to how its samples turn into 8-bit gray or RGB.
+enum ColorModel {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// Palette entries already converted to RGB.
+    Indexed(Vec<[u8; 3]>),
+}
+
+impl ColorModel {
+    fn components(&self) -> usize {
+        match self {
+            ColorModel::Gray | ColorModel::Indexed(_) => 1,
+            ColorModel::Rgb => 3,
+            ColorModel::Cmyk => 4,
+        }
+    }
+}
+
+fn cmyk_to_rgb(c: u8, m: u8, y: u8, k: u8) -> [u8; 3] {
+    let convert = |v: u8| ((255 - v as u32) * (255 - k as u32) / 255) as u8;
+    [convert(c), convert(m), convert(y)]
+}
+
+fn color_model(
+    doc: &Document,
+    object: &Object,
+    resources: Option<&Dictionary>,
+    depth: usize,
+) -> Option<ColorModel> {
+    if depth > 4 {
+        return None;
+    }
+    match resolve(doc, object) {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" => Some(ColorModel::Gray),
+            b"DeviceRGB" | b"CalRGB" => Some(ColorModel::Rgb),
+            b"DeviceCMYK" => Some(ColorModel::Cmyk),
+            // A named resource in the /ColorSpace dictionary.
+            other => {
+                let named = resources?
+                    .get(b"ColorSpace")
+                    .map(|o| resolve(doc, o))
+                    .ok()?
+                    .as_dict()
+                    .ok()?
+                    .get(other)
+                    .ok()?;
+                color_model(doc, named, resources, depth + 1)
+            }
+        },
+        Object::Array(items) => {
+            let family = resolve(doc, items.first()?).as_name().ok()?;
+            match family {
+                b"CalGray" | b"Separation" => Some(ColorModel::Gray),
+                b"CalRGB" | b"Lab" => Some(ColorModel::Rgb),
+                b"ICCBased" => {
+                    let stream = resolve(doc, items.get(1)?).as_stream().ok()?;
+                    match stream.dict.get(b"N").and_then(Object::as_i64).unwrap_or(3) {
+                        1 => Some(ColorModel::Gray),
+                        4 => Some(ColorModel::Cmyk),
+                        _ => Some(ColorModel::Rgb),
+                    }
+                }
+                b"Indexed" | b"I" => {
+                    let base = color_model(doc, items.get(1)?, resources, depth + 1)?;
+                    let lookup = match resolve(doc, items.get(3)?) {
+                        Object::String(bytes, _) => bytes.clone(),
+                        Object::Stream(stream) => stream.get_plain_content().ok()?,
+                        _ => return None,
+                    };
+                    let palette = lookup
+                        .chunks(base.components())
+                        .filter(|entry| entry.len() == base.components())
+                        .map(|entry| match base {
+                            ColorModel::Gray => [entry[0]; 3],
+                            ColorModel::Cmyk => cmyk_to_rgb(entry[0], entry[1], entry[2], entry[3]),
+                            _ => [entry[0], entry[1], entry[2]],
+                        })
+                        .collect();
+                    Some(ColorModel::Indexed(palette))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Checks a `BitsPerComponent` value against the depths PDF allows.
+fn bits_per_component(value: i64) -> Option<usize> {
+    matches!(value, 1 | 2 | 4 | 8 | 16).then_some(value as usize)
+}
+
+/// Unpacks `bpc`-bit samples into one byte per sample. With `scale`, values
+/// are stretched to the 0..=255 range; otherwise the raw value is kept
+/// (used for palette indices).
+fn unpack_samples(
+    data: &[u8],
+    bpc: usize,
+    per_row: usize,
+    rows: usize,
+    scale: bool,
+) -> Option<Vec<u8>> {
+    let row_bytes = per_row.checked_mul(bpc)?.div_ceil(8);
+    if row_bytes == 0 || data.len() < row_bytes.checked_mul(rows)? {
+        return None;
+    }
+    let max = (1u32 << bpc.min(8)) - 1;
+    let mut samples = Vec::with_capacity(per_row * rows);
+    for row in data.chunks(row_bytes).take(rows) {
+        for i in 0..per_row {
+            let value = match bpc {
+                8 => row[i] as u32,
+                16 => row[i * 2] as u32,
+                1 | 2 | 4 => {
+                    let bit = i * bpc;
+                    ((row[bit / 8] >> (8 - bpc - bit % 8)) as u32) & max
+                }
+                _ => return None,
+            };
+            samples.push(if scale && bpc < 8 {
+                (value * 255 / max) as u8
+            } else {
+                value as u8
+            });
+        }
+    }
+    Some(samples)
+}
+
+/// Decodes an 8-bit gray soft mask matching the image's dimensions.
+fn soft_mask(doc: &Document, dict: &Dictionary, width: usize, height: usize) -> Option<Vec<u8>> {
+    let stream = resolve(doc, dict.get(b"SMask").ok()?).as_stream().ok()?;
+    let mask_width = stream.dict.get(b"Width").and_then(Object::as_i64).ok()? as usize;
+    let mask_height = stream.dict.get(b"Height").and_then(Object::as_i64).ok()? as usize;
+    if (mask_width, mask_height) != (width, height) {
+        return None;
+    }
+    let bpc = bits_per_component(
+        stream
+            .dict
+            .get(b"BitsPerComponent")
+            .and_then(Object::as_i64)
+            .unwrap_or(8),
+    )?;
+    match apply_filters(doc, &stream.dict, &stream.content)? {
+        Decoded::Samples(data) => unpack_samples(&data, bpc, width, height, true),
+        Decoded::Encoded(..) => None,
+    }
+}
+
+/// Turns an image XObject (or expanded inline image) into file bytes and a
+/// format name: `jpeg` and `jp2` data is passed through unchanged, raw and
+/// Flate/LZW-compressed samples are re-encoded as `png`.
+pub fn extract_image(
+    doc: &Document,
+    dict: &Dictionary,
+    data: &[u8],
+    resources: Option<&Dictionary>,
+) -> Option<(Vec<u8>, String)> {
+    let samples = match apply_filters(doc, dict, data)? {
+        Decoded::Samples(samples) => samples,
+        Decoded::Encoded(data, format) => return Some((data, format.to_string())),
+    };
+
+    let int = |key: &[u8]| {
+        dict.get(key)
+            .map(|o| resolve(doc, o))
+            .and_then(Object::as_i64)
+            .ok()
+    };
+    let width = int(b"Width")?.max(0) as usize;
+    let height = int(b"Height")?.max(0) as usize;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let image_mask = dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let bpc = if image_mask {
+        1
+    } else {
+        bits_per_component(int(b"BitsPerComponent").unwrap_or(8))?
+    };
+    let model = if image_mask {
+        ColorModel::Gray
+    } else {
+        color_model(doc, dict.get(b"ColorSpace").ok()?, resources, 0)?
+    };
+    let indexed = matches!(model, ColorModel::Indexed(_));
+    let per_row = width.checked_mul(model.components())?;
+    let mut samples = unpack_samples(&samples, bpc, per_row, height, !indexed)?;
+
+    // A `/Decode [1 0]` array inverts single-component images; for stencil
+    // masks this means 1 rather than 0 marks painted pixels.
+    let inverted = dict
+        .get(b"Decode")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|d| d.first())
+        .and_then(|v| v.as_float().ok())
+        .is_some_and(|first| first > 0.5);
+    if inverted && !indexed && model.components() == 1 {
+        samples.iter_mut().for_each(|s| *s = 255 - *s);
+    }
+
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 3);
+    let channels = match &model {
+        ColorModel::Gray => {
+            pixels = samples;
+            1
+        }
+        ColorModel::Rgb => {
+            pixels = samples;
+            3
+        }
+        ColorModel::Cmyk => {
+            for cmyk in samples.chunks_exact(4) {
+                pixels.extend(cmyk_to_rgb(cmyk[0], cmyk[1], cmyk[2], cmyk[3]));
+            }
+            3
+        }
+        ColorModel::Indexed(palette) => {
+            for index in samples {
+                pixels.extend(palette.get(index as usize).copied().unwrap_or([0; 3]));
+            }
+            3
+        }
+    };
+
+    let (pixels, channels) = match soft_mask(doc, dict, width, height) {
+        Some(alpha) => {
+            let mut with_alpha = Vec::with_capacity(width * height * (channels + 1));
+            for (pixel, a) in pixels.chunks_exact(channels).zip(alpha) {
+                with_alpha.extend_from_slice(pixel);
+                with_alpha.push(a);
+            }
+            (with_alpha, channels + 1)
+        }
+        None => (pixels, channels),
+    };
+
+    Some((
+        encode_png(width as u32, height as u32, channels as u8, &pixels),
+        "png".to_string(),
+    ))
+}
+
+fn png_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+    output.extend_from_slice(&hasher.finalize().to_be_bytes());
+}
+
+/// Encodes 8-bit pixels as a PNG file. `channels` is 1 (gray), 2 (gray +
+/// alpha), 3 (RGB) or 4 (RGBA).
+pub fn encode_png(width: u32, height: u32, channels: u8, pixels: &[u8]) -> Vec<u8> {
+    let color_type = match channels {
+        1 => 0,
+        2 => 4,
+        3 => 2,
+        _ => 6,
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let row_len = width as usize * channels as usize;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(row_len).take(height as usize) {
+        // Filter type 0 (None) for every scanline.
+        let _ = encoder.write_all(&[0]);
+        let _ = encoder.write_all(row);
+    }
+    let compressed = encoder.finish().unwrap_or_default();
+
+    let mut output = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut output, b"IHDR", &header);
+    png_chunk(&mut output, b"IDAT", &compressed);
+    png_chunk(&mut output, b"IEND", &[]);
+    output
+}
+
+/// An image ready to be written as an image XObject, with the soft mask
+/// holding its alpha channel if it has one.
+pub struct EmbeddedImage {
+    pub stream: Stream,
+    pub soft_mask: Option<Stream>,
+}
+
+/// Converts an image file (`"jpeg"`, `"png"`) or raw 8-bit pixels (`"rgb"`,
+/// `"gray"`, which need the pixel dimensions) into an image XObject.
+pub fn embed_image(
+    format: &str,
+    data: &[u8],
+    pixel_width: u32,
+    pixel_height: u32,
+) -> Result<EmbeddedImage, String> {
+    match format.to_ascii_lowercase().as_str() {
+        "jpeg" | "jpg" => embed_jpeg(data),
+        "png" => embed_png(data),
+        "rgb" => embed_raw(data, pixel_width, pixel_height, 3),
+        "gray" | "grey" => embed_raw(data, pixel_width, pixel_height, 1),
+        other => Err(format!("unsupported image format '{}'", other)),
+    }
+}
+
+fn image_stream_dict(width: u32, height: u32, color_space: Object, bpc: u8) -> Dictionary {
+    Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"XObject".to_vec())),
+        ("Subtype", Object::Name(b"Image".to_vec())),
+        ("Width", (width as i64).into()),
+        ("Height", (height as i64).into()),
+        ("ColorSpace", color_space),
+        ("BitsPerComponent", (bpc as i64).into()),
+    ])
+}
+
+fn device_color_space(components: usize) -> Object {
+    let name: &[u8] = match components {
+        1 => b"DeviceGray",
+        4 => b"DeviceCMYK",
+        _ => b"DeviceRGB",
+    };
+    Object::Name(name.to_vec())
+}
+
+fn embed_raw(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    channels: usize,
+) -> Result<EmbeddedImage, String> {
+    let expected = width as usize * height as usize * channels;
+    if width == 0 || height == 0 || data.len() != expected {
+        return Err(format!(
+            "raw image data is {} bytes, expected {} for {}x{} pixels",
+            data.len(),
+            expected,
+            width,
+            height
+        ));
+    }
+    let dict = image_stream_dict(width, height, device_color_space(channels), 8);
+    Ok(EmbeddedImage {
+        stream: Stream::new(dict, data.to_vec()),
+        soft_mask: None,
+    })
+}
+
+/// Embeds a JPEG file unchanged, reading its size and components from the
+/// start-of-frame marker.
+fn embed_jpeg(data: &[u8]) -> Result<EmbeddedImage, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err("not a JPEG file".to_string());
+    }
+    let mut adobe = false;
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return Err("corrupt JPEG marker".to_string());
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data
+            .get(pos + 4..pos + 2 + length)
+            .ok_or("truncated JPEG segment")?;
+        if marker == 0xEE && segment.starts_with(b"Adobe") {
+            adobe = true;
+        }
+        let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_frame && segment.len() >= 6 {
+            let bpc = segment[0];
+            let height = u16::from_be_bytes([segment[1], segment[2]]) as u32;
+            let width = u16::from_be_bytes([segment[3], segment[4]]) as u32;
+            let components = segment[5] as usize;
+            let mut dict = image_stream_dict(width, height, device_color_space(components), bpc);
+            dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+            if components == 4 && adobe {
+                // Adobe writes CMYK JPEGs inverted.
+                dict.set(
+                    "Decode",
+                    [1, 0, 1, 0, 1, 0, 1, 0].map(Object::from).to_vec(),
+                );
+            }
+            return Ok(EmbeddedImage {
+                stream: Stream::new(dict, data.to_vec()).with_compression(false),
+                soft_mask: None,
+            });
+        }
+        pos += 2 + length;
+    }
+    Err("JPEG file has no frame header".to_string())
+}
+
+struct Png<'a> {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    palette: &'a [u8],
+    transparency: Option<&'a [u8]>,
+    idat: Vec<u8>,
+}
+
+fn parse_png(data: &[u8]) -> Result<Png<'_>, String> {
+    if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Err("not a PNG file".to_string());
+    }
+    let mut png = Png {
+        width: 0,
+        height: 0,
+        bit_depth: 0,
+        color_type: 0,
+        palette: &[],
+        transparency: None,
+        idat: Vec::new(),
+    };
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let length =
+            u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let chunk = data
+            .get(pos + 8..pos + 8 + length)
+            .ok_or("truncated PNG chunk")?;
+        match kind {
+            b"IHDR" if chunk.len() >= 13 => {
+                png.width = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                png.height = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+                png.bit_depth = chunk[8];
+                png.color_type = chunk[9];
+                if chunk[12] != 0 {
+                    return Err("interlaced PNG images are not supported".to_string());
+                }
+            }
+            b"PLTE" => png.palette = chunk,
+            b"tRNS" => png.transparency = Some(chunk),
+            b"IDAT" => png.idat.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Length, type and CRC surround the chunk data.
+        pos += 12 + length;
+    }
+    if png.width == 0 || png.height == 0 || png.idat.is_empty() {
+        return Err("PNG file has no image data".to_string());
+    }
+    Ok(png)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Inflates and unfilters PNG scanlines into rows of `stride` bytes.
+fn png_scanlines(png: &Png, bpp: usize, stride: usize) -> Result<Vec<u8>, String> {
+    let mut filtered = Vec::new();
+    flate2::read::ZlibDecoder::new(png.idat.as_slice())
+        .read_to_end(&mut filtered)
+        .map_err(|e| format!("corrupt PNG data: {}", e))?;
+    let height = png.height as usize;
+    if filtered.len() < (stride + 1) * height {
+        return Err("truncated PNG data".to_string());
+    }
+    let mut pixels = vec![0u8; stride * height];
+    for row in 0..height {
+        let line = &filtered[row * (stride + 1)..(row + 1) * (stride + 1)];
+        let (filter, line) = (line[0], &line[1..]);
+        let (done, current) = pixels.split_at_mut(row * stride);
+        let previous = if row > 0 {
+            &done[(row - 1) * stride..]
+        } else {
+            &[][..]
+        };
+        let current = &mut current[..stride];
+        for i in 0..stride {
+            let left = if i >= bpp { current[i - bpp] } else { 0 };
+            let up = previous.get(i).copied().unwrap_or(0);
+            let up_left = if i >= bpp {
+                previous.get(i - bpp).copied().unwrap_or(0)
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(format!("invalid PNG filter type {}", filter)),
+            };
+            current[i] = line[i].wrapping_add(predictor);
+        }
+    }
+    Ok(pixels)
+}
+
+/// Embeds a PNG file. Without an alpha channel the compressed data is kept
+/// and decoded through the PNG predictor; otherwise the pixels are split
+/// into colour and a soft mask.
+fn embed_png(data: &[u8]) -> Result<EmbeddedImage, String> {
+    let png = parse_png(data)?;
+    let (channels, bit_depths): (usize, &[u8]) = match png.color_type {
+        0 => (1, &[1, 2, 4, 8, 16]),
+        2 => (3, &[8, 16]),
+        3 => (1, &[1, 2, 4, 8]),
+        4 => (2, &[8, 16]),
+        6 => (4, &[8, 16]),
+        other => return Err(format!("invalid PNG colour type {}", other)),
+    };
+    if !bit_depths.contains(&png.bit_depth) {
+        return Err(format!(
+            "invalid PNG bit depth {} for colour type {}",
+            png.bit_depth, png.color_type
+        ));
+    }
+    let bit_depth = png.bit_depth as usize;
+    let (width, height) = (png.width as usize, png.height as usize);
+    let bpp = (channels * bit_depth).div_ceil(8);
+    let stride = (width * channels * bit_depth).div_ceil(8);
+    let color_space = match png.color_type {
+        0 | 4 => device_color_space(1),
+        3 => Object::Array(vec![
+            Object::Name(b"Indexed".to_vec()),
+            Object::Name(b"DeviceRGB".to_vec()),
+            ((png.palette.len() / 3).max(1) as i64 - 1).into(),
+            Object::string_literal(png.palette.to_vec()),
+        ]),
+        _ => device_color_space(3),
+    };
+
+    if channels == 2 || channels == 4 {
+        let pixels = png_scanlines(&png, bpp, stride)?;
+        let sample = bit_depth / 8;
+        let color_samples = (channels - 1) * sample;
+        let mut color = Vec::with_capacity(width * height * color_samples);
+        let mut alpha = Vec::with_capacity(width * height * sample);
+        for pixel in pixels.chunks_exact(channels * sample) {
+            color.extend_from_slice(&pixel[..color_samples]);
+            alpha.extend_from_slice(&pixel[color_samples..]);
+        }
+        let dict = image_stream_dict(png.width, png.height, color_space, png.bit_depth);
+        let mask = image_stream_dict(png.width, png.height, device_color_space(1), png.bit_depth);
+        return Ok(EmbeddedImage {
+            stream: Stream::new(dict, color),
+            soft_mask: Some(Stream::new(mask, alpha)),
+        });
+    }
+
+    let mut dict = image_stream_dict(png.width, png.height, color_space, png.bit_depth);
+    dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+    dict.set(
+        "DecodeParms",
+        Dictionary::from_iter(vec![
+            ("Predictor", 15.into()),
+            ("Colors", (channels as i64).into()),
+            ("BitsPerComponent", (bit_depth as i64).into()),
+            ("Columns", (width as i64).into()),
+        ]),
+    );
+    let mut soft_mask = None;
+    match (png.color_type, png.transparency) {
+        (3, Some(alphas)) => {
+            // Palette transparency: look up each pixel's index.
+            let indices = png_scanlines(&png, bpp, stride)?;
+            let per_byte = 8 / bit_depth;
+            let mut alpha = Vec::with_capacity(width * height);
+            for row in indices.chunks_exact(stride) {
+                for x in 0..width {
+                    let byte = row[x / per_byte];
+                    let shift = 8 - bit_depth * (x % per_byte + 1);
+                    let index = (byte >> shift) as usize & ((1 << bit_depth) - 1);
+                    alpha.push(alphas.get(index).copied().unwrap_or(255));
+                }
+            }
+            let mask = image_stream_dict(png.width, png.height, device_color_space(1), 8);
+            soft_mask = Some(Stream::new(mask, alpha));
+        }
+        (0 | 2, Some(key)) => {
+            // A single transparent colour becomes a colour key mask.
+            let mask: Vec<Object> = key
+                .chunks_exact(2)
+                .flat_map(|v| {
+                    let value = u16::from_be_bytes([v[0], v[1]]) as i64;
+                    [value.into(), value.into()]
+                })
+                .collect();
+            dict.set("Mask", mask);
+        }
+        _ => {}
+    }
+    Ok(EmbeddedImage {
+        stream: Stream::new(dict, png.idat).with_compression(false),
+        soft_mask,
+    })
+}
@@ -0,0 +1,315 @@
+//! Layout analysis: groups the characters of a page's text blocks into
+//! words, lines, paragraphs and columns, and orders them for reading.
+//!
+//! Text is assumed to run horizontally, left to right. Columns and reading
+//! order come from recursively cutting the page's paragraphs along the
+//! vertical and horizontal gaps between them (an "XY cut").
+
+use crate::structure::{Column, Page, Paragraph, Rect, TextLine, Word};
+
+// Distances below are fractions of the font size.
+
+/// Extent of a glyph's box above and below its baseline.
+const ASCENT: f32 = 0.8;
+const DESCENT: f32 = 0.2;
+
+/// Characters further apart than this belong to different words.
+const WORD_GAP: f32 = 0.15;
+
+/// Characters on one baseline further apart than this belong to different
+/// lines, e.g. on both sides of a column gutter.
+const LINE_GAP: f32 = 1.0;
+
+/// Lines further apart vertically than this belong to different paragraphs.
+const PARAGRAPH_GAP: f32 = 0.5;
+
+/// A line starting this much to the right of the paragraph above it is the
+/// indented first line of a new one.
+const INDENT: f32 = 1.0;
+
+/// Lines whose font sizes differ by more than this factor belong to
+/// different paragraphs.
+const SIZE_RATIO: f32 = 1.25;
+
+/// A character and its box.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Glyph {
+    ch: char,
+    x0: f32,
+    x1: f32,
+    baseline: f32,
+    size: f32,
+}
+
+impl Glyph {
+    fn bottom(&self) -> f32 {
+        self.baseline - DESCENT * self.size
+    }
+
+    fn top(&self) -> f32 {
+        self.baseline + ASCENT * self.size
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::new(self.x0, self.bottom(), self.x1, self.top())
+    }
+
+    /// Whether the middle of the glyph's box lies inside `rect`.
+    pub(crate) fn is_inside(&self, rect: &Rect) -> bool {
+        let x = (self.x0 + self.x1) / 2.0;
+        let y = (self.bottom() + self.top()) / 2.0;
+        x >= rect.x0 && x <= rect.x1 && y >= rect.y0 && y <= rect.y1
+    }
+}
+
+/// Groups the text of `page` into columns in reading order.
+pub fn analyze(page: &Page) -> Vec<Column> {
+    let lines = row_lines(glyphs(page)).into_iter().flatten().collect();
+    let mut columns = Vec::new();
+    order(paragraphs(lines), &mut columns);
+    columns
+}
+
+/// The characters of the text blocks of `page`.
+pub(crate) fn glyphs(page: &Page) -> Vec<Glyph> {
+    let mut glyphs = Vec::new();
+    for block in page.text_blocks() {
+        if block.font_size <= 0.0 {
+            continue;
+        }
+        for (ch, x, advance) in block.char_positions() {
+            glyphs.push(Glyph {
+                ch,
+                x0: x.min(x + advance),
+                x1: x.max(x + advance),
+                baseline: block.y,
+                size: block.font_size,
+            });
+        }
+    }
+    glyphs
+}
+
+/// Groups `glyphs` into rows, top to bottom, and the rows into lines, left
+/// to right.
+pub(crate) fn row_lines(glyphs: Vec<Glyph>) -> Vec<Vec<TextLine>> {
+    rows(glyphs).into_iter().map(split_row).collect()
+}
+
+/// Groups glyphs whose boxes overlap vertically by at least half the
+/// smaller height into rows, each sorted left to right.
+fn rows(mut glyphs: Vec<Glyph>) -> Vec<Vec<Glyph>> {
+    glyphs.sort_by(|a, b| b.baseline.total_cmp(&a.baseline));
+    // Each row is compared against its first glyph, so that rows of
+    // slightly offset baselines do not chain together.
+    let mut rows: Vec<(Glyph, Vec<Glyph>)> = Vec::new();
+    for glyph in glyphs {
+        let row = rows
+            .iter_mut()
+            .rev()
+            .take_while(|(first, _)| first.bottom() <= glyph.top())
+            .find(|(first, _)| {
+                let overlap = first.top().min(glyph.top()) - first.bottom().max(glyph.bottom());
+                overlap >= 0.5 * (ASCENT + DESCENT) * first.size.min(glyph.size)
+            });
+        match row {
+            Some((_, row)) => row.push(glyph),
+            None => rows.push((glyph, vec![glyph])),
+        }
+    }
+    rows.into_iter()
+        .map(|(_, mut row)| {
+            row.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+            row
+        })
+        .collect()
+}
+
+/// Splits a row into lines at wide gaps, and each line into words at
+/// whitespace and narrower gaps.
+fn split_row(row: Vec<Glyph>) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    let mut words: Vec<Word> = Vec::new();
+    let mut word: Option<Word> = None;
+    // Right edge of the line so far, and the size of its last glyph.
+    let mut end: Option<(f32, f32)> = None;
+    for glyph in row {
+        if let Some((x1, size)) = end {
+            let (gap, size) = (glyph.x0 - x1, size.max(glyph.size));
+            if gap > LINE_GAP * size {
+                words.extend(word.take());
+                lines.extend(line(std::mem::take(&mut words)));
+                end = None;
+            } else if gap > WORD_GAP * size {
+                words.extend(word.take());
+            }
+        }
+        end = Some(match end {
+            Some((x1, _)) => (x1.max(glyph.x1), glyph.size),
+            None => (glyph.x1, glyph.size),
+        });
+        if glyph.ch.is_whitespace() {
+            words.extend(word.take());
+            continue;
+        }
+        match word.as_mut() {
+            Some(word) => {
+                word.text.push(glyph.ch);
+                word.bbox = union(&word.bbox, &glyph.rect());
+                word.font_size = word.font_size.max(glyph.size);
+            }
+            None => {
+                word = Some(Word {
+                    text: glyph.ch.to_string(),
+                    bbox: glyph.rect(),
+                    font_size: glyph.size,
+                })
+            }
+        }
+    }
+    words.extend(word);
+    lines.extend(line(words));
+    lines
+}
+
+fn line(words: Vec<Word>) -> Option<TextLine> {
+    let bbox = words.iter().map(|w| w.bbox).reduce(|a, b| union(&a, &b))?;
+    Some(TextLine { words, bbox })
+}
+
+fn font_size(line: &TextLine) -> f32 {
+    line.words.iter().map(|w| w.font_size).fold(0.0, f32::max)
+}
+
+/// Joins lines that follow each other closely, in a similar size and
+/// without an indent, into paragraphs.
+fn paragraphs(mut lines: Vec<TextLine>) -> Vec<Paragraph> {
+    lines.sort_by(|a, b| {
+        b.bbox
+            .y1
+            .total_cmp(&a.bbox.y1)
+            .then(a.bbox.x0.total_cmp(&b.bbox.x0))
+    });
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    for line in lines {
+        let size = font_size(&line);
+        let mut best: Option<(usize, f32)> = None;
+        for (index, paragraph) in paragraphs.iter().enumerate() {
+            let Some(last) = paragraph.lines.last() else {
+                continue;
+            };
+            let last_size = font_size(last);
+            let gap = last.bbox.y0 - line.bbox.y1;
+            let overlaps = line.bbox.x0 < last.bbox.x1 && last.bbox.x0 < line.bbox.x1;
+            let below = line.bbox.y1 < last.bbox.y1 - 0.5 * size;
+            let similar = size.max(last_size) <= SIZE_RATIO * size.min(last_size);
+            // Lines after the first show where the paragraph's left edge is.
+            let indented = paragraph.lines[1..]
+                .iter()
+                .map(|l| l.bbox.x0)
+                .reduce(f32::min)
+                .is_some_and(|x0| line.bbox.x0 > x0 + INDENT * size);
+            if overlaps
+                && below
+                && similar
+                && !indented
+                && gap <= PARAGRAPH_GAP * size
+                && best.is_none_or(|(_, best)| gap < best)
+            {
+                best = Some((index, gap));
+            }
+        }
+        match best {
+            Some((index, _)) => {
+                let paragraph = &mut paragraphs[index];
+                paragraph.bbox = union(&paragraph.bbox, &line.bbox);
+                paragraph.lines.push(line);
+            }
+            None => paragraphs.push(Paragraph {
+                bbox: line.bbox,
+                lines: vec![line],
+            }),
+        }
+    }
+    paragraphs
+}
+
+/// Appends the columns of `paragraphs`, in reading order, to `columns`.
+///
+/// Paragraphs separated by a vertical gap are read left side first. Other
+/// sets are cut into horizontal bands, read top to bottom; consecutive
+/// bands that can be cut vertically together are handled as one set, so
+/// that columns continue across the lines that happen to align in them.
+fn order(paragraphs: Vec<Paragraph>, columns: &mut Vec<Column>) {
+    if has_vertical_gap(paragraphs.iter()) {
+        for side in split(paragraphs, |r| (r.x0, r.x1)) {
+            order(side, columns);
+        }
+        return;
+    }
+    let bands = split(paragraphs, |r| (-r.y1, -r.y0));
+    let mut groups: Vec<Vec<Paragraph>> = Vec::new();
+    for band in bands {
+        match groups.last_mut() {
+            Some(group) if has_vertical_gap(group.iter().chain(&band)) => group.extend(band),
+            _ => groups.push(band),
+        }
+    }
+    let mut current: Vec<Paragraph> = Vec::new();
+    for group in groups {
+        if has_vertical_gap(group.iter()) {
+            columns.extend(column(std::mem::take(&mut current)));
+            order(group, columns);
+        } else {
+            current.extend(group);
+        }
+    }
+    columns.extend(column(current));
+}
+
+/// Splits `paragraphs` into runs separated by gaps along the axis `span`
+/// projects boxes onto, sorted by where they start on it.
+fn split(mut paragraphs: Vec<Paragraph>, span: fn(&Rect) -> (f32, f32)) -> Vec<Vec<Paragraph>> {
+    paragraphs.sort_by(|a, b| span(&a.bbox).0.total_cmp(&span(&b.bbox).0));
+    let mut runs: Vec<Vec<Paragraph>> = Vec::new();
+    let mut end = f32::NEG_INFINITY;
+    for paragraph in paragraphs {
+        let (start, stop) = span(&paragraph.bbox);
+        match runs.last_mut() {
+            Some(run) if start <= end => run.push(paragraph),
+            _ => runs.push(vec![paragraph]),
+        }
+        end = end.max(stop);
+    }
+    runs
+}
+
+fn has_vertical_gap<'a>(paragraphs: impl Iterator<Item = &'a Paragraph>) -> bool {
+    let mut spans: Vec<(f32, f32)> = paragraphs.map(|p| (p.bbox.x0, p.bbox.x1)).collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut end = f32::NEG_INFINITY;
+    for (index, (start, stop)) in spans.into_iter().enumerate() {
+        if index > 0 && start > end {
+            return true;
+        }
+        end = end.max(stop);
+    }
+    false
+}
+
+fn column(paragraphs: Vec<Paragraph>) -> Option<Column> {
+    let bbox = paragraphs
+        .iter()
+        .map(|p| p.bbox)
+        .reduce(|a, b| union(&a, &b))?;
+    Some(Column { paragraphs, bbox })
+}
+
+pub(crate) fn union(a: &Rect, b: &Rect) -> Rect {
+    Rect::new(
+        a.x0.min(b.x0),
+        a.y0.min(b.y0),
+        a.x1.max(b.x1),
+        a.y1.max(b.y1),
+    )
+}
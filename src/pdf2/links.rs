@@ -0,0 +1,120 @@
+//! Link annotations (ISO 32000-1, 12.5.6.5): areas of a page that open a
+//! URI or go to a place in the document when clicked.
+
+use crate::metadata::decode_text_string;
+use crate::names::{destination_array, item_destination, read_destination};
+use crate::parser::rect_from_object;
+use crate::structure::Link;
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+use std::collections::{BTreeMap, HashMap};
+
+/// The links of page `page_id`, looking named destinations up in `named`
+/// and pages up in `page_indices` (page id to index). Links with a target
+/// the model cannot hold, such as JavaScript actions, are skipped.
+pub fn read_links(
+    doc: &lopdf::Document,
+    page_id: ObjectId,
+    named: &BTreeMap<Vec<u8>, Object>,
+    page_indices: &HashMap<ObjectId, usize>,
+) -> Vec<Link> {
+    let annotations = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Annots").ok())
+        .and_then(|a| doc.dereference(a).ok())
+        .and_then(|(_, a)| a.as_array().ok());
+    let mut links = Vec::new();
+    for annotation in annotations.into_iter().flatten() {
+        let Ok((_, Object::Dictionary(annotation))) = doc.dereference(annotation) else {
+            continue;
+        };
+        if annotation.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Link") {
+            continue;
+        }
+        let Some(rect) = annotation
+            .get(b"Rect")
+            .ok()
+            .and_then(|r| rect_from_object(doc, r))
+        else {
+            continue;
+        };
+        let mut link = Link {
+            rect: rect.normalized(),
+            uri: link_uri(doc, annotation),
+            ..Default::default()
+        };
+        if let Some(dest) = item_destination(doc, annotation) {
+            link.named_destination = match doc.dereference(dest).map(|(_, d)| d) {
+                Ok(Object::Name(name)) => Some(String::from_utf8_lossy(name).into_owned()),
+                Ok(Object::String(name, _)) => Some(decode_text_string(name)),
+                _ => None,
+            };
+            link.destination = read_destination(doc, dest, named, page_indices);
+        }
+        if link.uri.is_some() || link.destination.is_some() || link.named_destination.is_some() {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// The URI a link's URI action opens.
+fn link_uri(doc: &lopdf::Document, annotation: &Dictionary) -> Option<String> {
+    let (_, action) = doc.dereference(annotation.get(b"A").ok()?).ok()?;
+    let action = action.as_dict().ok()?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"URI" {
+        return None;
+    }
+    let (_, uri) = doc.dereference(action.get(b"URI").ok()?).ok()?;
+    Some(String::from_utf8_lossy(uri.as_str().ok()?).into_owned())
+}
+
+/// The annotation dictionary for `link` in a document whose pages are
+/// `pages`.
+pub fn link_annotation(link: &Link, pages: &[ObjectId]) -> Result<Dictionary, String> {
+    let rect = link.rect.normalized();
+    let mut annotation = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Annot".to_vec())),
+        ("Subtype", Object::Name(b"Link".to_vec())),
+        (
+            "Rect",
+            vec![
+                rect.x0.into(),
+                rect.y0.into(),
+                rect.x1.into(),
+                rect.y1.into(),
+            ]
+            .into(),
+        ),
+        ("Border", vec![0.into(), 0.into(), 0.into()].into()),
+    ]);
+    if let Some(uri) = &link.uri {
+        // URIs are 7-bit ASCII; anything else is percent-encoded.
+        let mut encoded = String::new();
+        for byte in uri.bytes() {
+            if byte.is_ascii_graphic() {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        annotation.set(
+            "A",
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Action".to_vec())),
+                ("S", Object::Name(b"URI".to_vec())),
+                (
+                    "URI",
+                    Object::String(encoded.into_bytes(), StringFormat::Literal),
+                ),
+            ]),
+        );
+    } else if let Some(name) = &link.named_destination {
+        annotation.set("Dest", Object::string_literal(name.as_bytes().to_vec()));
+    } else if let Some(destination) = &link.destination {
+        annotation.set("Dest", destination_array(destination, pages)?);
+    } else {
+        return Err("link has no uri, destination or named destination".to_string());
+    }
+    Ok(annotation)
+}
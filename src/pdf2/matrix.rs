@@ -0,0 +1,72 @@
+/// A PDF transformation matrix `[a b c d e f]`.
+///
+/// Points are treated as row vectors, so `p' = p × M`, matching the
+/// convention used throughout ISO 32000 (the `cm` and `Tm` operators).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::identity()
+    }
+}
+
+impl Matrix {
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Matrix { a, b, c, d, e, f }
+    }
+
+    pub fn identity() -> Self {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn translate(tx: f32, ty: f32) -> Self {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, tx, ty)
+    }
+
+    /// Builds a matrix from the six numeric operands of `cm`/`Tm` or a
+    /// `/Matrix` array. Returns `None` if fewer than six numbers are given.
+    pub fn from_slice(values: &[f32]) -> Option<Self> {
+        match values {
+            [a, b, c, d, e, f, ..] => Some(Matrix::new(*a, *b, *c, *d, *e, *f)),
+            _ => None,
+        }
+    }
+
+    /// Returns `self × other`, i.e. `self` applied first, then `other`.
+    pub fn multiply(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * other.a + self.b * other.c,
+            b: self.a * other.b + self.b * other.d,
+            c: self.c * other.a + self.d * other.c,
+            d: self.c * other.b + self.d * other.d,
+            e: self.e * other.a + self.f * other.c + other.e,
+            f: self.e * other.b + self.f * other.d + other.f,
+        }
+    }
+
+    pub fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.a + y * self.c + self.e,
+            x * self.b + y * self.d + self.f,
+        )
+    }
+
+    /// The length a unit vector along the y axis has after transformation.
+    /// Used to derive the rendered font size from the text rendering matrix.
+    pub fn vertical_scale(&self) -> f32 {
+        (self.c * self.c + self.d * self.d).sqrt()
+    }
+
+    /// The length a unit vector along the x axis has after transformation.
+    pub fn horizontal_scale(&self) -> f32 {
+        (self.a * self.a + self.b * self.b).sqrt()
+    }
+}
@@ -0,0 +1,606 @@
+//! Combining pages from several PDFs into one document.
+//!
+//! Objects are copied from each input under fresh ids, following references
+//! from the selected pages, their outline items and named destinations;
+//! references to pages that were not selected become `null`. Each page
+//! keeps only the resources its content uses, and identical fonts, images
+//! and other shared resources are then stored once.
+
+use crate::content::resource_usage;
+use crate::encryption::load_document;
+use crate::metadata::{info_dictionary, read_metadata, xmp_stream};
+use crate::names::{destination_page, item_destination, name_tree, named_destinations};
+use crate::outline::{link_outline_items, OutlineNode, MAX_OUTLINE_DEPTH, MAX_OUTLINE_ITEMS};
+use crate::page_selection::{PageSelection, PageSelectionError};
+use crate::parser::{inherited_attribute, INHERITABLE};
+use crate::pyfile::PdfFile;
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+use pyo3::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to load input {index}: {message}")]
+    Load { index: usize, message: String },
+    #[error("Invalid pages for input {index}: {error}")]
+    Pages {
+        index: usize,
+        error: PageSelectionError,
+    },
+}
+
+/// A document to merge and the pages to take from it; `None` takes them all.
+pub struct MergeInput {
+    pub document: lopdf::Document,
+    pub pages: Option<PageSelection>,
+}
+
+/// Keys linking outline items together, rewritten when the outline is
+/// rebuilt.
+const OUTLINE_LINKS: [&[u8]; 6] = [b"Parent", b"Prev", b"Next", b"First", b"Last", b"Count"];
+
+/// Merges the PDF files `inputs`, each with an optional page selection, into
+/// `output`.
+pub fn merge_pdfs(
+    inputs: &[(&Path, Option<PageSelection>)],
+    output: &Path,
+) -> Result<(), MergeError> {
+    let mut documents = Vec::with_capacity(inputs.len());
+    for (index, (path, pages)) in inputs.iter().enumerate() {
+        documents.push(MergeInput {
+            document: load_input(index, &std::fs::read(path)?)?,
+            pages: pages.clone(),
+        });
+    }
+    let mut merged = merge_documents(&documents)?;
+    merged.save(output)?;
+    Ok(())
+}
+
+/// Loads input `index` from its file contents, decrypting it if the empty
+/// user password opens it.
+fn load_input(index: usize, bytes: &[u8]) -> Result<lopdf::Document, MergeError> {
+    load_document(bytes, None).map_err(|e| MergeError::Load {
+        index,
+        message: e.to_string(),
+    })
+}
+
+/// Concatenates the selected pages of `inputs` into a new document.
+///
+/// Top-level outline items of every input become top-level items of the
+/// result, dropping items that only lead to pages that were left out.
+/// Named destinations are merged, renaming clashes from later inputs. The
+/// first input's metadata is kept.
+pub fn merge_documents(inputs: &[MergeInput]) -> Result<lopdf::Document, MergeError> {
+    let mut parts = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.iter().enumerate() {
+        let count = input.document.get_pages().len();
+        let indices = match &input.pages {
+            Some(selection) => selection
+                .indices(count)
+                .map_err(|error| MergeError::Pages { index, error })?,
+            None => (0..count).collect(),
+        };
+        parts.push((&input.document, indices));
+    }
+    Ok(copy_pages(&parts))
+}
+
+/// Builds a document from the pages at the given 0-based indices of each
+/// source, as described for `merge_documents`.
+pub(crate) fn copy_pages(parts: &[(&lopdf::Document, Vec<usize>)]) -> lopdf::Document {
+    let version = parts
+        .iter()
+        .map(|(document, _)| document.version.as_str())
+        .max_by(|a, b| {
+            a.parse::<f32>()
+                .unwrap_or(0.0)
+                .total_cmp(&b.parse().unwrap_or(0.0))
+        })
+        .unwrap_or("1.7");
+    let mut target = lopdf::Document::with_version(version);
+    let pages_id = target.new_object_id();
+    let mut kids = Vec::new();
+    let mut outline_items = Vec::new();
+    let mut destinations = Vec::new();
+    let mut used_names = HashSet::new();
+
+    for &(source, ref indices) in parts {
+        let page_ids: Vec<ObjectId> = source.get_pages().into_values().collect();
+        let selected: Vec<ObjectId> = indices.iter().map(|&i| page_ids[i]).collect();
+
+        // Names from earlier inputs win; later clashes get a suffix.
+        let named = named_destinations(source);
+        let mut renames = HashMap::new();
+        for name in named.keys() {
+            let mut unique = name.clone();
+            let mut n = 2;
+            while used_names.contains(&unique) {
+                unique = [name.as_slice(), format!("-{}", n).as_bytes()].concat();
+                n += 1;
+            }
+            used_names.insert(unique.clone());
+            renames.insert(name.clone(), unique);
+        }
+
+        let mut importer = Importer {
+            source,
+            target: &mut target,
+            pages: selected.iter().copied().collect(),
+            ids: HashMap::new(),
+            queue: Vec::new(),
+            renames: &renames,
+        };
+        let mut imported_pages = HashSet::new();
+        for &page_id in &selected {
+            // A page selected more than once is copied again.
+            let id = if imported_pages.insert(page_id) {
+                importer.import(page_id)
+            } else {
+                importer.import_copy(page_id)
+            };
+            kids.push(Object::Reference(id));
+        }
+        importer.run(pages_id);
+
+        for (name, dest) in &named {
+            if destination_page(source, dest, &named).is_some_and(|p| importer.pages.contains(&p)) {
+                let value = importer.convert(dest);
+                destinations.push((renames[name].clone(), value));
+            }
+        }
+        outline_items.extend(importer.import_outline(&named));
+        importer.run(pages_id);
+    }
+
+    let count = kids.len() as i64;
+    target.objects.insert(
+        pages_id,
+        Object::Dictionary(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", kids.into()),
+            ("Count", count.into()),
+        ])),
+    );
+    let mut catalog = Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Catalog".to_vec())),
+        ("Pages", pages_id.into()),
+    ]);
+    if !outline_items.is_empty() {
+        let outlines_id = target.new_object_id();
+        let first = outline_items[0].id;
+        let last = outline_items[outline_items.len() - 1].id;
+        let visible = link_outline_items(&mut target, outlines_id, outline_items);
+        target.objects.insert(
+            outlines_id,
+            Object::Dictionary(Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Outlines".to_vec())),
+                ("First", first.into()),
+                ("Last", last.into()),
+                ("Count", visible.into()),
+            ])),
+        );
+        catalog.set("Outlines", outlines_id);
+    }
+    if !destinations.is_empty() {
+        let dests_id = target.add_object(name_tree(destinations));
+        catalog.set(
+            "Names",
+            Dictionary::from_iter(vec![("Dests", dests_id.into())]),
+        );
+    }
+    if let Some((first, _)) = parts.first() {
+        let metadata = read_metadata(first);
+        if !metadata.is_empty() {
+            let info_id = target.add_object(info_dictionary(&metadata));
+            target.trailer.set("Info", info_id);
+            catalog.set("Metadata", target.add_object(xmp_stream(&metadata)));
+        }
+    }
+    deduplicate(&mut target);
+    let catalog_id = target.add_object(catalog);
+    target.trailer.set("Root", catalog_id);
+    target
+}
+
+/// Copies the objects reachable from one input's selected pages into the
+/// merged document.
+struct Importer<'s, 't> {
+    source: &'s lopdf::Document,
+    target: &'t mut lopdf::Document,
+    /// Source ids of the selected pages. Other pages, and the source's page
+    /// tree and catalog, are not copied.
+    pages: HashSet<ObjectId>,
+    /// Source id to merged id of the objects copied so far.
+    ids: HashMap<ObjectId, ObjectId>,
+    /// Objects that have an id but have not been copied yet.
+    queue: Vec<(ObjectId, ObjectId)>,
+    /// Named destinations renamed to avoid clashes.
+    renames: &'s HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Importer<'_, '_> {
+    /// The merged id of source object `id`, copying it on first use.
+    fn import(&mut self, id: ObjectId) -> ObjectId {
+        if let Some(&new) = self.ids.get(&id) {
+            return new;
+        }
+        let new = self.target.new_object_id();
+        self.ids.insert(id, new);
+        self.queue.push((id, new));
+        new
+    }
+
+    /// Copies source object `id` under a new id even if it was copied before.
+    fn import_copy(&mut self, id: ObjectId) -> ObjectId {
+        let new = self.target.new_object_id();
+        self.queue.push((id, new));
+        new
+    }
+
+    /// Whether references to source object `id` are dropped.
+    fn excluded(&self, id: ObjectId) -> bool {
+        let Ok(dict) = self.source.get_dictionary(id) else {
+            return false;
+        };
+        match dict.get(b"Type").and_then(Object::as_name) {
+            Ok(b"Page") => !self.pages.contains(&id),
+            Ok(b"Pages" | b"Catalog" | b"Outlines") => true,
+            _ => false,
+        }
+    }
+
+    /// Copies the queued objects, and everything they refer to, with pages
+    /// placed under `pages_id`.
+    fn run(&mut self, pages_id: ObjectId) {
+        while let Some((old, new)) = self.queue.pop() {
+            let Some(object) = self.source.objects.get(&old) else {
+                continue;
+            };
+            let object = match object {
+                Object::Dictionary(page) if self.pages.contains(&old) => {
+                    Object::Dictionary(self.convert_page(old, page, pages_id))
+                }
+                other => self.convert(other),
+            };
+            self.target.objects.insert(new, object);
+        }
+    }
+
+    /// A copy of page `id` placed under `pages_id`, with its inherited
+    /// attributes filled in and only the resources its content uses.
+    fn convert_page(&mut self, id: ObjectId, page: &Dictionary, pages_id: ObjectId) -> Dictionary {
+        let mut page = page.clone();
+        for key in INHERITABLE {
+            if let Some(value) = inherited_attribute(self.source, id, key) {
+                page.set(key, value.clone());
+            }
+        }
+        if let Some(resources) = used_resources(self.source, id, &page) {
+            page.set("Resources", resources);
+        }
+        let mut page = self.convert_dictionary(&page);
+        page.set("Parent", pages_id);
+        page
+    }
+
+    /// A deep copy of `object` with references mapped to merged ids and
+    /// named destinations renamed.
+    fn convert(&mut self, object: &Object) -> Object {
+        match object {
+            Object::Reference(id) => {
+                if self.excluded(*id) {
+                    Object::Null
+                } else {
+                    Object::Reference(self.import(*id))
+                }
+            }
+            Object::Array(items) => Object::Array(items.iter().map(|o| self.convert(o)).collect()),
+            Object::Dictionary(dict) => Object::Dictionary(self.convert_dictionary(dict)),
+            Object::Stream(stream) => {
+                let mut copy = stream.clone();
+                copy.dict = self.convert_dictionary(&stream.dict);
+                Object::Stream(copy)
+            }
+            other => other.clone(),
+        }
+    }
+
+    fn convert_dictionary(&mut self, dict: &Dictionary) -> Dictionary {
+        let is_goto = dict.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo");
+        let mut copy = Dictionary::new();
+        for (key, value) in dict.iter() {
+            let names_destination = key == b"Dest" || (is_goto && key == b"D");
+            let value = match value {
+                // Named destinations all end up in the name tree, where they
+                // are looked up by string.
+                Object::Name(name) | Object::String(name, _) if names_destination => {
+                    let name = self.renames.get(name).unwrap_or(name);
+                    Object::string_literal(name.clone())
+                }
+                _ => self.convert(value),
+            };
+            copy.set(key.clone(), value);
+        }
+        copy
+    }
+
+    /// Copies the outline items leading to selected pages (or to other
+    /// targets, such as web links), returning the top-level ones.
+    fn import_outline(&mut self, named: &BTreeMap<Vec<u8>, Object>) -> Vec<OutlineNode> {
+        let first = self
+            .source
+            .catalog()
+            .ok()
+            .and_then(|c| c.get(b"Outlines").ok())
+            .and_then(|o| self.source.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+            .and_then(|outlines| outlines.get(b"First").ok())
+            .and_then(|o| o.as_reference().ok());
+        let mut visited = HashSet::new();
+        self.import_outline_items(first, named, &mut visited, 1)
+    }
+
+    fn import_outline_items(
+        &mut self,
+        first: Option<ObjectId>,
+        named: &BTreeMap<Vec<u8>, Object>,
+        visited: &mut HashSet<ObjectId>,
+        depth: usize,
+    ) -> Vec<OutlineNode> {
+        let mut nodes = Vec::new();
+        if depth > MAX_OUTLINE_DEPTH {
+            return nodes;
+        }
+        let mut next = first;
+        while let Some(id) = next {
+            if !visited.insert(id) || visited.len() > MAX_OUTLINE_ITEMS {
+                break;
+            }
+            let Ok(item) = self.source.get_dictionary(id) else {
+                break;
+            };
+            next = item.get(b"Next").and_then(Object::as_reference).ok();
+            let child = item.get(b"First").and_then(Object::as_reference).ok();
+            let children = self.import_outline_items(child, named, visited, depth + 1);
+
+            let dest = item_destination(self.source, item);
+            let page = dest.and_then(|d| destination_page(self.source, d, named));
+            let leads_nowhere = page.is_some_and(|p| !self.pages.contains(&p))
+                || dest.is_some_and(|d| is_missing_name(self.source, d, named));
+            if leads_nowhere && children.is_empty() {
+                continue;
+            }
+            let mut copy = item.clone();
+            for key in OUTLINE_LINKS {
+                copy.remove(key);
+            }
+            if leads_nowhere {
+                copy.remove(b"Dest");
+                copy.remove(b"A");
+            }
+            let copy = self.convert_dictionary(&copy);
+            let open = item.get(b"Count").and_then(Object::as_i64).unwrap_or(0) >= 0;
+            nodes.push(OutlineNode {
+                id: self.target.add_object(copy),
+                open,
+                children,
+            });
+        }
+        nodes
+    }
+}
+
+/// Resource categories whose entries content streams use by name.
+const NAMED_RESOURCES: [&[u8]; 7] = [
+    b"Font",
+    b"XObject",
+    b"ExtGState",
+    b"ColorSpace",
+    b"Pattern",
+    b"Shading",
+    b"Properties",
+];
+
+/// The resources of page `id` that its content uses, or `None` to keep them
+/// all: when the content cannot be parsed, or when a form XObject or Type 3
+/// font it uses has no resources of its own and so draws on the page's.
+fn used_resources(doc: &lopdf::Document, id: ObjectId, page: &Dictionary) -> Option<Dictionary> {
+    let (_, resources) = doc.dereference(page.get(b"Resources").ok()?).ok()?;
+    let resources = resources.as_dict().ok()?;
+    let usage = resource_usage(&doc.get_page_content(id).ok()?)?;
+    let mut used = Dictionary::new();
+    for (key, value) in resources.iter() {
+        if !NAMED_RESOURCES.contains(&key.as_slice()) {
+            used.set(key.clone(), value.clone());
+            continue;
+        }
+        let Some(names) = usage.names.get(key) else {
+            continue;
+        };
+        let category = doc.dereference(value).ok()?.1.as_dict().ok()?;
+        let mut kept = Dictionary::new();
+        for (name, entry) in category.iter() {
+            if !names.contains(name) {
+                continue;
+            }
+            if let Ok((_, Object::Stream(Stream { dict, .. }) | Object::Dictionary(dict))) =
+                doc.dereference(entry)
+            {
+                let subtype = dict.get(b"Subtype").and_then(Object::as_name);
+                if matches!(subtype, Ok(b"Form" | b"Type3")) && !dict.has(b"Resources") {
+                    return None;
+                }
+            }
+            kept.set(name.clone(), entry.clone());
+        }
+        if !kept.is_empty() {
+            used.set(key.clone(), kept);
+        }
+    }
+    Some(used)
+}
+
+/// Whether `dest` names a destination the document does not define.
+fn is_missing_name(
+    doc: &lopdf::Document,
+    dest: &Object,
+    named: &BTreeMap<Vec<u8>, Object>,
+) -> bool {
+    match doc.dereference(dest).map(|(_, o)| o) {
+        Ok(Object::Name(name) | Object::String(name, _)) => !named.contains_key(name),
+        _ => false,
+    }
+}
+
+/// Whether an object may be shared by whatever refers to it: fonts, images
+/// and other streams, and the arrays and dictionaries resources are built
+/// from. Pages, annotations and form fields are never merged.
+fn is_shareable(object: &Object) -> bool {
+    match object {
+        Object::Stream(_) | Object::Array(_) => true,
+        Object::Dictionary(dict) => matches!(
+            dict.get(b"Type").and_then(Object::as_name),
+            Ok(b"Font" | b"FontDescriptor" | b"Encoding" | b"ExtGState" | b"Pattern")
+        ),
+        _ => false,
+    }
+}
+
+/// Hashes the parts of an object that `same_object` compares.
+fn shallow_hash(object: &Object) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match object {
+        Object::Stream(stream) => {
+            stream.content.hash(&mut hasher);
+            stream.dict.len().hash(&mut hasher);
+        }
+        Object::Dictionary(dict) => {
+            let mut keys: Vec<&Vec<u8>> = dict.iter().map(|(k, _)| k).collect();
+            keys.sort();
+            keys.hash(&mut hasher);
+        }
+        Object::Array(items) => items.len().hash(&mut hasher),
+        _ => {}
+    }
+    hasher.finish()
+}
+
+fn same_object(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        // Stream equality would also compare where they were read from.
+        (Object::Stream(a), Object::Stream(b)) => a.dict == b.dict && a.content == b.content,
+        _ => a == b,
+    }
+}
+
+/// Stores identical shareable objects once. Merging objects can make the
+/// objects referring to them identical in turn, so this repeats until
+/// nothing changes.
+fn deduplicate(target: &mut lopdf::Document) {
+    loop {
+        let mut buckets: HashMap<u64, Vec<ObjectId>> = HashMap::new();
+        let mut replace: HashMap<ObjectId, ObjectId> = HashMap::new();
+        for (&id, object) in &target.objects {
+            if !is_shareable(object) {
+                continue;
+            }
+            let bucket = buckets.entry(shallow_hash(object)).or_default();
+            match bucket
+                .iter()
+                .find(|&&other| same_object(&target.objects[&other], object))
+            {
+                Some(&original) => {
+                    replace.insert(id, original);
+                }
+                None => bucket.push(id),
+            }
+        }
+        if replace.is_empty() {
+            return;
+        }
+        for id in replace.keys() {
+            target.objects.remove(id);
+        }
+        for object in target.objects.values_mut() {
+            replace_references(object, &replace);
+        }
+    }
+}
+
+fn replace_references(object: &mut Object, replace: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(&new) = replace.get(id) {
+                *id = new;
+            }
+        }
+        Object::Array(items) => {
+            for item in items {
+                replace_references(item, replace);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter_mut() {
+                replace_references(value, replace);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter_mut() {
+                replace_references(value, replace);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An input of the Python `merge` function: a file, or a file and the pages
+/// to take from it.
+pub enum MergeSource {
+    File(PdfFile),
+    Pages(PdfFile, String),
+}
+
+impl<'py> FromPyObject<'py> for MergeSource {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok((file, pages)) = ob.extract::<(PdfFile, String)>() {
+            return Ok(MergeSource::Pages(file, pages));
+        }
+        ob.extract().map(MergeSource::File)
+    }
+}
+
+/// Merges `inputs` into `output_path`. Each input is a path or file object,
+/// or a `(file, pages)` tuple selecting pages such as `"1-3,7,10-"`.
+#[pyfunction]
+pub fn merge(inputs: Vec<MergeSource>, output_path: PdfFile) -> PyResult<()> {
+    let value_error = |e: &dyn std::fmt::Display| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to merge PDFs: {}", e))
+    };
+    let mut documents = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.iter().enumerate() {
+        let (file, pages) = match input {
+            MergeSource::File(file) => (file, None),
+            MergeSource::Pages(file, spec) => {
+                let pages = PageSelection::parse(spec)
+                    .map_err(|error| value_error(&MergeError::Pages { index, error }))?;
+                (file, Some(pages))
+            }
+        };
+        let bytes = file.read().map_err(|e| value_error(&e))?;
+        documents.push(MergeInput {
+            document: load_input(index, &bytes).map_err(|e| value_error(&e))?,
+            pages,
+        });
+    }
+    let mut merged = merge_documents(&documents).map_err(|e| value_error(&e))?;
+    let mut bytes = Vec::new();
+    merged.save_to(&mut bytes).map_err(|e| value_error(&e))?;
+    output_path.write(&bytes).map_err(|e| value_error(&e))
+}
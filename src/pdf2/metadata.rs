@@ -0,0 +1,448 @@
+//! Document metadata: the trailer's Info dictionary and the catalog's XMP
+//! packet, read into and written from `structure::Metadata`.
+//!
+//! When both are present XMP takes precedence, as ISO 32000-2 (14.3.3)
+//! specifies; Info entries fill in what the packet lacks. Generated files
+//! carry the same values in both.
+
+use crate::encoding::BaseEncoding;
+use crate::structure::Metadata;
+use lopdf::{Dictionary, Object, Stream, StringFormat};
+use roxmltree::Node;
+use std::fmt::Write as _;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const PDF: &str = "http://ns.adobe.com/pdf/1.3/";
+const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+/// Namespace Acrobat uses for custom Info entries.
+const PDFX: &str = "http://ns.adobe.com/pdfx/1.3/";
+
+/// Info dictionary keys of the standard `Metadata` fields.
+const INFO_KEYS: [&[u8]; 8] = [
+    b"Title",
+    b"Author",
+    b"Subject",
+    b"Keywords",
+    b"Creator",
+    b"Producer",
+    b"CreationDate",
+    b"ModDate",
+];
+
+/// Decodes a PDF text string: UTF-16BE or UTF-8 with a byte order mark,
+/// otherwise PDFDocEncoding.
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else {
+        bytes
+            .iter()
+            .map(|&b| BaseEncoding::PdfDoc.decode(b).unwrap_or('\u{FFFD}'))
+            .collect()
+    }
+}
+
+/// Encodes `text` as a PDF text string, in PDFDocEncoding when possible and
+/// as UTF-16BE otherwise.
+pub fn encode_text_string(text: &str) -> Object {
+    let pdf_doc: Option<Vec<u8>> = text
+        .chars()
+        .map(|ch| BaseEncoding::PdfDoc.encode(ch))
+        .collect();
+    let bytes = pdf_doc.unwrap_or_else(|| {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        bytes
+    });
+    Object::String(bytes, StringFormat::Literal)
+}
+
+/// Converts a PDF date (`D:YYYYMMDDHHmmSSOHH'mm'`, all but the year
+/// optional) to ISO 8601 with the same precision.
+pub fn pdf_date_to_iso(date: &str) -> Option<String> {
+    let date = date.trim();
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.bytes().take_while(u8::is_ascii_digit).count();
+    if !(4..=14).contains(&digits) || digits % 2 != 0 {
+        return None;
+    }
+    let (number, zone) = date.split_at(digits);
+    let field = |start: usize| number.get(start..start + 2).unwrap_or("00");
+    let mut iso = number[..4].to_string();
+    for start in [4, 6] {
+        if digits > start {
+            let _ = write!(iso, "-{}", field(start));
+        }
+    }
+    if digits > 8 {
+        let _ = write!(iso, "T{}:{}:{}", field(8), field(10), field(12));
+        match zone.chars().next() {
+            None => {}
+            Some('Z') => iso.push('Z'),
+            Some(sign @ ('+' | '-')) => {
+                let offset: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+                if offset.len() != 2 && offset.len() != 4 {
+                    return None;
+                }
+                let minutes = offset.get(2..4).unwrap_or("00");
+                let _ = write!(iso, "{}{}:{}", sign, &offset[..2], minutes);
+            }
+            Some(_) => return None,
+        }
+    }
+    Some(iso)
+}
+
+/// Converts an ISO 8601 date (`YYYY[-MM[-DD[THH:MM[:SS[.s]][TZD]]]]`) to a
+/// PDF date.
+pub fn iso_to_pdf_date(date: &str) -> Option<String> {
+    let is_number = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+    let date = date.trim();
+    let (day, time) = match date.split_once('T') {
+        Some((day, time)) => (day, Some(time)),
+        None => (date, None),
+    };
+    let fields: Vec<&str> = day.split('-').collect();
+    let lengths = [4, 2, 2];
+    if fields.len() > 3
+        || fields
+            .iter()
+            .zip(lengths)
+            .any(|(f, len)| !is_number(f, len))
+    {
+        return None;
+    }
+    let mut pdf = format!("D:{}", fields.concat());
+    let Some(time) = time else {
+        return Some(pdf);
+    };
+    if fields.len() != 3 {
+        return None;
+    }
+    let zone_start = time.find(['Z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(zone_start);
+    let clock = clock.split('.').next().unwrap_or_default();
+    let parts: Vec<&str> = clock.split(':').collect();
+    if !(2..=3).contains(&parts.len()) || parts.iter().any(|p| !is_number(p, 2)) {
+        return None;
+    }
+    pdf.push_str(&parts.concat());
+    match zone.split_once(':') {
+        _ if zone.is_empty() => {}
+        _ if zone == "Z" => pdf.push('Z'),
+        Some((hours, minutes)) if is_number(&hours[1..], 2) && is_number(minutes, 2) => {
+            let _ = write!(pdf, "{}'{}'", hours, minutes);
+        }
+        _ => return None,
+    }
+    Some(pdf)
+}
+
+/// Reads the document's metadata from its Info dictionary and XMP packet.
+pub fn read_metadata(pdf: &lopdf::Document) -> Metadata {
+    let mut metadata = read_info(pdf);
+    if let Some(xmp) = xmp_packet(pdf) {
+        merge_xmp(&mut metadata, &xmp);
+    }
+    metadata
+}
+
+fn read_info(pdf: &lopdf::Document) -> Metadata {
+    let mut metadata = Metadata::default();
+    let Some(info) = pdf
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(|o| pdf.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+    else {
+        return metadata;
+    };
+    for (key, value) in info.iter() {
+        let Ok((_, Object::String(bytes, _))) = pdf.dereference(value) else {
+            continue;
+        };
+        let text = decode_text_string(bytes);
+        let date = || Some(pdf_date_to_iso(&text).unwrap_or_else(|| text.clone()));
+        match key.as_slice() {
+            b"Title" => metadata.title = Some(text),
+            b"Author" => metadata.author = Some(text),
+            b"Subject" => metadata.subject = Some(text),
+            b"Keywords" => metadata.keywords = Some(text),
+            b"Creator" => metadata.creator = Some(text),
+            b"Producer" => metadata.producer = Some(text),
+            b"CreationDate" => metadata.creation_date = date(),
+            b"ModDate" => metadata.mod_date = date(),
+            _ => {
+                metadata
+                    .custom
+                    .insert(String::from_utf8_lossy(key).into_owned(), text);
+            }
+        }
+    }
+    metadata
+}
+
+/// The catalog's `/Metadata` stream contents, decoded.
+fn xmp_packet(pdf: &lopdf::Document) -> Option<String> {
+    let catalog = pdf.catalog().ok()?;
+    let (_, object) = pdf.dereference(catalog.get(b"Metadata").ok()?).ok()?;
+    let stream = object.as_stream().ok()?;
+    let data = stream.get_plain_content().ok()?;
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Overrides `metadata` with the values found in the XMP packet `xmp`.
+fn merge_xmp(metadata: &mut Metadata, xmp: &str) {
+    let Ok(tree) =
+        roxmltree::Document::parse(xmp.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+    else {
+        return;
+    };
+    let descriptions = tree
+        .descendants()
+        .filter(|n| n.has_tag_name((RDF, "Description")));
+    let mut subject_keywords = None;
+    for description in descriptions {
+        // Simple properties may be written as attributes of the description.
+        let attributes = description
+            .attributes()
+            .filter_map(|a| Some((a.namespace()?, a.name(), a.value().to_string())));
+        let elements = description
+            .children()
+            .filter(Node::is_element)
+            .filter_map(|e| {
+                Some((
+                    e.tag_name().namespace()?,
+                    e.tag_name().name(),
+                    property_value(e)?,
+                ))
+            });
+        for (namespace, name, value) in attributes.chain(elements) {
+            let field = match (namespace, name) {
+                (DC, "title") => &mut metadata.title,
+                (DC, "creator") => &mut metadata.author,
+                (DC, "description") => &mut metadata.subject,
+                (DC, "subject") => &mut subject_keywords,
+                (PDF, "Keywords") => &mut metadata.keywords,
+                (PDF, "Producer") => &mut metadata.producer,
+                (XMP, "CreatorTool") => &mut metadata.creator,
+                (XMP, "CreateDate") => &mut metadata.creation_date,
+                (XMP, "ModifyDate") => &mut metadata.mod_date,
+                (PDFX, _) => {
+                    metadata.custom.insert(name.to_string(), value);
+                    continue;
+                }
+                _ => continue,
+            };
+            *field = Some(value);
+        }
+    }
+    if metadata.keywords.is_none() {
+        metadata.keywords = subject_keywords;
+    }
+}
+
+/// The text of an XMP property: a simple value, the `x-default` (or first)
+/// item of a language alternative, or the items of an array joined
+/// together.
+fn property_value(element: Node) -> Option<String> {
+    let container = element.children().find(|c| {
+        c.tag_name().namespace() == Some(RDF)
+            && ["Alt", "Seq", "Bag"].contains(&c.tag_name().name())
+    });
+    let Some(container) = container else {
+        return Some(text_content(element));
+    };
+    let items: Vec<Node> = container
+        .children()
+        .filter(|c| c.has_tag_name((RDF, "li")))
+        .collect();
+    match container.tag_name().name() {
+        "Alt" => {
+            let default = items.iter().find(|li| {
+                li.attribute(("http://www.w3.org/XML/1998/namespace", "lang")) == Some("x-default")
+            });
+            default.or(items.first()).map(|li| text_content(*li))
+        }
+        "Seq" => Some(
+            items
+                .iter()
+                .map(|li| text_content(*li))
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+        _ => Some(
+            items
+                .iter()
+                .map(|li| text_content(*li))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    }
+}
+
+fn text_content(node: Node) -> String {
+    node.descendants()
+        .filter(Node::is_text)
+        .filter_map(|t| t.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// The Info dictionary for `metadata`. Dates that are not ISO 8601 are
+/// written unchanged, on the assumption that they are already PDF dates.
+pub fn info_dictionary(metadata: &Metadata) -> Dictionary {
+    let date = |d: &str| iso_to_pdf_date(d).unwrap_or_else(|| d.to_string());
+    let values = [
+        metadata.title.clone(),
+        metadata.author.clone(),
+        metadata.subject.clone(),
+        metadata.keywords.clone(),
+        metadata.creator.clone(),
+        metadata.producer.clone(),
+        metadata.creation_date.as_deref().map(date),
+        metadata.mod_date.as_deref().map(date),
+    ];
+    let mut info = Dictionary::new();
+    for (key, value) in INFO_KEYS.iter().zip(values) {
+        if let Some(value) = value {
+            info.set(*key, encode_text_string(&value));
+        }
+    }
+    for (key, value) in &metadata.custom {
+        info.set(key.as_bytes(), encode_text_string(value));
+    }
+    info
+}
+
+/// The XMP metadata stream for `metadata`, left uncompressed so that tools
+/// which do not parse PDF can still find it.
+pub fn xmp_stream(metadata: &Metadata) -> Stream {
+    let mut properties = String::new();
+    let mut simple = |prefix: &str, name: &str, value: &str| {
+        let _ = writeln!(
+            properties,
+            "   <{0}:{1}>{2}</{0}:{1}>",
+            prefix,
+            name,
+            escape_xml(value)
+        );
+    };
+    simple("dc", "format", "application/pdf");
+    if let Some(keywords) = &metadata.keywords {
+        simple("pdf", "Keywords", keywords);
+    }
+    if let Some(producer) = &metadata.producer {
+        simple("pdf", "Producer", producer);
+    }
+    if let Some(creator) = &metadata.creator {
+        simple("xmp", "CreatorTool", creator);
+    }
+    // XMP dates must be ISO 8601; other values are left to the Info
+    // dictionary.
+    let iso = |date: &Option<String>| {
+        date.as_deref()
+            .filter(|d| iso_to_pdf_date(d).is_some())
+            .map(str::to_string)
+    };
+    if let Some(created) = iso(&metadata.creation_date) {
+        simple("xmp", "CreateDate", &created);
+    }
+    if let Some(modified) = iso(&metadata.mod_date) {
+        simple("xmp", "ModifyDate", &modified);
+        simple("xmp", "MetadataDate", &modified);
+    }
+    for (key, value) in &metadata.custom {
+        // Only keys that are valid XML names can become pdfx properties.
+        let valid = key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+        if valid {
+            simple("pdfx", key, value);
+        }
+    }
+
+    let language_alternative = |name: &str, value: &str| {
+        format!(
+            "   <dc:{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></dc:{0}>\n",
+            name,
+            escape_xml(value)
+        )
+    };
+    if let Some(title) = &metadata.title {
+        properties.push_str(&language_alternative("title", title));
+    }
+    if let Some(subject) = &metadata.subject {
+        properties.push_str(&language_alternative("description", subject));
+    }
+    if let Some(author) = &metadata.author {
+        let _ = writeln!(
+            properties,
+            "   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+            escape_xml(author)
+        );
+    }
+    if let Some(keywords) = &metadata.keywords {
+        let items: String = keywords
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(|k| format!("<rdf:li>{}</rdf:li>", escape_xml(k)))
+            .collect();
+        if !items.is_empty() {
+            let _ = writeln!(
+                properties,
+                "   <dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>",
+                items
+            );
+        }
+    }
+
+    let packet = format!(
+        "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+         <rdf:RDF xmlns:rdf=\"{RDF}\">\n  \
+         <rdf:Description rdf:about=\"\" xmlns:dc=\"{DC}\" xmlns:pdf=\"{PDF}\" \
+         xmlns:xmp=\"{XMP}\" xmlns:pdfx=\"{PDFX}\">\n\
+         {properties}  \
+         </rdf:Description>\n \
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>"
+    );
+    Stream::new(
+        Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Metadata".to_vec())),
+            ("Subtype", Object::Name(b"XML".to_vec())),
+        ]),
+        packet.into_bytes(),
+    )
+    .with_compression(false)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
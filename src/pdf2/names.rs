@@ -0,0 +1,231 @@
+//! Name trees (ISO 32000-1, 7.9.6) and the destinations that outline items,
+//! links and named destinations point to.
+
+use crate::structure::Destination;
+use lopdf::{Dictionary, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+
+/// Limits recursion into malformed, cyclic name trees.
+const MAX_DEPTH: usize = 32;
+
+/// The entries of the name tree rooted at `node`, in key order. Values are
+/// returned as stored, possibly as references.
+pub fn name_tree_entries(doc: &lopdf::Document, node: &Dictionary) -> Vec<(Vec<u8>, Object)> {
+    let mut entries = Vec::new();
+    collect_entries(doc, node, 0, &mut entries);
+    entries
+}
+
+fn collect_entries(
+    doc: &lopdf::Document,
+    node: &Dictionary,
+    depth: usize,
+    entries: &mut Vec<(Vec<u8>, Object)>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let array = |key: &[u8]| {
+        node.get(key)
+            .ok()
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_array().ok())
+    };
+    if let Some(names) = array(b"Names") {
+        for pair in names.chunks_exact(2) {
+            if let Ok(Object::String(key, _)) = doc.dereference(&pair[0]).map(|(_, o)| o) {
+                entries.push((key.clone(), pair[1].clone()));
+            }
+        }
+    }
+    for kid in array(b"Kids").into_iter().flatten() {
+        if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+            collect_entries(doc, kid, depth + 1, entries);
+        }
+    }
+}
+
+/// A flat name tree holding `entries`, which are sorted by key as the
+/// format requires.
+pub fn name_tree(mut entries: Vec<(Vec<u8>, Object)>) -> Dictionary {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let names: Vec<Object> = entries
+        .into_iter()
+        .flat_map(|(key, value)| [Object::string_literal(key), value])
+        .collect();
+    Dictionary::from_iter(vec![("Names", Object::Array(names))])
+}
+
+/// The document's named destinations, from both the PDF 1.1 `/Dests`
+/// dictionary (named by names) and the `/Names` `/Dests` tree (named by
+/// strings). Values are dereferenced.
+pub fn named_destinations(doc: &lopdf::Document) -> BTreeMap<Vec<u8>, Object> {
+    let mut destinations = BTreeMap::new();
+    let Ok(catalog) = doc.catalog() else {
+        return destinations;
+    };
+    let dictionary = |object| dereference_dictionary(doc, object);
+    if let Some(dests) = catalog.get(b"Dests").ok().and_then(dictionary) {
+        for (name, value) in dests.iter() {
+            if let Ok((_, value)) = doc.dereference(value) {
+                destinations.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    let tree = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(dictionary)
+        .and_then(|names| names.get(b"Dests").ok())
+        .and_then(dictionary);
+    if let Some(tree) = tree {
+        for (name, value) in name_tree_entries(doc, tree) {
+            if let Ok((_, value)) = doc.dereference(&value) {
+                destinations.insert(name, value.clone());
+            }
+        }
+    }
+    destinations
+}
+
+fn dereference_dictionary<'a>(
+    doc: &'a lopdf::Document,
+    object: &'a Object,
+) -> Option<&'a Dictionary> {
+    doc.dereference(object)
+        .ok()
+        .and_then(|(_, o)| o.as_dict().ok())
+}
+
+/// The destination of an outline item or link annotation: its `/Dest`, or
+/// the `/D` of a GoTo action.
+pub fn item_destination<'a>(doc: &'a lopdf::Document, item: &'a Dictionary) -> Option<&'a Object> {
+    if let Ok(dest) = item.get(b"Dest") {
+        return Some(dest);
+    }
+    let (_, action) = doc.dereference(item.get(b"A").ok()?).ok()?;
+    let action = action.as_dict().ok()?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+        return None;
+    }
+    action.get(b"D").ok()
+}
+
+/// The page a destination points to, looking names up in `named`.
+pub fn destination_page(
+    doc: &lopdf::Document,
+    dest: &Object,
+    named: &BTreeMap<Vec<u8>, Object>,
+) -> Option<ObjectId> {
+    let (_, dest) = doc.dereference(dest).ok()?;
+    let dest = match dest {
+        Object::Name(name) | Object::String(name, _) => named.get(name)?,
+        other => other,
+    };
+    let array = match dest {
+        Object::Dictionary(dict) => doc.dereference(dict.get(b"D").ok()?).ok()?.1,
+        other => other,
+    };
+    array.as_array().ok()?.first()?.as_reference().ok()
+}
+
+/// The fit types of explicit destinations and the coordinates each takes,
+/// in order.
+const FIT_TYPES: [(&str, &[&str]); 8] = [
+    ("XYZ", &["left", "top", "zoom"]),
+    ("Fit", &[]),
+    ("FitH", &["top"]),
+    ("FitV", &["left"]),
+    ("FitR", &["left", "bottom", "right", "top"]),
+    ("FitB", &[]),
+    ("FitBH", &["top"]),
+    ("FitBV", &["left"]),
+];
+
+/// Reads a destination, looking names up in `named` and pages up in
+/// `page_indices` (page id to index). Destinations whose page is not in the
+/// document are `None`.
+pub fn read_destination(
+    doc: &lopdf::Document,
+    dest: &Object,
+    named: &BTreeMap<Vec<u8>, Object>,
+    page_indices: &HashMap<ObjectId, usize>,
+) -> Option<Destination> {
+    let (_, dest) = doc.dereference(dest).ok()?;
+    let dest = match dest {
+        Object::Name(name) | Object::String(name, _) => named.get(name)?,
+        other => other,
+    };
+    let array = match dest {
+        Object::Dictionary(dict) => doc.dereference(dict.get(b"D").ok()?).ok()?.1,
+        other => other,
+    };
+    let array = array.as_array().ok()?;
+    let page = match array.first()? {
+        Object::Reference(id) => *page_indices.get(id)?,
+        // Some writers number pages from 0 as in remote destinations.
+        Object::Integer(index) => usize::try_from(*index)
+            .ok()
+            .filter(|&i| i < page_indices.len())?,
+        _ => return None,
+    };
+    let kind = array
+        .get(1)
+        .and_then(|k| k.as_name().ok())
+        .map(|k| String::from_utf8_lossy(k).into_owned())
+        .unwrap_or_else(|| "Fit".to_string());
+    let mut destination = Destination {
+        page,
+        kind,
+        ..Default::default()
+    };
+    let keys = FIT_TYPES
+        .iter()
+        .find(|(kind, _)| *kind == destination.kind)
+        .map_or(&[][..], |(_, keys)| keys);
+    for (key, value) in keys.iter().zip(array.iter().skip(2)) {
+        // `null`, and a zoom of 0, keep the viewer's current value.
+        let value = value
+            .as_float()
+            .ok()
+            .filter(|v| *key != "zoom" || *v != 0.0);
+        match *key {
+            "left" => destination.left = value,
+            "top" => destination.top = value,
+            "right" => destination.right = value,
+            "bottom" => destination.bottom = value,
+            _ => destination.zoom = value,
+        }
+    }
+    Some(destination)
+}
+
+/// The explicit destination array for `dest`, pointing at `pages[dest.page]`.
+pub fn destination_array(dest: &Destination, pages: &[ObjectId]) -> Result<Object, String> {
+    let page = pages.get(dest.page).ok_or_else(|| {
+        format!(
+            "destination page {} is out of range (the document has {} pages)",
+            dest.page,
+            pages.len()
+        )
+    })?;
+    let (kind, keys) = FIT_TYPES
+        .iter()
+        .find(|(kind, _)| *kind == dest.kind)
+        .ok_or_else(|| format!("unknown destination kind '{}'", dest.kind))?;
+    let mut array = vec![
+        Object::Reference(*page),
+        Object::Name(kind.as_bytes().to_vec()),
+    ];
+    for key in keys.iter() {
+        let value = match *key {
+            "left" => dest.left,
+            "top" => dest.top,
+            "right" => dest.right,
+            "bottom" => dest.bottom,
+            _ => dest.zoom,
+        };
+        array.push(value.map_or(Object::Null, Object::Real));
+    }
+    Ok(Object::Array(array))
+}
@@ -0,0 +1,165 @@
+//! The document outline (ISO 32000-1, 12.3.3): the tree of bookmarks
+//! under the catalog's `/Outlines` dictionary.
+
+use crate::metadata::{decode_text_string, encode_text_string};
+use crate::names::{destination_array, item_destination, named_destinations, read_destination};
+use crate::structure::OutlineItem;
+use lopdf::{Dictionary, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Guards walks over outline sibling lists against cycles.
+pub(crate) const MAX_OUTLINE_ITEMS: usize = 100_000;
+
+/// Items nested deeper than this are dropped, which bounds the recursion
+/// over the outline tree.
+pub(crate) const MAX_OUTLINE_DEPTH: usize = 64;
+
+/// Reads the outline of `doc`. Entries whose destination is not a page of
+/// the document keep their title and children but have no destination.
+/// Items nested more than `MAX_OUTLINE_DEPTH` levels deep are left out.
+pub fn read_outline(doc: &lopdf::Document) -> Vec<OutlineItem> {
+    let named = named_destinations(doc);
+    let page_indices: HashMap<ObjectId, usize> = doc
+        .get_pages()
+        .into_values()
+        .enumerate()
+        .map(|(index, id)| (id, index))
+        .collect();
+    let first = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"Outlines").ok())
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|outlines| outlines.get(b"First").ok())
+        .and_then(|o| o.as_reference().ok());
+
+    read_items(doc, first, &named, &page_indices, &mut HashSet::new(), 1)
+}
+
+fn read_items(
+    doc: &lopdf::Document,
+    first: Option<ObjectId>,
+    named: &BTreeMap<Vec<u8>, Object>,
+    page_indices: &HashMap<ObjectId, usize>,
+    visited: &mut HashSet<ObjectId>,
+    depth: usize,
+) -> Vec<OutlineItem> {
+    let mut items = Vec::new();
+    if depth > MAX_OUTLINE_DEPTH {
+        return items;
+    }
+    let mut next = first;
+    while let Some(id) = next {
+        if !visited.insert(id) || visited.len() > MAX_OUTLINE_ITEMS {
+            break;
+        }
+        let Ok(item) = doc.get_dictionary(id) else {
+            break;
+        };
+        next = item.get(b"Next").and_then(Object::as_reference).ok();
+        let child = item.get(b"First").and_then(Object::as_reference).ok();
+        let title = item
+            .get(b"Title")
+            .ok()
+            .and_then(|t| doc.dereference(t).ok())
+            .and_then(|(_, t)| t.as_str().ok())
+            .map(decode_text_string)
+            .unwrap_or_default();
+        let destination =
+            item_destination(doc, item).and_then(|d| read_destination(doc, d, named, page_indices));
+        items.push(OutlineItem {
+            title,
+            destination,
+            open: item.get(b"Count").and_then(Object::as_i64).unwrap_or(0) > 0,
+            children: read_items(doc, child, named, page_indices, visited, depth + 1),
+        });
+    }
+    items
+}
+
+/// Writes `items` as the outline of `pdf`, whose pages are `pages`, and
+/// returns the id of the outline dictionary for the catalog's `/Outlines`.
+pub fn write_outline(
+    pdf: &mut lopdf::Document,
+    items: &[OutlineItem],
+    pages: &[ObjectId],
+) -> Result<ObjectId, String> {
+    let outlines_id = pdf.new_object_id();
+    let nodes = add_items(pdf, items, pages)?;
+    let mut outlines = Dictionary::from_iter(vec![("Type", Object::Name(b"Outlines".to_vec()))]);
+    if let (Some(first), Some(last)) = (nodes.first(), nodes.last()) {
+        outlines.set("First", first.id);
+        outlines.set("Last", last.id);
+    }
+    let visible = link_outline_items(pdf, outlines_id, nodes);
+    outlines.set("Count", visible);
+    pdf.objects
+        .insert(outlines_id, Object::Dictionary(outlines));
+    Ok(outlines_id)
+}
+
+fn add_items(
+    pdf: &mut lopdf::Document,
+    items: &[OutlineItem],
+    pages: &[ObjectId],
+) -> Result<Vec<OutlineNode>, String> {
+    items
+        .iter()
+        .map(|item| {
+            let mut dict = Dictionary::from_iter(vec![("Title", encode_text_string(&item.title))]);
+            if let Some(destination) = &item.destination {
+                let array = destination_array(destination, pages)
+                    .map_err(|e| format!("outline item '{}': {}", item.title, e))?;
+                dict.set("Dest", array);
+            }
+            Ok(OutlineNode {
+                id: pdf.add_object(dict),
+                open: item.open,
+                children: add_items(pdf, &item.children, pages)?,
+            })
+        })
+        .collect()
+}
+
+/// An outline item written to a document, with its children.
+pub(crate) struct OutlineNode {
+    pub id: ObjectId,
+    /// Whether the item's children are shown.
+    pub open: bool,
+    pub children: Vec<OutlineNode>,
+}
+
+/// Links `items` as the children of `parent`, returning the number of
+/// items visible when `parent` is open.
+pub(crate) fn link_outline_items(
+    target: &mut lopdf::Document,
+    parent: ObjectId,
+    items: Vec<OutlineNode>,
+) -> i64 {
+    let ids: Vec<ObjectId> = items.iter().map(|item| item.id).collect();
+    let mut visible = 0;
+    for (index, item) in items.into_iter().enumerate() {
+        let has_children = !item.children.is_empty();
+        let first_child = item.children.first().map(|c| c.id);
+        let last_child = item.children.last().map(|c| c.id);
+        let descendants = link_outline_items(target, item.id, item.children);
+        visible += 1 + if item.open { descendants } else { 0 };
+        let Ok(dict) = target.get_dictionary_mut(item.id) else {
+            continue;
+        };
+        dict.set("Parent", parent);
+        if index > 0 {
+            dict.set("Prev", ids[index - 1]);
+        }
+        if let Some(&next) = ids.get(index + 1) {
+            dict.set("Next", next);
+        }
+        if has_children {
+            dict.set("First", first_child.unwrap());
+            dict.set("Last", last_child.unwrap());
+            dict.set("Count", if item.open { descendants } else { -descendants });
+        }
+    }
+    visible
+}
@@ -0,0 +1,99 @@
+//! Page selections such as `"1-3,7,10-"`: comma-separated 1-based page
+//! numbers and inclusive ranges, either end of which may be left open.
+
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum PageSelectionError {
+    #[error("invalid page selection '{0}'")]
+    Syntax(String),
+    #[error("page {page} is out of range (the document has {count} pages)")]
+    OutOfRange { page: usize, count: usize },
+}
+
+/// One item of a selection; `None` ends mean the first or last page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageRange {
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    ranges: Vec<PageRange>,
+}
+
+impl PageSelection {
+    /// Every page, in order.
+    pub fn all() -> Self {
+        PageSelection {
+            ranges: vec![PageRange {
+                start: None,
+                end: None,
+            }],
+        }
+    }
+
+    /// Parses a selection. Ranges may run backwards (`"5-3"` is pages 5, 4
+    /// and 3) and pages may be repeated.
+    pub fn parse(spec: &str) -> Result<Self, PageSelectionError> {
+        let syntax = || PageSelectionError::Syntax(spec.to_string());
+        let number = |s: &str| match s.trim() {
+            "" => Ok(None),
+            s => match s.parse::<usize>() {
+                Ok(n) if n >= 1 => Ok(Some(n)),
+                _ => Err(syntax()),
+            },
+        };
+        let ranges = spec
+            .split(',')
+            .map(|item| {
+                let range = match item.split_once('-') {
+                    Some((start, end)) => PageRange {
+                        start: number(start)?,
+                        end: number(end)?,
+                    },
+                    None => {
+                        let page = number(item)?.ok_or_else(syntax)?;
+                        PageRange {
+                            start: Some(page),
+                            end: Some(page),
+                        }
+                    }
+                };
+                Ok(range)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PageSelection { ranges })
+    }
+
+    /// The selected pages of a document with `count` pages, as 0-based
+    /// indices in selection order.
+    pub fn indices(&self, count: usize) -> Result<Vec<usize>, PageSelectionError> {
+        let mut indices = Vec::new();
+        for range in &self.ranges {
+            if count == 0 && range.start.is_none() && range.end.is_none() {
+                continue;
+            }
+            let start = range.start.unwrap_or(1);
+            let end = range.end.unwrap_or(count);
+            if let Some(page) = [start, end].into_iter().find(|&p| p > count) {
+                return Err(PageSelectionError::OutOfRange { page, count });
+            }
+            if start <= end {
+                indices.extend(start - 1..end);
+            } else {
+                indices.extend((end - 1..start).rev());
+            }
+        }
+        Ok(indices)
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = PageSelectionError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        PageSelection::parse(spec)
+    }
+}
@@ -1,4 +1,14 @@
-use crate::structure::{Document, Page, TextBlock};
+use crate::annotations::read_annotations;
+use crate::content::ContentInterpreter;
+use crate::encryption::{load_document, DecryptError};
+use crate::forms::read_form_fields;
+use crate::links::read_links;
+use crate::metadata::read_metadata;
+use crate::names::named_destinations;
+use crate::outline::read_outline;
+use crate::structure::{Document, Page, PageContent, Rect, TextBlock};
+use lopdf::{Dictionary, Object, ObjectId};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -8,37 +18,138 @@ pub enum ParseError {
     Extract(String),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("PDF is encrypted: incorrect or missing password")]
+    Password,
+}
+
+/// Page attributes that page tree nodes can pass down to their pages.
+pub(crate) const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Looks up a page attribute, walking up the page tree for the inheritable
+/// ones (`/Resources`, `/MediaBox`, `/CropBox`, `/Rotate`).
+pub(crate) fn inherited_attribute<'a>(
+    doc: &'a lopdf::Document,
+    page_id: ObjectId,
+    key: &[u8],
+) -> Option<&'a Object> {
+    let mut node_id = page_id;
+    // Guard against cycles in malformed page trees.
+    for _ in 0..64 {
+        let node = doc.get_dictionary(node_id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return doc.dereference(value).ok().map(|(_, o)| o);
+        }
+        node_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+    }
+    None
+}
+
+/// Reads a rectangle array such as `/MediaBox [0 0 612 792]`.
+pub(crate) fn rect_from_object(doc: &lopdf::Document, object: &Object) -> Option<Rect> {
+    let values: Vec<f32> = object
+        .as_array()
+        .ok()?
+        .iter()
+        .filter_map(|o| doc.dereference(o).ok()?.1.as_float().ok())
+        .collect();
+    match values.as_slice() {
+        [x0, y0, x1, y1] => Some(Rect::new(*x0, *y0, *x1, *y1).normalized()),
+        _ => None,
+    }
+}
+
+/// Reads the page boundaries and rotation into an otherwise empty `Page`.
+fn page_geometry(doc: &lopdf::Document, page_id: ObjectId) -> Page {
+    let page_box = |key: &[u8], inheritable: bool| {
+        let object = if inheritable {
+            inherited_attribute(doc, page_id, key)
+        } else {
+            doc.get_dictionary(page_id)
+                .ok()
+                .and_then(|page| page.get(key).ok())
+                .and_then(|o| doc.dereference(o).ok())
+                .map(|(_, o)| o)
+        };
+        object.and_then(|o| rect_from_object(doc, o))
+    };
+
+    // MediaBox is required; fall back to US Letter like most viewers do.
+    let media_box = page_box(b"MediaBox", true).unwrap_or(Rect::new(0.0, 0.0, 612.0, 792.0));
+    let rotation = inherited_attribute(doc, page_id, b"Rotate")
+        .and_then(|o| o.as_i64().ok())
+        .map(|r| ((r / 90) * 90).rem_euclid(360) as i32)
+        .unwrap_or(0);
+
+    Page {
+        width: media_box.width(),
+        height: media_box.height(),
+        rotation,
+        media_box: Some(media_box),
+        crop_box: page_box(b"CropBox", true),
+        bleed_box: page_box(b"BleedBox", false),
+        trim_box: page_box(b"TrimBox", false),
+        art_box: page_box(b"ArtBox", false),
+        ..Default::default()
+    }
 }
 
 pub fn parse_pdf(path: &Path) -> Result<Document, ParseError> {
-    let bytes = fs::read(path)?;
-    let text = pdf_extract::extract_text_from_mem(&bytes)
-        .map_err(|e| ParseError::Extract(e.to_string()))?;
+    parse_pdf_with_password(path, None)
+}
 
-    // pdf-extract returns the text for the whole document in one string,
-    // with pages separated by form feed characters (\f).
-    // We will split the text by this character to create pages.
-    let pages_text: Vec<&str> = text.split('\u{000C}').collect();
+/// Parses a PDF that may be encrypted, opening it with `password` (user or
+/// owner).
+pub fn parse_pdf_with_password(
+    path: &Path,
+    password: Option<&str>,
+) -> Result<Document, ParseError> {
+    parse_bytes(&fs::read(path)?, password)
+}
 
+/// Parses a PDF held in memory, opening it with `password` if it is
+/// encrypted.
+pub fn parse_bytes(bytes: &[u8], password: Option<&str>) -> Result<Document, ParseError> {
+    let pdf = load_document(bytes, password).map_err(|e| match e {
+        DecryptError::Password => ParseError::Password,
+        other => ParseError::Extract(other.to_string()),
+    })?;
+
+    let named = named_destinations(&pdf);
+    let page_indices: HashMap<ObjectId, usize> = pdf
+        .get_pages()
+        .into_values()
+        .enumerate()
+        .map(|(index, id)| (id, index))
+        .collect();
     let mut pages = Vec::new();
-    for page_text in pages_text.iter() {
-        // Only create a page if there is some text, to avoid empty pages at the end.
-        if !page_text.trim().is_empty() {
-            let text_block = TextBlock {
-                text: page_text.to_string(),
-                x: 0.0, // Positional info is not available from this library
-                y: 0.0,
-                font_size: 0.0,
-            };
-            let page = Page {
-                width: 595.0, // Using standard A4 size as a placeholder
-                height: 842.0,
-                text_blocks: vec![text_block],
-                images: vec![],
-            };
-            pages.push(page);
-        }
+    for page_id in pdf.get_pages().into_values() {
+        let resources: Option<&Dictionary> =
+            inherited_attribute(&pdf, page_id, b"Resources").and_then(|o| o.as_dict().ok());
+        // A page whose content cannot be read is kept, without contents.
+        let content = pdf.get_page_content(page_id).unwrap_or_default();
+
+        let contents = ContentInterpreter::new(&pdf).run(&content, resources);
+        let text_blocks: Vec<&TextBlock> = contents
+            .iter()
+            .filter_map(|item| match item {
+                PageContent::Text(block) => Some(block),
+                _ => None,
+            })
+            .collect();
+        let annotations = read_annotations(&pdf, page_id, &text_blocks);
+
+        pages.push(Page {
+            contents,
+            annotations,
+            links: read_links(&pdf, page_id, &named, &page_indices),
+            ..page_geometry(&pdf, page_id)
+        });
     }
 
-    Ok(Document { pages })
+    Ok(Document {
+        pages,
+        metadata: read_metadata(&pdf),
+        outline: read_outline(&pdf),
+        form_fields: read_form_fields(&pdf, &page_indices),
+    })
 }
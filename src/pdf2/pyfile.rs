@@ -0,0 +1,77 @@
+//! File arguments of the Python functions: a path (`str` or
+//! `os.PathLike`) or a binary file object with `read`/`write` methods.
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes};
+use std::io;
+use std::path::PathBuf;
+
+pub enum PdfFile {
+    Path(PathBuf),
+    Object(Py<PyAny>),
+}
+
+impl From<String> for PdfFile {
+    fn from(path: String) -> Self {
+        PdfFile::Path(path.into())
+    }
+}
+
+impl From<PathBuf> for PdfFile {
+    fn from(path: PathBuf) -> Self {
+        PdfFile::Path(path)
+    }
+}
+
+impl<'py> FromPyObject<'py> for PdfFile {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        // `os.fspath` accepts bytes paths, but bytes here are far more
+        // likely to be PDF data.
+        if ob.is_instance_of::<PyBytes>() || ob.is_instance_of::<PyByteArray>() {
+            return Err(PyTypeError::new_err(
+                "expected a path or a binary file object; \
+                 use parse_bytes/generate_to_bytes/encrypt_bytes for PDF data in memory",
+            ));
+        }
+        if ob.hasattr("read")? || ob.hasattr("write")? {
+            return Ok(PdfFile::Object(ob.clone().unbind()));
+        }
+        ob.extract::<PathBuf>().map(PdfFile::Path).map_err(|_| {
+            PyTypeError::new_err(format!(
+                "expected a path or a binary file object, not {}",
+                ob.get_type()
+            ))
+        })
+    }
+}
+
+impl PdfFile {
+    /// Reads the whole file, or the rest of a file object.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        match self {
+            PdfFile::Path(path) => std::fs::read(path),
+            PdfFile::Object(object) => Python::with_gil(|py| {
+                let data = object.bind(py).call_method0("read")?;
+                let bytes = data.downcast::<PyBytes>().map_err(|_| {
+                    PyTypeError::new_err("file object must be opened in binary mode")
+                })?;
+                Ok(bytes.as_bytes().to_vec())
+            }),
+        }
+    }
+
+    /// Writes `data` to the file, replacing it, or to a file object at its
+    /// current position.
+    pub fn write(&self, data: &[u8]) -> io::Result<()> {
+        match self {
+            PdfFile::Path(path) => std::fs::write(path, data),
+            PdfFile::Object(object) => Python::with_gil(|py| {
+                object
+                    .bind(py)
+                    .call_method1("write", (PyBytes::new(py, data),))?;
+                Ok(())
+            }),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Splitting a PDF into several documents, and extracting pages from one.
+//!
+//! Each output is built the way `merge` builds a document from a single
+//! input, so it keeps the bookmarks and named destinations leading to its
+//! pages, and only the resources those pages use.
+
+use crate::content::resource_usage;
+use crate::encryption::load_document;
+use crate::merge::copy_pages;
+use crate::names::{destination_page, item_destination, named_destinations};
+use crate::outline::MAX_OUTLINE_ITEMS;
+use crate::page_selection::{PageSelection, PageSelectionError};
+use crate::pyfile::PdfFile;
+use lopdf::{Object, ObjectId};
+use pyo3::prelude::*;
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SplitError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to load PDF: {0}")]
+    Load(String),
+    #[error("Invalid pages: {0}")]
+    Pages(#[from] PageSelectionError),
+    #[error("Invalid split: {0}")]
+    Mode(String),
+}
+
+/// How to divide a document's pages into parts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitMode {
+    /// One part per selection.
+    Ranges(Vec<PageSelection>),
+    /// Parts of this many pages; the last one may be shorter.
+    Every(usize),
+    /// A part starting at each bookmark of this depth, 1 being the top-level
+    /// bookmarks. Pages before the first such bookmark form a part of their
+    /// own.
+    BookmarkLevel(usize),
+    /// Parts separated by blank pages, which are left out.
+    BlankSeparators,
+}
+
+/// The 0-based page indices of each part `mode` divides `doc` into. Parts
+/// are never empty.
+pub fn split_indices(
+    doc: &lopdf::Document,
+    mode: &SplitMode,
+) -> Result<Vec<Vec<usize>>, SplitError> {
+    let count = doc.get_pages().len();
+    let parts = match mode {
+        SplitMode::Ranges(selections) => selections
+            .iter()
+            .map(|selection| selection.indices(count))
+            .collect::<Result<Vec<_>, _>>()?,
+        SplitMode::Every(0) => {
+            return Err(SplitError::Mode("pages per part must be at least 1".into()));
+        }
+        SplitMode::Every(n) => (0..count)
+            .collect::<Vec<_>>()
+            .chunks(*n)
+            .map(<[usize]>::to_vec)
+            .collect(),
+        SplitMode::BookmarkLevel(0) => {
+            return Err(SplitError::Mode("bookmark levels start at 1".into()));
+        }
+        SplitMode::BookmarkLevel(level) => {
+            let mut starts = bookmark_pages(doc, *level);
+            starts.push(0);
+            starts.push(count);
+            starts.sort_unstable();
+            starts.dedup();
+            starts.windows(2).map(|w| (w[0]..w[1]).collect()).collect()
+        }
+        SplitMode::BlankSeparators => {
+            let mut parts = vec![Vec::new()];
+            for (index, &id) in doc.get_pages().values().enumerate() {
+                if is_blank_page(doc, id) {
+                    parts.push(Vec::new());
+                } else {
+                    parts.last_mut().unwrap().push(index);
+                }
+            }
+            parts
+        }
+    };
+    Ok(parts.into_iter().filter(|part| !part.is_empty()).collect())
+}
+
+/// The 0-based indices of the pages that bookmarks at depth `level` lead
+/// to, in outline order.
+fn bookmark_pages(doc: &lopdf::Document, level: usize) -> Vec<usize> {
+    let page_indices: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let named = named_destinations(doc);
+    let first = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"Outlines").ok())
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|outlines| outlines.get(b"First").ok())
+        .and_then(|o| o.as_reference().ok());
+
+    // Depth-first, keeping each sibling list's next item on the stack.
+    let mut pages = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![(first, 1)];
+    while let Some((next, depth)) = stack.pop() {
+        let Some(id) = next else {
+            continue;
+        };
+        if !visited.insert(id) || visited.len() > MAX_OUTLINE_ITEMS {
+            continue;
+        }
+        let Ok(item) = doc.get_dictionary(id) else {
+            continue;
+        };
+        stack.push((item.get(b"Next").and_then(Object::as_reference).ok(), depth));
+        if depth == level {
+            let page = item_destination(doc, item).and_then(|d| destination_page(doc, d, &named));
+            if let Some(index) = page.and_then(|p| page_indices.iter().position(|&id| id == p)) {
+                pages.push(index);
+            }
+        } else {
+            stack.push((
+                item.get(b"First").and_then(Object::as_reference).ok(),
+                depth + 1,
+            ));
+        }
+    }
+    pages
+}
+
+/// Whether page `id` paints nothing: no text, paths, shadings or images.
+/// Pages whose content cannot be parsed are not considered blank.
+pub fn is_blank_page(doc: &lopdf::Document, id: ObjectId) -> bool {
+    doc.get_page_content(id)
+        .ok()
+        .and_then(|content| resource_usage(&content))
+        .is_some_and(|usage| !usage.paints)
+}
+
+/// Divides `doc` into the documents `mode` describes.
+pub fn split_document(
+    doc: &lopdf::Document,
+    mode: &SplitMode,
+) -> Result<Vec<lopdf::Document>, SplitError> {
+    Ok(split_indices(doc, mode)?
+        .into_iter()
+        .map(|indices| copy_pages(&[(doc, indices)]))
+        .collect())
+}
+
+/// A new document holding the `selection` of `doc`'s pages.
+pub fn extract_pages(
+    doc: &lopdf::Document,
+    selection: &PageSelection,
+) -> Result<lopdf::Document, SplitError> {
+    let indices = selection.indices(doc.get_pages().len())?;
+    Ok(copy_pages(&[(doc, indices)]))
+}
+
+fn load_input(bytes: &[u8]) -> Result<lopdf::Document, SplitError> {
+    load_document(bytes, None).map_err(|e| SplitError::Load(e.to_string()))
+}
+
+/// The path of part `number` (1-based): `template` with `{}` replaced by the
+/// number, zero-padded to the width of the part count so the files sort in
+/// order.
+fn part_path(template: &str, number: usize, count: usize) -> String {
+    let width = count.to_string().len();
+    template.replace("{}", &format!("{:0width$}", number))
+}
+
+/// Splits the PDF file `input`, writing the parts to `output_template`
+/// (see `part_path`). Returns the paths written.
+pub fn split_pdf(
+    input: &Path,
+    mode: &SplitMode,
+    output_template: &str,
+) -> Result<Vec<String>, SplitError> {
+    let doc = load_input(&std::fs::read(input)?)?;
+    write_parts(&doc, mode, output_template)
+}
+
+fn write_parts(
+    doc: &lopdf::Document,
+    mode: &SplitMode,
+    output_template: &str,
+) -> Result<Vec<String>, SplitError> {
+    if !output_template.contains("{}") {
+        return Err(SplitError::Mode(
+            "the output template must contain '{}' for the part number".into(),
+        ));
+    }
+    let mut parts = split_document(doc, mode)?;
+    let count = parts.len();
+    let mut paths = Vec::with_capacity(count);
+    for (index, part) in parts.iter_mut().enumerate() {
+        let path = part_path(output_template, index + 1, count);
+        part.save(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Splits `input_path` into parts written to `output_template`, a path
+/// containing `{}` for the part number. Exactly one way of splitting must
+/// be given.
+#[pyfunction]
+#[pyo3(signature = (
+    input_path,
+    output_template,
+    *,
+    ranges=None,
+    every=None,
+    bookmark_level=None,
+    blank_separators=false,
+))]
+pub fn split(
+    input_path: PdfFile,
+    output_template: &str,
+    ranges: Option<Vec<String>>,
+    every: Option<usize>,
+    bookmark_level: Option<usize>,
+    blank_separators: bool,
+) -> PyResult<Vec<String>> {
+    let value_error = |e: &dyn std::fmt::Display| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to split PDF: {}", e))
+    };
+    let mut modes = Vec::new();
+    if let Some(ranges) = ranges {
+        let selections = ranges
+            .iter()
+            .map(|spec| PageSelection::parse(spec))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| value_error(&SplitError::from(e)))?;
+        modes.push(SplitMode::Ranges(selections));
+    }
+    modes.extend(every.map(SplitMode::Every));
+    modes.extend(bookmark_level.map(SplitMode::BookmarkLevel));
+    if blank_separators {
+        modes.push(SplitMode::BlankSeparators);
+    }
+    let [mode] = modes.as_slice() else {
+        return Err(value_error(&SplitError::Mode(
+            "give exactly one of ranges, every, bookmark_level or blank_separators".into(),
+        )));
+    };
+    let bytes = input_path.read().map_err(|e| value_error(&e))?;
+    let doc = load_input(&bytes).map_err(|e| value_error(&e))?;
+    write_parts(&doc, mode, output_template).map_err(|e| value_error(&e))
+}
+
+/// Writes the `pages` of `input_path`, such as `"1-3,7,10-"`, to
+/// `output_path`.
+#[pyfunction(name = "extract_pages")]
+pub fn py_extract_pages(input_path: PdfFile, output_path: PdfFile, pages: &str) -> PyResult<()> {
+    let value_error = |e: &dyn std::fmt::Display| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to extract pages: {}", e))
+    };
+    let selection = PageSelection::parse(pages).map_err(|e| value_error(&SplitError::from(e)))?;
+    let bytes = input_path.read().map_err(|e| value_error(&e))?;
+    let doc = load_input(&bytes).map_err(|e| value_error(&e))?;
+    let mut extracted = extract_pages(&doc, &selection).map_err(|e| value_error(&e))?;
+    let mut bytes = Vec::new();
+    extracted.save_to(&mut bytes).map_err(|e| value_error(&e))?;
+    output_path.write(&bytes).map_err(|e| value_error(&e))
+}
@@ -1,10 +1,17 @@
+use crate::{layout, tables};
 use serde::{Deserialize, Serialize};
 
 // Using pyo3's prelude to get access to the #[pyclass] macro if needed later.
 // And to derive PyObjectProtocol for our structs.
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::BTreeMap;
 
 /// Represents a single text block with its content and position.
+///
+/// Spacing, scaling and rise follow the PDF text state operators (`Tc`,
+/// `Tw`, `Tz` and `Ts`), with lengths in points. Colours are 1 (gray),
+/// 3 (RGB) or 4 (CMYK) components from 0 to 1.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[pyclass]
 pub struct TextBlock {
@@ -16,12 +23,86 @@ pub struct TextBlock {
     pub y: f32,
     #[pyo3(get, set)]
     pub font_size: f32,
-    // We can add more properties like font name, color, etc. later.
+    /// Name of a font in the `FontRegistry` passed to the generator; text
+    /// without one, or naming an unregistered font, is drawn in Helvetica.
+    /// The parser sets it to the family name of the font used.
+    #[pyo3(get, set)]
+    pub font_name: Option<String>,
+    /// Advance width of the text as drawn, in points. Set by the parser and
+    /// ignored by the generator.
+    #[pyo3(get, set)]
+    pub width: f32,
+    /// The `BaseFont` of the font the text was drawn in, including any
+    /// subset prefix. Set by the parser and ignored by the generator.
+    #[pyo3(get, set)]
+    pub base_font: Option<String>,
+    /// Whether the font program was embedded in the PDF. Set by the parser
+    /// and ignored by the generator.
+    #[pyo3(get, set)]
+    pub embedded: bool,
+    /// Bold and italic text uses the registered `"<font_name>-Bold"`,
+    /// `"-Italic"` or `"-BoldItalic"` font when there is one, the matching
+    /// Helvetica face for the built-in font, and is emboldened or slanted
+    /// otherwise.
+    #[pyo3(get, set)]
+    pub bold: bool,
+    #[pyo3(get, set)]
+    pub italic: bool,
+    /// Colour used to fill the glyphs; empty means black.
+    #[pyo3(get, set)]
+    pub fill_color: Vec<f32>,
+    /// Colour used to stroke the glyphs in the stroking render modes; empty
+    /// means black.
+    #[pyo3(get, set)]
+    pub stroke_color: Vec<f32>,
+    #[pyo3(get, set)]
+    pub char_spacing: f32,
+    #[pyo3(get, set)]
+    pub word_spacing: f32,
+    /// Horizontal scaling as a percentage of the normal glyph width.
+    #[pyo3(get, set)]
+    pub horizontal_scaling: f32,
+    /// Baseline offset, already included in `y`.
+    #[pyo3(get, set)]
+    pub rise: f32,
+    /// PDF text rendering mode: 0 fill, 1 stroke, 2 fill and stroke,
+    /// 3 invisible, and 4 to 7 the same adding the text to the clip.
+    #[pyo3(get, set)]
+    pub render_mode: u8,
+    // Advance of each character of `text` along the baseline, in points,
+    // recorded by the parser for layout analysis.
+    pub char_advances: Vec<f32>,
+}
+
+impl Default for TextBlock {
+    fn default() -> Self {
+        TextBlock {
+            text: String::new(),
+            x: 0.0,
+            y: 0.0,
+            font_size: 0.0,
+            font_name: None,
+            width: 0.0,
+            base_font: None,
+            embedded: false,
+            bold: false,
+            italic: false,
+            fill_color: Vec::new(),
+            stroke_color: Vec::new(),
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 100.0,
+            rise: 0.0,
+            render_mode: 0,
+            char_advances: Vec::new(),
+        }
+    }
 }
 
 /// Represents an image with its data and position.
-/// For now, we'll just store the raw image data and its format.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// `data` is an image file in `format`, or raw 8-bit pixels for the `"rgb"`
+/// and `"gray"` formats.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[pyclass]
 pub struct Image {
     #[pyo3(get, set)]
@@ -32,54 +113,766 @@ pub struct Image {
     pub width: f32,
     #[pyo3(get, set)]
     pub height: f32,
-    // The raw image data.
+    // The encoded image file, exposed to Python as `bytes` by the
+    // `data` getter below.
     pub data: Vec<u8>,
     // E.g., "jpeg", "png", etc.
     #[pyo3(get, set)]
     pub format: String,
+    // Pixel dimensions of raw `"rgb"`/`"gray"` data; file formats carry
+    // their own and leave these at 0.
+    #[pyo3(get, set)]
+    pub pixel_width: u32,
+    #[pyo3(get, set)]
+    pub pixel_height: u32,
+}
+
+/// One step of a path. `kind` is `"move"` or `"line"` with one point,
+/// `"curve"` with a cubic Bézier curve's two control points and its end
+/// point, or `"close"`, which joins the subpath back to its start.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct PathSegment {
+    #[pyo3(get, set)]
+    pub kind: String,
+    #[pyo3(get, set)]
+    pub points: Vec<(f32, f32)>,
+}
+
+/// Names of the line cap styles, indexed by their PDF values.
+pub const LINE_CAPS: [&str; 3] = ["butt", "round", "square"];
+
+/// Names of the line join styles, indexed by their PDF values.
+pub const LINE_JOINS: [&str; 3] = ["miter", "round", "bevel"];
+
+/// A vector path, such as a table rule, an underline or a filled box, in
+/// page coordinates. A shape is filled when it has a `fill_color` and
+/// stroked when it has a `stroke_color`; colours are 1 (gray), 3 (RGB) or
+/// 4 (CMYK) components from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Shape {
+    #[pyo3(get, set)]
+    pub segments: Vec<PathSegment>,
+    #[pyo3(get, set)]
+    pub fill_color: Option<Vec<f32>>,
+    #[pyo3(get, set)]
+    pub stroke_color: Option<Vec<f32>>,
+    #[pyo3(get, set)]
+    pub line_width: f32,
+    /// Lengths of alternating dashes and gaps; empty for a solid line.
+    #[pyo3(get, set)]
+    pub dash: Vec<f32>,
+    #[pyo3(get, set)]
+    pub dash_phase: f32,
+    /// `"butt"`, `"round"` or `"square"`.
+    #[pyo3(get, set)]
+    pub line_cap: String,
+    /// `"miter"`, `"round"` or `"bevel"`.
+    #[pyo3(get, set)]
+    pub line_join: String,
+    /// Whether the fill uses the even-odd rule rather than nonzero winding.
+    #[pyo3(get, set)]
+    pub even_odd: bool,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Shape {
+            segments: Vec::new(),
+            fill_color: None,
+            stroke_color: None,
+            line_width: 1.0,
+            dash: Vec::new(),
+            dash_phase: 0.0,
+            line_cap: "butt".to_string(),
+            line_join: "miter".to_string(),
+            even_odd: false,
+        }
+    }
+}
+
+/// A rectangle in PDF user space, given by its lower-left `(x0, y0)` and
+/// upper-right `(x1, y1)` corners.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[pyclass]
+pub struct Rect {
+    #[pyo3(get, set)]
+    pub x0: f32,
+    #[pyo3(get, set)]
+    pub y0: f32,
+    #[pyo3(get, set)]
+    pub x1: f32,
+    #[pyo3(get, set)]
+    pub y1: f32,
 }
 
-/// An enum to represent any element that can be on a page.
+/// An element drawn on a page. In Python each variant is a subclass, such
+/// as `PageContent.Text(block)`, holding its element as `value`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[pyclass]
 pub enum PageContent {
     Text(TextBlock),
     Image(Image),
-    // We can add Shape, Table, etc. later.
+    Shape(Shape),
+}
+
+impl From<TextBlock> for PageContent {
+    fn from(block: TextBlock) -> Self {
+        PageContent::Text(block)
+    }
+}
+
+impl From<Image> for PageContent {
+    fn from(image: Image) -> Self {
+        PageContent::Image(image)
+    }
+}
+
+impl From<Shape> for PageContent {
+    fn from(shape: Shape) -> Self {
+        PageContent::Shape(shape)
+    }
+}
+
+#[pymethods]
+impl PageContent {
+    /// `"text"`, `"image"` or `"shape"`.
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self {
+            PageContent::Text(_) => "text",
+            PageContent::Image(_) => "image",
+            PageContent::Shape(_) => "shape",
+        }
+    }
+
+    /// The element: a `TextBlock`, `Image` or `Shape`.
+    #[getter]
+    fn value<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(match self {
+            PageContent::Text(block) => block.clone().into_pyobject(py)?.into_any(),
+            PageContent::Image(image) => image.clone().into_pyobject(py)?.into_any(),
+            PageContent::Shape(shape) => shape.clone().into_pyobject(py)?.into_any(),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        match self {
+            PageContent::Text(block) => format!("PageContent.Text({:?})", block.text),
+            PageContent::Image(image) => format!(
+                "PageContent.Image({}, {}x{})",
+                image.format, image.width, image.height
+            ),
+            PageContent::Shape(shape) => {
+                format!("PageContent.Shape(segments={})", shape.segments.len())
+            }
+        }
+    }
 }
 
 /// Represents a single page in the document.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `width` and `height` are the size of the media box; text and image
+/// coordinates are in the same (unrotated) user space.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[pyclass]
 pub struct Page {
     #[pyo3(get, set)]
     pub width: f32,
     #[pyo3(get, set)]
     pub height: f32,
-    // pub contents: Vec<PageContent>, // This will cause issues with pyo3 if PageContent is not a pyclass
-    // For now, let's keep it simple and add specific vectors for each type.
+    /// Clockwise rotation applied when displaying the page: 0, 90, 180 or 270.
+    #[pyo3(get, set)]
+    pub rotation: i32,
+    // The page boundaries. `None` means the entry is absent from the file; the
+    // crop box then defaults to the media box, and the other boxes to the
+    // crop box.
+    #[pyo3(get, set)]
+    pub media_box: Option<Rect>,
+    #[pyo3(get, set)]
+    pub crop_box: Option<Rect>,
+    #[pyo3(get, set)]
+    pub bleed_box: Option<Rect>,
+    #[pyo3(get, set)]
+    pub trim_box: Option<Rect>,
+    #[pyo3(get, set)]
+    pub art_box: Option<Rect>,
+    /// Text, images and shapes in painting order, so that later elements
+    /// are drawn over earlier ones. `text_blocks`, `images` and `shapes`
+    /// are views of it.
+    #[pyo3(get, set)]
+    pub contents: Vec<PageContent>,
+    #[pyo3(get, set)]
+    pub links: Vec<Link>,
+    // Fillable form fields, written by the generator. Parsed forms are read
+    // into `Document::form_fields` instead.
+    #[pyo3(get, set)]
+    pub text_fields: Vec<TextField>,
+    #[pyo3(get, set)]
+    pub checkboxes: Vec<CheckBox>,
+    #[pyo3(get, set)]
+    pub radio_groups: Vec<RadioGroup>,
+    #[pyo3(get, set)]
+    pub dropdowns: Vec<Dropdown>,
+    #[pyo3(get, set)]
+    pub signature_fields: Vec<SignatureField>,
+    /// Review annotations: notes, text markup, drawings and stamps.
+    #[pyo3(get, set)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// A word found by layout analysis, with the bounding box of its glyphs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Word {
+    #[pyo3(get, set)]
+    pub text: String,
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+    #[pyo3(get, set)]
+    pub font_size: f32,
+}
+
+/// Words sharing a baseline, left to right.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct TextLine {
+    #[pyo3(get, set)]
+    pub words: Vec<Word>,
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+}
+
+/// Consecutive lines of one block of text, top to bottom.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Paragraph {
+    #[pyo3(get, set)]
+    pub lines: Vec<TextLine>,
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+}
+
+/// Paragraphs read one after the other. Parts of a page spanning several
+/// columns, such as a title above them, form columns of their own.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Column {
+    #[pyo3(get, set)]
+    pub paragraphs: Vec<Paragraph>,
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+}
+
+/// A cell of a `Table`, covering `row_span` rows and `column_span`
+/// columns from `row` and `column`, counted from the top left.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct TableCell {
+    #[pyo3(get, set)]
+    pub row: usize,
+    #[pyo3(get, set)]
+    pub column: usize,
+    #[pyo3(get, set)]
+    pub row_span: usize,
+    #[pyo3(get, set)]
+    pub column_span: usize,
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+    /// The cell's lines of text, joined by spaces.
+    #[pyo3(get, set)]
+    pub text: String,
+}
+
+/// A table found on a page, from its ruling lines or from the alignment of
+/// its text.
+///
+/// `row_edges` are the y coordinates of the row boundaries from top to
+/// bottom, and `column_edges` the x coordinates of the column boundaries
+/// from left to right. Every position of the grid is covered by exactly
+/// one of `cells`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Table {
+    #[pyo3(get, set)]
+    pub bbox: Rect,
+    #[pyo3(get, set)]
+    pub row_edges: Vec<f32>,
+    #[pyo3(get, set)]
+    pub column_edges: Vec<f32>,
+    #[pyo3(get, set)]
+    pub cells: Vec<TableCell>,
+}
+
+/// A clickable area of a page, leading to a URI, a place in the document,
+/// or a named destination.
+///
+/// When parsing, named destinations are also resolved into `destination`.
+/// When generating, `uri` takes precedence over `named_destination`, which
+/// takes precedence over `destination`; a link with both of the latter
+/// defines the name as leading to `destination`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Link {
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    #[pyo3(get, set)]
+    pub uri: Option<String>,
+    #[pyo3(get, set)]
+    pub destination: Option<Destination>,
+    #[pyo3(get, set)]
+    pub named_destination: Option<String>,
+}
+
+/// A review annotation (ISO 32000-1, 12.5.6).
+///
+/// `kind` is one of `"text"` (a sticky note), `"highlight"`, `"underline"`,
+/// `"strikeout"`, `"squiggly"`, `"freetext"`, `"ink"`, `"square"`,
+/// `"circle"` or `"stamp"`. Colours are 1 (gray), 3 (RGB) or 4 (CMYK)
+/// components from 0 to 1; dates are ISO 8601 strings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Annotation {
+    #[pyo3(get, set)]
+    pub kind: String,
+    /// Where the annotation is drawn. When generating, an empty rectangle is
+    /// replaced by the bounds of `quads` or `ink`.
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    /// The note's text, or the text a free text annotation shows.
+    #[pyo3(get, set)]
+    pub contents: Option<String>,
+    #[pyo3(get, set)]
+    pub author: Option<String>,
+    #[pyo3(get, set)]
+    pub created: Option<String>,
+    #[pyo3(get, set)]
+    pub modified: Option<String>,
+    #[pyo3(get, set)]
+    pub color: Vec<f32>,
+    /// The fill colour of squares and circles.
+    #[pyo3(get, set)]
+    pub interior_color: Vec<f32>,
+    #[pyo3(get, set)]
+    pub border_width: Option<f32>,
+    /// The areas text markup covers, one per line of text.
+    #[pyo3(get, set)]
+    pub quads: Vec<Rect>,
+    /// The page text under `quads`, found by the parser. Positions within a
+    /// text run are estimated, so the ends may be off by a character.
+    #[pyo3(get, set)]
+    pub text: Option<String>,
+    /// The strokes of an ink annotation, as lists of points.
+    #[pyo3(get, set)]
+    pub ink: Vec<Vec<(f32, f32)>>,
+    /// The icon of a note (such as `"Comment"`) or stamp (such as
+    /// `"Approved"`).
+    #[pyo3(get, set)]
+    pub icon: Option<String>,
+}
+
+/// A fillable text input. Field names may be dotted (`person.name`) to
+/// group fields under a common parent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct TextField {
+    #[pyo3(get, set)]
+    pub name: String,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    /// The initial text, which resetting the form restores.
+    #[pyo3(get, set)]
+    pub value: Option<String>,
+    #[pyo3(get, set)]
+    pub multiline: bool,
+    /// The maximum number of characters.
+    #[pyo3(get, set)]
+    pub max_length: Option<u32>,
+    /// Text size in points; 0 fits the text to the field.
+    #[pyo3(get, set)]
+    pub font_size: f32,
+    #[pyo3(get, set)]
+    pub required: bool,
+    #[pyo3(get, set)]
+    pub read_only: bool,
+}
+
+/// A checkbox, whose value is `export_value` when checked and `Off`
+/// otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct CheckBox {
+    #[pyo3(get, set)]
+    pub name: String,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    #[pyo3(get, set)]
+    pub checked: bool,
+    #[pyo3(get, set)]
+    pub export_value: String,
+    #[pyo3(get, set)]
+    pub required: bool,
+    #[pyo3(get, set)]
+    pub read_only: bool,
+}
+
+impl Default for CheckBox {
+    fn default() -> Self {
+        CheckBox {
+            name: String::new(),
+            rect: Rect::default(),
+            checked: false,
+            export_value: "Yes".to_string(),
+            required: false,
+            read_only: false,
+        }
+    }
+}
+
+/// One button of a radio group: the value the group takes when it is
+/// selected, and where it is drawn.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct RadioButton {
+    #[pyo3(get, set)]
+    pub value: String,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+}
+
+/// A group of radio buttons of which at most one is selected.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct RadioGroup {
     #[pyo3(get, set)]
-    pub text_blocks: Vec<TextBlock>,
+    pub name: String,
     #[pyo3(get, set)]
-    pub images: Vec<Image>,
+    pub buttons: Vec<RadioButton>,
+    /// The value of the initially selected button.
+    #[pyo3(get, set)]
+    pub selected: Option<String>,
+    #[pyo3(get, set)]
+    pub required: bool,
+    #[pyo3(get, set)]
+    pub read_only: bool,
+}
+
+/// A drop-down list (combo box).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Dropdown {
+    #[pyo3(get, set)]
+    pub name: String,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    #[pyo3(get, set)]
+    pub options: Vec<String>,
+    /// The initially selected option.
+    #[pyo3(get, set)]
+    pub value: Option<String>,
+    /// Whether text other than the options can be typed in.
+    #[pyo3(get, set)]
+    pub editable: bool,
+    /// Text size in points; 0 fits the text to the field.
+    #[pyo3(get, set)]
+    pub font_size: f32,
+    #[pyo3(get, set)]
+    pub required: bool,
+    #[pyo3(get, set)]
+    pub read_only: bool,
+}
+
+/// An unsigned signature field, which viewers offer to sign.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct SignatureField {
+    #[pyo3(get, set)]
+    pub name: String,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    #[pyo3(get, set)]
+    pub required: bool,
+}
+
+/// Document-level metadata, stored both in the trailer's Info dictionary
+/// and in the catalog's XMP packet.
+///
+/// Dates are ISO 8601 strings such as `2024-03-01T12:30:00+01:00`, the form
+/// XMP uses; `None` means the entry is absent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Metadata {
+    #[pyo3(get, set)]
+    pub title: Option<String>,
+    #[pyo3(get, set)]
+    pub author: Option<String>,
+    #[pyo3(get, set)]
+    pub subject: Option<String>,
+    #[pyo3(get, set)]
+    pub keywords: Option<String>,
+    /// The application that created the original document.
+    #[pyo3(get, set)]
+    pub creator: Option<String>,
+    /// The application that wrote the PDF.
+    #[pyo3(get, set)]
+    pub producer: Option<String>,
+    #[pyo3(get, set)]
+    pub creation_date: Option<String>,
+    #[pyo3(get, set)]
+    pub mod_date: Option<String>,
+    /// Info dictionary entries other than the standard ones above.
+    #[pyo3(get, set)]
+    pub custom: BTreeMap<String, String>,
+}
+
+/// A place in the document that a bookmark or link leads to: a page, and
+/// how to show it.
+///
+/// `kind` is the PDF fit type, which decides the coordinates used:
+/// `"XYZ"` (`left`, `top` and `zoom`; a missing value keeps the viewer's
+/// current one), `"Fit"`, `"FitH"` (`top`), `"FitV"` (`left`), `"FitR"`
+/// (`left`, `bottom`, `right` and `top`), and `"FitB"`, `"FitBH"` and
+/// `"FitBV"`, which fit the page's content instead of the whole page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct Destination {
+    /// Index of the target page in `Document::pages`.
+    #[pyo3(get, set)]
+    pub page: usize,
+    #[pyo3(get, set)]
+    pub kind: String,
+    #[pyo3(get, set)]
+    pub left: Option<f32>,
+    #[pyo3(get, set)]
+    pub top: Option<f32>,
+    #[pyo3(get, set)]
+    pub right: Option<f32>,
+    #[pyo3(get, set)]
+    pub bottom: Option<f32>,
+    #[pyo3(get, set)]
+    pub zoom: Option<f32>,
+}
+
+impl Default for Destination {
+    fn default() -> Self {
+        Destination {
+            page: 0,
+            kind: "Fit".to_string(),
+            left: None,
+            top: None,
+            right: None,
+            bottom: None,
+            zoom: None,
+        }
+    }
+}
+
+/// An entry of the document outline (bookmarks), with its nested entries.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct OutlineItem {
+    #[pyo3(get, set)]
+    pub title: String,
+    /// `None` for entries that lead nowhere in the document, such as
+    /// headings or web links.
+    #[pyo3(get, set)]
+    pub destination: Option<Destination>,
+    /// Whether the children are shown when the outline is first displayed.
+    #[pyo3(get, set)]
+    pub open: bool,
+    #[pyo3(get, set)]
+    pub children: Vec<OutlineItem>,
+}
+
+/// A widget annotation: where a form field appears on a page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct FieldWidget {
+    /// Index of the page in `Document::pages`, if the widget is on one.
+    #[pyo3(get, set)]
+    pub page: Option<usize>,
+    #[pyo3(get, set)]
+    pub rect: Rect,
+    /// For checkboxes and radio buttons, the value the field takes when
+    /// this widget is selected.
+    #[pyo3(get, set)]
+    pub on_state: Option<String>,
+}
+
+/// Field flag bits (`/Ff`, ISO 32000-1, 12.7.3.1 and 12.7.4).
+pub const FIELD_READ_ONLY: u32 = 1 << 0;
+pub const FIELD_REQUIRED: u32 = 1 << 1;
+pub const FIELD_NO_EXPORT: u32 = 1 << 2;
+pub const FIELD_MULTILINE: u32 = 1 << 12;
+pub const FIELD_PASSWORD: u32 = 1 << 13;
+pub const FIELD_NO_TOGGLE_TO_OFF: u32 = 1 << 14;
+pub const FIELD_RADIO: u32 = 1 << 15;
+pub const FIELD_PUSHBUTTON: u32 = 1 << 16;
+pub const FIELD_COMBO: u32 = 1 << 17;
+pub const FIELD_EDIT: u32 = 1 << 18;
+pub const FIELD_MULTI_SELECT: u32 = 1 << 21;
+
+/// An interactive form (AcroForm) field.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[pyclass]
+pub struct FormField {
+    /// The fully qualified name: the partial names of the field and its
+    /// ancestors, joined with `.`.
+    #[pyo3(get, set)]
+    pub name: String,
+    /// `"text"`, `"checkbox"`, `"radio"`, `"choice"`, `"signature"` or
+    /// `"button"` (a push button).
+    #[pyo3(get, set)]
+    pub field_type: String,
+    /// The current value: the text of a text field, the selected option of
+    /// a choice field, or the state of a checkbox or radio button (`"Off"`
+    /// when not selected). `None` when the field has no value.
+    #[pyo3(get, set)]
+    pub value: Option<String>,
+    /// All selected options of a list box that allows several; otherwise
+    /// the value alone.
+    #[pyo3(get, set)]
+    pub values: Vec<String>,
+    #[pyo3(get, set)]
+    pub default_value: Option<String>,
+    /// The values the field can take: the options of a choice field, or the
+    /// on states of a checkbox's or radio group's widgets.
+    #[pyo3(get, set)]
+    pub options: Vec<String>,
+    /// The text shown for each option, which may differ from its value.
+    #[pyo3(get, set)]
+    pub option_labels: Vec<String>,
+    /// The field flags (`/Ff`); see the `FIELD_*` constants.
+    #[pyo3(get, set)]
+    pub flags: u32,
+    #[pyo3(get, set)]
+    pub widgets: Vec<FieldWidget>,
 }
 
 /// Represents the entire PDF document.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[pyclass]
 pub struct Document {
     #[pyo3(get, set)]
     pub pages: Vec<Page>,
+    #[pyo3(get, set)]
+    pub metadata: Metadata,
+    /// The top-level outline entries.
+    #[pyo3(get, set)]
+    pub outline: Vec<OutlineItem>,
+    /// The interactive form's fields, in the order the form lists them.
+    #[pyo3(get, set)]
+    pub form_fields: Vec<FormField>,
 }
 
 #[pymethods]
 impl TextBlock {
     #[new]
-    pub fn new(text: String, x: f32, y: f32, font_size: f32) -> Self {
+    #[pyo3(signature = (
+        text, x, y, font_size, font_name=None, *, bold=false, italic=false,
+        fill_color=None, stroke_color=None, char_spacing=0.0, word_spacing=0.0,
+        horizontal_scaling=100.0, rise=0.0, render_mode=0
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        text: String,
+        x: f32,
+        y: f32,
+        font_size: f32,
+        font_name: Option<String>,
+        bold: bool,
+        italic: bool,
+        fill_color: Option<Vec<f32>>,
+        stroke_color: Option<Vec<f32>>,
+        char_spacing: f32,
+        word_spacing: f32,
+        horizontal_scaling: f32,
+        rise: f32,
+        render_mode: u8,
+    ) -> Self {
+        TextBlock {
+            bold,
+            italic,
+            fill_color: fill_color.unwrap_or_default(),
+            stroke_color: stroke_color.unwrap_or_default(),
+            char_spacing,
+            word_spacing,
+            horizontal_scaling,
+            rise,
+            render_mode,
+            ..TextBlock::new(text, x, y, font_size, font_name)
+        }
+    }
+}
+
+impl TextBlock {
+    pub fn new(text: String, x: f32, y: f32, font_size: f32, font_name: Option<String>) -> Self {
         TextBlock {
             text,
             x,
             y,
             font_size,
+            font_name,
+            ..Default::default()
+        }
+    }
+
+    /// Each character of `text` with its start x and advance. Characters
+    /// are spread evenly over `width` unless the parser recorded their
+    /// advances.
+    pub fn char_positions(&self) -> Vec<(char, f32, f32)> {
+        let count = self.text.chars().count();
+        let even = self.width / count.max(1) as f32;
+        let recorded = self.char_advances.len() == count;
+        let mut x = self.x;
+        self.text
+            .chars()
+            .enumerate()
+            .map(|(index, ch)| {
+                let advance = if recorded {
+                    self.char_advances[index]
+                } else {
+                    even
+                };
+                x += advance;
+                (ch, x - advance, advance)
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Rect {
+    #[new]
+    pub fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        Rect { x0, y0, x1, y1 }
+    }
+
+    #[getter]
+    pub fn width(&self) -> f32 {
+        (self.x1 - self.x0).abs()
+    }
+
+    #[getter]
+    pub fn height(&self) -> f32 {
+        (self.y1 - self.y0).abs()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Rect({}, {}, {}, {})", self.x0, self.y0, self.x1, self.y1)
+    }
+}
+
+impl Rect {
+    /// Returns the rectangle with its corners ordered so that
+    /// `x0 <= x1` and `y0 <= y1`.
+    pub fn normalized(&self) -> Rect {
+        Rect {
+            x0: self.x0.min(self.x1),
+            y0: self.y0.min(self.y1),
+            x1: self.x0.max(self.x1),
+            y1: self.y0.max(self.y1),
         }
     }
 }
@@ -87,7 +880,18 @@ impl TextBlock {
 #[pymethods]
 impl Image {
     #[new]
-    pub fn new(x: f32, y: f32, width: f32, height: f32, data: Vec<u8>, format: String) -> Self {
+    #[pyo3(signature = (x, y, width, height, data, format, pixel_width=0, pixel_height=0))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        data: Vec<u8>,
+        format: String,
+        pixel_width: u32,
+        pixel_height: u32,
+    ) -> Self {
         Image {
             x,
             y,
@@ -95,27 +899,966 @@ impl Image {
             height,
             data,
             format,
+            pixel_width,
+            pixel_height,
+        }
+    }
+
+    #[getter(data)]
+    fn py_data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.data)
+    }
+
+    #[setter(data)]
+    fn set_py_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+    }
+}
+
+#[pymethods]
+impl PathSegment {
+    #[new]
+    #[pyo3(signature = (kind, points=None))]
+    pub fn new(kind: String, points: Option<Vec<(f32, f32)>>) -> Self {
+        PathSegment {
+            kind,
+            points: points.unwrap_or_default(),
         }
     }
+
+    fn __repr__(&self) -> String {
+        format!("PathSegment({:?}, {:?})", self.kind, self.points)
+    }
+}
+
+impl PathSegment {
+    pub fn move_to(x: f32, y: f32) -> Self {
+        PathSegment::new("move".to_string(), Some(vec![(x, y)]))
+    }
+
+    pub fn line_to(x: f32, y: f32) -> Self {
+        PathSegment::new("line".to_string(), Some(vec![(x, y)]))
+    }
+
+    pub fn curve_to(control1: (f32, f32), control2: (f32, f32), end: (f32, f32)) -> Self {
+        PathSegment::new("curve".to_string(), Some(vec![control1, control2, end]))
+    }
+
+    pub fn close() -> Self {
+        PathSegment::new("close".to_string(), None)
+    }
+}
+
+#[pymethods]
+impl Shape {
+    #[new]
+    #[pyo3(signature = (
+        segments,
+        *,
+        fill_color=None,
+        stroke_color=None,
+        line_width=1.0,
+        dash=None,
+        dash_phase=0.0,
+        line_cap=None,
+        line_join=None,
+        even_odd=false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        segments: Vec<PathSegment>,
+        fill_color: Option<Vec<f32>>,
+        stroke_color: Option<Vec<f32>>,
+        line_width: f32,
+        dash: Option<Vec<f32>>,
+        dash_phase: f32,
+        line_cap: Option<String>,
+        line_join: Option<String>,
+        even_odd: bool,
+    ) -> Self {
+        let default = Shape::default();
+        Shape {
+            segments,
+            fill_color,
+            stroke_color,
+            line_width,
+            dash: dash.unwrap_or_default(),
+            dash_phase,
+            line_cap: line_cap.unwrap_or(default.line_cap),
+            line_join: line_join.unwrap_or(default.line_join),
+            even_odd,
+        }
+    }
+
+    /// A straight line from `(x0, y0)` to `(x1, y1)`, stroked in black
+    /// unless a colour is given.
+    #[staticmethod]
+    #[pyo3(signature = (x0, y0, x1, y1, line_width=1.0, color=None))]
+    pub fn line(
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        line_width: f32,
+        color: Option<Vec<f32>>,
+    ) -> Self {
+        Shape {
+            segments: vec![PathSegment::move_to(x0, y0), PathSegment::line_to(x1, y1)],
+            stroke_color: Some(color.unwrap_or(vec![0.0])),
+            line_width,
+            ..Default::default()
+        }
+    }
+
+    /// The outline of `rect`, stroked in black unless a colour is given, and
+    /// filled if `fill_color` is given.
+    #[staticmethod]
+    #[pyo3(signature = (rect, line_width=1.0, color=None, fill_color=None))]
+    pub fn rectangle(
+        rect: Rect,
+        line_width: f32,
+        color: Option<Vec<f32>>,
+        fill_color: Option<Vec<f32>>,
+    ) -> Self {
+        Shape {
+            segments: vec![
+                PathSegment::move_to(rect.x0, rect.y0),
+                PathSegment::line_to(rect.x1, rect.y0),
+                PathSegment::line_to(rect.x1, rect.y1),
+                PathSegment::line_to(rect.x0, rect.y1),
+                PathSegment::close(),
+            ],
+            stroke_color: Some(color.unwrap_or(vec![0.0])),
+            fill_color,
+            line_width,
+            ..Default::default()
+        }
+    }
+
+    /// The smallest rectangle holding every point of the path, control
+    /// points included, or an empty one for an empty path.
+    pub fn bounds(&self) -> Rect {
+        let mut points = self.segments.iter().flat_map(|s| s.points.iter());
+        let Some(&(x, y)) = points.next() else {
+            return Rect::default();
+        };
+        points.fold(Rect::new(x, y, x, y), |r, &(x, y)| {
+            Rect::new(r.x0.min(x), r.y0.min(y), r.x1.max(x), r.y1.max(y))
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Shape(segments={}, fill_color={:?}, stroke_color={:?}, line_width={})",
+            self.segments.len(),
+            self.fill_color,
+            self.stroke_color,
+            self.line_width
+        )
+    }
 }
 
 #[pymethods]
 impl Page {
     #[new]
-    pub fn new(width: f32, height: f32, text_blocks: Vec<TextBlock>, images: Vec<Image>) -> Self {
+    #[pyo3(signature = (
+        width,
+        height,
+        text_blocks=None,
+        images=None,
+        links=None,
+        *,
+        text_fields=None,
+        checkboxes=None,
+        radio_groups=None,
+        dropdowns=None,
+        signature_fields=None,
+        annotations=None,
+        shapes=None,
+        contents=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        width: f32,
+        height: f32,
+        text_blocks: Option<Vec<TextBlock>>,
+        images: Option<Vec<Image>>,
+        links: Option<Vec<Link>>,
+        text_fields: Option<Vec<TextField>>,
+        checkboxes: Option<Vec<CheckBox>>,
+        radio_groups: Option<Vec<RadioGroup>>,
+        dropdowns: Option<Vec<Dropdown>>,
+        signature_fields: Option<Vec<SignatureField>>,
+        annotations: Option<Vec<Annotation>>,
+        shapes: Option<Vec<Shape>>,
+        contents: Option<Vec<PageContent>>,
+    ) -> Self {
+        // Images go beneath shapes, and shapes beneath text, followed by
+        // anything given in painting order.
+        let ordered = images
+            .unwrap_or_default()
+            .into_iter()
+            .map(PageContent::Image)
+            .chain(
+                shapes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(PageContent::Shape),
+            )
+            .chain(
+                text_blocks
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(PageContent::Text),
+            )
+            .chain(contents.unwrap_or_default())
+            .collect();
+        Page {
+            contents: ordered,
+            text_fields: text_fields.unwrap_or_default(),
+            checkboxes: checkboxes.unwrap_or_default(),
+            radio_groups: radio_groups.unwrap_or_default(),
+            dropdowns: dropdowns.unwrap_or_default(),
+            signature_fields: signature_fields.unwrap_or_default(),
+            annotations: annotations.unwrap_or_default(),
+            ..Page::new(width, height, vec![], vec![], links)
+        }
+    }
+
+    /// The text blocks of `contents`. Setting them replaces the text blocks
+    /// there in place, appending any extra ones at the end.
+    #[getter(text_blocks)]
+    fn py_text_blocks(&self) -> Vec<TextBlock> {
+        self.text_blocks().into_iter().cloned().collect()
+    }
+
+    #[setter(text_blocks)]
+    fn set_py_text_blocks(&mut self, text_blocks: Vec<TextBlock>) {
+        self.set_text_blocks(text_blocks);
+    }
+
+    /// The images of `contents`, replaced in place when set.
+    #[getter(images)]
+    fn py_images(&self) -> Vec<Image> {
+        self.images().into_iter().cloned().collect()
+    }
+
+    #[setter(images)]
+    fn set_py_images(&mut self, images: Vec<Image>) {
+        self.set_images(images);
+    }
+
+    /// The shapes of `contents`, replaced in place when set.
+    #[getter(shapes)]
+    fn py_shapes(&self) -> Vec<Shape> {
+        self.shapes().into_iter().cloned().collect()
+    }
+
+    #[setter(shapes)]
+    fn set_py_shapes(&mut self, shapes: Vec<Shape>) {
+        self.set_shapes(shapes);
+    }
+
+    /// Groups the characters of the text blocks into words, lines,
+    /// paragraphs and columns, in reading order.
+    pub fn columns(&self) -> Vec<Column> {
+        layout::analyze(self)
+    }
+
+    /// The text of the page in reading order, with paragraphs separated by
+    /// blank lines.
+    pub fn text(&self) -> String {
+        let columns: Vec<String> = self.columns().iter().map(Column::text).collect();
+        columns.join("\n\n")
+    }
+
+    /// Finds the tables of the page, top to bottom.
+    pub fn tables(&self) -> Vec<Table> {
+        tables::find_tables(self)
+    }
+
+    /// The visible area of the page: the crop box, or the media box if
+    /// there is none.
+    pub fn visible_box(&self) -> Rect {
+        self.crop_box
+            .or(self.media_box)
+            .unwrap_or(Rect::new(0.0, 0.0, self.width, self.height))
+    }
+
+    /// Width and height of the visible area as displayed, i.e. swapped for
+    /// pages rotated by 90 or 270 degrees.
+    pub fn display_size(&self) -> (f32, f32) {
+        let visible = self.visible_box();
+        if self.rotation.rem_euclid(180) == 90 {
+            (visible.height(), visible.width())
+        } else {
+            (visible.width(), visible.height())
+        }
+    }
+}
+
+impl Page {
+    pub fn new(
+        width: f32,
+        height: f32,
+        text_blocks: Vec<TextBlock>,
+        images: Vec<Image>,
+        links: Option<Vec<Link>>,
+    ) -> Self {
+        let contents = images
+            .into_iter()
+            .map(PageContent::Image)
+            .chain(text_blocks.into_iter().map(PageContent::Text))
+            .collect();
         Page {
             width,
             height,
-            text_blocks,
-            images,
+            contents,
+            links: links.unwrap_or_default(),
+            ..Default::default()
         }
     }
+
+    pub fn text_blocks(&self) -> Vec<&TextBlock> {
+        self.contents
+            .iter()
+            .filter_map(|item| match item {
+                PageContent::Text(block) => Some(block),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn images(&self) -> Vec<&Image> {
+        self.contents
+            .iter()
+            .filter_map(|item| match item {
+                PageContent::Image(image) => Some(image),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn shapes(&self) -> Vec<&Shape> {
+        self.contents
+            .iter()
+            .filter_map(|item| match item {
+                PageContent::Shape(shape) => Some(shape),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn set_text_blocks(&mut self, text_blocks: Vec<TextBlock>) {
+        self.replace_contents(text_blocks, |item| matches!(item, PageContent::Text(_)));
+    }
+
+    pub fn set_images(&mut self, images: Vec<Image>) {
+        self.replace_contents(images, |item| matches!(item, PageContent::Image(_)));
+    }
+
+    pub fn set_shapes(&mut self, shapes: Vec<Shape>) {
+        self.replace_contents(shapes, |item| matches!(item, PageContent::Shape(_)));
+    }
+
+    /// Replaces the elements of `contents` that `is_kind` selects with
+    /// `items`: the n-th item takes the place of the n-th old element,
+    /// items beyond their number are appended, and old elements beyond the
+    /// number of items are removed.
+    fn replace_contents<T: Into<PageContent>>(
+        &mut self,
+        items: Vec<T>,
+        is_kind: fn(&PageContent) -> bool,
+    ) {
+        let mut items = items.into_iter();
+        let mut contents = Vec::with_capacity(self.contents.len());
+        for item in std::mem::take(&mut self.contents) {
+            if !is_kind(&item) {
+                contents.push(item);
+            } else if let Some(replacement) = items.next() {
+                contents.push(replacement.into());
+            }
+        }
+        contents.extend(items.map(Into::into));
+        self.contents = contents;
+    }
+
+    /// Whether the page has any form fields to write.
+    pub fn has_fields(&self) -> bool {
+        !(self.text_fields.is_empty()
+            && self.checkboxes.is_empty()
+            && self.radio_groups.is_empty()
+            && self.dropdowns.is_empty()
+            && self.signature_fields.is_empty())
+    }
+}
+
+#[pymethods]
+impl Word {
+    fn __repr__(&self) -> String {
+        format!("Word({:?}, {})", self.text, self.bbox.__repr__())
+    }
+}
+
+#[pymethods]
+impl TextLine {
+    /// The words separated by spaces.
+    pub fn text(&self) -> String {
+        let words: Vec<&str> = self.words.iter().map(|w| w.text.as_str()).collect();
+        words.join(" ")
+    }
+
+    fn __repr__(&self) -> String {
+        format!("TextLine({:?}, {})", self.text(), self.bbox.__repr__())
+    }
+}
+
+#[pymethods]
+impl Paragraph {
+    /// The lines separated by newlines.
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(TextLine::text).collect();
+        lines.join("\n")
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Paragraph({} lines, {})",
+            self.lines.len(),
+            self.bbox.__repr__()
+        )
+    }
+}
+
+#[pymethods]
+impl Column {
+    /// The paragraphs separated by blank lines.
+    pub fn text(&self) -> String {
+        let paragraphs: Vec<String> = self.paragraphs.iter().map(Paragraph::text).collect();
+        paragraphs.join("\n\n")
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Column({} paragraphs, {})",
+            self.paragraphs.len(),
+            self.bbox.__repr__()
+        )
+    }
+}
+
+#[pymethods]
+impl TableCell {
+    fn __repr__(&self) -> String {
+        format!(
+            "TableCell({}, {}, {:?}, {})",
+            self.row,
+            self.column,
+            self.text,
+            self.bbox.__repr__()
+        )
+    }
+}
+
+#[pymethods]
+impl Table {
+    #[getter]
+    pub fn row_count(&self) -> usize {
+        self.row_edges.len().saturating_sub(1)
+    }
+
+    #[getter]
+    pub fn column_count(&self) -> usize {
+        self.column_edges.len().saturating_sub(1)
+    }
+
+    /// The text of each row, cell by cell. A merged cell's text is placed
+    /// at its top left position, and the positions it also covers are
+    /// empty.
+    pub fn to_list(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![vec![String::new(); self.column_count()]; self.row_count()];
+        for cell in &self.cells {
+            if let Some(text) = rows
+                .get_mut(cell.row)
+                .and_then(|row| row.get_mut(cell.column))
+            {
+                *text = cell.text.clone();
+            }
+        }
+        rows
+    }
+
+    /// The rows of `to_list` as CSV, quoting fields as in RFC 4180.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in self.to_list() {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|field| {
+                    if field.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field.clone()
+                    }
+                })
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Table({} x {}, {})",
+            self.row_count(),
+            self.column_count(),
+            self.bbox.__repr__()
+        )
+    }
+}
+
+#[pymethods]
+impl Link {
+    #[new]
+    #[pyo3(signature = (rect, uri=None, destination=None, named_destination=None))]
+    pub fn new(
+        rect: Rect,
+        uri: Option<String>,
+        destination: Option<Destination>,
+        named_destination: Option<String>,
+    ) -> Self {
+        Link {
+            rect,
+            uri,
+            destination,
+            named_destination,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let target = match (&self.uri, &self.named_destination, &self.destination) {
+            (Some(uri), _, _) => format!("uri={:?}", uri),
+            (None, Some(name), _) => format!("named_destination={:?}", name),
+            (None, None, Some(destination)) => format!("page={}", destination.page),
+            (None, None, None) => "no target".to_string(),
+        };
+        format!("Link({}, {})", self.rect.__repr__(), target)
+    }
+}
+
+#[pymethods]
+impl Annotation {
+    #[new]
+    #[pyo3(signature = (
+        kind,
+        rect=None,
+        contents=None,
+        author=None,
+        *,
+        created=None,
+        modified=None,
+        color=None,
+        interior_color=None,
+        border_width=None,
+        quads=None,
+        ink=None,
+        icon=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        kind: String,
+        rect: Option<Rect>,
+        contents: Option<String>,
+        author: Option<String>,
+        created: Option<String>,
+        modified: Option<String>,
+        color: Option<Vec<f32>>,
+        interior_color: Option<Vec<f32>>,
+        border_width: Option<f32>,
+        quads: Option<Vec<Rect>>,
+        ink: Option<Vec<Vec<(f32, f32)>>>,
+        icon: Option<String>,
+    ) -> Self {
+        Annotation {
+            kind,
+            rect: rect.unwrap_or_default(),
+            contents,
+            author,
+            created,
+            modified,
+            color: color.unwrap_or_default(),
+            interior_color: interior_color.unwrap_or_default(),
+            border_width,
+            quads: quads.unwrap_or_default(),
+            text: None,
+            ink: ink.unwrap_or_default(),
+            icon,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Annotation({:?}, {}, contents={:?})",
+            self.kind,
+            self.rect.__repr__(),
+            self.contents
+        )
+    }
+}
+
+#[pymethods]
+impl TextField {
+    #[new]
+    #[pyo3(signature = (
+        name,
+        rect,
+        value=None,
+        multiline=false,
+        max_length=None,
+        font_size=0.0,
+        required=false,
+        read_only=false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        rect: Rect,
+        value: Option<String>,
+        multiline: bool,
+        max_length: Option<u32>,
+        font_size: f32,
+        required: bool,
+        read_only: bool,
+    ) -> Self {
+        TextField {
+            name,
+            rect,
+            value,
+            multiline,
+            max_length,
+            font_size,
+            required,
+            read_only,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("TextField({:?}, value={:?})", self.name, self.value)
+    }
+}
+
+#[pymethods]
+impl CheckBox {
+    #[new]
+    #[pyo3(signature = (
+        name,
+        rect,
+        checked=false,
+        export_value="Yes".to_string(),
+        required=false,
+        read_only=false,
+    ))]
+    pub fn new(
+        name: String,
+        rect: Rect,
+        checked: bool,
+        export_value: String,
+        required: bool,
+        read_only: bool,
+    ) -> Self {
+        CheckBox {
+            name,
+            rect,
+            checked,
+            export_value,
+            required,
+            read_only,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CheckBox({:?}, checked={})", self.name, self.checked)
+    }
+}
+
+#[pymethods]
+impl RadioButton {
+    #[new]
+    pub fn new(value: String, rect: Rect) -> Self {
+        RadioButton { value, rect }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("RadioButton({:?}, {})", self.value, self.rect.__repr__())
+    }
+}
+
+#[pymethods]
+impl RadioGroup {
+    #[new]
+    #[pyo3(signature = (name, buttons, selected=None, required=false, read_only=false))]
+    pub fn new(
+        name: String,
+        buttons: Vec<RadioButton>,
+        selected: Option<String>,
+        required: bool,
+        read_only: bool,
+    ) -> Self {
+        RadioGroup {
+            name,
+            buttons,
+            selected,
+            required,
+            read_only,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "RadioGroup({:?}, buttons={}, selected={:?})",
+            self.name,
+            self.buttons.len(),
+            self.selected
+        )
+    }
+}
+
+#[pymethods]
+impl Dropdown {
+    #[new]
+    #[pyo3(signature = (
+        name,
+        rect,
+        options,
+        value=None,
+        editable=false,
+        font_size=0.0,
+        required=false,
+        read_only=false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        rect: Rect,
+        options: Vec<String>,
+        value: Option<String>,
+        editable: bool,
+        font_size: f32,
+        required: bool,
+        read_only: bool,
+    ) -> Self {
+        Dropdown {
+            name,
+            rect,
+            options,
+            value,
+            editable,
+            font_size,
+            required,
+            read_only,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Dropdown({:?}, value={:?})", self.name, self.value)
+    }
+}
+
+#[pymethods]
+impl SignatureField {
+    #[new]
+    #[pyo3(signature = (name, rect, required=false))]
+    pub fn new(name: String, rect: Rect, required: bool) -> Self {
+        SignatureField {
+            name,
+            rect,
+            required,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SignatureField({:?})", self.name)
+    }
+}
+
+#[pymethods]
+impl Metadata {
+    #[new]
+    #[pyo3(signature = (
+        title=None,
+        author=None,
+        subject=None,
+        keywords=None,
+        creator=None,
+        producer=None,
+        creation_date=None,
+        mod_date=None,
+        custom=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        title: Option<String>,
+        author: Option<String>,
+        subject: Option<String>,
+        keywords: Option<String>,
+        creator: Option<String>,
+        producer: Option<String>,
+        creation_date: Option<String>,
+        mod_date: Option<String>,
+        custom: Option<BTreeMap<String, String>>,
+    ) -> Self {
+        Metadata {
+            title,
+            author,
+            subject,
+            keywords,
+            creator,
+            producer,
+            creation_date,
+            mod_date,
+            custom: custom.unwrap_or_default(),
+        }
+    }
+
+    /// Whether no entry is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    fn __repr__(&self) -> String {
+        let entries = [
+            ("title", &self.title),
+            ("author", &self.author),
+            ("subject", &self.subject),
+            ("keywords", &self.keywords),
+            ("creator", &self.creator),
+            ("producer", &self.producer),
+            ("creation_date", &self.creation_date),
+            ("mod_date", &self.mod_date),
+        ];
+        let mut fields: Vec<String> = entries
+            .iter()
+            .filter_map(|(key, value)| value.as_ref().map(|v| format!("{}={:?}", key, v)))
+            .collect();
+        if !self.custom.is_empty() {
+            fields.push(format!("custom={:?}", self.custom));
+        }
+        format!("Metadata({})", fields.join(", "))
+    }
+}
+
+#[pymethods]
+impl Destination {
+    #[new]
+    #[pyo3(signature = (page, kind="Fit".to_string(), left=None, top=None, right=None, bottom=None, zoom=None))]
+    pub fn new(
+        page: usize,
+        kind: String,
+        left: Option<f32>,
+        top: Option<f32>,
+        right: Option<f32>,
+        bottom: Option<f32>,
+        zoom: Option<f32>,
+    ) -> Self {
+        Destination {
+            page,
+            kind,
+            left,
+            top,
+            right,
+            bottom,
+            zoom,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let coordinates = [
+            ("left", self.left),
+            ("top", self.top),
+            ("right", self.right),
+            ("bottom", self.bottom),
+            ("zoom", self.zoom),
+        ];
+        let mut fields = vec![
+            format!("page={}", self.page),
+            format!("kind={:?}", self.kind),
+        ];
+        fields.extend(
+            coordinates
+                .iter()
+                .filter_map(|(key, value)| value.map(|v| format!("{}={}", key, v))),
+        );
+        format!("Destination({})", fields.join(", "))
+    }
+}
+
+#[pymethods]
+impl OutlineItem {
+    #[new]
+    #[pyo3(signature = (title, destination=None, children=None, open=false))]
+    pub fn new(
+        title: String,
+        destination: Option<Destination>,
+        children: Option<Vec<OutlineItem>>,
+        open: bool,
+    ) -> Self {
+        OutlineItem {
+            title,
+            destination,
+            open,
+            children: children.unwrap_or_default(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let page = self
+            .destination
+            .as_ref()
+            .map_or("None".to_string(), |d| d.page.to_string());
+        format!(
+            "OutlineItem({:?}, page={}, children={})",
+            self.title,
+            page,
+            self.children.len()
+        )
+    }
+}
+
+#[pymethods]
+impl FormField {
+    #[getter]
+    pub fn read_only(&self) -> bool {
+        self.flags & FIELD_READ_ONLY != 0
+    }
+
+    #[getter]
+    pub fn required(&self) -> bool {
+        self.flags & FIELD_REQUIRED != 0
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "FormField({:?}, field_type={:?}, value={:?})",
+            self.name, self.field_type, self.value
+        )
+    }
 }
 
 #[pymethods]
 impl Document {
     #[new]
-    pub fn new(pages: Vec<Page>) -> Self {
-        Document { pages }
+    #[pyo3(signature = (pages, metadata=None, outline=None))]
+    pub fn new(
+        pages: Vec<Page>,
+        metadata: Option<Metadata>,
+        outline: Option<Vec<OutlineItem>>,
+    ) -> Self {
+        Document {
+            pages,
+            metadata: metadata.unwrap_or_default(),
+            outline: outline.unwrap_or_default(),
+            ..Default::default()
+        }
     }
 }
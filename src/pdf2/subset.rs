@@ -0,0 +1,226 @@
+//! TrueType glyph subsetting for embedded fonts, and extraction of single
+//! fonts from TrueType/OpenType collections.
+//!
+//! Glyph ids are kept unchanged so that text can be written with an
+//! identity CID-to-GID mapping; glyphs that are not used are emptied and the
+//! tables PDF viewers do not need (`cmap`, `name`, `post`, ...) are dropped.
+
+use std::collections::BTreeSet;
+
+/// Tables copied into the subset, in tag order.
+const KEPT_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+// Composite glyph component flags (OpenType `glyf` table).
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Finds a table in the font's table directory.
+fn find_table<'a>(data: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u16(data, 4)? as usize;
+    (0..count).find_map(|i| {
+        let record = 12 + i * 16;
+        if data.get(record..record + 4)? != tag {
+            return None;
+        }
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        data.get(offset..offset + length)
+    })
+}
+
+/// Whether `data` is a TrueType font with `glyf` outlines.
+pub fn is_truetype(data: &[u8]) -> bool {
+    find_table(data, b"glyf").is_some() && find_table(data, b"loca").is_some()
+}
+
+/// Glyph ids referenced by a composite glyph.
+fn components(glyph: &[u8]) -> Vec<u16> {
+    let mut ids = Vec::new();
+    // A non-negative contour count marks a simple glyph.
+    if glyph.len() < 10 || (glyph[0] & 0x80) == 0 {
+        return ids;
+    }
+    let mut pos = 10;
+    while let (Some(flags), Some(id)) = (read_u16(glyph, pos), read_u16(glyph, pos + 2)) {
+        ids.push(id);
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    ids
+}
+
+fn checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Returns a copy of the TrueType font `data` containing only the outlines
+/// of `glyphs` (plus `.notdef` and the components of composite glyphs).
+pub fn subset_truetype(data: &[u8], glyphs: &BTreeSet<u16>) -> Result<Vec<u8>, String> {
+    let missing = |tag: &str| format!("font has no '{}' table", tag);
+    let head = find_table(data, b"head").ok_or_else(|| missing("head"))?;
+    let maxp = find_table(data, b"maxp").ok_or_else(|| missing("maxp"))?;
+    let loca = find_table(data, b"loca").ok_or_else(|| missing("loca"))?;
+    let glyf = find_table(data, b"glyf").ok_or_else(|| missing("glyf"))?;
+    let num_glyphs = read_u16(maxp, 4).ok_or("corrupt 'maxp' table")? as usize;
+    let long_offsets = read_u16(head, 50).ok_or("corrupt 'head' table")? != 0;
+
+    let offset = |index: usize| -> Option<usize> {
+        if long_offsets {
+            read_u32(loca, index * 4).map(|o| o as usize)
+        } else {
+            read_u16(loca, index * 2).map(|o| o as usize * 2)
+        }
+    };
+    let glyph = |id: usize| -> &[u8] {
+        match (offset(id), offset(id + 1)) {
+            (Some(start), Some(end)) if start <= end => glyf.get(start..end).unwrap_or(&[]),
+            _ => &[],
+        }
+    };
+
+    // Close the set over composite glyph components.
+    let mut keep: BTreeSet<u16> = glyphs.clone();
+    keep.insert(0);
+    let mut pending: Vec<u16> = keep.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        for component in components(glyph(id as usize)) {
+            if (component as usize) < num_glyphs && keep.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for id in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(glyph(id));
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    let tables: Vec<(&[u8; 4], Vec<u8>)> = KEPT_TABLES
+        .iter()
+        .filter_map(|&tag| {
+            let table = match tag {
+                b"glyf" => new_glyf.clone(),
+                b"loca" => new_loca.clone(),
+                b"head" => new_head.clone(),
+                _ => find_table(data, tag)?.to_vec(),
+            };
+            Some((tag, table))
+        })
+        .collect();
+
+    Ok(write_sfnt(0x0001_0000, tables))
+}
+
+/// Returns font `index` of the collection (`ttcf`) `data` as a standalone
+/// font file.
+pub fn collection_face(data: &[u8], index: u32) -> Result<Vec<u8>, String> {
+    let corrupt = || "corrupt font collection".to_string();
+    let count = read_u32(data, 8).ok_or_else(corrupt)?;
+    if index >= count {
+        return Err(format!(
+            "font index {} is out of range for a collection of {} fonts",
+            index, count
+        ));
+    }
+    let start = read_u32(data, 12 + index as usize * 4).ok_or_else(corrupt)? as usize;
+    let version = read_u32(data, start).ok_or_else(corrupt)?;
+    let table_count = read_u16(data, start + 4).ok_or_else(corrupt)? as usize;
+    // Table offsets in a collection are from the start of the file.
+    let tables = (0..table_count)
+        .map(|i| {
+            let record = start + 12 + i * 16;
+            let tag: &[u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+            let offset = read_u32(data, record + 8)? as usize;
+            let length = read_u32(data, record + 12)? as usize;
+            Some((tag, data.get(offset..offset.checked_add(length)?)?.to_vec()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(corrupt)?;
+    Ok(write_sfnt(version, tables))
+}
+
+/// Writes an sfnt font file with `tables`, which must be in tag order,
+/// recomputing the checksums and the `head` checksum adjustment.
+fn write_sfnt(version: u32, mut tables: Vec<(&[u8; 4], Vec<u8>)>) -> Vec<u8> {
+    for (tag, table) in &mut tables {
+        if *tag == b"head" && table.len() >= 12 {
+            table[8..12].copy_from_slice(&[0; 4]);
+        }
+    }
+
+    // Offset table and table directory.
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let mut output = Vec::new();
+    output.extend_from_slice(&version.to_be_bytes());
+    output.extend_from_slice(&count.to_be_bytes());
+    output.extend_from_slice(&search_range.to_be_bytes());
+    output.extend_from_slice(&entry_selector.to_be_bytes());
+    output.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, table) in &tables {
+        output.extend_from_slice(*tag);
+        output.extend_from_slice(&checksum(table).to_be_bytes());
+        output.extend_from_slice(&(offset as u32).to_be_bytes());
+        output.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().div_ceil(4) * 4;
+    }
+    let mut head_offset = None;
+    for (tag, table) in &tables {
+        if *tag == b"head" && table.len() >= 12 {
+            head_offset = Some(output.len());
+        }
+        output.extend_from_slice(table);
+        while output.len() % 4 != 0 {
+            output.push(0);
+        }
+    }
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&output));
+        output[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    output
+}
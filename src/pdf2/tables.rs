@@ -0,0 +1,460 @@
+//! Table detection. Ruled tables are found from the grid the page's
+//! horizontal and vertical lines form, with a missing inner border merging
+//! the cells on both sides of it. Merged regions that are not rectangles
+//! are left as separate cells. Unruled tables are found from runs of
+//! text rows that split into segments lining up in columns.
+
+use crate::layout::{glyphs, row_lines, union, Glyph};
+use crate::structure::{Page, Rect, Table, TableCell, TextLine};
+
+/// Lines this close (in points) are taken to be the same line, and lines
+/// ending this close to another are taken to touch it.
+const SNAP: f32 = 2.0;
+
+/// Lines shorter than this are ignored, such as the ends of thin filled
+/// rectangles used as rules.
+const MIN_RULE: f32 = 4.0;
+
+/// Fraction of a cell's side a line has to cover to count as its border.
+const BORDER_COVERAGE: f32 = 0.5;
+
+/// Unruled tables need at least this many rows with several segments.
+const MIN_ROWS: usize = 3;
+
+/// Rows further apart than this, as a fraction of the font size, end an
+/// unruled table.
+const ROW_GAP: f32 = 1.0;
+
+/// Unruled tables average at most this many words per cell, which tells
+/// them apart from text set in columns.
+const MAX_WORDS_PER_CELL: f32 = 4.0;
+
+/// A horizontal line at `at` from `start` to `end`, or a vertical one.
+#[derive(Debug, Clone, Copy)]
+struct Rule {
+    at: f32,
+    start: f32,
+    end: f32,
+}
+
+/// Finds the tables of `page`, top to bottom.
+pub fn find_tables(page: &Page) -> Vec<Table> {
+    let (horizontal, vertical) = rules(page);
+    let mut glyphs = glyphs(page);
+    let mut tables = ruled_tables(&merge(horizontal), &merge(vertical), &glyphs);
+    glyphs.retain(|g| !tables.iter().any(|t| g.is_inside(&t.bbox)));
+    tables.extend(unruled_tables(glyphs));
+    tables.sort_by(|a, b| b.bbox.y1.total_cmp(&a.bbox.y1));
+    tables
+}
+
+/// The horizontal and vertical straight lines of the page's painted shapes.
+fn rules(page: &Page) -> (Vec<Rule>, Vec<Rule>) {
+    let (mut horizontal, mut vertical) = (Vec::new(), Vec::new());
+    for shape in page.shapes() {
+        if shape.fill_color.is_none() && shape.stroke_color.is_none() {
+            continue;
+        }
+        let mut start = (0.0, 0.0);
+        let mut current = (0.0, 0.0);
+        for segment in &shape.segments {
+            let end = match segment.kind.as_str() {
+                "move" => {
+                    start = segment.points.first().copied().unwrap_or(current);
+                    current = start;
+                    continue;
+                }
+                "line" => segment.points.first().copied().unwrap_or(current),
+                "close" => start,
+                _ => {
+                    current = segment.points.last().copied().unwrap_or(current);
+                    continue;
+                }
+            };
+            let ((x0, y0), (x1, y1)) = (current, end);
+            if (y1 - y0).abs() <= SNAP && (x1 - x0).abs() >= MIN_RULE {
+                horizontal.push(Rule {
+                    at: (y0 + y1) / 2.0,
+                    start: x0.min(x1),
+                    end: x0.max(x1),
+                });
+            } else if (x1 - x0).abs() <= SNAP && (y1 - y0).abs() >= MIN_RULE {
+                vertical.push(Rule {
+                    at: (x0 + x1) / 2.0,
+                    start: y0.min(y1),
+                    end: y0.max(y1),
+                });
+            }
+            current = end;
+        }
+    }
+    (horizontal, vertical)
+}
+
+/// Joins lines at the same position that overlap or nearly touch.
+fn merge(mut rules: Vec<Rule>) -> Vec<Rule> {
+    rules.sort_by(|a, b| a.at.total_cmp(&b.at).then(a.start.total_cmp(&b.start)));
+    let mut merged: Vec<Rule> = Vec::new();
+    for rule in rules {
+        let joined = merged.iter_mut().rev().find(|m| {
+            (m.at - rule.at).abs() <= SNAP
+                && rule.start <= m.end + SNAP
+                && m.start <= rule.end + SNAP
+        });
+        match joined {
+            Some(m) => {
+                m.start = m.start.min(rule.start);
+                m.end = m.end.max(rule.end);
+            }
+            None => merged.push(rule),
+        }
+    }
+    merged
+}
+
+fn crosses(horizontal: &Rule, vertical: &Rule) -> bool {
+    vertical.at >= horizontal.start - SNAP
+        && vertical.at <= horizontal.end + SNAP
+        && horizontal.at >= vertical.start - SNAP
+        && horizontal.at <= vertical.end + SNAP
+}
+
+/// Sorted positions of `rules`, with positions closer than `SNAP` joined.
+fn edges(rules: &[&Rule]) -> Vec<f32> {
+    let mut positions: Vec<f32> = rules.iter().map(|r| r.at).collect();
+    positions.sort_by(f32::total_cmp);
+    positions.dedup_by(|b, a| *b - *a <= SNAP);
+    positions
+}
+
+/// Whether one of `rules` lies at `at` and covers enough of `start..end`.
+fn has_border(rules: &[&Rule], at: f32, start: f32, end: f32) -> bool {
+    let covered: f32 = rules
+        .iter()
+        .filter(|r| (r.at - at).abs() <= SNAP)
+        .map(|r| (r.end.min(end) - r.start.max(start)).max(0.0))
+        .sum();
+    covered >= BORDER_COVERAGE * (end - start)
+}
+
+fn ruled_tables(horizontal: &[Rule], vertical: &[Rule], glyphs: &[Glyph]) -> Vec<Table> {
+    // Group the lines into connected grids; horizontal lines come first in
+    // `parents`, vertical ones after them.
+    let count = horizontal.len() + vertical.len();
+    let mut parents: Vec<usize> = (0..count).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for (h, row) in horizontal.iter().enumerate() {
+        for (v, column) in vertical.iter().enumerate() {
+            if crosses(row, column) {
+                let (a, b) = (
+                    root(&mut parents, h),
+                    root(&mut parents, horizontal.len() + v),
+                );
+                parents[a] = b;
+            }
+        }
+    }
+    let mut tables = Vec::new();
+    for group in 0..count {
+        if root(&mut parents, group) != group {
+            continue;
+        }
+        let in_group = |parents: &mut [usize], i: usize| root(parents, i) == group;
+        let rows: Vec<&Rule> = (0..horizontal.len())
+            .filter(|&h| in_group(&mut parents, h))
+            .map(|h| &horizontal[h])
+            .collect();
+        let columns: Vec<&Rule> = (0..vertical.len())
+            .filter(|&v| in_group(&mut parents, horizontal.len() + v))
+            .map(|v| &vertical[v])
+            .collect();
+        if let Some(table) = grid_table(&rows, &columns, glyphs) {
+            tables.push(table);
+        }
+    }
+    tables
+}
+
+/// The table the lines of one grid form, if it has at least two cells.
+fn grid_table(horizontal: &[&Rule], vertical: &[&Rule], glyphs: &[Glyph]) -> Option<Table> {
+    let mut row_edges = edges(horizontal);
+    row_edges.reverse();
+    let column_edges = edges(vertical);
+    let (rows, columns) = (
+        row_edges.len().checked_sub(1)?,
+        column_edges.len().checked_sub(1)?,
+    );
+    if rows * columns < 2 {
+        return None;
+    }
+    // Neighbouring positions without a border between them are one cell.
+    let open_right = |r: usize, c: usize| {
+        !has_border(
+            vertical,
+            column_edges[c + 1],
+            row_edges[r + 1],
+            row_edges[r],
+        )
+    };
+    let open_below = |r: usize, c: usize| {
+        !has_border(
+            horizontal,
+            row_edges[r + 1],
+            column_edges[c],
+            column_edges[c + 1],
+        )
+    };
+    let mut covered = vec![vec![false; columns]; rows];
+    // Positions of merged regions that are not rectangles, which are kept
+    // as separate cells so that no two cells share a position.
+    let mut unmerged = vec![vec![false; columns]; rows];
+    let mut cells = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            if covered[row][column] {
+                continue;
+            }
+            let (mut last_row, mut last_column) = (row, column);
+            if !unmerged[row][column] {
+                // Earlier rows are covered, so the region starts on this one.
+                let mut first_column = column;
+                let mut stack = vec![(row, column)];
+                let mut seen = vec![(row, column)];
+                while let Some((r, c)) = stack.pop() {
+                    let mut neighbours = Vec::new();
+                    if c + 1 < columns && open_right(r, c) {
+                        neighbours.push((r, c + 1));
+                    }
+                    if c > 0 && open_right(r, c - 1) {
+                        neighbours.push((r, c - 1));
+                    }
+                    if r + 1 < rows && open_below(r, c) {
+                        neighbours.push((r + 1, c));
+                    }
+                    if r > 0 && open_below(r - 1, c) {
+                        neighbours.push((r - 1, c));
+                    }
+                    for next in neighbours {
+                        if !covered[next.0][next.1] && !seen.contains(&next) {
+                            seen.push(next);
+                            stack.push(next);
+                            first_column = first_column.min(next.1);
+                            last_row = last_row.max(next.0);
+                            last_column = last_column.max(next.1);
+                        }
+                    }
+                }
+                let area = (last_row - row + 1) * (last_column - column + 1);
+                if first_column < column || seen.len() != area {
+                    for (r, c) in seen {
+                        unmerged[r][c] = true;
+                    }
+                    (last_row, last_column) = (row, column);
+                }
+            }
+            for covered_row in &mut covered[row..=last_row] {
+                for position in &mut covered_row[column..=last_column] {
+                    *position = true;
+                }
+            }
+            let bbox = Rect::new(
+                column_edges[column],
+                row_edges[last_row + 1],
+                column_edges[last_column + 1],
+                row_edges[row],
+            );
+            cells.push(TableCell {
+                row,
+                column,
+                row_span: last_row - row + 1,
+                column_span: last_column - column + 1,
+                text: cell_text(glyphs, &bbox),
+                bbox,
+            });
+        }
+    }
+    Some(Table {
+        bbox: Rect::new(
+            column_edges[0],
+            row_edges[rows],
+            column_edges[columns],
+            row_edges[0],
+        ),
+        row_edges,
+        column_edges,
+        cells,
+    })
+}
+
+/// The text of the glyphs inside `bbox`, line by line.
+fn cell_text(glyphs: &[Glyph], bbox: &Rect) -> String {
+    let inside = glyphs
+        .iter()
+        .filter(|g| g.is_inside(bbox))
+        .copied()
+        .collect();
+    let lines: Vec<String> = row_lines(inside)
+        .into_iter()
+        .flatten()
+        .map(|line| line.text())
+        .collect();
+    lines.join(" ")
+}
+
+/// A row of text and the segments it splits into.
+struct TextRow {
+    segments: Vec<TextLine>,
+    bbox: Rect,
+    font_size: f32,
+}
+
+fn unruled_tables(glyphs: Vec<Glyph>) -> Vec<Table> {
+    let rows: Vec<TextRow> = row_lines(glyphs)
+        .into_iter()
+        .filter_map(|segments| {
+            let bbox = segments
+                .iter()
+                .map(|s| s.bbox)
+                .reduce(|a, b| union(&a, &b))?;
+            let font_size = segments
+                .iter()
+                .flat_map(|s| &s.words)
+                .map(|w| w.font_size)
+                .fold(0.0, f32::max);
+            Some(TextRow {
+                segments,
+                bbox,
+                font_size,
+            })
+        })
+        .collect();
+    // Rows with a single segment are kept inside a table, e.g. for a
+    // wrapped description, but not at its ends.
+    let split = |i: usize| rows[i].segments.len() >= 2;
+    let keep = |i: usize| split(i) || (i > 0 && i + 1 < rows.len() && split(i - 1) && split(i + 1));
+    let mut tables = Vec::new();
+    let mut run: Vec<&TextRow> = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let close = run.last().is_some_and(|last| {
+            last.bbox.y0 - row.bbox.y1 <= ROW_GAP * last.font_size.max(row.font_size)
+        });
+        if !(keep(index) && (close || run.is_empty())) {
+            tables.extend(aligned_table(std::mem::take(&mut run)));
+        }
+        if keep(index) {
+            run.push(row);
+        }
+    }
+    tables.extend(aligned_table(run));
+    tables
+}
+
+/// The table formed by `rows`, whose columns are the spans covered by the
+/// segments of the rows with the most segments. Segments spanning several
+/// columns become merged cells.
+fn aligned_table(rows: Vec<&TextRow>) -> Option<Table> {
+    if rows.iter().filter(|r| r.segments.len() >= 2).count() < MIN_ROWS {
+        return None;
+    }
+    let segments = || rows.iter().flat_map(|r| &r.segments);
+    let words: usize = segments().map(|s| s.words.len()).sum();
+    if words as f32 > MAX_WORDS_PER_CELL * segments().count() as f32 {
+        return None;
+    }
+    let most = rows.iter().map(|r| r.segments.len()).max()?;
+    let mut spans: Vec<(f32, f32)> = rows
+        .iter()
+        .filter(|r| r.segments.len() == most)
+        .flat_map(|r| &r.segments)
+        .map(|s| (s.bbox.x0, s.bbox.x1))
+        .collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f32, f32)> = Vec::new();
+    for (start, end) in spans {
+        match columns.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => columns.push((start, end)),
+        }
+    }
+    if columns.len() < 2 {
+        return None;
+    }
+
+    let bbox = rows.iter().map(|r| r.bbox).reduce(|a, b| union(&a, &b))?;
+    let mut column_edges = vec![bbox.x0];
+    column_edges.extend(columns.windows(2).map(|w| (w[0].1 + w[1].0) / 2.0));
+    column_edges.push(bbox.x1);
+    let mut row_edges = vec![bbox.y1];
+    row_edges.extend(rows.windows(2).map(|w| (w[0].bbox.y0 + w[1].bbox.y1) / 2.0));
+    row_edges.push(bbox.y0);
+
+    let mut cells = Vec::new();
+    for (row, text_row) in rows.iter().enumerate() {
+        // (first column, last column, text) of each segment.
+        let mut placed: Vec<(usize, usize, String)> = Vec::new();
+        for segment in &text_row.segments {
+            let overlapping: Vec<usize> = (0..columns.len())
+                .filter(|&c| segment.bbox.x0 < columns[c].1 && columns[c].0 < segment.bbox.x1)
+                .collect();
+            let (first, last) = match (overlapping.first(), overlapping.last()) {
+                (Some(&first), Some(&last)) => (first, last),
+                _ => {
+                    let middle = (segment.bbox.x0 + segment.bbox.x1) / 2.0;
+                    let nearest = (0..columns.len())
+                        .min_by(|&a, &b| {
+                            let distance = |c: usize| {
+                                (columns[c].0 - middle)
+                                    .abs()
+                                    .min((columns[c].1 - middle).abs())
+                            };
+                            distance(a).total_cmp(&distance(b))
+                        })
+                        .unwrap_or(0);
+                    (nearest, nearest)
+                }
+            };
+            match placed.last_mut() {
+                Some(previous) if first <= previous.1 => {
+                    previous.1 = previous.1.max(last);
+                    previous.2.push(' ');
+                    previous.2.push_str(&segment.text());
+                }
+                _ => placed.push((first, last, segment.text())),
+            }
+        }
+        let mut column = 0;
+        let mut placed = placed.into_iter().peekable();
+        while column < columns.len() {
+            let (last, text) = match placed.next_if(|p| p.0 == column) {
+                Some((_, last, text)) => (last, text),
+                None => (column, String::new()),
+            };
+            cells.push(TableCell {
+                row,
+                column,
+                row_span: 1,
+                column_span: last - column + 1,
+                bbox: Rect::new(
+                    column_edges[column],
+                    row_edges[row + 1],
+                    column_edges[last + 1],
+                    row_edges[row],
+                ),
+                text,
+            });
+            column = last + 1;
+        }
+    }
+    Some(Table {
+        bbox,
+        row_edges,
+        column_edges,
+        cells,
+    })
+}
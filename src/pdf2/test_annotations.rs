@@ -0,0 +1,253 @@
+use crate::annotations::read_annotations;
+use crate::editor::{EditError, PdfEditor};
+use crate::font_registry::FontRegistry;
+use crate::generator::generate_to_bytes;
+use crate::parser::parse_bytes;
+use crate::structure::{Annotation, Document, Page, Rect};
+use crate::test_utils::{text_operations, PdfBuilder};
+use lopdf::{Dictionary, Object};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(values: &[f32]) -> Object {
+        values
+            .iter()
+            .map(|&v| Object::from(v))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// A page showing "Hello world" in 12pt Helvetica at (72, 720), with a
+    /// sticky note and a highlight over "world".
+    fn annotated_document() -> lopdf::Document {
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let page = builder.add_page(
+            text_operations("Hello world", 72.0, 720.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let doc = &mut builder.doc;
+        let note = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Text".to_vec())),
+            ("Rect", numbers(&[300.0, 700.0, 320.0, 720.0])),
+            ("Contents", Object::string_literal("Check this")),
+            ("T", Object::string_literal("Ada")),
+            ("M", Object::string_literal("D:20240102030405Z")),
+            ("Name", Object::Name(b"Comment".to_vec())),
+            ("C", numbers(&[1.0, 1.0, 0.0])),
+        ]));
+        let highlight = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Highlight".to_vec())),
+            ("Rect", numbers(&[102.5, 716.0, 131.5, 732.0])),
+            (
+                "QuadPoints",
+                numbers(&[102.5, 732.0, 131.5, 732.0, 102.5, 716.0, 131.5, 716.0]),
+            ),
+            ("Border", numbers(&[0.0, 0.0, 2.0])),
+        ]));
+        let link = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Link".to_vec())),
+            ("Rect", numbers(&[0.0, 0.0, 10.0, 10.0])),
+        ]));
+        doc.get_dictionary_mut(page)
+            .unwrap()
+            .set("Annots", vec![note.into(), highlight.into(), link.into()]);
+        builder.finish()
+    }
+
+    #[test]
+    fn test_read_annotations_reads_notes_and_covered_text() {
+        // Arrange
+        let doc = annotated_document();
+        let mut bytes = Vec::new();
+        doc.clone().save_to(&mut bytes).unwrap();
+        let page = &parse_bytes(&bytes, None).unwrap().pages[0];
+        let page_id = *doc.get_pages().get(&1).unwrap();
+
+        // Act
+        let annotations = read_annotations(&doc, page_id, &page.text_blocks());
+
+        // Assert
+        assert_eq!(annotations.len(), 2);
+        let note = &annotations[0];
+        assert_eq!(note.kind, "text");
+        assert_eq!(note.contents.as_deref(), Some("Check this"));
+        assert_eq!(note.author.as_deref(), Some("Ada"));
+        assert_eq!(note.modified.as_deref(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(note.icon.as_deref(), Some("Comment"));
+        assert_eq!(note.color, vec![1.0, 1.0, 0.0]);
+        assert_eq!(note.text, None);
+        let highlight = &annotations[1];
+        assert_eq!(highlight.kind, "highlight");
+        assert_eq!(highlight.quads, vec![Rect::new(102.5, 716.0, 131.5, 732.0)]);
+        assert_eq!(highlight.border_width, Some(2.0));
+        assert_eq!(highlight.text.as_deref(), Some("world"));
+        assert_eq!(page.annotations, annotations);
+    }
+
+    #[test]
+    fn test_generate_writes_annotations_with_appearances() {
+        // Arrange
+        let document = Document {
+            pages: vec![Page {
+                width: 595.0,
+                height: 842.0,
+                annotations: vec![
+                    Annotation {
+                        kind: "underline".to_string(),
+                        author: Some("Ada".to_string()),
+                        created: Some("2024-05-06T07:08:09Z".to_string()),
+                        quads: vec![
+                            Rect::new(72.0, 700.0, 200.0, 714.0),
+                            Rect::new(72.0, 680.0, 150.0, 694.0),
+                        ],
+                        ..Default::default()
+                    },
+                    Annotation {
+                        kind: "ink".to_string(),
+                        border_width: Some(2.0),
+                        ink: vec![vec![(10.0, 10.0), (50.0, 40.0), (90.0, 10.0)]],
+                        ..Default::default()
+                    },
+                    Annotation {
+                        kind: "freetext".to_string(),
+                        rect: Rect::new(300.0, 500.0, 450.0, 540.0),
+                        contents: Some("Typed note".to_string()),
+                        ..Default::default()
+                    },
+                    Annotation {
+                        kind: "circle".to_string(),
+                        rect: Rect::new(100.0, 100.0, 200.0, 150.0),
+                        interior_color: vec![0.0, 0.0, 1.0],
+                        ..Default::default()
+                    },
+                    Annotation {
+                        kind: "stamp".to_string(),
+                        rect: Rect::new(400.0, 100.0, 500.0, 140.0),
+                        icon: Some("Approved".to_string()),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+
+        // Assert
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let annots = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        for annot in annots {
+            let annot = doc.get_dictionary(annot.as_reference().unwrap()).unwrap();
+            assert!(annot.get(b"AP").is_ok());
+        }
+        let annotations = &parse_bytes(&bytes, None).unwrap().pages[0].annotations;
+        let kinds: Vec<&str> = annotations.iter().map(|a| a.kind.as_str()).collect();
+        assert_eq!(kinds, ["underline", "ink", "freetext", "circle", "stamp"]);
+        assert_eq!(annotations[0].quads.len(), 2);
+        assert_eq!(annotations[0].rect, Rect::new(72.0, 680.0, 200.0, 714.0));
+        assert_eq!(annotations[0].author.as_deref(), Some("Ada"));
+        assert_eq!(
+            annotations[0].created.as_deref(),
+            Some("2024-05-06T07:08:09Z")
+        );
+        assert_eq!(annotations[0].color, vec![1.0, 0.0, 0.0]);
+        assert_eq!(annotations[1].ink, document.pages[0].annotations[1].ink);
+        assert_eq!(annotations[1].rect, Rect::new(8.0, 8.0, 92.0, 42.0));
+        assert_eq!(annotations[2].contents.as_deref(), Some("Typed note"));
+        assert_eq!(annotations[3].interior_color, vec![0.0, 0.0, 1.0]);
+        assert_eq!(annotations[4].icon.as_deref(), Some("Approved"));
+    }
+
+    #[test]
+    fn test_generate_leaves_text_helvetica_cannot_show_to_viewer() {
+        // Arrange
+        let annotation = |kind: &str, contents: Option<&str>, icon: Option<&str>| Annotation {
+            kind: kind.to_string(),
+            rect: Rect::new(300.0, 500.0, 450.0, 540.0),
+            contents: contents.map(str::to_string),
+            icon: icon.map(str::to_string),
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![Page {
+                annotations: vec![
+                    annotation("freetext", Some("日本語のメモ"), None),
+                    annotation("stamp", None, Some("承認")),
+                    annotation("freetext", Some("Déjà vu"), None),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+
+        // Assert
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let annots = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        let has_appearance: Vec<bool> = annots
+            .iter()
+            .map(|a| {
+                let annot = doc.get_dictionary(a.as_reference().unwrap()).unwrap();
+                annot.get(b"AP").is_ok()
+            })
+            .collect();
+        assert_eq!(has_appearance, vec![false, false, true]);
+        let annotations = &parse_bytes(&bytes, None).unwrap().pages[0].annotations;
+        assert_eq!(annotations[0].contents.as_deref(), Some("日本語のメモ"));
+    }
+
+    #[test]
+    fn test_editor_add_annotation_keeps_existing_annotations() {
+        // Arrange
+        let mut bytes = Vec::new();
+        annotated_document().save_to(&mut bytes).unwrap();
+        let mut editor = PdfEditor::load(&bytes, None).unwrap();
+        let strikeout = Annotation {
+            kind: "strikeout".to_string(),
+            contents: Some("Remove".to_string()),
+            quads: vec![Rect::new(70.0, 716.0, 101.0, 732.0)],
+            ..Default::default()
+        };
+
+        // Act
+        editor.add_annotation(0, &strikeout).unwrap();
+        let invalid = editor.add_annotation(0, &Annotation::default());
+        let out_of_range = editor.add_annotation(3, &strikeout);
+
+        // Assert
+        assert!(matches!(invalid, Err(EditError::Annotation(_))));
+        assert!(matches!(out_of_range, Err(EditError::PageIndex { .. })));
+        let page = &parse_bytes(&editor.to_bytes().unwrap(), None)
+            .unwrap()
+            .pages[0];
+        let kinds: Vec<&str> = page.annotations.iter().map(|a| a.kind.as_str()).collect();
+        assert_eq!(kinds, ["text", "highlight", "strikeout"]);
+        assert_eq!(page.annotations[2].text.as_deref(), Some("Hello"));
+        assert_eq!(page.annotations[2].contents.as_deref(), Some("Remove"));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::content::{resource_usage, ContentInterpreter};
+use crate::structure::{Image, PageContent, PathSegment, Rect, Shape, TextBlock};
+use crate::test_utils::PdfBuilder;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Object, Stream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_blocks(contents: Vec<PageContent>) -> Vec<TextBlock> {
+        contents
+            .into_iter()
+            .filter_map(|c| match c {
+                PageContent::Text(tb) => Some(tb),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn run(operations: Vec<Operation>) -> Vec<TextBlock> {
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Courier");
+        let resources = PdfBuilder::font_resources(font_id);
+        let content = Content { operations }.encode().unwrap();
+        let doc = builder.finish();
+        text_blocks(ContentInterpreter::new(&doc).run(&content, Some(&resources)))
+    }
+
+    #[test]
+    fn test_text_matrix_and_ctm_are_applied() {
+        // Arrange
+        let operations = vec![
+            Operation::new(
+                "cm",
+                vec![2.into(), 0.into(), 0.into(), 2.into(), 10.into(), 20.into()],
+            ),
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new(
+                "Tm",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), 5.into(), 6.into()],
+            ),
+            Operation::new("Tj", vec![Object::string_literal("Scaled")]),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].text, "Scaled");
+        assert_eq!((blocks[0].x, blocks[0].y), (20.0, 32.0));
+        assert_eq!(blocks[0].font_size, 20.0);
+    }
+
+    #[test]
+    fn test_consecutive_runs_advance_by_glyph_widths() {
+        // Arrange: Courier glyphs are 600 units wide.
+        let operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("Td", vec![100.into(), 200.into()]),
+            Operation::new("Tj", vec![Object::string_literal("abc")]),
+            Operation::new("Tj", vec![Object::string_literal("def")]),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[1].x, blocks[1].y), (118.0, 200.0));
+    }
+
+    #[test]
+    fn test_tj_array_inserts_space_for_large_gaps() {
+        // Arrange
+        let operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new(
+                "TJ",
+                vec![vec![
+                    Object::string_literal("Hel"),
+                    (-20).into(),
+                    Object::string_literal("lo"),
+                    (-600).into(),
+                    Object::string_literal("World"),
+                ]
+                .into()],
+            ),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].text, "Hello World");
+    }
+
+    #[test]
+    fn test_leading_and_next_line_operators() {
+        // Arrange
+        let operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("TL", vec![14.into()]),
+            Operation::new("Td", vec![50.into(), 500.into()]),
+            Operation::new("Tj", vec![Object::string_literal("one")]),
+            Operation::new("'", vec![Object::string_literal("two")]),
+            Operation::new("T*", vec![]),
+            Operation::new("Tj", vec![Object::string_literal("three")]),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        let positions: Vec<(f32, f32)> = blocks.iter().map(|b| (b.x, b.y)).collect();
+        assert_eq!(positions, vec![(50.0, 500.0), (50.0, 486.0), (50.0, 472.0)]);
+    }
+
+    #[test]
+    fn test_text_style_is_recorded_in_page_space() {
+        // Arrange
+        let operations = vec![
+            Operation::new(
+                "cm",
+                vec![2.into(), 0.into(), 0.into(), 2.into(), 0.into(), 0.into()],
+            ),
+            Operation::new("rg", vec![1.into(), 0.into(), 0.into()]),
+            Operation::new("G", vec![0.5.into()]),
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("Tc", vec![1.into()]),
+            Operation::new("Tw", vec![2.into()]),
+            Operation::new("Tz", vec![50.into()]),
+            Operation::new("Ts", vec![3.into()]),
+            Operation::new("Tr", vec![2.into()]),
+            Operation::new("Td", vec![10.into(), 100.into()]),
+            Operation::new("Tj", vec![Object::string_literal("Styled")]),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        let block = &blocks[0];
+        assert_eq!((block.x, block.y), (20.0, 206.0));
+        assert_eq!(block.font_name.as_deref(), Some("Courier"));
+        assert_eq!(block.base_font.as_deref(), Some("Courier"));
+        assert!(!block.embedded && !block.bold && !block.italic);
+        assert_eq!(block.fill_color, vec![1.0, 0.0, 0.0]);
+        assert_eq!(block.stroke_color, vec![0.5]);
+        assert_eq!(block.char_spacing, 2.0);
+        assert_eq!(block.word_spacing, 4.0);
+        assert_eq!(block.horizontal_scaling, 50.0);
+        assert_eq!(block.rise, 6.0);
+        assert_eq!(block.render_mode, 2);
+    }
+
+    #[test]
+    fn test_text_inside_form_xobject() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let form_content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 8.into()]),
+                Operation::new("Tj", vec![Object::string_literal("In form")]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let mut form_dict = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Form".to_vec())),
+            (
+                "Matrix",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), 30.into(), 40.into()].into(),
+            ),
+        ]);
+        form_dict.set("Resources", PdfBuilder::font_resources(font_id));
+        let form_id = builder
+            .doc
+            .add_object(Stream::new(form_dict, form_content.encode().unwrap()));
+        let resources = Dictionary::from_iter(vec![(
+            "XObject",
+            Dictionary::from_iter(vec![("Fm1", form_id.into())]).into(),
+        )]);
+        let content = Content {
+            operations: vec![Operation::new("Do", vec!["Fm1".into()])],
+        }
+        .encode()
+        .unwrap();
+        let doc = builder.finish();
+
+        // Act
+        let blocks = text_blocks(ContentInterpreter::new(&doc).run(&content, Some(&resources)));
+
+        // Assert
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].text, "In form");
+        assert_eq!((blocks[0].x, blocks[0].y), (30.0, 40.0));
+    }
+
+    #[test]
+    fn test_text_without_font_is_ignored() {
+        // Arrange
+        let operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tj", vec![Object::string_literal("orphan")]),
+            Operation::new("ET", vec![]),
+        ];
+
+        // Act
+        let blocks = run(operations);
+
+        // Assert
+        assert!(blocks.is_empty());
+    }
+
+    fn images(contents: Vec<PageContent>) -> Vec<Image> {
+        contents
+            .into_iter()
+            .filter_map(|c| match c {
+                PageContent::Image(image) => Some(image),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_image_xobject_placement_from_ctm() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let image_id = builder.doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"XObject".to_vec())),
+                ("Subtype", Object::Name(b"Image".to_vec())),
+                ("Width", 1.into()),
+                ("Height", 1.into()),
+                ("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+                ("BitsPerComponent", 8.into()),
+            ]),
+            vec![128],
+        ));
+        let resources = Dictionary::from_iter(vec![(
+            "XObject",
+            Dictionary::from_iter(vec![("Im1", image_id.into())]).into(),
+        )]);
+        let content = Content {
+            operations: vec![
+                Operation::new("q", vec![]),
+                Operation::new(
+                    "cm",
+                    vec![
+                        200.into(),
+                        0.into(),
+                        0.into(),
+                        100.into(),
+                        50.into(),
+                        60.into(),
+                    ],
+                ),
+                Operation::new("Do", vec!["Im1".into()]),
+                Operation::new("Q", vec![]),
+                // Flipped vertically: the rectangle is still reported bottom-up.
+                Operation::new(
+                    "cm",
+                    vec![
+                        10.into(),
+                        0.into(),
+                        0.into(),
+                        (-20).into(),
+                        0.into(),
+                        20.into(),
+                    ],
+                ),
+                Operation::new("Do", vec!["Im1".into()]),
+            ],
+        }
+        .encode()
+        .unwrap();
+        let doc = builder.finish();
+
+        // Act
+        let images = images(ContentInterpreter::new(&doc).run(&content, Some(&resources)));
+
+        // Assert
+        assert_eq!(images.len(), 2);
+        let rect = |i: &Image| (i.x, i.y, i.width, i.height);
+        assert_eq!(rect(&images[0]), (50.0, 60.0, 200.0, 100.0));
+        assert_eq!(rect(&images[1]), (0.0, 0.0, 10.0, 20.0));
+        assert_eq!(images[0].format, "png");
+    }
+
+    #[test]
+    fn test_inline_image_does_not_hide_text() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let resources = PdfBuilder::font_resources(font_id);
+        let doc = builder.finish();
+        let content = b"q 30 0 0 10 5 5 cm BI /W 2 /H 1 /CS /G /BPC 8 /F /AHx ID 00FF> EI Q
+BT /F1 12 Tf 1 2 Td (After) Tj ET";
+
+        // Act
+        let contents = ContentInterpreter::new(&doc).run(content, Some(&resources));
+
+        // Assert
+        assert!(matches!(contents[0], PageContent::Image(_)));
+        let images = images(contents.clone());
+        assert_eq!(
+            (images[0].x, images[0].y, images[0].width, images[0].height),
+            (5.0, 5.0, 30.0, 10.0)
+        );
+        let blocks = text_blocks(contents);
+        assert_eq!(blocks[0].text, "After");
+    }
+
+    #[test]
+    fn test_names_spelling_inline_image_keywords_are_not_inline_images() {
+        // Arrange: a marked-content tag named `/BI` before an inline image.
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let resources = PdfBuilder::font_resources(font_id);
+        let doc = builder.finish();
+        let content = b"/BI BMC BT /F1 12 Tf 1 2 Td (Named) Tj ET EMC
+q 30 0 0 10 5 5 cm BI /W 2 /H 1 /CS /G /BPC 8 /F /AHx ID 00FF> EI Q";
+
+        // Act
+        let contents = ContentInterpreter::new(&doc).run(content, Some(&resources));
+
+        // Assert
+        let blocks = text_blocks(contents.clone());
+        assert_eq!(blocks[0].text, "Named");
+        assert_eq!(images(contents).len(), 1);
+    }
+
+    fn shapes(content: &[u8], resources: Dictionary) -> Vec<Shape> {
+        let doc = PdfBuilder::new().finish();
+        ContentInterpreter::new(&doc)
+            .run(content, Some(&resources))
+            .into_iter()
+            .filter_map(|c| match c {
+                PageContent::Shape(shape) => Some(shape),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paths_are_recorded_with_style_in_page_space() {
+        // Arrange: a scaled, dashed rule, a filled box in an ICC based
+        // colour space, a curve, and a clipping path that paints nothing.
+        let content = b"q 2 0 0 2 10 10 cm 0.5 w 1 J 2 j [3 1] 0 d 1 0 0 RG \
+            0 0 m 50 0 l S Q \
+            /CS0 cs 0.2 0.4 0.6 sc 10 20 30 40 re f* \
+            0 0 1 0 k 0 0 m 10 10 20 10 v b \
+            0 0 100 100 re W n";
+        let mut builder = PdfBuilder::new();
+        let profile_id = builder.doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![("N", 3.into())]),
+            vec![],
+        ));
+        let doc = builder.finish();
+        let resources = Dictionary::from_iter(vec![(
+            "ColorSpace",
+            Dictionary::from_iter(vec![(
+                "CS0",
+                vec![Object::Name(b"ICCBased".to_vec()), profile_id.into()].into(),
+            )])
+            .into(),
+        )]);
+
+        // Act
+        let shapes: Vec<Shape> = ContentInterpreter::new(&doc)
+            .run(content, Some(&resources))
+            .into_iter()
+            .filter_map(|c| match c {
+                PageContent::Shape(shape) => Some(shape),
+                _ => None,
+            })
+            .collect();
+
+        // Assert
+        assert_eq!(shapes.len(), 3);
+        let rule = &shapes[0];
+        assert_eq!(
+            rule.segments,
+            vec![
+                PathSegment::move_to(10.0, 10.0),
+                PathSegment::line_to(110.0, 10.0)
+            ]
+        );
+        assert_eq!(rule.stroke_color, Some(vec![1.0, 0.0, 0.0]));
+        assert_eq!(rule.fill_color, None);
+        assert_eq!(rule.line_width, 1.0);
+        assert_eq!((rule.dash.clone(), rule.dash_phase), (vec![6.0, 2.0], 0.0));
+        assert_eq!(
+            (rule.line_cap.as_str(), rule.line_join.as_str()),
+            ("round", "bevel")
+        );
+        let area = &shapes[1];
+        assert_eq!(area.segments.len(), 5);
+        assert_eq!(area.bounds(), Rect::new(10.0, 20.0, 40.0, 60.0));
+        assert_eq!(area.fill_color, Some(vec![0.2, 0.4, 0.6]));
+        assert_eq!(area.stroke_color, None);
+        assert!(area.even_odd);
+        assert_eq!(area.line_width, 1.0);
+        let curve = &shapes[2];
+        assert_eq!(
+            curve.segments[1],
+            PathSegment::curve_to((0.0, 0.0), (10.0, 10.0), (20.0, 10.0))
+        );
+        assert_eq!(curve.segments[2], PathSegment::close());
+        assert_eq!(curve.fill_color, Some(vec![0.0, 0.0, 1.0, 0.0]));
+        assert_eq!(curve.stroke_color, Some(vec![0.0]));
+    }
+
+    #[test]
+    fn test_pattern_and_separation_colours_are_ignored() {
+        // Arrange
+        let content = b"0.5 g /P0 scn /Pattern cs /P0 scn 0 0 10 10 re f \
+            /Sep cs 0.7 sc 0 0 10 10 re f";
+        let resources = Dictionary::from_iter(vec![(
+            "ColorSpace",
+            Dictionary::from_iter(vec![(
+                "Sep",
+                vec![Object::Name(b"Separation".to_vec())].into(),
+            )])
+            .into(),
+        )]);
+
+        // Act
+        let shapes = shapes(content, resources);
+
+        // Assert
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].fill_color, Some(vec![0.5]));
+        assert_eq!(shapes[1].fill_color, Some(vec![0.5]));
+    }
+
+    #[test]
+    fn test_resource_usage_collects_names_by_category() {
+        // Arrange
+        let content = b"/GS0 gs /CS0 cs /P0 scn q /Im1 Do Q \
+            BI /W 1 /H 1 /CS /CS1 /BPC 8 ID \x00 EI \
+            BT /F1 12 Tf (Hi) Tj ET";
+
+        // Act
+        let usage = resource_usage(content).unwrap();
+        let blank = resource_usage(b"q 1 0 0 1 0 0 cm /F1 12 Tf Q").unwrap();
+
+        // Assert
+        let names =
+            |category: &[u8]| -> Vec<Vec<u8>> { usage.names[category].iter().cloned().collect() };
+        assert_eq!(names(b"ExtGState"), vec![b"GS0".to_vec()]);
+        assert_eq!(names(b"ColorSpace"), vec![b"CS0".to_vec(), b"CS1".to_vec()]);
+        assert_eq!(names(b"Pattern"), vec![b"P0".to_vec()]);
+        assert_eq!(names(b"XObject"), vec![b"Im1".to_vec()]);
+        assert_eq!(names(b"Font"), vec![b"F1".to_vec()]);
+        assert!(usage.paints);
+        assert!(!blank.paints);
+    }
+}
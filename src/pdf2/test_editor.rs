@@ -0,0 +1,206 @@
+use crate::editor::{EditError, PdfEditor};
+use crate::parser::parse_bytes;
+use crate::test_utils::{page_texts, text_document};
+use lopdf::{Dictionary, Object};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_rotates_pages_with_inherited_rotation() {
+        // Arrange
+        let (doc, _) = text_document(&["A", "B"], vec![("Rotate", 90.into())]);
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.rotate(&[0], -90).unwrap();
+        editor.rotate(&[1, 1], 270).unwrap();
+
+        // Assert
+        assert_eq!(editor.rotation(0).unwrap(), 0);
+        assert_eq!(editor.rotation(1).unwrap(), 0);
+        assert!(matches!(
+            editor.rotate(&[0], 45),
+            Err(EditError::Rotation(45))
+        ));
+        assert!(matches!(
+            editor.rotate(&[2], 90),
+            Err(EditError::PageIndex { index: 2, count: 2 })
+        ));
+        let parsed = parse_bytes(&editor.to_bytes().unwrap(), None).unwrap();
+        assert_eq!(parsed.pages[0].rotation, 0);
+    }
+
+    #[test]
+    fn test_editor_rotates_by_extreme_angles_without_overflow() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["A", "B"], vec![("Rotate", 270.into())]);
+        doc.get_dictionary_mut(pages[1])
+            .unwrap()
+            .set("Rotate", Object::Integer(i64::MAX - 7));
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.rotate(&[0], 2_147_483_610).unwrap();
+        editor.rotate(&[1], -2_147_483_610).unwrap();
+
+        // Assert
+        assert_eq!(editor.rotation(0).unwrap(), 0);
+        assert_eq!(editor.rotation(1).unwrap(), 270);
+    }
+
+    #[test]
+    fn test_editor_reorders_deletes_inserts_and_duplicates() {
+        // Arrange
+        let (doc, _) = text_document(&["A", "B", "C"], vec![]);
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.reorder(&[2, 0, 1]).unwrap();
+        editor.delete(&[1]).unwrap();
+        editor.insert_blank(1, 200.0, 300.0).unwrap();
+        editor.duplicate(0, None).unwrap();
+        editor.duplicate(3, Some(4)).unwrap();
+
+        // Assert
+        assert_eq!(editor.page_count(), 5);
+        assert_eq!(
+            page_texts(&editor.to_bytes().unwrap()),
+            vec!["C", "C", "", "B", "B"]
+        );
+        let parsed = parse_bytes(&editor.to_bytes().unwrap(), None).unwrap();
+        assert_eq!(
+            (parsed.pages[2].width, parsed.pages[2].height),
+            (200.0, 300.0)
+        );
+        assert!(matches!(
+            editor.reorder(&[0, 0, 1, 2, 3]),
+            Err(EditError::Order(_))
+        ));
+        assert!(matches!(editor.reorder(&[0, 1]), Err(EditError::Order(_))));
+        assert!(matches!(
+            editor.insert_blank(0, 0.0, 100.0),
+            Err(EditError::PageSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_editor_delete_drops_references_and_unused_objects() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["A", "B"], vec![]);
+        let outlines_id = doc.new_object_id();
+        let item = doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("B")),
+            ("Parent", outlines_id.into()),
+            ("Dest", Object::Array(vec![pages[1].into(), "Fit".into()])),
+        ]));
+        doc.objects.insert(
+            outlines_id,
+            Dictionary::from_iter(vec![("First", item.into()), ("Last", item.into())]).into(),
+        );
+        doc.catalog_mut().unwrap().set("Outlines", outlines_id);
+        let content_id = doc
+            .get_dictionary(pages[1])
+            .unwrap()
+            .get(b"Contents")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.delete(&[1]).unwrap();
+        let doc = editor.into_document();
+
+        // Assert
+        assert_eq!(doc.get_pages().len(), 1);
+        assert!(!doc.objects.contains_key(&pages[1]));
+        assert!(!doc.objects.contains_key(&content_id));
+        let dest = doc.get_dictionary(item).unwrap().get(b"Dest").unwrap();
+        assert_eq!(dest.as_array().unwrap()[0], Object::Null);
+    }
+
+    #[test]
+    fn test_editor_duplicate_copies_annotations_except_widgets() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["A"], vec![]);
+        let annotation = |subtype: &str| {
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Annot".to_vec())),
+                ("Subtype", Object::Name(subtype.as_bytes().to_vec())),
+                ("P", pages[0].into()),
+            ])
+        };
+        let link = doc.add_object(annotation("Link"));
+        let widget = doc.add_object(annotation("Widget"));
+        doc.get_dictionary_mut(pages[0])
+            .unwrap()
+            .set("Annots", vec![link.into(), widget.into()]);
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.duplicate(0, None).unwrap();
+        let doc = editor.into_document();
+
+        // Assert
+        let copy = doc.get_pages()[&2];
+        let annotations = doc
+            .get_dictionary(copy)
+            .unwrap()
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(annotations.len(), 1);
+        let copied = doc
+            .get_dictionary(annotations[0].as_reference().unwrap())
+            .unwrap();
+        assert_ne!(annotations[0].as_reference().unwrap(), link);
+        assert_eq!(copied.get(b"Subtype").unwrap().as_name().unwrap(), b"Link");
+        assert_eq!(copied.get(b"P").unwrap().as_reference().unwrap(), copy);
+    }
+
+    #[test]
+    fn test_editor_flattens_nested_page_tree() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["A", "B", "C"], vec![]);
+        let root = doc
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let node = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Parent", root.into()),
+            ("Kids", vec![pages[1].into(), pages[2].into()].into()),
+            ("Count", 2.into()),
+            (
+                "MediaBox",
+                vec![0.into(), 0.into(), 100.into(), 100.into()].into(),
+            ),
+        ]));
+        for &page in &pages[1..] {
+            let page = doc.get_dictionary_mut(page).unwrap();
+            page.set("Parent", node);
+            page.remove(b"MediaBox");
+        }
+        doc.get_dictionary_mut(root)
+            .unwrap()
+            .set("Kids", vec![pages[0].into(), node.into()]);
+        let mut editor = PdfEditor::from_document(doc).unwrap();
+
+        // Act
+        editor.reorder(&[2, 1, 0]).unwrap();
+
+        // Assert
+        let parsed = parse_bytes(&editor.to_bytes().unwrap(), None).unwrap();
+        let widths: Vec<f32> = parsed.pages.iter().map(|page| page.width).collect();
+        assert_eq!(widths, vec![100.0, 100.0, 595.0]);
+        assert_eq!(page_texts(&editor.to_bytes().unwrap()), vec!["C", "B", "A"]);
+        let doc = editor.into_document();
+        assert!(!doc.objects.contains_key(&node));
+    }
+}
@@ -0,0 +1,41 @@
+use crate::encoding::{glyph_name_to_string, standard_font_width, BaseEncoding};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyph_names() {
+        // Assert
+        assert_eq!(glyph_name_to_string("A").as_deref(), Some("A"));
+        assert_eq!(glyph_name_to_string("comma").as_deref(), Some(","));
+        assert_eq!(glyph_name_to_string("Aacute").as_deref(), Some("Á"));
+        assert_eq!(glyph_name_to_string("uni30423044").as_deref(), Some("あい"));
+        assert_eq!(glyph_name_to_string("u1F600").as_deref(), Some("😀"));
+        assert_eq!(glyph_name_to_string("a.sc").as_deref(), Some("a"));
+        assert_eq!(glyph_name_to_string("g123"), None);
+    }
+
+    #[test]
+    fn test_base_encodings() {
+        // Assert
+        assert_eq!(BaseEncoding::WinAnsi.decode(0x80), Some('€'));
+        assert_eq!(BaseEncoding::WinAnsi.decode(0x81), None);
+        assert_eq!(BaseEncoding::MacRoman.decode(0x80), Some('Ä'));
+        assert_eq!(BaseEncoding::MacRoman.decode(0xFF), Some('ˇ'));
+        assert_eq!(BaseEncoding::Standard.decode(0x27), Some('’'));
+        assert_eq!(BaseEncoding::Standard.decode(0xE1), Some('Æ'));
+        assert_eq!(BaseEncoding::WinAnsi.encode('€'), Some(0x80));
+        assert_eq!(BaseEncoding::WinAnsi.encode('-'), Some(b'-'));
+        assert_eq!(BaseEncoding::WinAnsi.encode('あ'), None);
+    }
+
+    #[test]
+    fn test_standard_font_widths() {
+        // Assert
+        assert_eq!(standard_font_width("Courier-Bold", b'i'), Some(600.0));
+        assert_eq!(standard_font_width("Helvetica", b'W'), Some(944.0));
+        assert_eq!(standard_font_width("ABCDEF+Helvetica", b' '), Some(278.0));
+        assert_eq!(standard_font_width("Symbol", b'a'), None);
+    }
+}
@@ -1,10 +1,21 @@
-use super::encryption::encrypt_pdf;
+use super::encryption::{
+    decrypt_pdf, encrypt_bytes, encrypt_pdf, load_document, DecryptError, DocumentPermissions,
+    EncryptError, EncryptOptions, EncryptionAlgorithm,
+};
+use super::parser::{parse_bytes, parse_pdf_with_password, ParseError};
+use super::test_utils::TempPath;
+use lopdf::encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter};
+use lopdf::encryption::encrypt_object;
 use lopdf::{
     content::{Content, Operation},
-    Dictionary, Document, Object, Stream,
+    Dictionary, Document, EncryptionState, EncryptionVersion, Object, Permissions, Stream,
 };
 use rand::random;
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 struct TestFixture {
     input_path: String,
@@ -13,8 +24,11 @@ struct TestFixture {
 
 impl TestFixture {
     fn new() -> Self {
-        let input_path = "test_input.pdf".to_string();
-        let output_path = "test_output.pdf".to_string();
+        // Tests run in parallel, so each fixture gets its own files.
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        let input_path = format!("test_input_{}_{}.pdf", std::process::id(), id);
+        let output_path = format!("test_output_{}_{}.pdf", std::process::id(), id);
         TestFixture {
             input_path,
             output_path,
@@ -95,10 +109,13 @@ fn test_encrypt_pdf_with_correct_password() {
 
     // Act
     let result = encrypt_pdf(
-        fixture.input_path.clone(),
-        fixture.output_path.clone(),
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
         user_password.clone(),
         None,
+        None,
+        "aes-128",
+        true,
     );
 
     // Assert
@@ -119,10 +136,13 @@ fn test_encrypt_pdf_with_incorrect_password() {
 
     // Act
     let result = encrypt_pdf(
-        fixture.input_path.clone(),
-        fixture.output_path.clone(),
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
         user_password.clone(),
         None,
+        None,
+        "aes-128",
+        true,
     );
 
     // Assert
@@ -132,3 +152,416 @@ fn test_encrypt_pdf_with_incorrect_password() {
     assert!(doc.is_encrypted());
     assert!(doc.decrypt(&wrong_password).is_err());
 }
+
+/// The fixture's PDF encrypted in memory with the given handler.
+fn encrypted_bytes(fixture: &TestFixture, handler: &str, user: &str, owner: &str) -> Vec<u8> {
+    fixture.create_dummy_pdf();
+    let mut doc = Document::load(&fixture.input_path).unwrap();
+    let filters = |filter: Arc<dyn CryptFilter>| BTreeMap::from([(b"StdCF".to_vec(), filter)]);
+    let key: [u8; 32] = random();
+    let version = match handler {
+        "rc4-40" => EncryptionVersion::V1 {
+            document: &doc,
+            owner_password: owner,
+            user_password: user,
+            permissions: Permissions::all(),
+        },
+        "rc4-128" => EncryptionVersion::V2 {
+            document: &doc,
+            owner_password: owner,
+            user_password: user,
+            key_length: 128,
+            permissions: Permissions::all(),
+        },
+        "aes-128" => EncryptionVersion::V4 {
+            document: &doc,
+            encrypt_metadata: true,
+            crypt_filters: filters(Arc::new(Aes128CryptFilter)),
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: owner,
+            user_password: user,
+            permissions: Permissions::all(),
+        },
+        _ => EncryptionVersion::V5 {
+            encrypt_metadata: true,
+            crypt_filters: filters(Arc::new(Aes256CryptFilter)),
+            file_encryption_key: &key,
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: owner,
+            user_password: user,
+            permissions: Permissions::all(),
+        },
+    };
+    let state = EncryptionState::try_from(version).unwrap();
+    doc.encrypt(&state).unwrap();
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+/// The fixture's PDF encrypted with RC4 and saved with its dictionaries,
+/// including the page tree, in an object stream.
+fn object_stream_bytes(fixture: &TestFixture, user: &str, owner: &str) -> Vec<u8> {
+    fixture.create_dummy_pdf();
+    let mut doc = Document::load(&fixture.input_path).unwrap();
+    let state = EncryptionState::try_from(EncryptionVersion::V2 {
+        document: &doc,
+        owner_password: owner,
+        user_password: user,
+        key_length: 128,
+        permissions: Permissions::all(),
+    })
+    .unwrap();
+    // The objects that go into the object stream have no strings, so only
+    // the streams stored directly need encrypting.
+    for (&id, object) in doc.objects.iter_mut() {
+        if let Object::Stream(_) = object {
+            encrypt_object(&state, id, object).unwrap();
+        }
+    }
+    let encrypt_id = doc.add_object(state.encode().unwrap());
+    doc.trailer.set("Encrypt", encrypt_id);
+    let mut bytes = Vec::new();
+    doc.save_modern(&mut bytes).unwrap();
+
+    // lopdf writes the object stream in the clear. RC4 keeps its length, so
+    // it can be encrypted in place.
+    let find = |needle: &[u8], from: usize| {
+        from + bytes[from..]
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .unwrap()
+    };
+    let marker = find(b"/ObjStm", 0);
+    let header = bytes[..marker]
+        .windows(4)
+        .rposition(|w| w == b" obj")
+        .unwrap();
+    let line = bytes[..header].iter().rposition(|&b| b == b'\n').unwrap() + 1;
+    let number: u32 = std::str::from_utf8(&bytes[line..header])
+        .unwrap()
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let start = find(b"stream\n", marker) + b"stream\n".len();
+    let end = find(b"\nendstream", start);
+    let mut stream = Object::Stream(Stream::new(Dictionary::new(), bytes[start..end].to_vec()));
+    encrypt_object(&state, (number, 0), &mut stream).unwrap();
+    bytes[start..end].copy_from_slice(&stream.as_stream().unwrap().content);
+    bytes
+}
+
+fn first_page_content(doc: &Document) -> Vec<u8> {
+    let page_id = *doc.get_pages().values().next().unwrap();
+    doc.get_page_content(page_id).unwrap()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn test_load_document_decrypts_each_handler() {
+    for handler in ["rc4-40", "rc4-128", "aes-128", "aes-256"] {
+        // Arrange
+        let fixture = TestFixture::new();
+        let bytes = encrypted_bytes(&fixture, handler, "user", "owner");
+
+        // Act
+        let with_user = load_document(&bytes, Some("user"));
+        let with_owner = load_document(&bytes, Some("owner"));
+
+        // Assert
+        for doc in [with_user, with_owner] {
+            let doc = doc.unwrap_or_else(|e| panic!("{}: {}", handler, e));
+            assert!(!doc.is_encrypted(), "{}", handler);
+            assert!(
+                contains(&first_page_content(&doc), b"Hello World!"),
+                "{}",
+                handler
+            );
+        }
+    }
+}
+
+#[test]
+fn test_load_document_rejects_wrong_or_missing_password() {
+    // Arrange
+    let fixture = TestFixture::new();
+    let bytes = encrypted_bytes(&fixture, "aes-128", "user", "owner");
+
+    // Act
+    let wrong = load_document(&bytes, Some("guess"));
+    let missing = load_document(&bytes, None);
+
+    // Assert
+    assert!(matches!(wrong, Err(DecryptError::Password)));
+    assert!(matches!(missing, Err(DecryptError::Password)));
+}
+
+#[test]
+fn test_load_document_opens_empty_user_password_without_password() {
+    // Arrange
+    let fixture = TestFixture::new();
+    let bytes = encrypted_bytes(&fixture, "rc4-128", "", "owner");
+
+    // Act
+    let doc = load_document(&bytes, None).unwrap();
+
+    // Assert
+    assert!(contains(&first_page_content(&doc), b"Hello World!"));
+}
+
+#[test]
+fn test_load_document_unpacks_encrypted_object_streams() {
+    for user in ["user", ""] {
+        // Arrange
+        let fixture = TestFixture::new();
+        let bytes = object_stream_bytes(&fixture, user, "owner");
+        let password = Some(user).filter(|p| !p.is_empty());
+
+        // Act
+        let doc = load_document(&bytes, password).unwrap();
+        let with_owner = load_document(&bytes, Some("owner")).unwrap();
+        let parsed = parse_bytes(&bytes, password).unwrap();
+
+        // Assert
+        for doc in [doc, with_owner] {
+            assert!(!doc.is_encrypted());
+            assert_eq!(doc.get_pages().len(), 1);
+            assert!(contains(&first_page_content(&doc), b"Hello World!"));
+        }
+        assert_eq!(parsed.pages.len(), 1);
+        assert_eq!(parsed.pages[0].text_blocks()[0].text, "Hello World!");
+    }
+}
+
+#[test]
+fn test_parse_and_decrypt_encrypted_file() {
+    // Arrange
+    let fixture = TestFixture::new();
+    let encrypted = TempPath::new("encrypted_input.pdf");
+    let decrypted = TempPath::new("decrypted_output.pdf");
+    fs::write(
+        encrypted.path(),
+        encrypted_bytes(&fixture, "aes-256", "user", "owner"),
+    )
+    .unwrap();
+
+    // Act
+    let parsed = parse_pdf_with_password(encrypted.path(), Some("user")).unwrap();
+    let without_password = parse_pdf_with_password(encrypted.path(), None);
+    let result = decrypt_pdf(
+        encrypted.0.clone().into(),
+        decrypted.0.clone().into(),
+        "owner".to_string(),
+    );
+
+    // Assert
+    assert_eq!(parsed.pages[0].text_blocks()[0].text, "Hello World!");
+    assert!(matches!(without_password, Err(ParseError::Password)));
+    assert!(result.is_ok());
+    let doc = Document::load(Path::new(&decrypted.0)).unwrap();
+    assert!(!doc.is_encrypted());
+    assert!(contains(&first_page_content(&doc), b"Hello World!"));
+}
+
+#[test]
+fn test_encrypt_pdf_writes_permissions_to_p_entry() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+    let permissions = DocumentPermissions {
+        print: true,
+        print_high_quality: false,
+        copy: false,
+        ..DocumentPermissions::none()
+    };
+
+    // Act
+    let result = encrypt_pdf(
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
+        "user".to_string(),
+        Some("owner".to_string()),
+        Some(permissions),
+        "aes-128",
+        true,
+    );
+
+    // Assert
+    assert!(result.is_ok());
+    let doc = Document::load(&fixture.output_path).unwrap();
+    let p = doc
+        .get_encrypted()
+        .unwrap()
+        .get(b"P")
+        .unwrap()
+        .as_i64()
+        .unwrap() as i32;
+    // Bit 3 (print) is set; bits 4-6 and 9-12 are clear; reserved bits are 1.
+    assert_eq!(p, 0xFFFF_F0C4u32 as i32);
+}
+
+#[test]
+fn test_encrypt_pdf_validates_permissions() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+    let high_quality_only = DocumentPermissions {
+        print: false,
+        ..DocumentPermissions::all()
+    };
+    let restricted = DocumentPermissions {
+        copy: false,
+        ..DocumentPermissions::all()
+    };
+    let encrypt = |owner: Option<&str>, permissions| {
+        encrypt_pdf(
+            fixture.input_path.clone().into(),
+            fixture.output_path.clone().into(),
+            "user".to_string(),
+            owner.map(str::to_string),
+            Some(permissions),
+            "aes-128",
+            true,
+        )
+    };
+
+    // Act & Assert
+    assert!(high_quality_only.validate().is_err());
+    assert!(encrypt(Some("owner"), high_quality_only).is_err());
+    assert!(encrypt(None, restricted).is_err());
+    assert!(encrypt(Some("user"), restricted).is_err());
+    assert!(encrypt(Some("owner"), restricted).is_ok());
+    assert!(encrypt(None, DocumentPermissions::all()).is_ok());
+}
+
+#[test]
+fn test_encrypt_pdf_with_aes_256() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+
+    // Act
+    let result = encrypt_pdf(
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
+        "user".to_string(),
+        Some("owner".to_string()),
+        None,
+        "aes-256",
+        true,
+    );
+
+    // Assert
+    assert!(result.is_ok());
+    let bytes = fs::read(&fixture.output_path).unwrap();
+    assert!(bytes.starts_with(b"%PDF-2.0"));
+    let encrypted = Document::load_mem(&bytes).unwrap();
+    let dict = encrypted.get_encrypted().unwrap();
+    assert_eq!(dict.get(b"V").unwrap().as_i64().unwrap(), 5);
+    assert_eq!(dict.get(b"R").unwrap().as_i64().unwrap(), 6);
+    for password in ["user", "owner"] {
+        let doc = load_document(&bytes, Some(password)).unwrap();
+        assert!(contains(&first_page_content(&doc), b"Hello World!"));
+    }
+}
+
+#[test]
+fn test_encrypt_pdf_can_leave_metadata_unencrypted() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+    let mut doc = Document::load(&fixture.input_path).unwrap();
+    let xmp = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'>plain metadata</x:xmpmeta>".to_vec();
+    let metadata_id = doc.add_object(
+        Stream::new(
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Metadata".to_vec())),
+                ("Subtype", Object::Name(b"XML".to_vec())),
+            ]),
+            xmp.clone(),
+        )
+        .with_compression(false),
+    );
+    doc.catalog_mut().unwrap().set("Metadata", metadata_id);
+    doc.save(&fixture.input_path).unwrap();
+
+    // Act
+    let result = encrypt_pdf(
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
+        "user".to_string(),
+        None,
+        None,
+        "aes-256",
+        false,
+    );
+
+    // Assert
+    assert!(result.is_ok());
+    let bytes = fs::read(&fixture.output_path).unwrap();
+    assert!(contains(&bytes, &xmp));
+    let encrypted = Document::load_mem(&bytes).unwrap();
+    let dict = encrypted.get_encrypted().unwrap();
+    assert!(!dict.get(b"EncryptMetadata").unwrap().as_bool().unwrap());
+    let doc = load_document(&bytes, Some("user")).unwrap();
+    assert!(contains(&first_page_content(&doc), b"Hello World!"));
+}
+
+#[test]
+fn test_encrypt_pdf_rejects_unknown_algorithm() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+
+    // Act
+    let result = encrypt_pdf(
+        fixture.input_path.clone().into(),
+        fixture.output_path.clone().into(),
+        "user".to_string(),
+        None,
+        None,
+        "rc4-40",
+        true,
+    );
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_encrypt_bytes_in_memory() {
+    // Arrange
+    let fixture = TestFixture::new();
+    fixture.create_dummy_pdf();
+    let data = fs::read(&fixture.input_path).unwrap();
+    let options = EncryptOptions {
+        user_password: "user",
+        owner_password: Some("owner"),
+        permissions: DocumentPermissions::none(),
+        algorithm: EncryptionAlgorithm::Aes256,
+        encrypt_metadata: true,
+    };
+
+    // Act
+    let encrypted = encrypt_bytes(&data, &options).unwrap();
+    let same_passwords = encrypt_bytes(
+        &data,
+        &EncryptOptions {
+            owner_password: None,
+            ..options.clone()
+        },
+    );
+
+    // Assert
+    assert!(Document::load_mem(&encrypted).unwrap().is_encrypted());
+    let doc = load_document(&encrypted, Some("owner")).unwrap();
+    assert!(contains(&first_page_content(&doc), b"Hello World!"));
+    assert!(matches!(same_passwords, Err(EncryptError::Permissions(_))));
+}
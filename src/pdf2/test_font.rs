@@ -0,0 +1,305 @@
+use crate::font::{PdfFont, ToUnicode};
+use lopdf::{Dictionary, Document, Object, Stream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMAP: &[u8] = b"/CIDInit /ProcSet findresource begin
+begincmap
+1 begincodespacerange
+<0000> <FFFF>
+endcodespacerange
+2 beginbfchar
+<0003> <0020>
+<0011> <3042>
+endbfchar
+1 beginbfrange
+<0024> <0026> <0041>
+endbfrange
+endcmap";
+
+    #[test]
+    fn test_to_unicode_bfchar_and_bfrange() {
+        // Act
+        let cmap = ToUnicode::parse(CMAP);
+
+        // Assert
+        assert_eq!(cmap.get(2, 0x0003), Some(" "));
+        assert_eq!(cmap.get(2, 0x0011), Some("あ"));
+        assert_eq!(cmap.get(2, 0x0025), Some("B"));
+        assert_eq!(cmap.get(2, 0x0027), None);
+    }
+
+    #[test]
+    fn test_simple_font_with_differences() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"ABCDEF+Custom".to_vec())),
+            (
+                "Encoding",
+                Dictionary::from_iter(vec![(
+                    "Differences",
+                    vec![
+                        1.into(),
+                        Object::Name(b"eacute".to_vec()),
+                        Object::Name(b"uni3042".to_vec()),
+                    ]
+                    .into(),
+                )])
+                .into(),
+            ),
+            ("FirstChar", 1.into()),
+            ("Widths", vec![500.into(), 1000.into()].into()),
+        ]);
+
+        // Act
+        let font = PdfFont::from_dict(&doc, &font);
+        let glyphs = font.decode(&[1, 2, b'A']);
+
+        // Assert
+        let text: String = glyphs.iter().map(|g| g.text.as_str()).collect();
+        assert_eq!(text, "éあA");
+        assert_eq!(glyphs[0].width, 0.5);
+        assert_eq!(glyphs[1].width, 1.0);
+    }
+
+    #[test]
+    fn test_win_ansi_font_decodes_upper_range() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"TrueType".to_vec())),
+            ("BaseFont", Object::Name(b"Arial".to_vec())),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]);
+
+        // Act
+        let font = PdfFont::from_dict(&doc, &font);
+        let glyphs = font.decode(&[0x80, 0x93, 0xE9, b' ']);
+
+        // Assert
+        let text: String = glyphs.iter().map(|g| g.text.as_str()).collect();
+        assert_eq!(text, "€“é ");
+        assert!(glyphs[3].is_space);
+    }
+
+    #[test]
+    fn test_composite_font_uses_to_unicode_and_cid_widths() {
+        // Arrange
+        let mut doc = Document::with_version("1.5");
+        let to_unicode = doc.add_object(Stream::new(Dictionary::new(), CMAP.to_vec()));
+        let descendant = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType2".to_vec())),
+            ("DW", 1000.into()),
+            (
+                "W",
+                vec![
+                    3.into(),
+                    vec![250.into()].into(),
+                    0x24.into(),
+                    0x26.into(),
+                    600.into(),
+                ]
+                .into(),
+            ),
+        ]));
+        let font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("BaseFont", Object::Name(b"NotoSansJP".to_vec())),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("DescendantFonts", vec![descendant.into()].into()),
+            ("ToUnicode", to_unicode.into()),
+        ]);
+
+        // Act
+        let font = PdfFont::from_dict(&doc, &font);
+        let glyphs = font.decode(&[0x00, 0x24, 0x00, 0x03, 0x00, 0x11]);
+
+        // Assert
+        let text: String = glyphs.iter().map(|g| g.text.as_str()).collect();
+        assert_eq!(text, "A あ");
+        let widths: Vec<f32> = glyphs.iter().map(|g| g.width).collect();
+        assert_eq!(widths, vec![0.6, 0.25, 1.0]);
+        assert!(
+            !glyphs[1].is_space,
+            "word spacing never applies to 2-byte codes"
+        );
+    }
+
+    /// A Type0 font with `encoding` and a descendant whose `/W` gives CID
+    /// 0x41 a width of 300, CIDs 231-233 500 and CID 842 800.
+    fn cid_font(doc: &mut Document, encoding: Object) -> PdfFont {
+        let descendant = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType0".to_vec())),
+            ("DW", 1000.into()),
+            (
+                "W",
+                vec![
+                    0x41.into(),
+                    vec![300.into()].into(),
+                    231.into(),
+                    233.into(),
+                    500.into(),
+                    842.into(),
+                    vec![800.into()].into(),
+                ]
+                .into(),
+            ),
+        ]));
+        let font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("BaseFont", Object::Name(b"KozMinPr6N-Regular".to_vec())),
+            ("Encoding", encoding),
+            ("DescendantFonts", vec![descendant.into()].into()),
+        ]);
+        PdfFont::from_dict(doc, &font)
+    }
+
+    #[test]
+    fn test_composite_font_maps_codes_through_embedded_encoding_cmap() {
+        // Arrange
+        let cmap = b"/CIDInit /ProcSet findresource begin
+begincmap
+/CMapName /Test-RKSJ-H def
+2 begincodespacerange
+<00> <80>
+<8140> <9FFC>
+endcodespacerange
+1 begincidrange
+<20> <7e> 231
+endcidrange
+1 begincidchar
+<82a0> 842
+endcidchar
+endcmap";
+        let mut doc = Document::with_version("1.5");
+        let encoding = doc.add_object(Stream::new(Dictionary::new(), cmap.to_vec()));
+
+        // Act
+        let font = cid_font(&mut doc, encoding.into());
+        let glyphs = font.decode(&[0x21, 0x82, 0xA0, 0x22, 0x81, 0x40]);
+
+        // Assert: `<8140>` is in the codespace but unmapped, so it is CID 0.
+        let widths: Vec<f32> = glyphs.iter().map(|g| g.width).collect();
+        assert_eq!(widths, vec![0.5, 0.8, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_composite_font_splits_codes_by_predefined_cmap() {
+        // Arrange
+        let mut doc = Document::with_version("1.5");
+
+        // Act
+        let sjis = cid_font(&mut doc, Object::Name(b"90ms-RKSJ-H".to_vec()));
+        let ucs2 = cid_font(&mut doc, Object::Name(b"UniJIS-UCS2-H".to_vec()));
+        let sjis_glyphs = sjis.decode(&[b'A', 0x82, 0xA0, b'B']);
+        let ucs2_glyphs = ucs2.decode(&[0x30, 0x42, 0x00, 0x41]);
+
+        // Assert: the codes' CIDs are not known, so the default width is
+        // used rather than the width of the CID equal to the code.
+        let widths: Vec<f32> = sjis_glyphs.iter().map(|g| g.width).collect();
+        assert_eq!(widths, vec![1.0, 1.0, 1.0]);
+        let text: String = ucs2_glyphs.iter().map(|g| g.text.as_str()).collect();
+        assert_eq!(text, "あA");
+        assert_eq!(ucs2_glyphs[1].width, 1.0);
+    }
+
+    #[test]
+    fn test_codes_and_cids_near_u32_max_do_not_overflow() {
+        // Arrange
+        let cmap = b"1 beginbfrange
+<FFFFFFF0> <FFFFFFFF> <0041>
+endbfrange";
+        let mut doc = Document::with_version("1.5");
+        let descendant = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType2".to_vec())),
+            (
+                "W",
+                vec![
+                    Object::Integer(u32::MAX as i64),
+                    vec![100.into(), 200.into()].into(),
+                    Object::Integer(u32::MAX as i64),
+                    Object::Integer(u32::MAX as i64),
+                    300.into(),
+                    Object::Integer(1 << 40),
+                    vec![400.into()].into(),
+                    0x24.into(),
+                    vec![600.into()].into(),
+                ]
+                .into(),
+            ),
+        ]));
+        let font = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("DescendantFonts", vec![descendant.into()].into()),
+        ]);
+
+        // Act
+        let cmap = ToUnicode::parse(cmap);
+        let font = PdfFont::from_dict(&doc, &font);
+
+        // Assert
+        assert_eq!(cmap.get(4, 0xFFFF_FFF0), Some("A"));
+        assert_eq!(cmap.get(4, 0xFFFF_FFFF), Some("P"));
+        assert_eq!(font.decode(&[0x00, 0x24])[0].width, 0.6);
+    }
+
+    #[test]
+    fn test_font_style_and_embedding_from_descriptor_and_name() {
+        // Arrange
+        let mut doc = Document::with_version("1.5");
+        let font_file = doc.add_object(Stream::new(Dictionary::new(), vec![0]));
+        let descriptor = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"FontDescriptor".to_vec())),
+            ("Flags", (32 | 1 << 6).into()),
+            ("ItalicAngle", (-12).into()),
+            ("FontFile2", font_file.into()),
+        ]));
+        let descendant = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType2".to_vec())),
+            ("FontDescriptor", descriptor.into()),
+        ]));
+        let composite = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            (
+                "BaseFont",
+                Object::Name(b"ABCDEF+Arial-BoldItalicMT".to_vec()),
+            ),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("DescendantFonts", vec![descendant.into()].into()),
+        ]);
+        let standard = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Times-Roman".to_vec())),
+        ]);
+
+        // Act
+        let composite = PdfFont::from_dict(&doc, &composite);
+        let standard = PdfFont::from_dict(&doc, &standard);
+
+        // Assert
+        assert_eq!(composite.family(), "Arial");
+        assert!(composite.embedded);
+        assert!(composite.bold, "bold comes from the style suffix");
+        assert!(composite.italic);
+        assert_eq!(standard.family(), "Times");
+        assert!(!standard.embedded);
+        assert!(!standard.bold);
+        assert!(!standard.italic);
+    }
+}
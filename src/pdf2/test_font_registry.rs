@@ -0,0 +1,129 @@
+use crate::font_registry::{EmbeddedFont, FontRegistry};
+use crate::subset::is_truetype;
+use crate::test_utils::{font_collection, test_font};
+use lopdf::{Document, Object};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_validates_font_data() {
+        // Arrange
+        let mut registry = FontRegistry::new();
+
+        // Act
+        let valid = registry.register_data("Test", test_font(), 0);
+        let invalid = registry.register_data("Broken", b"not a font".to_vec(), 0);
+        let collection = registry.register_data("Collection", b"ttcf\0\x01\0\0".to_vec(), 0);
+
+        // Assert
+        assert!(valid.is_ok());
+        assert!(invalid.is_err());
+        assert!(collection.is_err());
+        assert_eq!(registry.names(), vec!["Test".to_string()]);
+        assert!(registry.get("Test").is_some());
+    }
+
+    /// The test font with its `glyf` table renamed to `CFF `.
+    fn cff_font() -> Vec<u8> {
+        let mut data = test_font();
+        let tag = data.windows(4).position(|w| w == b"glyf").unwrap();
+        data[tag..tag + 4].copy_from_slice(b"CFF ");
+        data
+    }
+
+    #[test]
+    fn test_fonts_with_cff_outlines_are_embedded_whole() {
+        // Arrange
+        let data = cff_font();
+        let mut registry = FontRegistry::new();
+        let mut pdf = Document::with_version("1.7");
+        let font_id = pdf.new_object_id();
+
+        // Act
+        let registered = registry.register_data("Cff", data.clone(), 0);
+        let mut font = EmbeddedFont::new("Cff", &data).unwrap();
+        let bytes = font.encode("A");
+        font.write(&mut pdf, font_id).unwrap();
+
+        // Assert
+        assert!(registered.is_ok());
+        assert_eq!(bytes, vec![0, 1]);
+        let type0 = pdf.get_dictionary(font_id).unwrap();
+        let descendants = type0.get(b"DescendantFonts").unwrap().as_array().unwrap();
+        let cid_font = pdf
+            .get_dictionary(descendants[0].as_reference().unwrap())
+            .unwrap();
+        assert_eq!(
+            cid_font.get(b"Subtype").unwrap().as_name().unwrap(),
+            b"CIDFontType0"
+        );
+        assert!(cid_font.get(b"CIDToGIDMap").is_err());
+        let descriptor = pdf
+            .get_dictionary(
+                cid_font
+                    .get(b"FontDescriptor")
+                    .unwrap()
+                    .as_reference()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(descriptor.get(b"FontFile2").is_err());
+        let file_id = descriptor
+            .get(b"FontFile3")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let Ok(Object::Stream(file)) = pdf.get_object(file_id) else {
+            panic!("FontFile3 is not a stream");
+        };
+        assert_eq!(
+            file.dict.get(b"Subtype").unwrap().as_name().unwrap(),
+            b"OpenType"
+        );
+        assert_eq!(file.content, data);
+    }
+
+    #[test]
+    fn test_register_selects_font_from_collection() {
+        // Arrange
+        let collection = font_collection(&[cff_font(), test_font()]);
+        let mut registry = FontRegistry::new();
+
+        // Act
+        let first = registry.register_data("First", collection.clone(), 0);
+        let second = registry.register_data("Second", collection.clone(), 1);
+        let missing = registry.register_data("Missing", collection, 2);
+        let not_collection = registry.register_data("Single", test_font(), 1);
+
+        // Assert
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert!(!is_truetype(registry.get("First").unwrap()));
+        let second = registry.get("Second").unwrap();
+        assert!(is_truetype(second));
+        let mut font = EmbeddedFont::new("Second", second).unwrap();
+        assert_eq!(font.encode("あ"), vec![0, 2]);
+        let message = missing.unwrap_err().to_string();
+        assert!(message.contains("out of range"), "{}", message);
+        assert!(not_collection.is_err());
+        assert_eq!(
+            registry.names(),
+            vec!["First".to_string(), "Second".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_embedded_font_encodes_glyph_ids() {
+        // Arrange
+        let data = test_font();
+        let mut font = EmbeddedFont::new("Test", &data).unwrap();
+
+        // Act
+        let bytes = font.encode("AあZ");
+
+        // Assert: `Z` has no glyph and falls back to `.notdef`.
+        assert_eq!(bytes, vec![0, 1, 0, 2, 0, 0]);
+    }
+}
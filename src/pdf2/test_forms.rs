@@ -0,0 +1,604 @@
+use crate::font_registry::FontRegistry;
+use crate::forms::{fill_form, read_form_fields, FieldValue, FillOptions, FormError};
+use crate::generator::generate_to_bytes;
+use crate::parser::parse_bytes;
+use crate::structure::{
+    CheckBox, Document, Dropdown, Page, RadioButton, RadioGroup, Rect, SignatureField, TextField,
+    FIELD_COMBO, FIELD_MULTILINE, FIELD_MULTI_SELECT, FIELD_RADIO, FIELD_REQUIRED,
+};
+use crate::test_utils::PdfBuilder;
+use lopdf::content::Content;
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: i64, y0: i64, x1: i64, y1: i64) -> Object {
+        vec![x0.into(), y0.into(), x1.into(), y1.into()].into()
+    }
+
+    /// Normal appearances with an `Off` state and the state `on`.
+    fn appearances(doc: &mut lopdf::Document, on: &str) -> Dictionary {
+        let stream = doc.add_object(Stream::new(Dictionary::new(), vec![]));
+        Dictionary::from_iter(vec![(
+            "N",
+            Dictionary::from_iter(vec![(on, stream.into()), ("Off", stream.into())]).into(),
+        )])
+    }
+
+    /// A two-page form: `person.name` (text, required) and `agree`
+    /// (checkbox) on page 1, and `color` (radio, two widgets) and `sizes`
+    /// (multi-select list box) on page 2. The checkbox has no `/P` entry.
+    fn form_document() -> lopdf::Document {
+        let mut builder = PdfBuilder::new();
+        let first = builder.add_page(vec![], Dictionary::new(), vec![]);
+        let second = builder.add_page(vec![], Dictionary::new(), vec![]);
+        let doc = &mut builder.doc;
+
+        let person = doc.new_object_id();
+        let name = doc.add_object(Dictionary::from_iter(vec![
+            ("FT", Object::Name(b"Tx".to_vec())),
+            ("Parent", person.into()),
+            ("T", Object::string_literal("name")),
+            ("V", Object::string_literal("Ada")),
+            ("Subtype", Object::Name(b"Widget".to_vec())),
+            ("Rect", rect(100, 700, 300, 720)),
+            ("P", first.into()),
+        ]));
+        doc.objects.insert(
+            person,
+            Dictionary::from_iter(vec![
+                ("T", Object::string_literal("person")),
+                ("Ff", (FIELD_REQUIRED as i64).into()),
+                ("Kids", vec![name.into()].into()),
+            ])
+            .into(),
+        );
+
+        let agree_ap = appearances(doc, "Yes");
+        let agree = doc.add_object(Dictionary::from_iter(vec![
+            ("FT", Object::Name(b"Btn".to_vec())),
+            ("T", Object::string_literal("agree")),
+            ("V", Object::Name(b"Off".to_vec())),
+            ("Subtype", Object::Name(b"Widget".to_vec())),
+            ("Rect", rect(100, 650, 112, 662)),
+            ("AP", agree_ap.into()),
+        ]));
+
+        let color = doc.new_object_id();
+        let mut color_widgets = Vec::new();
+        for (state, x) in [("red", 100), ("blue", 150)] {
+            let ap = appearances(doc, state);
+            color_widgets.push(Object::from(doc.add_object(Dictionary::from_iter(vec![
+                ("Parent", color.into()),
+                ("Subtype", Object::Name(b"Widget".to_vec())),
+                ("Rect", rect(x, 700, x + 12, 712)),
+                ("P", second.into()),
+                ("AP", ap.into()),
+            ]))));
+        }
+        doc.objects.insert(
+            color,
+            Dictionary::from_iter(vec![
+                ("FT", Object::Name(b"Btn".to_vec())),
+                ("T", Object::string_literal("color")),
+                ("Ff", (FIELD_RADIO as i64).into()),
+                ("V", Object::Name(b"blue".to_vec())),
+                ("Kids", color_widgets.clone().into()),
+            ])
+            .into(),
+        );
+
+        let sizes = doc.add_object(Dictionary::from_iter(vec![
+            ("FT", Object::Name(b"Ch".to_vec())),
+            ("T", Object::string_literal("sizes")),
+            ("Ff", (FIELD_MULTI_SELECT as i64).into()),
+            (
+                "Opt",
+                vec![
+                    vec![Object::string_literal("s"), Object::string_literal("Small")].into(),
+                    Object::string_literal("m"),
+                    vec![Object::string_literal("l"), Object::string_literal("Large")].into(),
+                ]
+                .into(),
+            ),
+            (
+                "V",
+                vec![Object::string_literal("s"), Object::string_literal("l")].into(),
+            ),
+            ("Subtype", Object::Name(b"Widget".to_vec())),
+            ("Rect", rect(100, 500, 200, 560)),
+            ("P", second.into()),
+        ]));
+
+        let set_annots = |doc: &mut lopdf::Document, page: ObjectId, annots: Vec<Object>| {
+            doc.get_dictionary_mut(page).unwrap().set("Annots", annots);
+        };
+        set_annots(doc, first, vec![name.into(), agree.into()]);
+        let mut second_annots = color_widgets;
+        second_annots.push(sizes.into());
+        set_annots(doc, second, second_annots);
+
+        let form = Dictionary::from_iter(vec![(
+            "Fields",
+            vec![person.into(), agree.into(), color.into(), sizes.into()].into(),
+        )]);
+        let mut doc = builder.finish();
+        doc.catalog_mut().unwrap().set("AcroForm", form);
+        doc
+    }
+
+    fn page_indices(doc: &lopdf::Document) -> HashMap<ObjectId, usize> {
+        doc.get_pages()
+            .into_values()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_form_fields_reads_names_types_values_and_widgets() {
+        // Arrange
+        let doc = form_document();
+
+        // Act
+        let fields = read_form_fields(&doc, &page_indices(&doc));
+
+        // Assert
+        let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["person.name", "agree", "color", "sizes"]);
+
+        let name = &fields[0];
+        assert_eq!(name.field_type, "text");
+        assert_eq!(name.value.as_deref(), Some("Ada"));
+        assert!(name.required());
+        assert!(!name.read_only());
+        assert_eq!(name.widgets[0].page, Some(0));
+        assert_eq!(name.widgets[0].rect, Rect::new(100.0, 700.0, 300.0, 720.0));
+
+        let agree = &fields[1];
+        assert_eq!(agree.field_type, "checkbox");
+        assert_eq!(agree.value.as_deref(), Some("Off"));
+        assert_eq!(agree.options, vec!["Yes"]);
+        assert_eq!(agree.widgets[0].page, Some(0));
+
+        let color = &fields[2];
+        assert_eq!(color.field_type, "radio");
+        assert_eq!(color.value.as_deref(), Some("blue"));
+        assert_eq!(color.options, vec!["red", "blue"]);
+        assert_eq!(color.widgets.len(), 2);
+        assert_eq!(color.widgets[1].page, Some(1));
+        assert_eq!(color.widgets[1].on_state.as_deref(), Some("blue"));
+
+        let sizes = &fields[3];
+        assert_eq!(sizes.field_type, "choice");
+        assert_eq!(sizes.options, vec!["s", "m", "l"]);
+        assert_eq!(sizes.option_labels, vec!["Small", "m", "Large"]);
+        assert_eq!(sizes.values, vec!["s", "l"]);
+        assert_eq!(sizes.value.as_deref(), Some("s"));
+    }
+
+    #[test]
+    fn test_parse_bytes_returns_form_fields() {
+        // Arrange
+        let mut doc = form_document();
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+
+        // Act
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        assert_eq!(parsed.form_fields.len(), 4);
+        assert_eq!(parsed.form_fields[2].name, "color");
+    }
+
+    #[test]
+    fn test_read_form_fields_without_form_is_empty() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        builder.add_page(vec![], Dictionary::new(), vec![]);
+        let doc = builder.finish();
+
+        // Act
+        let fields = read_form_fields(&doc, &page_indices(&doc));
+
+        // Assert
+        assert!(fields.is_empty());
+    }
+
+    fn values(entries: Vec<(&str, FieldValue)>) -> BTreeMap<String, FieldValue> {
+        entries
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    fn reparse(doc: &mut lopdf::Document) -> Document {
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        parse_bytes(&bytes, None).unwrap()
+    }
+
+    #[test]
+    fn test_fill_form_sets_values_and_appearances() {
+        // Arrange
+        let mut doc = form_document();
+        let values = values(vec![
+            ("person.name", FieldValue::Text("Grace Hopper".to_string())),
+            ("agree", FieldValue::Checked(true)),
+            ("color", FieldValue::Text("red".to_string())),
+            (
+                "sizes",
+                FieldValue::Choices(vec!["m".to_string(), "l".to_string()]),
+            ),
+        ]);
+        let options = FillOptions {
+            need_appearances: true,
+            ..Default::default()
+        };
+
+        // Act
+        fill_form(&mut doc, &values, options).unwrap();
+
+        // Assert
+        let parsed = reparse(&mut doc);
+        let filled: Vec<Vec<String>> = parsed
+            .form_fields
+            .iter()
+            .map(|f| f.values.clone())
+            .collect();
+        assert_eq!(
+            filled,
+            vec![
+                vec!["Grace Hopper"],
+                vec!["Yes"],
+                vec!["red"],
+                vec!["m", "l"]
+            ]
+        );
+
+        let widget = |page: u32, index: usize| {
+            let page = doc.get_dictionary(doc.get_pages()[&page]).unwrap();
+            let annotations = page.get(b"Annots").unwrap().as_array().unwrap();
+            doc.get_dictionary(annotations[index].as_reference().unwrap())
+                .unwrap()
+        };
+        let appearance = widget(1, 0).get(b"AP").unwrap().as_dict().unwrap();
+        let stream_id = appearance.get(b"N").unwrap().as_reference().unwrap();
+        let stream = doc.get_object(stream_id).unwrap().as_stream().unwrap();
+        let content = String::from_utf8_lossy(&stream.content).into_owned();
+        assert!(content.contains("(Grace Hopper) Tj"), "{}", content);
+
+        let states: Vec<&[u8]> = [widget(2, 0), widget(2, 1)]
+            .iter()
+            .map(|widget| widget.get(b"AS").unwrap().as_name().unwrap())
+            .collect();
+        assert_eq!(states, vec![b"red".as_slice(), b"Off".as_slice()]);
+
+        let form = doc
+            .catalog()
+            .unwrap()
+            .get(b"AcroForm")
+            .unwrap()
+            .as_dict()
+            .unwrap();
+        assert_eq!(
+            form.get(b"NeedAppearances").unwrap(),
+            &Object::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn test_fill_form_rejects_unknown_fields_and_options() {
+        // Arrange
+        let mut doc = form_document();
+        let unknown = values(vec![("nope", FieldValue::Text("x".to_string()))]);
+        let bad_option = values(vec![("color", FieldValue::Text("green".to_string()))]);
+
+        // Act
+        let unknown = fill_form(&mut doc, &unknown, FillOptions::default());
+        let bad_option = fill_form(&mut doc, &bad_option, FillOptions::default());
+
+        // Assert
+        assert!(matches!(unknown, Err(FormError::UnknownField(name)) if name == "nope"));
+        assert!(matches!(bad_option, Err(FormError::Value { name, .. }) if name == "color"));
+    }
+
+    #[test]
+    fn test_fill_form_flatten_draws_fields_into_pages() {
+        // Arrange
+        let mut doc = form_document();
+        let values = values(vec![("person.name", FieldValue::Text("Ada".to_string()))]);
+        let options = FillOptions {
+            flatten: true,
+            ..Default::default()
+        };
+
+        // Act
+        fill_form(&mut doc, &values, options).unwrap();
+
+        // Assert
+        assert!(doc.catalog().unwrap().get(b"AcroForm").is_err());
+        for page_id in doc.get_pages().into_values() {
+            assert!(doc.get_dictionary(page_id).unwrap().get(b"Annots").is_err());
+        }
+        let parsed = reparse(&mut doc);
+        assert!(parsed.form_fields.is_empty());
+        let texts: Vec<&str> = parsed.pages[0]
+            .text_blocks()
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect();
+        assert!(texts.contains(&"Ada"), "{:?}", texts);
+    }
+
+    /// The first widget of the first page.
+    fn name_widget(doc: &lopdf::Document) -> ObjectId {
+        let page = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+        let annotations = page.get(b"Annots").unwrap().as_array().unwrap();
+        annotations[0].as_reference().unwrap()
+    }
+
+    fn normal_appearance(doc: &lopdf::Document, widget: ObjectId) -> ObjectId {
+        let widget = doc.get_dictionary(widget).unwrap();
+        let appearance = widget.get(b"AP").unwrap().as_dict().unwrap();
+        appearance.get(b"N").unwrap().as_reference().unwrap()
+    }
+
+    #[test]
+    fn test_fill_form_sets_text_in_composite_default_resource_font() {
+        // Arrange
+        let mut doc = form_document();
+        let to_unicode = b"1 begincodespacerange
+<0000> <FFFF>
+endcodespacerange
+2 beginbfchar
+<0011> <3042>
+<0024> <0041>
+endbfchar";
+        let to_unicode = doc.add_object(Stream::new(Dictionary::new(), to_unicode.to_vec()));
+        let descendant = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType2".to_vec())),
+            ("DW", 1000.into()),
+            ("W", vec![0x24.into(), vec![600.into()].into()].into()),
+        ]));
+        let font = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("BaseFont", Object::Name(b"NotoSansJP".to_vec())),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("DescendantFonts", vec![descendant.into()].into()),
+            ("ToUnicode", to_unicode.into()),
+        ]));
+        let widget = name_widget(&doc);
+        doc.get_dictionary_mut(widget)
+            .unwrap()
+            .set("DA", Object::string_literal("/Jp 12 Tf 0 g"));
+        let resources = Dictionary::from_iter(vec![(
+            "Font",
+            Dictionary::from_iter(vec![("Jp", font.into())]).into(),
+        )]);
+        let form = doc.catalog_mut().unwrap().get_mut(b"AcroForm").unwrap();
+        form.as_dict_mut().unwrap().set("DR", resources);
+        let values = values(vec![("person.name", FieldValue::Text("Aあ".to_string()))]);
+
+        // Act
+        fill_form(&mut doc, &values, FillOptions::default()).unwrap();
+
+        // Assert
+        let stream = doc.get_object(normal_appearance(&doc, widget)).unwrap();
+        let content = Content::decode(&stream.as_stream().unwrap().content).unwrap();
+        let shown: Vec<&[u8]> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Tj")
+            .map(|op| op.operands[0].as_str().unwrap())
+            .collect();
+        assert_eq!(shown, vec![[0x00, 0x24, 0x00, 0x11].as_slice()]);
+        let form = doc.catalog().unwrap().get(b"AcroForm").unwrap();
+        assert!(form.as_dict().unwrap().get(b"NeedAppearances").is_err());
+    }
+
+    #[test]
+    fn test_fill_form_keeps_appearance_its_font_cannot_show() {
+        // Arrange
+        let mut doc = form_document();
+        let widget = name_widget(&doc);
+        let existing = doc.add_object(Stream::new(Dictionary::new(), b"% Ada".to_vec()));
+        doc.get_dictionary_mut(widget)
+            .unwrap()
+            .set("AP", Dictionary::from_iter(vec![("N", existing.into())]));
+        let mut flattened = doc.clone();
+        let values = values(vec![("person.name", FieldValue::Text("日本".to_string()))]);
+        let flatten = FillOptions {
+            flatten: true,
+            ..Default::default()
+        };
+
+        // Act
+        let filled = fill_form(&mut doc, &values, FillOptions::default());
+        let flatten = fill_form(&mut flattened, &values, flatten);
+
+        // Assert
+        assert!(filled.is_ok());
+        assert_eq!(normal_appearance(&doc, widget), existing);
+        let form = doc.catalog().unwrap().get(b"AcroForm").unwrap();
+        let form = form.as_dict().unwrap();
+        assert_eq!(
+            form.get(b"NeedAppearances").unwrap(),
+            &Object::Boolean(true)
+        );
+        assert_eq!(reparse(&mut doc).form_fields[0].values, vec!["日本"]);
+        let message = flatten.unwrap_err().to_string();
+        assert!(message.contains("cannot show '日'"), "{}", message);
+    }
+
+    #[test]
+    fn test_generate_writes_fillable_fields() {
+        // Arrange
+        let page = Page {
+            width: 595.0,
+            height: 842.0,
+            text_fields: vec![
+                TextField {
+                    name: "person.name".to_string(),
+                    rect: Rect::new(100.0, 700.0, 300.0, 720.0),
+                    value: Some("Ada".to_string()),
+                    max_length: Some(20),
+                    required: true,
+                    ..Default::default()
+                },
+                TextField {
+                    name: "person.notes".to_string(),
+                    rect: Rect::new(100.0, 600.0, 300.0, 680.0),
+                    multiline: true,
+                    ..Default::default()
+                },
+            ],
+            checkboxes: vec![CheckBox {
+                name: "agree".to_string(),
+                rect: Rect::new(100.0, 560.0, 112.0, 572.0),
+                checked: true,
+                ..Default::default()
+            }],
+            radio_groups: vec![RadioGroup::new(
+                "color".to_string(),
+                vec![
+                    RadioButton::new("red".to_string(), Rect::new(100.0, 520.0, 112.0, 532.0)),
+                    RadioButton::new("blue".to_string(), Rect::new(150.0, 520.0, 162.0, 532.0)),
+                ],
+                Some("blue".to_string()),
+                false,
+                false,
+            )],
+            dropdowns: vec![Dropdown {
+                name: "size".to_string(),
+                rect: Rect::new(100.0, 480.0, 200.0, 500.0),
+                options: vec!["S".to_string(), "M".to_string(), "L".to_string()],
+                value: Some("M".to_string()),
+                ..Default::default()
+            }],
+            signature_fields: vec![SignatureField::new(
+                "signature".to_string(),
+                Rect::new(100.0, 400.0, 300.0, 440.0),
+                false,
+            )],
+            ..Default::default()
+        };
+        let document = Document::new(vec![page], None, None);
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        let summary: Vec<(&str, &str, Option<&str>)> = parsed
+            .form_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.field_type.as_str(), f.value.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("person.name", "text", Some("Ada")),
+                ("person.notes", "text", None),
+                ("agree", "checkbox", Some("Yes")),
+                ("color", "radio", Some("blue")),
+                ("size", "choice", Some("M")),
+                ("signature", "signature", None),
+            ]
+        );
+        let fields = &parsed.form_fields;
+        assert!(fields[0].required());
+        assert_ne!(fields[1].flags & FIELD_MULTILINE, 0);
+        assert_eq!(fields[3].options, vec!["red", "blue"]);
+        assert_eq!(
+            fields[3].widgets[1].rect,
+            Rect::new(150.0, 520.0, 162.0, 532.0)
+        );
+        assert_ne!(fields[4].flags & FIELD_COMBO, 0);
+        assert_eq!(fields[4].options, vec!["S", "M", "L"]);
+        assert!(fields
+            .iter()
+            .all(|f| f.widgets.iter().all(|w| w.page == Some(0))));
+
+        let mut pdf = lopdf::Document::load_mem(&bytes).unwrap();
+        let values = values(vec![("person.notes", FieldValue::Text("Hi".to_string()))]);
+        fill_form(&mut pdf, &values, FillOptions::default()).unwrap();
+    }
+
+    #[test]
+    fn test_generate_rejects_conflicting_field_names() {
+        // Arrange
+        let field = |name: &str| TextField {
+            name: name.to_string(),
+            rect: Rect::new(0.0, 0.0, 100.0, 20.0),
+            ..Default::default()
+        };
+        let page = |names: &[&str]| Page {
+            text_fields: names.iter().map(|name| field(name)).collect(),
+            ..Default::default()
+        };
+        let duplicate = Document::new(vec![page(&["a"]), page(&["a"])], None, None);
+        let parent = Document::new(vec![page(&["a", "a.b"])], None, None);
+
+        // Act
+        let duplicate = generate_to_bytes(&duplicate, &FontRegistry::default());
+        let parent = generate_to_bytes(&parent, &FontRegistry::default());
+
+        // Assert
+        assert!(duplicate.unwrap_err().to_string().contains("duplicate"));
+        assert!(parent.unwrap_err().to_string().contains("parent"));
+    }
+
+    #[test]
+    fn test_generate_leaves_appearance_helvetica_cannot_show_to_viewer() {
+        // Arrange
+        let page = Page {
+            text_fields: vec![TextField {
+                name: "name".to_string(),
+                rect: Rect::new(100.0, 700.0, 300.0, 720.0),
+                value: Some("日本".to_string()),
+                ..Default::default()
+            }],
+            dropdowns: vec![Dropdown {
+                name: "size".to_string(),
+                rect: Rect::new(100.0, 480.0, 200.0, 500.0),
+                options: vec!["S".to_string(), "M".to_string()],
+                value: Some("M".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let document = Document::new(vec![page], None, None);
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+
+        // Assert
+        let pdf = lopdf::Document::load_mem(&bytes).unwrap();
+        let page = pdf.get_dictionary(pdf.get_pages()[&1]).unwrap();
+        let widgets: Vec<&Dictionary> = page
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| pdf.get_dictionary(w.as_reference().unwrap()).unwrap())
+            .collect();
+        assert!(widgets[0].get(b"AP").is_err());
+        assert!(widgets[1].get(b"AP").is_ok());
+        let form = pdf.catalog().unwrap().get(b"AcroForm").unwrap();
+        let form = pdf.get_dictionary(form.as_reference().unwrap()).unwrap();
+        assert_eq!(
+            form.get(b"NeedAppearances").unwrap(),
+            &Object::Boolean(true)
+        );
+        let parsed = parse_bytes(&bytes, None).unwrap();
+        assert_eq!(parsed.form_fields[0].value.as_deref(), Some("日本"));
+    }
+}
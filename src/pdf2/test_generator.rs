@@ -1,5 +1,12 @@
-use crate::generator::generate_pdf;
-use crate::structure::{Document, Page, TextBlock};
+use crate::font_registry::FontRegistry;
+use crate::generator::{build_document, generate_pdf, generate_pdf_with_fonts, generate_to_bytes};
+use crate::images::encode_png;
+use crate::parser::{parse_bytes, parse_pdf};
+use crate::structure::{
+    Document, Image, Metadata, Page, PageContent, PathSegment, Rect, Shape, TextBlock,
+};
+use crate::test_utils::{test_font, TempPath};
+use lopdf::content::Content;
 use std::fs;
 use std::path::Path;
 
@@ -15,14 +22,18 @@ mod tests {
             x: 10.0,
             y: 280.0,
             font_size: 12.0,
+            ..Default::default()
         };
         let page = Page {
             width: 210.0,
             height: 297.0,
-            text_blocks: vec![text_block],
-            images: vec![],
+            contents: vec![PageContent::Text(text_block)],
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![page],
+            ..Default::default()
         };
-        let document = Document { pages: vec![page] };
         let output_path = Path::new("test_output.pdf");
 
         // Clean up existing file
@@ -46,7 +57,7 @@ mod tests {
     #[test]
     fn test_generate_pdf_with_empty_document() {
         // Arrange
-        let document = Document { pages: vec![] };
+        let document = Document::default();
         let output_path = Path::new("test_empty.pdf");
 
         // Clean up existing file
@@ -78,29 +89,32 @@ mod tests {
             x: 10.0,
             y: 280.0,
             font_size: 12.0,
+            ..Default::default()
         };
         let text_block2 = TextBlock {
             text: "Page 2 content".to_string(),
             x: 10.0,
             y: 280.0,
             font_size: 12.0,
+            ..Default::default()
         };
 
         let page1 = Page {
             width: 210.0,
             height: 297.0,
-            text_blocks: vec![text_block1],
-            images: vec![],
+            contents: vec![PageContent::Text(text_block1)],
+            ..Default::default()
         };
         let page2 = Page {
             width: 210.0,
             height: 297.0,
-            text_blocks: vec![text_block2],
-            images: vec![],
+            contents: vec![PageContent::Text(text_block2)],
+            ..Default::default()
         };
 
         let document = Document {
             pages: vec![page1, page2],
+            ..Default::default()
         };
         let output_path = Path::new("test_multiple_pages.pdf");
 
@@ -124,4 +138,548 @@ mod tests {
             fs::remove_file(output_path).unwrap();
         }
     }
+
+    #[test]
+    fn test_generate_pdf_round_trips_layout() {
+        // Arrange
+        let block = |text: &str, x: f32, y: f32, font_size: f32| TextBlock {
+            text: text.to_string(),
+            x,
+            y,
+            font_size,
+            ..Default::default()
+        };
+        let page1 = Page {
+            width: 595.0,
+            height: 842.0,
+            contents: vec![
+                PageContent::Text(block("Title", 72.0, 770.0, 24.0)),
+                PageContent::Text(block("Body (café)", 72.0, 700.0, 11.0)),
+            ],
+            ..Default::default()
+        };
+        let page2 = Page {
+            width: 612.0,
+            height: 396.0,
+            contents: vec![PageContent::Text(block("Landscape", 300.0, 200.0, 9.5))],
+            rotation: 90,
+            crop_box: Some(Rect::new(10.0, 10.0, 600.0, 380.0)),
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![page1, page2],
+            ..Default::default()
+        };
+        let output = TempPath::new("generate_round_trip.pdf");
+
+        // Act
+        generate_pdf(&document, output.path()).unwrap();
+        let parsed = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        assert_eq!(parsed.pages.len(), 2);
+        for (original, parsed) in document.pages.iter().zip(&parsed.pages) {
+            assert_eq!(
+                (parsed.width, parsed.height),
+                (original.width, original.height)
+            );
+            assert_eq!(parsed.rotation, original.rotation);
+            assert_eq!(parsed.crop_box, original.crop_box);
+            let blocks: Vec<_> = parsed
+                .text_blocks()
+                .iter()
+                .map(|b| (b.text.as_str(), b.x, b.y, b.font_size))
+                .collect();
+            let expected: Vec<_> = original
+                .text_blocks()
+                .iter()
+                .map(|b| (b.text.as_str(), b.x, b.y, b.font_size))
+                .collect();
+            assert_eq!(blocks, expected);
+        }
+    }
+
+    #[test]
+    fn test_generate_pdf_round_trips_shapes() {
+        // Arrange: a table with a header fill, its rules, and a dashed
+        // curve with round caps.
+        let header = Rect::new(72.0, 700.0, 372.0, 720.0);
+        let shapes = [
+            Shape::rectangle(header, 0.5, None, Some(vec![0.9])),
+            Shape::line(72.0, 680.0, 372.0, 680.0, 0.5, None),
+            Shape::line(
+                222.0,
+                660.0,
+                222.0,
+                720.0,
+                0.5,
+                Some(vec![0.0, 0.0, 0.0, 1.0]),
+            ),
+            Shape {
+                segments: vec![
+                    PathSegment::move_to(100.0, 100.0),
+                    PathSegment::curve_to((120.0, 150.0), (180.0, 150.0), (200.0, 100.0)),
+                ],
+                stroke_color: Some(vec![0.0, 0.5, 1.0]),
+                line_width: 2.0,
+                dash: vec![4.0, 2.0],
+                dash_phase: 1.0,
+                line_cap: "round".to_string(),
+                ..Default::default()
+            },
+        ];
+        let document = Document {
+            pages: vec![Page {
+                width: 595.0,
+                height: 842.0,
+                contents: shapes
+                    .iter()
+                    .cloned()
+                    .map(PageContent::Shape)
+                    .chain([PageContent::Text(TextBlock::new(
+                        "Name".to_string(),
+                        76.0,
+                        706.0,
+                        10.0,
+                        None,
+                    ))])
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        assert_eq!(parsed.pages[0].shapes(), shapes.iter().collect::<Vec<_>>());
+        assert_eq!(parsed.pages[0].text_blocks()[0].text, "Name");
+    }
+
+    #[test]
+    fn test_generate_pdf_keeps_painting_order() {
+        // Arrange: text under an image, a rule over it, then more text.
+        let block = |text: &str, y: f32| TextBlock::new(text.to_string(), 72.0, y, 12.0, None);
+        let image = Image::new(
+            60.0,
+            600.0,
+            200.0,
+            100.0,
+            vec![0, 255, 255, 0],
+            "gray".to_string(),
+            2,
+            2,
+        );
+        let document = Document {
+            pages: vec![Page {
+                width: 595.0,
+                height: 842.0,
+                contents: vec![
+                    PageContent::Text(block("Watermark", 650.0)),
+                    PageContent::Image(image),
+                    PageContent::Shape(Shape::line(60.0, 600.0, 260.0, 600.0, 1.0, None)),
+                    PageContent::Text(block("Caption", 580.0)),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        let order: Vec<String> = parsed.pages[0]
+            .contents
+            .iter()
+            .map(|item| match item {
+                PageContent::Text(block) => block.text.clone(),
+                PageContent::Image(image) => format!("image {}x{}", image.width, image.height),
+                PageContent::Shape(shape) => format!("shape {:?}", shape.bounds()),
+            })
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "Watermark".to_string(),
+                "image 200x100".to_string(),
+                format!("shape {:?}", Rect::new(60.0, 600.0, 260.0, 600.0)),
+                "Caption".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_pdf_rejects_invalid_shapes() {
+        // Arrange
+        let invalid = [
+            Shape {
+                segments: vec![PathSegment::new("arc".to_string(), None)],
+                ..Shape::line(0.0, 0.0, 1.0, 1.0, 1.0, None)
+            },
+            Shape::line(0.0, 0.0, 1.0, 1.0, 1.0, Some(vec![0.0, 1.0])),
+            Shape {
+                line_join: "sharp".to_string(),
+                ..Shape::line(0.0, 0.0, 1.0, 1.0, 1.0, None)
+            },
+        ];
+
+        for shape in invalid {
+            let document = Document {
+                pages: vec![Page {
+                    contents: vec![PageContent::Shape(shape)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+
+            // Act
+            let result = generate_to_bytes(&document, &FontRegistry::default());
+
+            // Assert
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_generate_pdf_round_trips_text_styles() {
+        // Arrange: a styled block, then a plain one that must not inherit
+        // its styles.
+        let styled = TextBlock {
+            bold: true,
+            italic: true,
+            fill_color: vec![0.0, 0.0, 1.0],
+            stroke_color: vec![0.0, 0.0, 0.0, 1.0],
+            char_spacing: 1.5,
+            word_spacing: 3.0,
+            horizontal_scaling: 80.0,
+            rise: 4.0,
+            render_mode: 2,
+            ..TextBlock::new("Styled text".to_string(), 72.0, 704.0, 12.0, None)
+        };
+        let plain = TextBlock::new("Plain".to_string(), 72.0, 600.0, 12.0, None);
+        let document = Document {
+            pages: vec![Page {
+                contents: vec![PageContent::Text(styled), PageContent::Text(plain)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        let blocks = parsed.pages[0].text_blocks();
+        let styled = blocks[0];
+        assert_eq!(styled.base_font.as_deref(), Some("Helvetica-BoldOblique"));
+        assert_eq!(styled.font_name.as_deref(), Some("Helvetica"));
+        assert!(styled.bold && styled.italic);
+        assert_eq!(styled.fill_color, vec![0.0, 0.0, 1.0]);
+        assert_eq!(styled.stroke_color, vec![0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(styled.char_spacing, 1.5);
+        assert_eq!(styled.word_spacing, 3.0);
+        assert_eq!(styled.horizontal_scaling, 80.0);
+        assert_eq!(styled.rise, 4.0);
+        assert_eq!(styled.render_mode, 2);
+        assert_eq!((styled.x, styled.y), (72.0, 704.0));
+        let plain = blocks[1];
+        assert_eq!(plain.base_font.as_deref(), Some("Helvetica"));
+        assert!(!plain.bold && !plain.italic);
+        assert_eq!(plain.fill_color, vec![0.0]);
+        assert_eq!(plain.char_spacing, 0.0);
+        assert_eq!(plain.horizontal_scaling, 100.0);
+        assert_eq!(plain.render_mode, 0);
+    }
+
+    #[test]
+    fn test_generate_pdf_uses_registered_faces_or_synthetic_styles() {
+        // Arrange
+        let mut fonts = FontRegistry::new();
+        fonts.register_data("Test Sans", test_font(), 0).unwrap();
+        fonts
+            .register_data("Test Sans-Bold", test_font(), 0)
+            .unwrap();
+        let block = |bold: bool, italic: bool| {
+            PageContent::Text(TextBlock {
+                bold,
+                italic,
+                ..TextBlock::new(
+                    "Face".to_string(),
+                    72.0,
+                    700.0,
+                    10.0,
+                    Some("Test Sans".to_string()),
+                )
+            })
+        };
+        let document = Document {
+            pages: vec![Page {
+                contents: vec![block(true, false), block(false, true), block(true, true)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let doc = build_document(&document, &fonts).unwrap();
+
+        // Assert
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operands = |operator: &str| -> Vec<Vec<f32>> {
+            content
+                .operations
+                .iter()
+                .filter(|op| op.operator == operator)
+                .map(|op| {
+                    op.operands
+                        .iter()
+                        .filter_map(|o| o.as_float().ok())
+                        .collect()
+                })
+                .collect()
+        };
+        let fonts: Vec<String> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Tf")
+            .map(|op| String::from_utf8_lossy(op.operands[0].as_name().unwrap()).into_owned())
+            .collect();
+        assert_eq!(fonts, ["F2", "F3", "F3"], "only bold has its own face");
+        let slants: Vec<f32> = operands("Tm").iter().map(|m| m[2]).collect();
+        assert_eq!(slants, [0.0, 0.2, 0.2]);
+        assert_eq!(operands("Tr"), [vec![2.0]], "bold italic is emboldened");
+    }
+
+    #[test]
+    fn test_generate_pdf_rejects_invalid_text_styles() {
+        // Arrange
+        let base = TextBlock::new("Bad".to_string(), 0.0, 0.0, 12.0, None);
+        let invalid = [
+            TextBlock {
+                fill_color: vec![0.0, 1.0],
+                ..base.clone()
+            },
+            TextBlock {
+                render_mode: 8,
+                ..base
+            },
+        ];
+
+        for block in invalid {
+            let document = Document {
+                pages: vec![Page {
+                    contents: vec![PageContent::Text(block)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+
+            // Act
+            let result = generate_to_bytes(&document, &FontRegistry::default());
+
+            // Assert
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_generate_pdf_places_and_shares_images() {
+        // Arrange
+        let logo = Image {
+            x: 40.0,
+            y: 700.0,
+            width: 120.0,
+            height: 60.0,
+            data: encode_png(2, 1, 4, &[255, 0, 0, 255, 0, 0, 255, 0]),
+            format: "png".to_string(),
+            ..Default::default()
+        };
+        let swatch = Image {
+            x: 300.0,
+            y: 100.0,
+            width: 20.0,
+            height: 20.0,
+            data: vec![0, 128, 255, 64],
+            format: "gray".to_string(),
+            pixel_width: 2,
+            pixel_height: 2,
+        };
+        let page = |images: Vec<Image>| Page {
+            width: 595.0,
+            height: 842.0,
+            contents: images.into_iter().map(PageContent::Image).collect(),
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![page(vec![logo.clone(), swatch]), page(vec![logo])],
+            ..Default::default()
+        };
+        let output = TempPath::new("generate_images.pdf");
+
+        // Act
+        let pdf = build_document(&document, &FontRegistry::default()).unwrap();
+        generate_pdf(&document, output.path()).unwrap();
+        let parsed = parse_pdf(output.path()).unwrap();
+
+        // Assert: one XObject for the logo, one for its soft mask, one for the swatch.
+        let image_count = pdf
+            .objects
+            .values()
+            .filter_map(|o| o.as_stream().ok())
+            .filter(|s| s.dict.get(b"Subtype").and_then(|o| o.as_name()).ok() == Some(b"Image"))
+            .count();
+        assert_eq!(image_count, 3);
+        let rects: Vec<Vec<_>> = parsed
+            .pages
+            .iter()
+            .map(|p| {
+                p.images()
+                    .iter()
+                    .map(|i| (i.x, i.y, i.width, i.height))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rects,
+            vec![
+                vec![(40.0, 700.0, 120.0, 60.0), (300.0, 100.0, 20.0, 20.0)],
+                vec![(40.0, 700.0, 120.0, 60.0)],
+            ]
+        );
+        let logo = &parsed.pages[1].images()[0];
+        assert_eq!(logo.format, "png");
+        assert_eq!(logo.data, document.pages[0].images()[0].data);
+    }
+
+    #[test]
+    fn test_generate_pdf_rejects_unsupported_image() {
+        // Arrange
+        let image = Image {
+            data: vec![1, 2, 3],
+            format: "bmp".to_string(),
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![Page {
+                contents: vec![PageContent::Image(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let result = build_document(&document, &FontRegistry::default());
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_generate_pdf_embeds_registered_font_subset() {
+        // Arrange
+        let mut fonts = FontRegistry::new();
+        fonts.register_data("Test Sans", test_font(), 0).unwrap();
+        let block = |text: &str, x: f32, font_name: Option<&str>| TextBlock {
+            text: text.to_string(),
+            x,
+            y: 500.0,
+            font_size: 10.0,
+            font_name: font_name.map(str::to_string),
+            ..Default::default()
+        };
+        let document = Document {
+            pages: vec![Page {
+                width: 595.0,
+                height: 842.0,
+                contents: vec![
+                    PageContent::Text(block("AあЖ", 100.0, Some("Test Sans"))),
+                    PageContent::Text(block("Latin", 300.0, None)),
+                    PageContent::Text(block("Fallback", 400.0, Some("Missing"))),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let output = TempPath::new("generate_fonts.pdf");
+
+        // Act
+        let pdf = build_document(&document, &fonts).unwrap();
+        generate_pdf_with_fonts(&document, &fonts, output.path()).unwrap();
+        let parsed = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        let texts: Vec<&str> = parsed.pages[0]
+            .text_blocks()
+            .iter()
+            .map(|b| b.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["AあЖ", "Latin", "Fallback"]);
+        let base_fonts: Vec<String> = pdf
+            .objects
+            .values()
+            .filter_map(|o| o.as_dict().ok())
+            .filter(|d| d.get(b"Subtype").and_then(|o| o.as_name()).ok() == Some(b"Type0"))
+            .map(|d| {
+                String::from_utf8_lossy(d.get(b"BaseFont").unwrap().as_name().unwrap()).into_owned()
+            })
+            .collect();
+        assert_eq!(base_fonts.len(), 1);
+        assert!(base_fonts[0].ends_with("+TestSans"), "{}", base_fonts[0]);
+    }
+
+    #[test]
+    fn test_generate_pdf_writes_metadata() {
+        // Arrange
+        let metadata = Metadata {
+            title: Some("Annual Report".to_string()),
+            author: Some("Jane Doe".to_string()),
+            producer: Some("pdf2".to_string()),
+            creation_date: Some("2024-01-15T09:00:00Z".to_string()),
+            ..Default::default()
+        };
+        let document = Document::new(
+            vec![Page::new(595.0, 842.0, vec![], vec![], None)],
+            Some(metadata),
+            None,
+        );
+        let output = TempPath::new("generate_metadata.pdf");
+
+        // Act
+        generate_pdf(&document, output.path()).unwrap();
+        let parsed = parse_pdf(output.path()).unwrap();
+        let untitled = build_document(&Document::default(), &FontRegistry::default()).unwrap();
+
+        // Assert
+        assert_eq!(parsed.metadata, document.metadata);
+        assert!(untitled.trailer.get(b"Info").is_err());
+        assert!(untitled.catalog().unwrap().get(b"Metadata").is_err());
+    }
+
+    #[test]
+    fn test_generate_to_bytes_matches_file_output() {
+        // Arrange
+        let text_block = TextBlock::new("Bytes".to_string(), 72.0, 700.0, 12.0, None);
+        let document = Document::new(
+            vec![Page::new(595.0, 842.0, vec![text_block], vec![], None)],
+            None,
+            None,
+        );
+        let output = TempPath::new("generate_bytes.pdf");
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        generate_pdf(&document, output.path()).unwrap();
+
+        // Assert
+        assert!(bytes.starts_with(b"%PDF-1.7"));
+        assert_eq!(bytes, fs::read(output.path()).unwrap());
+        let parsed = parse_bytes(&bytes, None).unwrap();
+        assert_eq!(parsed.pages[0].text_blocks()[0].text, "Bytes");
+    }
 }
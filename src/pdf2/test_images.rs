@@ -0,0 +1,335 @@
+use crate::images::{embed_image, encode_png, expand_inline_dictionary, extract_image};
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Document, Object, Stream};
+use std::io::Read;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `(width, height, color type, unfiltered scanlines)`.
+    fn read_png(png: &[u8]) -> (u32, u32, u8, Vec<u8>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut pos = 8;
+        let (mut width, mut height, mut color_type) = (0, 0, 0);
+        let mut idat = Vec::new();
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind = &png[pos + 4..pos + 8];
+            let data = &png[pos + 8..pos + 8 + len];
+            match kind {
+                b"IHDR" => {
+                    width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+                    height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+                    color_type = data[9];
+                }
+                b"IDAT" => idat.extend_from_slice(data),
+                _ => {}
+            }
+            pos += 12 + len;
+        }
+        let mut raw = Vec::new();
+        ZlibDecoder::new(idat.as_slice())
+            .read_to_end(&mut raw)
+            .unwrap();
+        (width, height, color_type, raw)
+    }
+
+    fn image_dict(width: i64, height: i64, color_space: Object, bpc: i64) -> Dictionary {
+        Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Image".to_vec())),
+            ("Width", width.into()),
+            ("Height", height.into()),
+            ("ColorSpace", color_space),
+            ("BitsPerComponent", bpc.into()),
+        ])
+    }
+
+    #[test]
+    fn test_encode_png_layout() {
+        // Act
+        let png = encode_png(2, 1, 3, &[255, 0, 0, 0, 0, 255]);
+
+        // Assert
+        let (width, height, color_type, raw) = read_png(&png);
+        assert_eq!((width, height, color_type), (2, 1, 2));
+        assert_eq!(raw, vec![0, 255, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_dct_image_is_passed_through() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let mut dict = image_dict(1, 1, Object::Name(b"DeviceRGB".to_vec()), 8);
+        dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 1, 2, 3];
+
+        // Act
+        let (data, format) = extract_image(&doc, &dict, &jpeg, None).unwrap();
+
+        // Assert
+        assert_eq!(format, "jpeg");
+        assert_eq!(data, jpeg);
+    }
+
+    #[test]
+    fn test_jpx_image_is_passed_through() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let mut dict = image_dict(1, 1, Object::Name(b"DeviceRGB".to_vec()), 8);
+        dict.set("Filter", Object::Name(b"JPXDecode".to_vec()));
+
+        // Act
+        let (_, format) = extract_image(&doc, &dict, b"jp2 data", None).unwrap();
+
+        // Assert
+        assert_eq!(format, "jp2");
+    }
+
+    #[test]
+    fn test_flate_rgb_image_becomes_png() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let mut stream = Stream::new(
+            image_dict(2, 2, Object::Name(b"DeviceRGB".to_vec()), 8),
+            vec![10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 110, 120],
+        );
+        stream.compress().unwrap();
+
+        // Act
+        let (data, format) = extract_image(&doc, &stream.dict, &stream.content, None).unwrap();
+
+        // Assert
+        assert_eq!(format, "png");
+        let (width, height, color_type, raw) = read_png(&data);
+        assert_eq!((width, height, color_type), (2, 2, 2));
+        assert_eq!(
+            raw,
+            vec![0, 10, 20, 30, 40, 50, 60, 0, 70, 80, 90, 100, 110, 120]
+        );
+    }
+
+    #[test]
+    fn test_indexed_cmyk_and_one_bit_images() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let indexed = Object::Array(vec![
+            Object::Name(b"Indexed".to_vec()),
+            Object::Name(b"DeviceRGB".to_vec()),
+            1.into(),
+            Object::string_literal(vec![255, 0, 0, 0, 0, 255]),
+        ]);
+        let indexed_dict = image_dict(2, 1, indexed, 8);
+        let cmyk_dict = image_dict(1, 1, Object::Name(b"DeviceCMYK".to_vec()), 8);
+        let bits_dict = image_dict(4, 1, Object::Name(b"DeviceGray".to_vec()), 1);
+
+        // Act
+        let (indexed_png, _) = extract_image(&doc, &indexed_dict, &[1, 0], None).unwrap();
+        let (cmyk_png, _) = extract_image(&doc, &cmyk_dict, &[0, 255, 255, 0], None).unwrap();
+        let (bits_png, _) = extract_image(&doc, &bits_dict, &[0b1010_0000], None).unwrap();
+
+        // Assert
+        assert_eq!(read_png(&indexed_png).3, vec![0, 0, 0, 255, 255, 0, 0]);
+        assert_eq!(read_png(&cmyk_png).3, vec![0, 255, 0, 0]);
+        let (_, _, color_type, raw) = read_png(&bits_png);
+        assert_eq!(color_type, 0);
+        assert_eq!(raw, vec![0, 255, 0, 255, 0]);
+    }
+
+    #[test]
+    fn test_soft_mask_adds_alpha_channel() {
+        // Arrange
+        let mut doc = Document::with_version("1.5");
+        let mask_id = doc.add_object(Stream::new(
+            image_dict(2, 1, Object::Name(b"DeviceGray".to_vec()), 8),
+            vec![255, 128],
+        ));
+        let mut dict = image_dict(2, 1, Object::Name(b"DeviceGray".to_vec()), 8);
+        dict.set("SMask", mask_id);
+
+        // Act
+        let (png, _) = extract_image(&doc, &dict, &[0, 200], None).unwrap();
+
+        // Assert
+        let (_, _, color_type, raw) = read_png(&png);
+        assert_eq!(color_type, 4);
+        assert_eq!(raw, vec![0, 0, 255, 200, 128]);
+    }
+
+    #[test]
+    fn test_inline_dictionary_expansion_and_filters() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let inline = Dictionary::from_iter(vec![
+            ("W", 2.into()),
+            ("H", 1.into()),
+            ("CS", Object::Name(b"G".to_vec())),
+            ("BPC", 8.into()),
+            (
+                "F",
+                vec![Object::Name(b"AHx".to_vec()), Object::Name(b"RL".to_vec())].into(),
+            ),
+        ]);
+
+        // Act
+        let dict = expand_inline_dictionary(&inline);
+        // Run-length: repeat 0x7F twice (257 - 255 = 2), then end of data.
+        let (png, format) = extract_image(&doc, &dict, b"FF7F80>", None).unwrap();
+
+        // Assert
+        assert_eq!(dict.get(b"Width").unwrap().as_i64().unwrap(), 2);
+        assert_eq!(
+            dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceGray"
+        );
+        assert_eq!(format, "png");
+        assert_eq!(read_png(&png).3, vec![0, 0x7F, 0x7F]);
+    }
+
+    #[test]
+    fn test_unsupported_color_space_is_skipped() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+        let dict = image_dict(1, 1, Object::Name(b"Pattern".to_vec()), 8);
+
+        // Act & Assert
+        assert!(extract_image(&doc, &dict, &[0], None).is_none());
+    }
+
+    #[test]
+    fn test_invalid_bits_per_component_is_skipped() {
+        // Arrange
+        let doc = Document::with_version("1.5");
+
+        for bpc in [0, -1, 3, 32, i64::MAX] {
+            let dict = image_dict(2, 2, Object::Name(b"DeviceGray".to_vec()), bpc);
+
+            // Act & Assert
+            assert!(extract_image(&doc, &dict, &[0; 64], None).is_none());
+        }
+    }
+
+    /// Smallest possible JPEG header: SOI followed by a baseline frame.
+    fn jpeg_header(width: u16, height: u16, components: u8) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 8]);
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.extend_from_slice(&[components, 1, 0x11, 0]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_embed_jpeg_reads_frame_header() {
+        // Arrange
+        let jpeg = jpeg_header(640, 480, 1);
+
+        // Act
+        let embedded = embed_image("jpeg", &jpeg, 0, 0).unwrap();
+
+        // Assert
+        let dict = &embedded.stream.dict;
+        assert_eq!(dict.get(b"Width").unwrap().as_i64().unwrap(), 640);
+        assert_eq!(dict.get(b"Height").unwrap().as_i64().unwrap(), 480);
+        assert_eq!(
+            dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceGray"
+        );
+        assert_eq!(
+            dict.get(b"Filter").unwrap().as_name().unwrap(),
+            b"DCTDecode"
+        );
+        assert_eq!(embedded.stream.content, jpeg);
+        assert!(embedded.soft_mask.is_none());
+    }
+
+    #[test]
+    fn test_embed_png_splits_alpha_into_soft_mask() {
+        // Arrange
+        let png = encode_png(2, 1, 4, &[255, 0, 0, 128, 0, 0, 255, 255]);
+
+        // Act
+        let embedded = embed_image("png", &png, 0, 0).unwrap();
+
+        // Assert
+        assert_eq!(embedded.stream.content, vec![255, 0, 0, 0, 0, 255]);
+        let mask = embedded.soft_mask.unwrap();
+        assert_eq!(mask.content, vec![128, 255]);
+        assert_eq!(
+            mask.dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceGray"
+        );
+    }
+
+    #[test]
+    fn test_embed_png_rejects_bit_depths_invalid_for_colour_type() {
+        // Arrange: the IHDR bit depth and colour type sit at bytes 24 and 25.
+        let png = encode_png(2, 1, 4, &[255, 0, 0, 128, 0, 0, 255, 255]);
+        let patched = |color_type: u8, bit_depth: u8| {
+            let mut png = png.clone();
+            png[24] = bit_depth;
+            png[25] = color_type;
+            png
+        };
+
+        for (color_type, bit_depth) in [
+            (0, 0),
+            (0, 3),
+            (2, 4),
+            (3, 16),
+            (4, 1),
+            (4, 4),
+            (6, 2),
+            (6, 4),
+        ] {
+            // Act
+            let result = embed_image("png", &patched(color_type, bit_depth), 0, 0);
+
+            // Assert
+            assert!(
+                result.is_err(),
+                "colour type {} at bit depth {} was accepted",
+                color_type,
+                bit_depth
+            );
+        }
+    }
+
+    #[test]
+    fn test_embed_opaque_png_keeps_compressed_data() {
+        // Arrange
+        let png = encode_png(2, 2, 3, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        let mut doc = Document::with_version("1.5");
+
+        // Act
+        let embedded = embed_image("png", &png, 0, 0).unwrap();
+        let id = doc.add_object(embedded.stream.clone());
+
+        // Assert: decoding through the PNG predictor yields the pixels again.
+        let stream = doc.get_object(id).unwrap().as_stream().unwrap();
+        assert_eq!(
+            stream.decompressed_content().unwrap(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+        );
+        assert!(embedded.soft_mask.is_none());
+    }
+
+    #[test]
+    fn test_embed_raw_pixels_checks_dimensions() {
+        // Act
+        let rgb = embed_image("rgb", &[0; 12], 2, 2);
+        let gray = embed_image("gray", &[0; 12], 2, 2);
+        let unknown = embed_image("bmp", &[0; 12], 2, 2);
+
+        // Assert
+        let dict = &rgb.unwrap().stream.dict;
+        assert_eq!(
+            dict.get(b"ColorSpace").unwrap().as_name().unwrap(),
+            b"DeviceRGB"
+        );
+        assert!(gray.is_err());
+        assert!(unknown.is_err());
+    }
+}
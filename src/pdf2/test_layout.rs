@@ -0,0 +1,153 @@
+use crate::parser::parse_bytes;
+use crate::structure::Rect;
+use crate::test_utils::{page, text_block, text_operations, PdfBuilder};
+use pyo3::prelude::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rect_near(actual: Rect, expected: Rect) {
+        let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+        assert!(
+            near(actual.x0, expected.x0)
+                && near(actual.y0, expected.y0)
+                && near(actual.x1, expected.x1)
+                && near(actual.y1, expected.y1),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_words_use_parsed_character_advances() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        builder.add_page(
+            text_operations("Hello world", 72.0, 720.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let mut bytes = Vec::new();
+        builder.finish().save_to(&mut bytes).unwrap();
+        let page = &parse_bytes(&bytes, None).unwrap().pages[0];
+
+        // Act
+        let columns = page.columns();
+
+        // Assert
+        assert_eq!(columns.len(), 1);
+        let line = &columns[0].paragraphs[0].lines[0];
+        let words: Vec<&str> = line.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, ["Hello", "world"]);
+        // Helvetica: "Hello" is 2278 units wide, the space 278.
+        assert_rect_near(line.words[0].bbox, Rect::new(72.0, 717.6, 99.336, 729.6));
+        assert_rect_near(line.words[1].bbox, Rect::new(102.672, 717.6, 131.34, 729.6));
+        assert_eq!(line.words[1].font_size, 12.0);
+        assert_rect_near(line.bbox, Rect::new(72.0, 717.6, 131.34, 729.6));
+    }
+
+    #[test]
+    fn test_separately_placed_characters_are_joined_into_words() {
+        // Arrange: "Foo" shown glyph by glyph, then "bar" after a gap.
+        let page = page(vec![
+            text_block("F", 72.0, 700.0, 6.0),
+            text_block("o", 78.0, 700.0, 5.0),
+            text_block("o", 83.0, 700.0, 5.0),
+            text_block("bar", 91.0, 700.0, 15.0),
+        ]);
+
+        // Act
+        let columns = page.columns();
+
+        // Assert
+        let line = &columns[0].paragraphs[0].lines[0];
+        assert_eq!(line.text(), "Foo bar");
+        assert_rect_near(line.words[0].bbox, Rect::new(72.0, 698.0, 88.0, 708.0));
+    }
+
+    #[test]
+    fn test_lines_are_grouped_into_paragraphs_by_gap_and_indent() {
+        // Arrange
+        let page = page(vec![
+            text_block("First paragraph", 72.0, 700.0, 200.0),
+            text_block("continues here.", 72.0, 688.0, 200.0),
+            text_block("After a gap", 72.0, 660.0, 200.0),
+            text_block("it goes on.", 72.0, 648.0, 150.0),
+            text_block("Indented start", 92.0, 636.0, 180.0),
+            text_block("of the last one.", 72.0, 624.0, 200.0),
+        ]);
+
+        // Act
+        let columns = page.columns();
+
+        // Assert
+        assert_eq!(columns.len(), 1);
+        let paragraphs = &columns[0].paragraphs;
+        assert_eq!(paragraphs.len(), 3);
+        assert_eq!(paragraphs[0].text(), "First paragraph\ncontinues here.");
+        assert_eq!(paragraphs[1].lines.len(), 2);
+        assert_eq!(paragraphs[2].text(), "Indented start\nof the last one.");
+        assert_rect_near(paragraphs[0].bbox, Rect::new(72.0, 686.0, 272.0, 708.0));
+    }
+
+    #[test]
+    fn test_columns_are_read_one_after_the_other() {
+        // Arrange: a title over two columns whose lines and paragraph
+        // breaks align, and a footer under them.
+        let mut contents = vec![text_block("Title across the page", 72.0, 760.0, 448.0)];
+        for (side, x) in [("left", 72.0), ("right", 320.0)] {
+            for (index, y) in [700.0, 688.0, 660.0, 648.0].into_iter().enumerate() {
+                contents.push(text_block(&format!("{} {}", side, index), x, y, 200.0));
+            }
+        }
+        contents.push(text_block("Footer", 72.0, 100.0, 448.0));
+        let page = page(contents);
+
+        // Act
+        let columns = page.columns();
+
+        // Assert
+        let texts: Vec<String> = columns.iter().map(|c| c.text()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Title across the page",
+                "left 0\nleft 1\n\nleft 2\nleft 3",
+                "right 0\nright 1\n\nright 2\nright 3",
+                "Footer",
+            ]
+        );
+        assert_rect_near(columns[2].bbox, Rect::new(320.0, 646.0, 520.0, 708.0));
+        assert_eq!(
+            page.text(),
+            "Title across the page\n\nleft 0\nleft 1\n\nleft 2\nleft 3\n\n\
+             right 0\nright 1\n\nright 2\nright 3\n\nFooter"
+        );
+    }
+
+    #[test]
+    fn test_page_text_from_python() {
+        Python::with_gil(|py| {
+            // Arrange
+            let page = Py::new(
+                py,
+                page(vec![
+                    text_block("world", 100.0, 700.0, 25.0),
+                    text_block("Hello", 72.0, 700.0, 25.0),
+                ]),
+            )
+            .unwrap();
+
+            // Act
+            let text: String = page.call_method0(py, "text").unwrap().extract(py).unwrap();
+            let columns = page.call_method0(py, "columns").unwrap();
+
+            // Assert
+            assert_eq!(text, "Hello world");
+            assert_eq!(columns.bind(py).len().unwrap(), 1);
+        });
+    }
+}
@@ -0,0 +1,163 @@
+use crate::font_registry::FontRegistry;
+use crate::generator::generate_to_bytes;
+use crate::links::read_links;
+use crate::names::named_destinations;
+use crate::parser::parse_bytes;
+use crate::structure::{Destination, Document, Link, Page, Rect};
+use crate::test_utils::{text_operations, PdfBuilder};
+use lopdf::{Dictionary, Object};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_round_trip_through_generate_and_parse() {
+        // Arrange
+        let links = vec![
+            Link {
+                rect: Rect::new(72.0, 700.0, 200.5, 720.0),
+                uri: Some("https://example.com/a b".to_string()),
+                ..Default::default()
+            },
+            Link {
+                rect: Rect::new(72.0, 650.0, 200.0, 670.0),
+                destination: Some(Destination {
+                    page: 1,
+                    kind: "XYZ".to_string(),
+                    top: Some(400.0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Link {
+                rect: Rect::new(72.0, 600.0, 200.0, 620.0),
+                destination: Some(Destination {
+                    page: 1,
+                    ..Default::default()
+                }),
+                named_destination: Some("chapter-2".to_string()),
+                ..Default::default()
+            },
+        ];
+        let document = Document::new(
+            vec![
+                Page::new(595.0, 842.0, vec![], vec![], Some(links.clone())),
+                Page::new(595.0, 842.0, vec![], vec![], None),
+            ],
+            None,
+            None,
+        );
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        let mut expected = links;
+        expected[0].uri = Some("https://example.com/a%20b".to_string());
+        assert_eq!(parsed.pages[0].links, expected);
+        assert!(parsed.pages[1].links.is_empty());
+        let pdf = lopdf::Document::load_mem(&bytes).unwrap();
+        assert!(named_destinations(&pdf).contains_key(b"chapter-2".as_slice()));
+    }
+
+    #[test]
+    fn test_generate_rejects_link_without_target() {
+        // Arrange
+        let link = Link::new(Rect::new(0.0, 0.0, 10.0, 10.0), None, None, None);
+        let document = Document::new(
+            vec![Page::new(595.0, 842.0, vec![], vec![], Some(vec![link]))],
+            None,
+            None,
+        );
+
+        // Act
+        let result = generate_to_bytes(&document, &FontRegistry::default());
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("no uri"));
+    }
+
+    #[test]
+    fn test_read_links_resolves_actions_and_skips_other_annotations() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let first = builder.add_page(
+            text_operations("One", 72.0, 720.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let second = builder.add_page(vec![], Dictionary::new(), vec![]);
+        let annotation = |rect: [i64; 4], entries: Vec<(&str, Object)>| {
+            let mut dict = Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Annot".to_vec())),
+                ("Subtype", Object::Name(b"Link".to_vec())),
+                (
+                    "Rect",
+                    rect.iter()
+                        .map(|&v| v.into())
+                        .collect::<Vec<Object>>()
+                        .into(),
+                ),
+            ]);
+            for (key, value) in entries {
+                dict.set(key, value);
+            }
+            dict
+        };
+        let action = |s: &str, key: &str, value: Object| {
+            Object::Dictionary(Dictionary::from_iter(vec![
+                ("S", Object::Name(s.as_bytes().to_vec())),
+                (key, value),
+            ]))
+        };
+        let goto = builder.doc.add_object(annotation(
+            [300, 720, 100, 700],
+            vec![("A", action("GoTo", "D", Object::Name(b"end".to_vec())))],
+        ));
+        let script = builder.doc.add_object(annotation(
+            [0, 0, 10, 10],
+            vec![(
+                "A",
+                action("JavaScript", "JS", Object::string_literal("app.alert(1)")),
+            )],
+        ));
+        let mut note = annotation([0, 0, 10, 10], vec![]);
+        note.set("Subtype", Object::Name(b"Text".to_vec()));
+        let note = builder.doc.add_object(note);
+        builder
+            .doc
+            .get_dictionary_mut(first)
+            .unwrap()
+            .set("Annots", vec![goto.into(), script.into(), note.into()]);
+        let mut doc = builder.finish();
+        let dests = Dictionary::from_iter(vec![(
+            "end",
+            Object::Array(vec![second.into(), "FitV".into(), 36.into()]),
+        )]);
+        doc.catalog_mut().unwrap().set("Dests", dests);
+        let page_indices = HashMap::from([(first, 0), (second, 1)]);
+
+        // Act
+        let links = read_links(&doc, first, &named_destinations(&doc), &page_indices);
+
+        // Assert
+        assert_eq!(
+            links,
+            vec![Link {
+                rect: Rect::new(100.0, 700.0, 300.0, 720.0),
+                destination: Some(Destination {
+                    page: 1,
+                    kind: "FitV".to_string(),
+                    left: Some(36.0),
+                    ..Default::default()
+                }),
+                named_destination: Some("end".to_string()),
+                ..Default::default()
+            }]
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use crate::matrix::Matrix;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiply_applies_left_operand_first() {
+        // Arrange
+        let scale = Matrix::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0);
+        let translate = Matrix::translate(10.0, 5.0);
+
+        // Act
+        let combined = scale.multiply(&translate);
+
+        // Assert
+        assert_eq!(combined.transform_point(1.0, 1.0), (12.0, 7.0));
+        assert_eq!(
+            translate.multiply(&scale).transform_point(1.0, 1.0),
+            (22.0, 12.0)
+        );
+    }
+
+    #[test]
+    fn test_from_slice_and_scales() {
+        // Act
+        let m = Matrix::from_slice(&[0.0, 3.0, -4.0, 0.0, 1.0, 2.0]).unwrap();
+
+        // Assert
+        assert_eq!(m.horizontal_scale(), 3.0);
+        assert_eq!(m.vertical_scale(), 4.0);
+        assert!(Matrix::from_slice(&[1.0, 2.0]).is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::merge::{merge_documents, MergeError, MergeInput};
+use crate::outline::MAX_OUTLINE_DEPTH;
+use crate::page_selection::PageSelection;
+use crate::test_utils::{
+    add_nested_outline, page_texts, text_document, text_operations, PdfBuilder,
+};
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Object, ObjectId, Stream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(document: lopdf::Document, pages: Option<&str>) -> MergeInput {
+        MergeInput {
+            document,
+            pages: pages.map(|spec| PageSelection::parse(spec).unwrap()),
+        }
+    }
+
+    fn outline_titles(doc: &lopdf::Document) -> Vec<(String, Dictionary)> {
+        let outlines = doc.catalog().unwrap().get(b"Outlines").unwrap();
+        let outlines = doc.dereference(outlines).unwrap().1.as_dict().unwrap();
+        let mut items = Vec::new();
+        let mut next = outlines.get(b"First").ok().cloned();
+        while let Some(Object::Reference(id)) = next {
+            let item = doc.get_dictionary(id).unwrap();
+            let title = item.get(b"Title").unwrap().as_str().unwrap();
+            items.push((String::from_utf8_lossy(title).into_owned(), item.clone()));
+            next = item.get(b"Next").ok().cloned();
+        }
+        items
+    }
+
+    fn count_objects(doc: &lopdf::Document, predicate: impl Fn(&Object) -> bool) -> usize {
+        doc.objects.values().filter(|o| predicate(o)).count()
+    }
+
+    #[test]
+    fn test_merge_documents_concatenates_selected_pages() {
+        // Arrange
+        let inputs = vec![
+            input(text_document(&["A1", "A2"], vec![]).0, Some("2")),
+            input(text_document(&["B1", "B2", "B3"], vec![]).0, Some("3-1")),
+        ];
+
+        // Act
+        let mut merged = merge_documents(&inputs).unwrap();
+
+        // Assert
+        let mut bytes = Vec::new();
+        merged.save_to(&mut bytes).unwrap();
+        assert_eq!(page_texts(&bytes), vec!["A2", "B3", "B2", "B1"]);
+    }
+
+    #[test]
+    fn test_merge_documents_rejects_pages_out_of_range() {
+        // Arrange
+        let inputs = vec![
+            input(text_document(&["A1"], vec![]).0, None),
+            input(text_document(&["B1"], vec![]).0, Some("2")),
+        ];
+
+        // Act
+        let result = merge_documents(&inputs);
+
+        // Assert
+        assert!(matches!(result, Err(MergeError::Pages { index: 1, .. })));
+    }
+
+    #[test]
+    fn test_merge_documents_shares_identical_fonts_and_images() {
+        // Arrange
+        let with_image = |text: &str| {
+            let mut builder = PdfBuilder::new();
+            let font_id = builder.add_standard_font("Helvetica");
+            let image_id = builder.doc.add_object(Stream::new(
+                Dictionary::from_iter(vec![
+                    ("Type", Object::Name(b"XObject".to_vec())),
+                    ("Subtype", Object::Name(b"Image".to_vec())),
+                    ("Width", 1.into()),
+                    ("Height", 1.into()),
+                    ("ColorSpace", Object::Name(b"DeviceRGB".to_vec())),
+                    ("BitsPerComponent", 8.into()),
+                ]),
+                vec![255, 0, 0],
+            ));
+            let mut resources = PdfBuilder::font_resources(font_id);
+            resources.set(
+                "XObject",
+                Dictionary::from_iter(vec![("Im1", image_id.into())]),
+            );
+            let mut operations = text_operations(text, 72.0, 720.0, 12.0);
+            operations.push(Operation::new("Do", vec!["Im1".into()]));
+            builder.add_page(operations, resources, vec![]);
+            builder.finish()
+        };
+        let inputs = vec![input(with_image("A"), None), input(with_image("B"), None)];
+
+        // Act
+        let mut merged = merge_documents(&inputs).unwrap();
+
+        // Assert
+        let is_type = |object: &Object, name: &[u8]| {
+            let dict = match object {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => return false,
+            };
+            dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(name)
+                || dict.get(b"Type").and_then(Object::as_name).ok() == Some(name)
+        };
+        assert_eq!(count_objects(&merged, |o| is_type(o, b"Font")), 1);
+        assert_eq!(count_objects(&merged, |o| is_type(o, b"Image")), 1);
+        assert_eq!(count_objects(&merged, |o| is_type(o, b"Page")), 2);
+        let mut bytes = Vec::new();
+        merged.save_to(&mut bytes).unwrap();
+        assert_eq!(page_texts(&bytes), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_merge_documents_bounds_outline_depth() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["A1"], vec![]);
+        add_nested_outline(&mut doc, pages[0], 20_000);
+
+        // Act
+        let merged = merge_documents(&[input(doc, None)]).unwrap();
+
+        // Assert
+        let mut depth = 0;
+        let mut next = outline_titles(&merged)
+            .first()
+            .map(|(_, item)| item.clone());
+        while let Some(item) = next {
+            depth += 1;
+            next = item
+                .get(b"First")
+                .and_then(Object::as_reference)
+                .ok()
+                .map(|id| merged.get_dictionary(id).unwrap().clone());
+        }
+        assert_eq!(depth, MAX_OUTLINE_DEPTH);
+    }
+
+    #[test]
+    fn test_merge_documents_merges_outlines_and_named_destinations() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let mut pages = Vec::new();
+        for text in ["A1", "A2"] {
+            pages.push(builder.add_page(
+                text_operations(text, 72.0, 720.0, 12.0),
+                PdfBuilder::font_resources(font_id),
+                vec![],
+            ));
+        }
+        let outline_id = builder.doc.new_object_id();
+        let dest = |page: ObjectId| Object::Array(vec![page.into(), "Fit".into()]);
+        let a1 = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("A1")),
+            ("Parent", outline_id.into()),
+            ("Dest", dest(pages[0])),
+        ]));
+        let a2 = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("A2")),
+            ("Parent", outline_id.into()),
+            ("Prev", a1.into()),
+            ("Dest", Object::string_literal("intro")),
+        ]));
+        builder.doc.get_dictionary_mut(a1).unwrap().set("Next", a2);
+        builder.doc.objects.insert(
+            outline_id,
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"Outlines".to_vec())),
+                ("First", a1.into()),
+                ("Last", a2.into()),
+                ("Count", 2.into()),
+            ])
+            .into(),
+        );
+        let mut first = builder.finish();
+        let names = Dictionary::from_iter(vec![(
+            "Names",
+            Object::Array(vec![Object::string_literal("intro"), dest(pages[1])]),
+        )]);
+        let catalog = first.catalog_mut().unwrap();
+        catalog.set("Outlines", outline_id);
+        catalog.set(
+            "Names",
+            Dictionary::from_iter(vec![("Dests", names.into())]),
+        );
+
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let page = builder.add_page(
+            text_operations("B1", 72.0, 720.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let outline_id = builder.doc.new_object_id();
+        let b1 = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("B1")),
+            ("Parent", outline_id.into()),
+            ("Dest", Object::string_literal("intro")),
+        ]));
+        builder.doc.objects.insert(
+            outline_id,
+            Dictionary::from_iter(vec![
+                ("First", b1.into()),
+                ("Last", b1.into()),
+                ("Count", 1.into()),
+            ])
+            .into(),
+        );
+        let mut second = builder.finish();
+        let dests = Dictionary::from_iter(vec![("intro", dest(page))]);
+        let catalog = second.catalog_mut().unwrap();
+        catalog.set("Outlines", outline_id);
+        catalog.set("Dests", dests);
+
+        let inputs = vec![input(first, Some("1")), input(second, None)];
+
+        // Act
+        let merged = merge_documents(&inputs).unwrap();
+
+        // Assert
+        let items = outline_titles(&merged);
+        let titles: Vec<&str> = items.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, vec!["A1", "B1"]);
+        assert_eq!(
+            items[1].1.get(b"Dest").unwrap(),
+            &Object::string_literal("intro-2")
+        );
+        let pages = merged.get_pages();
+        let a1_dest = items[0].1.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(a1_dest[0].as_reference().unwrap(), pages[&1]);
+
+        let names = merged.catalog().unwrap().get(b"Names").unwrap();
+        let names = merged.dereference(names).unwrap().1.as_dict().unwrap();
+        let tree = names.get(b"Dests").unwrap();
+        let tree = merged.dereference(tree).unwrap().1.as_dict().unwrap();
+        let entries = tree.get(b"Names").unwrap().as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], Object::string_literal("intro-2"));
+        let target = merged
+            .dereference(&entries[1])
+            .unwrap()
+            .1
+            .as_array()
+            .unwrap();
+        assert_eq!(target[0].as_reference().unwrap(), pages[&2]);
+    }
+}
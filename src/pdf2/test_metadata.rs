@@ -0,0 +1,140 @@
+use crate::metadata::{
+    decode_text_string, encode_text_string, info_dictionary, iso_to_pdf_date, pdf_date_to_iso,
+    read_metadata, xmp_stream,
+};
+use crate::structure::Metadata;
+use crate::test_utils::PdfBuilder;
+use lopdf::{Dictionary, Object, Stream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_dates_convert_to_and_from_iso() {
+        // Arrange
+        let cases = [
+            ("D:20240301123005+01'00'", "2024-03-01T12:30:05+01:00"),
+            ("D:20240301123005Z", "2024-03-01T12:30:05Z"),
+            ("D:199812231952-08'00'", "1998-12-23T19:52:00-08:00"),
+            ("D:202403", "2024-03"),
+        ];
+
+        // Act & Assert
+        for (pdf, iso) in cases {
+            assert_eq!(pdf_date_to_iso(pdf).as_deref(), Some(iso), "{}", pdf);
+        }
+        assert_eq!(
+            iso_to_pdf_date("2024-03-01T12:30:05.250+01:00").as_deref(),
+            Some("D:20240301123005+01'00'")
+        );
+        assert_eq!(
+            iso_to_pdf_date("1998-12-23T19:52-08:00").as_deref(),
+            Some("D:199812231952-08'00'")
+        );
+        assert_eq!(iso_to_pdf_date("2024").as_deref(), Some("D:2024"));
+        assert_eq!(pdf_date_to_iso("yesterday"), None);
+        assert_eq!(iso_to_pdf_date("D:20240301"), None);
+    }
+
+    #[test]
+    fn test_text_strings_use_pdf_doc_encoding_or_utf16() {
+        // Arrange
+        let latin = "Café – “quoted”";
+        let japanese = "日本語のタイトル";
+
+        // Act
+        let latin_object = encode_text_string(latin);
+        let japanese_object = encode_text_string(japanese);
+
+        // Assert
+        let latin_bytes = latin_object.as_str().unwrap();
+        assert_eq!(latin_bytes.len(), latin.chars().count());
+        assert_eq!(decode_text_string(latin_bytes), latin);
+        let japanese_bytes = japanese_object.as_str().unwrap();
+        assert!(japanese_bytes.starts_with(&[0xFE, 0xFF]));
+        assert_eq!(decode_text_string(japanese_bytes), japanese);
+        assert_eq!(decode_text_string(b"\xEF\xBB\xBFna\xC3\xAFve"), "naïve");
+    }
+
+    #[test]
+    fn test_read_metadata_prefers_xmp_over_info() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        builder.add_page(vec![], Dictionary::new(), vec![]);
+        let mut pdf = builder.finish();
+        let info_id = pdf.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("Old title")),
+            ("Author", Object::string_literal("Info Author")),
+            ("CreationDate", Object::string_literal("D:20200102030405Z")),
+            ("Trapped", Object::Name(b"False".to_vec())),
+            ("Department", Object::string_literal("Accounts")),
+        ]));
+        pdf.trailer.set("Info", info_id);
+        let xmp = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+    pdf:Producer="XMP Producer"/>
+  <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">
+   <dc:title><rdf:Alt>
+    <rdf:li xml:lang="fr">Titre</rdf:li>
+    <rdf:li xml:lang="x-default">New &amp; improved</rdf:li>
+   </rdf:Alt></dc:title>
+   <dc:subject><rdf:Bag><rdf:li>one</rdf:li><rdf:li>two</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="r"?>"#;
+        let xmp_id = pdf.add_object(Stream::new(Dictionary::new(), xmp.as_bytes().to_vec()));
+        pdf.catalog_mut().unwrap().set("Metadata", xmp_id);
+
+        // Act
+        let metadata = read_metadata(&pdf);
+
+        // Assert
+        assert_eq!(metadata.title.as_deref(), Some("New & improved"));
+        assert_eq!(metadata.author.as_deref(), Some("Info Author"));
+        assert_eq!(metadata.producer.as_deref(), Some("XMP Producer"));
+        assert_eq!(metadata.keywords.as_deref(), Some("one, two"));
+        assert_eq!(
+            metadata.creation_date.as_deref(),
+            Some("2020-01-02T03:04:05Z")
+        );
+        assert_eq!(metadata.custom.len(), 1);
+        assert_eq!(metadata.custom["Department"], "Accounts");
+    }
+
+    #[test]
+    fn test_info_and_xmp_carry_the_same_values() {
+        // Arrange
+        let metadata = Metadata {
+            title: Some("Quarterly <Report>".to_string()),
+            author: Some("Zoë Écrivain".to_string()),
+            keywords: Some("finance; q3".to_string()),
+            creator: Some("Writer".to_string()),
+            mod_date: Some("2024-03-01T12:30:05+01:00".to_string()),
+            custom: [("Department".to_string(), "Accounts".to_string())].into(),
+            ..Default::default()
+        };
+
+        // Act
+        let info = info_dictionary(&metadata);
+        let xmp = xmp_stream(&metadata);
+
+        // Assert
+        assert_eq!(
+            info.get(b"ModDate").unwrap().as_str().unwrap(),
+            b"D:20240301123005+01'00'"
+        );
+        assert!(!xmp.allows_compression);
+        let mut info_only = lopdf::Document::with_version("1.7");
+        let info_id = info_only.add_object(info);
+        info_only.trailer.set("Info", info_id);
+        let mut xmp_only = PdfBuilder::new().finish();
+        let xmp_id = xmp_only.add_object(xmp);
+        xmp_only.catalog_mut().unwrap().set("Metadata", xmp_id);
+        assert_eq!(read_metadata(&info_only), metadata);
+        assert_eq!(read_metadata(&xmp_only), metadata);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::font_registry::FontRegistry;
+use crate::generator::generate_to_bytes;
+use crate::outline::{read_outline, MAX_OUTLINE_DEPTH};
+use crate::parser::parse_bytes;
+use crate::structure::{Destination, Document, OutlineItem, Page};
+use crate::test_utils::{add_nested_outline, text_document, text_operations, PdfBuilder};
+use lopdf::{Dictionary, Object};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(count: usize) -> Vec<Page> {
+        (0..count)
+            .map(|_| Page::new(595.0, 842.0, vec![], vec![], None))
+            .collect()
+    }
+
+    #[test]
+    fn test_outline_round_trips_through_generate_and_parse() {
+        // Arrange
+        let outline = vec![
+            OutlineItem {
+                title: "Introduction".to_string(),
+                destination: Some(Destination {
+                    page: 0,
+                    kind: "XYZ".to_string(),
+                    left: Some(72.0),
+                    top: Some(700.0),
+                    ..Default::default()
+                }),
+                open: true,
+                children: vec![OutlineItem {
+                    title: "Scope".to_string(),
+                    destination: Some(Destination {
+                        page: 1,
+                        kind: "FitH".to_string(),
+                        top: Some(500.0),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+            },
+            OutlineItem {
+                title: "Übersicht – Anhang".to_string(),
+                children: vec![OutlineItem {
+                    title: "Tables".to_string(),
+                    destination: Some(Destination {
+                        page: 2,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+        let document = Document::new(pages(3), None, Some(outline.clone()));
+
+        // Act
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let parsed = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        assert_eq!(parsed.outline, outline);
+        let pdf = lopdf::Document::load_mem(&bytes).unwrap();
+        let catalog = pdf.catalog().unwrap();
+        assert_eq!(
+            catalog.get(b"PageMode").unwrap().as_name().unwrap(),
+            b"UseOutlines"
+        );
+        let outlines = pdf
+            .get_dictionary(catalog.get(b"Outlines").unwrap().as_reference().unwrap())
+            .unwrap();
+        // Both top-level items, and the open item's child.
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_generate_rejects_outline_with_missing_page() {
+        // Arrange
+        let outline = vec![OutlineItem::new(
+            "Nowhere".to_string(),
+            Some(Destination {
+                page: 5,
+                ..Default::default()
+            }),
+            None,
+            false,
+        )];
+        let document = Document::new(pages(1), None, Some(outline));
+
+        // Act
+        let result = generate_to_bytes(&document, &FontRegistry::default());
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("Nowhere"));
+    }
+
+    #[test]
+    fn test_read_outline_resolves_named_destinations_and_actions() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let mut page_ids = Vec::new();
+        for text in ["One", "Two"] {
+            page_ids.push(builder.add_page(
+                text_operations(text, 72.0, 720.0, 12.0),
+                PdfBuilder::font_resources(font_id),
+                vec![],
+            ));
+        }
+        let outlines_id = builder.doc.new_object_id();
+        let named = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("Named")),
+            ("Parent", outlines_id.into()),
+            ("Dest", Object::Name(b"second".to_vec())),
+            ("Count", (-1).into()),
+        ]));
+        let action = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("Action")),
+            ("Parent", named.into()),
+            (
+                "A",
+                Dictionary::from_iter(vec![
+                    ("S", Object::Name(b"GoTo".to_vec())),
+                    (
+                        "D",
+                        vec![
+                            page_ids[0].into(),
+                            "XYZ".into(),
+                            Object::Null,
+                            600.into(),
+                            0.into(),
+                        ]
+                        .into(),
+                    ),
+                ])
+                .into(),
+            ),
+        ]));
+        let link = builder.doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("Web")),
+            ("Parent", outlines_id.into()),
+            ("Prev", named.into()),
+            (
+                "A",
+                Dictionary::from_iter(vec![
+                    ("S", Object::Name(b"URI".to_vec())),
+                    ("URI", Object::string_literal("https://example.com")),
+                ])
+                .into(),
+            ),
+        ]));
+        let item = builder.doc.get_dictionary_mut(named).unwrap();
+        item.set("Next", link);
+        item.set("First", action);
+        item.set("Last", action);
+        builder.doc.objects.insert(
+            outlines_id,
+            Dictionary::from_iter(vec![("First", named.into()), ("Last", link.into())]).into(),
+        );
+        let mut doc = builder.finish();
+        let dests = Dictionary::from_iter(vec![(
+            "second",
+            Object::Array(vec![page_ids[1].into(), "Fit".into()]),
+        )]);
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set("Outlines", outlines_id);
+        catalog.set("Dests", dests);
+
+        // Act
+        let outline = read_outline(&doc);
+
+        // Assert
+        let titles: Vec<&str> = outline.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, vec!["Named", "Web"]);
+        assert_eq!(outline[0].destination.as_ref().unwrap().page, 1);
+        assert_eq!(outline[0].destination.as_ref().unwrap().kind, "Fit");
+        assert!(!outline[0].open);
+        assert_eq!(
+            outline[0].children[0].destination,
+            Some(Destination {
+                page: 0,
+                kind: "XYZ".to_string(),
+                top: Some(600.0),
+                ..Default::default()
+            })
+        );
+        assert_eq!(outline[1].destination, None);
+    }
+
+    #[test]
+    fn test_read_outline_bounds_nesting_depth() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["One"], vec![]);
+        add_nested_outline(&mut doc, pages[0], 20_000);
+
+        // Act
+        let outline = read_outline(&doc);
+
+        // Assert
+        let mut depth = 0;
+        let mut items = &outline;
+        while let Some(item) = items.first() {
+            assert_eq!(item.destination.as_ref().unwrap().page, 0);
+            depth += 1;
+            items = &item.children;
+        }
+        assert_eq!(depth, MAX_OUTLINE_DEPTH);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::page_selection::{PageSelection, PageSelectionError};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_selection_resolves_ranges_in_order() {
+        // Arrange
+        let selection: PageSelection = "1-3, 7,10-".parse().unwrap();
+
+        // Act
+        let indices = selection.indices(12).unwrap();
+
+        // Assert
+        assert_eq!(indices, vec![0, 1, 2, 6, 9, 10, 11]);
+        assert_eq!(
+            PageSelection::parse("-2").unwrap().indices(5).unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            PageSelection::parse("4-2,2").unwrap().indices(5).unwrap(),
+            vec![3, 2, 1, 1]
+        );
+        assert_eq!(PageSelection::all().indices(3).unwrap(), vec![0, 1, 2]);
+        assert!(PageSelection::all().indices(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_page_selection_rejects_bad_input() {
+        // Act & Assert
+        for spec in ["", "0", "1,,2", "a-3", "1-2-3", "3x"] {
+            assert_eq!(
+                PageSelection::parse(spec),
+                Err(PageSelectionError::Syntax(spec.to_string())),
+                "{}",
+                spec
+            );
+        }
+        assert_eq!(
+            PageSelection::parse("2-5").unwrap().indices(4),
+            Err(PageSelectionError::OutOfRange { page: 5, count: 4 })
+        );
+        assert_eq!(
+            PageSelection::parse("6-").unwrap().indices(4),
+            Err(PageSelectionError::OutOfRange { page: 6, count: 4 })
+        );
+    }
+}
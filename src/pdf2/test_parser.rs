@@ -1,5 +1,8 @@
-use crate::parser::parse_pdf;
-use crate::structure::{Document, Page, TextBlock};
+use crate::parser::{parse_bytes, parse_pdf, ParseError};
+use crate::structure::{Document, Page, PageContent, Rect, TextBlock};
+use crate::test_utils::{text_operations, PdfBuilder, TempPath};
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Object, Stream};
 use std::fs;
 use std::path::Path;
 
@@ -84,22 +87,285 @@ mod tests {
             x: 0.0,
             y: 0.0,
             font_size: 0.0,
+            ..Default::default()
         };
 
         let page = Page {
             width: 595.0,
             height: 842.0,
-            text_blocks: vec![text_block],
-            images: vec![],
+            contents: vec![PageContent::Text(text_block)],
+            ..Default::default()
         };
 
-        let document = Document { pages: vec![page] };
+        let document = Document {
+            pages: vec![page],
+            ..Default::default()
+        };
 
         // Act & Assert
         assert_eq!(document.pages.len(), 1);
-        assert_eq!(document.pages[0].text_blocks.len(), 1);
-        assert_eq!(document.pages[0].text_blocks[0].text, "Sample text");
+        assert_eq!(document.pages[0].text_blocks().len(), 1);
+        assert_eq!(document.pages[0].text_blocks()[0].text, "Sample text");
         assert_eq!(document.pages[0].width, 595.0);
         assert_eq!(document.pages[0].height, 842.0);
     }
+
+    #[test]
+    fn test_parse_pdf_extracts_text_positions() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        let mut operations = text_operations("Heading", 72.0, 750.0, 24.0);
+        operations.extend(text_operations("Body text", 72.0, 700.0, 12.0));
+        builder.add_page(operations, PdfBuilder::font_resources(font_id), vec![]);
+        let output = TempPath::new("parse_positions.pdf");
+        builder.finish().save(output.path()).unwrap();
+
+        // Act
+        let document = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        assert_eq!(document.pages.len(), 1);
+        let blocks = &document.pages[0].text_blocks();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].text, "Heading");
+        assert_eq!((blocks[0].x, blocks[0].y), (72.0, 750.0));
+        assert_eq!(blocks[0].font_size, 24.0);
+        assert_eq!(blocks[1].text, "Body text");
+        assert_eq!((blocks[1].x, blocks[1].y), (72.0, 700.0));
+        assert_eq!(blocks[1].font_size, 12.0);
+    }
+
+    #[test]
+    fn test_parse_bytes_reads_documents_in_memory() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        builder.add_page(
+            text_operations("In memory", 72.0, 750.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let mut bytes = Vec::new();
+        builder.finish().save_to(&mut bytes).unwrap();
+
+        // Act
+        let document = parse_bytes(&bytes, None).unwrap();
+        let garbage = parse_bytes(b"not a PDF", None);
+
+        // Assert
+        assert_eq!(document.pages[0].text_blocks()[0].text, "In memory");
+        assert!(matches!(garbage, Err(ParseError::Extract(_))));
+    }
+
+    #[test]
+    fn test_parse_pdf_keeps_pages_without_text() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        builder.add_page(vec![], PdfBuilder::font_resources(font_id), vec![]);
+        builder.add_page(
+            text_operations("Second", 10.0, 20.0, 10.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let output = TempPath::new("parse_blank_page.pdf");
+        builder.finish().save(output.path()).unwrap();
+
+        // Act
+        let document = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        assert_eq!(document.pages.len(), 2);
+        assert!(document.pages[0].text_blocks().is_empty());
+        assert_eq!(document.pages[1].text_blocks()[0].text, "Second");
+    }
+
+    #[test]
+    fn test_parse_bytes_keeps_pages_with_corrupt_contents() {
+        // Arrange: a page whose Flate stream is garbage, and one whose
+        // contents are not a stream at all, between two readable pages.
+        let mut builder = PdfBuilder::new();
+        let font_id = builder.add_standard_font("Helvetica");
+        builder.add_page(
+            text_operations("First", 72.0, 750.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let corrupt_id = builder.doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![("Filter", Object::Name(b"FlateDecode".to_vec()))]),
+            b"\x78\x9c not deflate data".to_vec(),
+        ));
+        builder.add_page(
+            vec![],
+            PdfBuilder::font_resources(font_id),
+            vec![
+                ("Contents", corrupt_id.into()),
+                (
+                    "MediaBox",
+                    vec![0.into(), 0.into(), 300.into(), 400.into()].into(),
+                ),
+            ],
+        );
+        builder.add_page(
+            vec![],
+            PdfBuilder::font_resources(font_id),
+            vec![("Contents", Object::string_literal("BT (Lost) Tj ET"))],
+        );
+        builder.add_page(
+            text_operations("Last", 72.0, 750.0, 12.0),
+            PdfBuilder::font_resources(font_id),
+            vec![],
+        );
+        let mut bytes = Vec::new();
+        builder.finish().save_to(&mut bytes).unwrap();
+
+        // Act
+        let document = parse_bytes(&bytes, None).unwrap();
+
+        // Assert
+        assert_eq!(document.pages.len(), 4);
+        assert_eq!(document.pages[0].text_blocks()[0].text, "First");
+        assert!(document.pages[1].contents.is_empty());
+        assert_eq!(
+            (document.pages[1].width, document.pages[1].height),
+            (300.0, 400.0)
+        );
+        assert!(document.pages[2].contents.is_empty());
+        assert_eq!(document.pages[2].width, 595.0);
+        assert_eq!(document.pages[3].text_blocks()[0].text, "Last");
+    }
+
+    #[test]
+    fn test_parse_pdf_reads_page_boxes() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        builder.add_page(
+            vec![],
+            Default::default(),
+            vec![
+                (
+                    "MediaBox",
+                    vec![0.into(), 0.into(), 612.into(), 792.into()].into(),
+                ),
+                (
+                    "CropBox",
+                    vec![10.into(), 20.into(), 602.into(), 772.into()].into(),
+                ),
+                (
+                    "TrimBox",
+                    vec![36.into(), 36.into(), 576.into(), 756.into()].into(),
+                ),
+            ],
+        );
+        let output = TempPath::new("parse_boxes.pdf");
+        builder.finish().save(output.path()).unwrap();
+
+        // Act
+        let document = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        let page = &document.pages[0];
+        assert_eq!((page.width, page.height), (612.0, 792.0));
+        assert_eq!(page.media_box, Some(Rect::new(0.0, 0.0, 612.0, 792.0)));
+        assert_eq!(page.crop_box, Some(Rect::new(10.0, 20.0, 602.0, 772.0)));
+        assert_eq!(page.trim_box, Some(Rect::new(36.0, 36.0, 576.0, 756.0)));
+        assert_eq!(page.bleed_box, None);
+        assert_eq!(page.art_box, None);
+        assert_eq!(page.rotation, 0);
+    }
+
+    #[test]
+    fn test_parse_pdf_inherits_rotation_and_media_box() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let page_id = builder.add_page(vec![], Default::default(), vec![]);
+        builder.add_page(vec![], Default::default(), vec![("Rotate", (-90).into())]);
+        builder
+            .doc
+            .get_dictionary_mut(page_id)
+            .unwrap()
+            .remove(b"MediaBox");
+        let mut doc = builder.finish_with(vec![
+            ("Rotate", 90.into()),
+            (
+                "MediaBox",
+                vec![0.into(), 0.into(), 842.into(), 595.into()].into(),
+            ),
+        ]);
+        let output = TempPath::new("parse_rotation.pdf");
+        doc.save(output.path()).unwrap();
+
+        // Act
+        let document = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        assert_eq!(document.pages[0].rotation, 90);
+        assert_eq!(
+            (document.pages[0].width, document.pages[0].height),
+            (842.0, 595.0)
+        );
+        assert_eq!(document.pages[0].display_size(), (595.0, 842.0));
+        assert_eq!(document.pages[1].rotation, 270);
+        assert_eq!(
+            (document.pages[1].width, document.pages[1].height),
+            (595.0, 842.0)
+        );
+    }
+
+    #[test]
+    fn test_parse_pdf_extracts_images() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xD9];
+        let image_id = builder.doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"XObject".to_vec())),
+                ("Subtype", Object::Name(b"Image".to_vec())),
+                ("Width", 4.into()),
+                ("Height", 4.into()),
+                ("ColorSpace", Object::Name(b"DeviceRGB".to_vec())),
+                ("BitsPerComponent", 8.into()),
+                ("Filter", Object::Name(b"DCTDecode".to_vec())),
+            ]),
+            jpeg.clone(),
+        ));
+        let resources = Dictionary::from_iter(vec![(
+            "XObject",
+            Dictionary::from_iter(vec![("Im0", image_id.into())]).into(),
+        )]);
+        builder.add_page(
+            vec![
+                Operation::new(
+                    "cm",
+                    vec![
+                        100.into(),
+                        0.into(),
+                        0.into(),
+                        50.into(),
+                        72.into(),
+                        600.into(),
+                    ],
+                ),
+                Operation::new("Do", vec!["Im0".into()]),
+            ],
+            resources,
+            vec![],
+        );
+        let output = TempPath::new("parse_images.pdf");
+        builder.finish().save(output.path()).unwrap();
+
+        // Act
+        let document = parse_pdf(output.path()).unwrap();
+
+        // Assert
+        let images = &document.pages[0].images();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, "jpeg");
+        assert_eq!(images[0].data, jpeg);
+        assert_eq!(
+            (images[0].x, images[0].y, images[0].width, images[0].height),
+            (72.0, 600.0, 100.0, 50.0)
+        );
+    }
 }
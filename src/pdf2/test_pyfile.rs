@@ -0,0 +1,46 @@
+use crate::pyfile::PdfFile;
+use crate::test_utils::TempPath;
+use pyo3::prelude::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_file_accepts_paths_and_file_objects() {
+        Python::with_gil(|py| {
+            // Arrange
+            let output = TempPath::new("pyfile.bin");
+            let io = py.import("io").unwrap();
+            let buffer = io.call_method0("BytesIO").unwrap();
+            let path = py
+                .import("pathlib")
+                .unwrap()
+                .call_method1("Path", (output.0.as_str(),))
+                .unwrap();
+
+            // Act
+            let from_path: PdfFile = path.extract().unwrap();
+            let from_buffer: PdfFile = buffer.extract().unwrap();
+            from_path.write(b"%PDF-path").unwrap();
+            from_buffer.write(b"%PDF-buffer").unwrap();
+            buffer.call_method1("seek", (0,)).unwrap();
+            let rejected = py
+                .eval(pyo3::ffi::c_str!("b'%PDF-1.7'"), None, None)
+                .unwrap()
+                .extract::<PdfFile>();
+            let text_buffer: PdfFile = io
+                .call_method1("StringIO", ("text",))
+                .unwrap()
+                .extract()
+                .unwrap();
+
+            // Assert
+            assert!(matches!(&from_path, PdfFile::Path(p) if p.as_path() == output.path()));
+            assert_eq!(from_path.read().unwrap(), b"%PDF-path");
+            assert_eq!(from_buffer.read().unwrap(), b"%PDF-buffer");
+            assert!(rejected.is_err());
+            assert!(text_buffer.read().is_err());
+        });
+    }
+}
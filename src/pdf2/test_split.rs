@@ -0,0 +1,179 @@
+use crate::page_selection::PageSelection;
+use crate::split::{extract_pages, split_indices, split_pdf, SplitError, SplitMode};
+use crate::test_utils::{page_texts, text_document, text_operations, PdfBuilder, TempPath};
+use lopdf::{Dictionary, Object, ObjectId};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An outline item's title, page and child items.
+    type OutlineItem<'a> = (&'a str, ObjectId, Vec<(&'a str, ObjectId)>);
+
+    /// Adds outline items `(title, page, children)` under `parent`.
+    fn add_outline_items(
+        doc: &mut lopdf::Document,
+        parent: ObjectId,
+        items: Vec<OutlineItem>,
+    ) -> Vec<ObjectId> {
+        let ids: Vec<ObjectId> = items.iter().map(|_| doc.new_object_id()).collect();
+        for (index, (title, page, children)) in items.into_iter().enumerate() {
+            let mut item = Dictionary::from_iter(vec![
+                ("Title", Object::string_literal(title)),
+                ("Parent", parent.into()),
+                ("Dest", Object::Array(vec![page.into(), "Fit".into()])),
+            ]);
+            if let Some(&next) = ids.get(index + 1) {
+                item.set("Next", next);
+            }
+            let children = children
+                .into_iter()
+                .map(|(title, page)| (title, page, vec![]))
+                .collect::<Vec<_>>();
+            let child_ids = add_outline_items(doc, ids[index], children);
+            if let (Some(&first), Some(&last)) = (child_ids.first(), child_ids.last()) {
+                item.set("First", first);
+                item.set("Last", last);
+            }
+            doc.objects.insert(ids[index], item.into());
+        }
+        ids
+    }
+
+    #[test]
+    fn test_split_indices_by_ranges_and_every_n_pages() {
+        // Arrange
+        let (doc, _) = text_document(&["1", "2", "3", "4", "5"], vec![]);
+        let ranges = SplitMode::Ranges(vec![
+            PageSelection::parse("1-2").unwrap(),
+            PageSelection::parse("3-").unwrap(),
+        ]);
+
+        // Act
+        let by_ranges = split_indices(&doc, &ranges).unwrap();
+        let every_two = split_indices(&doc, &SplitMode::Every(2)).unwrap();
+
+        // Assert
+        assert_eq!(by_ranges, vec![vec![0, 1], vec![2, 3, 4]]);
+        assert_eq!(every_two, vec![vec![0, 1], vec![2, 3], vec![4]]);
+        assert!(matches!(
+            split_indices(&doc, &SplitMode::Every(0)),
+            Err(SplitError::Mode(_))
+        ));
+        assert!(matches!(
+            split_indices(
+                &doc,
+                &SplitMode::Ranges(vec![PageSelection::parse("6").unwrap()])
+            ),
+            Err(SplitError::Pages(_))
+        ));
+    }
+
+    #[test]
+    fn test_split_indices_at_blank_separator_pages() {
+        // Arrange
+        let (doc, _) = text_document(&["A", "", "B", "C", "", "", "D", ""], vec![]);
+
+        // Act
+        let parts = split_indices(&doc, &SplitMode::BlankSeparators).unwrap();
+
+        // Assert
+        assert_eq!(parts, vec![vec![0], vec![2, 3], vec![6]]);
+    }
+
+    #[test]
+    fn test_split_indices_by_bookmark_level() {
+        // Arrange
+        let (mut doc, pages) = text_document(&["Cover", "One", "One b", "Two"], vec![]);
+        let outlines_id = doc.new_object_id();
+        let top = add_outline_items(
+            &mut doc,
+            outlines_id,
+            vec![
+                ("Chapter 1", pages[1], vec![("Section 1.2", pages[2])]),
+                ("Chapter 2", pages[3], vec![]),
+            ],
+        );
+        doc.objects.insert(
+            outlines_id,
+            Dictionary::from_iter(vec![("First", top[0].into()), ("Last", top[1].into())]).into(),
+        );
+        doc.catalog_mut().unwrap().set("Outlines", outlines_id);
+
+        // Act
+        let chapters = split_indices(&doc, &SplitMode::BookmarkLevel(1)).unwrap();
+        let sections = split_indices(&doc, &SplitMode::BookmarkLevel(2)).unwrap();
+
+        // Assert
+        assert_eq!(chapters, vec![vec![0], vec![1, 2], vec![3]]);
+        assert_eq!(sections, vec![vec![0, 1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn test_extract_pages_keeps_only_used_resources() {
+        // Arrange
+        let mut builder = PdfBuilder::new();
+        let regular = builder.add_standard_font("Helvetica");
+        let bold = builder.add_standard_font("Helvetica-Bold");
+        let mut bold_operations = text_operations("Bold", 72.0, 720.0, 12.0);
+        bold_operations[1] = lopdf::content::Operation::new("Tf", vec!["F2".into(), 12.into()]);
+        builder.add_page(
+            text_operations("Regular", 72.0, 720.0, 12.0),
+            Dictionary::new(),
+            vec![],
+        );
+        builder.add_page(bold_operations, Dictionary::new(), vec![]);
+        for page in builder.doc.objects.values_mut() {
+            if let Object::Dictionary(page) = page {
+                page.remove(b"Resources");
+            }
+        }
+        // Both pages inherit one resource dictionary holding both fonts.
+        let resources = Dictionary::from_iter(vec![(
+            "Font",
+            Dictionary::from_iter(vec![("F1", regular.into()), ("F2", bold.into())]).into(),
+        )]);
+        let doc = builder.finish_with(vec![("Resources", resources.into())]);
+
+        // Act
+        let mut extracted = extract_pages(&doc, &PageSelection::parse("2").unwrap()).unwrap();
+
+        // Assert
+        let fonts: Vec<&[u8]> = extracted
+            .objects
+            .values()
+            .filter_map(|o| o.as_dict().ok())
+            .filter(|d| d.get(b"Type").and_then(Object::as_name).ok() == Some(b"Font"))
+            .map(|d| d.get(b"BaseFont").unwrap().as_name().unwrap())
+            .collect();
+        assert_eq!(fonts, vec![b"Helvetica-Bold".as_slice()]);
+        let mut bytes = Vec::new();
+        extracted.save_to(&mut bytes).unwrap();
+        assert_eq!(page_texts(&bytes), vec!["Bold"]);
+    }
+
+    #[test]
+    fn test_split_pdf_writes_numbered_parts() {
+        // Arrange
+        let input = TempPath::new("split_input.pdf");
+        let names: Vec<String> = (1..=12).map(|n| n.to_string()).collect();
+        let texts: Vec<&str> = names.iter().map(String::as_str).collect();
+        text_document(&texts, vec![]).0.save(input.path()).unwrap();
+        let template = TempPath::new("split_part_{}.pdf");
+
+        // Act
+        let paths = split_pdf(input.path(), &SplitMode::Every(1), &template.0).unwrap();
+        let outputs: Vec<TempPath> = paths.iter().map(|p| TempPath(p.clone())).collect();
+
+        // Assert
+        assert_eq!(outputs.len(), 12);
+        assert_eq!(outputs[0].0, template.0.replace("{}", "01"));
+        assert_eq!(outputs[11].0, template.0.replace("{}", "12"));
+        let last = std::fs::read(outputs[11].path()).unwrap();
+        assert_eq!(page_texts(&last), vec!["12"]);
+        assert!(matches!(
+            split_pdf(input.path(), &SplitMode::Every(1), "no_placeholder.pdf"),
+            Err(SplitError::Mode(_))
+        ));
+    }
+}
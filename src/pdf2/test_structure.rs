@@ -1,4 +1,4 @@
-use crate::structure::{Document, Image, Page, TextBlock};
+use crate::structure::{Document, Image, Page, PageContent, PathSegment, Rect, Shape, TextBlock};
 
 #[cfg(test)]
 mod tests {
@@ -18,6 +18,7 @@ mod tests {
             x,
             y,
             font_size,
+            ..Default::default()
         };
 
         // Assert
@@ -36,7 +37,7 @@ mod tests {
         let font_size = 14.0;
 
         // Act
-        let text_block = TextBlock::new(text.clone(), x, y, font_size);
+        let text_block = TextBlock::new(text.clone(), x, y, font_size, None);
 
         // Assert
         assert_eq!(text_block.text, text);
@@ -63,6 +64,7 @@ mod tests {
             height,
             data: data.clone(),
             format: format.clone(),
+            ..Default::default()
         };
 
         // Assert
@@ -85,7 +87,7 @@ mod tests {
         let format = "png".to_string();
 
         // Act
-        let image = Image::new(x, y, width, height, data.clone(), format.clone());
+        let image = Image::new(x, y, width, height, data.clone(), format.clone(), 0, 0);
 
         // Assert
         assert_eq!(image.x, x);
@@ -101,26 +103,33 @@ mod tests {
         // Arrange
         let width = 210.0;
         let height = 297.0;
-        let text_block = TextBlock::new("Page content".to_string(), 10.0, 280.0, 12.0);
-        let image = Image::new(50.0, 100.0, 100.0, 80.0, vec![1, 2, 3], "jpeg".to_string());
-        let text_blocks = vec![text_block];
-        let images = vec![image];
+        let text_block = TextBlock::new("Page content".to_string(), 10.0, 280.0, 12.0, None);
+        let image = Image::new(
+            50.0,
+            100.0,
+            100.0,
+            80.0,
+            vec![1, 2, 3],
+            "jpeg".to_string(),
+            0,
+            0,
+        );
 
         // Act
         let page = Page {
             width,
             height,
-            text_blocks: text_blocks.clone(),
-            images: images.clone(),
+            contents: vec![PageContent::Image(image), PageContent::Text(text_block)],
+            ..Default::default()
         };
 
         // Assert
         assert_eq!(page.width, width);
         assert_eq!(page.height, height);
-        assert_eq!(page.text_blocks.len(), 1);
-        assert_eq!(page.images.len(), 1);
-        assert_eq!(page.text_blocks[0].text, "Page content");
-        assert_eq!(page.images[0].format, "jpeg");
+        assert_eq!(page.text_blocks().len(), 1);
+        assert_eq!(page.images().len(), 1);
+        assert_eq!(page.text_blocks()[0].text, "Page content");
+        assert_eq!(page.images()[0].format, "jpeg");
     }
 
     #[test]
@@ -128,31 +137,32 @@ mod tests {
         // Arrange
         let width = 595.0;
         let height = 842.0;
-        let text_block = TextBlock::new("New page content".to_string(), 20.0, 300.0, 14.0);
+        let text_block = TextBlock::new("New page content".to_string(), 20.0, 300.0, 14.0, None);
         let text_blocks = vec![text_block];
         let images = vec![];
 
         // Act
-        let page = Page::new(width, height, text_blocks.clone(), images.clone());
+        let page = Page::new(width, height, text_blocks.clone(), images.clone(), None);
 
         // Assert
         assert_eq!(page.width, width);
         assert_eq!(page.height, height);
-        assert_eq!(page.text_blocks.len(), 1);
-        assert_eq!(page.images.len(), 0);
-        assert_eq!(page.text_blocks[0].text, "New page content");
+        assert_eq!(page.text_blocks().len(), 1);
+        assert_eq!(page.images().len(), 0);
+        assert_eq!(page.text_blocks()[0].text, "New page content");
     }
 
     #[test]
     fn test_document_creation() {
         // Arrange
-        let page1 = Page::new(210.0, 297.0, vec![], vec![]);
-        let page2 = Page::new(210.0, 297.0, vec![], vec![]);
+        let page1 = Page::new(210.0, 297.0, vec![], vec![], None);
+        let page2 = Page::new(210.0, 297.0, vec![], vec![], None);
         let pages = vec![page1, page2];
 
         // Act
         let document = Document {
             pages: pages.clone(),
+            ..Default::default()
         };
 
         // Assert
@@ -164,17 +174,17 @@ mod tests {
     #[test]
     fn test_document_new_method() {
         // Arrange
-        let text_block = TextBlock::new("Document content".to_string(), 10.0, 280.0, 12.0);
-        let page = Page::new(210.0, 297.0, vec![text_block], vec![]);
+        let text_block = TextBlock::new("Document content".to_string(), 10.0, 280.0, 12.0, None);
+        let page = Page::new(210.0, 297.0, vec![text_block], vec![], None);
         let pages = vec![page];
 
         // Act
-        let document = Document::new(pages.clone());
+        let document = Document::new(pages.clone(), None, None);
 
         // Assert
         assert_eq!(document.pages.len(), 1);
-        assert_eq!(document.pages[0].text_blocks.len(), 1);
-        assert_eq!(document.pages[0].text_blocks[0].text, "Document content");
+        assert_eq!(document.pages[0].text_blocks().len(), 1);
+        assert_eq!(document.pages[0].text_blocks()[0].text, "Document content");
     }
 
     #[test]
@@ -183,7 +193,7 @@ mod tests {
         let pages = vec![];
 
         // Act
-        let document = Document::new(pages);
+        let document = Document::new(pages, None, None);
 
         // Assert
         assert_eq!(document.pages.len(), 0);
@@ -192,8 +202,17 @@ mod tests {
     #[test]
     fn test_page_content_enum() {
         // Arrange
-        let text_block = TextBlock::new("Enum test".to_string(), 10.0, 280.0, 12.0);
-        let image = Image::new(50.0, 100.0, 100.0, 80.0, vec![1, 2, 3], "png".to_string());
+        let text_block = TextBlock::new("Enum test".to_string(), 10.0, 280.0, 12.0, None);
+        let image = Image::new(
+            50.0,
+            100.0,
+            100.0,
+            80.0,
+            vec![1, 2, 3],
+            "png".to_string(),
+            0,
+            0,
+        );
 
         // Act
         let text_content = crate::structure::PageContent::Text(text_block);
@@ -214,4 +233,107 @@ mod tests {
             _ => panic!("Expected Image variant"),
         }
     }
+
+    #[test]
+    fn test_page_views_replace_contents_in_place() {
+        // Arrange
+        let block = |text: &str| TextBlock::new(text.to_string(), 0.0, 0.0, 12.0, None);
+        let rule = Shape::line(0.0, 0.0, 100.0, 0.0, 1.0, None);
+        let mut page = Page {
+            contents: vec![
+                PageContent::Text(block("a")),
+                PageContent::Shape(rule.clone()),
+                PageContent::Text(block("b")),
+                PageContent::Text(block("c")),
+            ],
+            ..Default::default()
+        };
+        let kinds = |page: &Page| -> Vec<String> {
+            page.contents
+                .iter()
+                .map(|item| match item {
+                    PageContent::Text(block) => block.text.clone(),
+                    PageContent::Image(_) => "image".to_string(),
+                    PageContent::Shape(_) => "shape".to_string(),
+                })
+                .collect()
+        };
+
+        // Act & Assert
+        assert_eq!(page.text_blocks().len(), 3);
+        assert_eq!(page.shapes(), vec![&rule]);
+        assert!(page.images().is_empty());
+
+        page.set_text_blocks(vec![block("x"), block("y")]);
+        assert_eq!(kinds(&page), ["x", "shape", "y"]);
+
+        page.set_text_blocks(vec![block("x"), block("y"), block("z")]);
+        page.set_images(vec![Image::default()]);
+        assert_eq!(kinds(&page), ["x", "shape", "y", "z", "image"]);
+
+        page.set_shapes(vec![]);
+        assert_eq!(kinds(&page), ["x", "y", "z", "image"]);
+    }
+
+    #[test]
+    fn test_rect_dimensions_and_normalization() {
+        // Arrange
+        let rect = Rect::new(100.0, 50.0, 20.0, 10.0);
+
+        // Act
+        let normalized = rect.normalized();
+
+        // Assert
+        assert_eq!(rect.width(), 80.0);
+        assert_eq!(rect.height(), 40.0);
+        assert_eq!(normalized, Rect::new(20.0, 10.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn test_shape_helpers_build_paths() {
+        // Arrange
+        let rect = Rect::new(10.0, 20.0, 110.0, 70.0);
+
+        // Act
+        let line = Shape::line(0.0, 0.0, 50.0, 0.0, 2.0, None);
+        let rectangle = Shape::rectangle(rect, 1.0, Some(vec![1.0, 0.0, 0.0]), Some(vec![0.5]));
+        let curve = Shape {
+            segments: vec![
+                PathSegment::move_to(0.0, 0.0),
+                PathSegment::curve_to((-10.0, 30.0), (40.0, 30.0), (30.0, 0.0)),
+            ],
+            ..Default::default()
+        };
+
+        // Assert
+        assert_eq!(line.stroke_color, Some(vec![0.0]));
+        assert_eq!(line.fill_color, None);
+        assert_eq!(line.line_width, 2.0);
+        assert_eq!(line.bounds(), Rect::new(0.0, 0.0, 50.0, 0.0));
+        assert_eq!(rectangle.segments.len(), 5);
+        assert_eq!(rectangle.segments[4], PathSegment::close());
+        assert_eq!(rectangle.bounds(), rect);
+        assert_eq!(rectangle.fill_color, Some(vec![0.5]));
+        assert_eq!(curve.bounds(), Rect::new(-10.0, 0.0, 40.0, 30.0));
+        assert_eq!(Shape::default().bounds(), Rect::default());
+        assert_eq!(
+            (Shape::default().line_cap, Shape::default().line_join),
+            ("butt".to_string(), "miter".to_string())
+        );
+    }
+
+    #[test]
+    fn test_page_visible_box_and_display_size() {
+        // Arrange
+        let mut page = Page::new(612.0, 792.0, vec![], vec![], None);
+
+        // Act & Assert
+        assert_eq!(page.visible_box(), Rect::new(0.0, 0.0, 612.0, 792.0));
+        assert_eq!(page.rotation, 0);
+        assert!(page.media_box.is_none());
+
+        page.crop_box = Some(Rect::new(0.0, 0.0, 500.0, 700.0));
+        page.rotation = 270;
+        assert_eq!(page.display_size(), (700.0, 500.0));
+    }
 }
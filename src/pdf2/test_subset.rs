@@ -0,0 +1,45 @@
+use crate::subset::{is_truetype, subset_truetype};
+use crate::test_utils::test_font;
+use std::collections::BTreeSet;
+use ttf_parser::{Face, GlyphId};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subset_keeps_used_glyphs_and_components() {
+        // Arrange
+        let font = test_font();
+        let used = BTreeSet::from([2, 3]);
+
+        // Act
+        let subset = subset_truetype(&font, &used).unwrap();
+
+        // Assert
+        assert!(is_truetype(&subset));
+        let face = Face::parse(&subset, 0).unwrap();
+        assert_eq!(face.number_of_glyphs(), 5);
+        assert_eq!(face.units_per_em(), 1000);
+        let outlined: Vec<bool> = (0..5)
+            .map(|id| face.glyph_bounding_box(GlyphId(id)).is_some())
+            .collect();
+        // Glyph 1 is kept as a component of glyph 3; glyph 4 is dropped.
+        assert_eq!(outlined, vec![false, true, true, true, false]);
+        assert_eq!(face.glyph_hor_advance(GlyphId(4)), Some(650));
+        assert!(subset.len() < font.len());
+    }
+
+    #[test]
+    fn test_subset_rejects_fonts_without_outlines() {
+        // Arrange
+        let font = vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        // Act
+        let result = subset_truetype(&font, &BTreeSet::new());
+
+        // Assert
+        assert!(!is_truetype(&font));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::font_registry::FontRegistry;
+use crate::generator::generate_to_bytes;
+use crate::parser::parse_bytes;
+use crate::structure::{Document, Page, Rect, Shape};
+use crate::test_utils::{page, text_block};
+use pyo3::prelude::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A statement without rules: a header, three transactions and a row
+    /// whose description runs into the amount column.
+    fn statement() -> Page {
+        page(vec![
+            text_block("Account statement", 72.0, 740.0, 85.0),
+            text_block("Date", 72.0, 700.0, 20.0),
+            text_block("Description", 150.0, 700.0, 55.0),
+            text_block("Amount", 400.0, 700.0, 30.0),
+            text_block("01/02", 72.0, 686.0, 25.0),
+            text_block("Coffee shop", 150.0, 686.0, 55.0),
+            text_block("4.50", 410.0, 686.0, 20.0),
+            text_block("01/03", 72.0, 672.0, 25.0),
+            text_block("Salary payment", 150.0, 672.0, 70.0),
+            text_block("2,000.00", 400.0, 672.0, 40.0),
+            text_block("01/04", 72.0, 658.0, 25.0),
+            text_block("Rent", 150.0, 658.0, 20.0),
+            text_block("900.00", 405.0, 658.0, 30.0),
+            text_block("Opening balance carried over", 150.0, 644.0, 300.0),
+            text_block("01/05", 72.0, 644.0, 25.0),
+        ])
+    }
+
+    #[test]
+    fn test_ruled_table_with_merged_header_round_trips() {
+        // Arrange: a 3 x 3 grid whose top right cells are merged, under a
+        // line of ordinary text.
+        let mut contents = vec![
+            Shape::rectangle(Rect::new(72.0, 640.0, 372.0, 700.0), 0.5, None, None).into(),
+            Shape::line(72.0, 680.0, 372.0, 680.0, 0.5, None).into(),
+            Shape::line(72.0, 660.0, 372.0, 660.0, 0.5, None).into(),
+            Shape::line(172.0, 640.0, 172.0, 700.0, 0.5, None).into(),
+            Shape::line(272.0, 640.0, 272.0, 680.0, 0.5, None).into(),
+            text_block("A line of text above the table.", 72.0, 750.0, 155.0),
+        ];
+        for (text, x, y) in [
+            ("Item", 76.0, 686.0),
+            ("Amounts", 180.0, 686.0),
+            ("Rent", 76.0, 666.0),
+            ("1,200.00", 176.0, 666.0),
+            ("USD", 276.0, 666.0),
+            ("Total", 76.0, 646.0),
+            ("1,200.00", 176.0, 646.0),
+        ] {
+            contents.push(text_block(text, x, y, 5.0 * text.len() as f32));
+        }
+        let document = Document {
+            pages: vec![page(contents)],
+            ..Default::default()
+        };
+        let bytes = generate_to_bytes(&document, &FontRegistry::default()).unwrap();
+        let page = &parse_bytes(&bytes, None).unwrap().pages[0];
+
+        // Act
+        let tables = page.tables();
+
+        // Assert
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!(table.bbox, Rect::new(72.0, 640.0, 372.0, 700.0));
+        assert_eq!(table.row_edges, vec![700.0, 680.0, 660.0, 640.0]);
+        assert_eq!(table.column_edges, vec![72.0, 172.0, 272.0, 372.0]);
+        assert_eq!((table.row_count(), table.column_count()), (3, 3));
+        assert_eq!(table.cells.len(), 8);
+        let header = &table.cells[1];
+        assert_eq!((header.row, header.column), (0, 1));
+        assert_eq!((header.row_span, header.column_span), (1, 2));
+        assert_eq!(header.bbox, Rect::new(172.0, 680.0, 372.0, 700.0));
+        assert_eq!(
+            table.to_list(),
+            [
+                ["Item", "Amounts", ""],
+                ["Rent", "1,200.00", "USD"],
+                ["Total", "1,200.00", ""],
+            ]
+        );
+        assert_eq!(
+            table.to_csv(),
+            "Item,Amounts,\nRent,\"1,200.00\",USD\nTotal,\"1,200.00\",\n"
+        );
+    }
+
+    #[test]
+    fn test_l_shaped_merged_region_is_left_unmerged() {
+        // Arrange: a 2 x 2 grid whose cells other than the top right one
+        // have no borders between them.
+        let mut contents = vec![
+            Shape::rectangle(Rect::new(72.0, 640.0, 272.0, 700.0), 0.5, None, None).into(),
+            Shape::line(172.0, 670.0, 172.0, 700.0, 0.5, None).into(),
+            Shape::line(172.0, 670.0, 272.0, 670.0, 0.5, None).into(),
+        ];
+        for (text, x, y) in [
+            ("Top", 76.0, 686.0),
+            ("Right", 176.0, 686.0),
+            ("Bottom", 76.0, 656.0),
+            ("Corner", 176.0, 656.0),
+        ] {
+            contents.push(text_block(text, x, y, 5.0 * text.len() as f32));
+        }
+        let page = page(contents);
+
+        // Act
+        let tables = page.tables();
+
+        // Assert
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!((table.row_count(), table.column_count()), (2, 2));
+        assert_eq!(table.cells.len(), 4);
+        assert!(table
+            .cells
+            .iter()
+            .all(|cell| (cell.row_span, cell.column_span) == (1, 1)));
+        assert_eq!(table.to_list(), [["Top", "Right"], ["Bottom", "Corner"]]);
+    }
+
+    #[test]
+    fn test_unruled_table_from_text_alignment() {
+        // Arrange
+        let page = statement();
+
+        // Act
+        let tables = page.tables();
+
+        // Assert
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!((table.row_count(), table.column_count()), (5, 3));
+        assert_eq!(
+            table.to_list(),
+            [
+                ["Date", "Description", "Amount"],
+                ["01/02", "Coffee shop", "4.50"],
+                ["01/03", "Salary payment", "2,000.00"],
+                ["01/04", "Rent", "900.00"],
+                ["01/05", "Opening balance carried over", ""],
+            ]
+        );
+        let carried = table.cells.last().unwrap();
+        assert_eq!((carried.column, carried.column_span), (1, 2));
+        assert_eq!(table.bbox.y1, 708.0);
+    }
+
+    #[test]
+    fn test_text_in_columns_is_not_a_table() {
+        // Arrange
+        let mut contents = Vec::new();
+        for y in [700.0, 688.0, 676.0, 664.0] {
+            contents.push(text_block(
+                "Long lines of prose set in the left column",
+                72.0,
+                y,
+                210.0,
+            ));
+            contents.push(text_block(
+                "and more of them set in the right column",
+                320.0,
+                y,
+                200.0,
+            ));
+        }
+        let page = page(contents);
+
+        // Act
+        let tables = page.tables();
+
+        // Assert
+        assert!(tables.is_empty());
+    }
+
+    #[test]
+    fn test_tables_from_python() {
+        Python::with_gil(|py| {
+            // Arrange
+            let page = Py::new(py, statement()).unwrap();
+
+            // Act
+            let tables = page.call_method0(py, "tables").unwrap();
+            let table = tables.bind(py).get_item(0).unwrap();
+            let rows: Vec<Vec<String>> = table.call_method0("to_list").unwrap().extract().unwrap();
+            let csv: String = table.call_method0("to_csv").unwrap().extract().unwrap();
+            let row_count: usize = table.getattr("row_count").unwrap().extract().unwrap();
+
+            // Assert
+            assert_eq!(row_count, 5);
+            assert_eq!(rows[2], ["01/03", "Salary payment", "2,000.00"]);
+            assert!(csv.starts_with("Date,Description,Amount\n01/02,Coffee shop,4.50\n"));
+        });
+    }
+}
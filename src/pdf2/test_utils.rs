@@ -0,0 +1,378 @@
+use crate::parser::parse_bytes;
+use crate::structure::{Page, PageContent, TextBlock};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::path::Path;
+
+/// Builds small PDF documents with lopdf for use as test inputs.
+pub struct PdfBuilder {
+    pub doc: Document,
+    pages_id: ObjectId,
+    kids: Vec<Object>,
+}
+
+impl Default for PdfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        PdfBuilder {
+            doc,
+            pages_id,
+            kids: Vec::new(),
+        }
+    }
+
+    /// Adds a non-embedded standard 14 font.
+    pub fn add_standard_font(&mut self, base_font: &str) -> ObjectId {
+        self.doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(base_font.as_bytes().to_vec())),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]))
+    }
+
+    /// Adds a page drawing `operations` with `resources`. `extra` entries are
+    /// merged into the page dictionary (they may override `/MediaBox`).
+    pub fn add_page(
+        &mut self,
+        operations: Vec<Operation>,
+        resources: Dictionary,
+        extra: Vec<(&str, Object)>,
+    ) -> ObjectId {
+        let content = Content { operations };
+        let content_id = self
+            .doc
+            .add_object(Stream::new(Dictionary::new(), content.encode().unwrap()));
+        let mut page = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", self.pages_id.into()),
+            ("Contents", content_id.into()),
+            ("Resources", resources.into()),
+            (
+                "MediaBox",
+                vec![0.into(), 0.into(), 595.into(), 842.into()].into(),
+            ),
+        ]);
+        for (key, value) in extra {
+            page.set(key, value);
+        }
+        let page_id = self.doc.add_object(page);
+        self.kids.push(page_id.into());
+        page_id
+    }
+
+    /// Resources dictionary with a single font named `F1`.
+    pub fn font_resources(font_id: ObjectId) -> Dictionary {
+        Dictionary::from_iter(vec![(
+            "Font",
+            Dictionary::from_iter(vec![("F1", font_id.into())]).into(),
+        )])
+    }
+
+    /// Writes the page tree and catalog, adding `pages_extra` to the root
+    /// `/Pages` node.
+    pub fn finish_with(mut self, pages_extra: Vec<(&str, Object)>) -> Document {
+        let count = self.kids.len() as i64;
+        let mut pages = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", self.kids.into()),
+            ("Count", count.into()),
+        ]);
+        for (key, value) in pages_extra {
+            pages.set(key, value);
+        }
+        self.doc
+            .objects
+            .insert(self.pages_id, Object::Dictionary(pages));
+        let catalog_id = self.doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", self.pages_id.into()),
+        ]));
+        self.doc.trailer.set("Root", catalog_id);
+        self.doc
+    }
+
+    pub fn finish(self) -> Document {
+        self.finish_with(vec![])
+    }
+}
+
+/// A file path that is removed when dropped, so tests running in parallel
+/// can each use their own output file.
+pub struct TempPath(pub String);
+
+impl TempPath {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("pdf2_{}_{}", std::process::id(), name));
+        TempPath(path.to_string_lossy().into_owned())
+    }
+
+    pub fn path(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Text showing operations for `text` at `(x, y)` in font `F1` at `size`.
+pub fn text_operations(text: &str, x: f32, y: f32, size: f32) -> Vec<Operation> {
+    vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), size.into()]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+        Operation::new("Tj", vec![Object::string_literal(text)]),
+        Operation::new("ET", vec![]),
+    ]
+}
+
+/// A document with one Helvetica page per entry of `texts`, under a root
+/// node that holds `pages_extra`, and the ids of its pages. Empty texts make
+/// blank pages.
+pub fn text_document(
+    texts: &[&str],
+    pages_extra: Vec<(&str, Object)>,
+) -> (Document, Vec<ObjectId>) {
+    let mut builder = PdfBuilder::new();
+    let font_id = builder.add_standard_font("Helvetica");
+    let pages = texts
+        .iter()
+        .map(|text| {
+            let operations = if text.is_empty() {
+                vec![]
+            } else {
+                text_operations(text, 72.0, 720.0, 12.0)
+            };
+            builder.add_page(operations, PdfBuilder::font_resources(font_id), vec![])
+        })
+        .collect();
+    (builder.finish_with(pages_extra), pages)
+}
+
+/// Gives `doc` an outline of `depth` items, each the only child of the one
+/// before and each leading to `page`.
+pub fn add_nested_outline(doc: &mut Document, page: ObjectId, depth: usize) {
+    let outlines_id = doc.new_object_id();
+    let mut parent = outlines_id;
+    let mut first = None;
+    for level in 0..depth {
+        let item = doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal(format!("Level {}", level))),
+            ("Parent", parent.into()),
+            ("Dest", Object::Array(vec![page.into(), "Fit".into()])),
+        ]));
+        if let Ok(dict) = doc.get_dictionary_mut(parent) {
+            dict.set("First", item);
+            dict.set("Last", item);
+        }
+        first.get_or_insert(item);
+        parent = item;
+    }
+    let mut outlines = Dictionary::from_iter(vec![("Type", Object::Name(b"Outlines".to_vec()))]);
+    if let Some(first) = first {
+        outlines.set("First", first);
+        outlines.set("Last", first);
+    }
+    doc.objects.insert(outlines_id, outlines.into());
+    doc.catalog_mut().unwrap().set("Outlines", outlines_id);
+}
+
+/// The first text block of each page of the PDF in `bytes`, with `""` for
+/// pages without text.
+pub fn page_texts(bytes: &[u8]) -> Vec<String> {
+    parse_bytes(bytes, None)
+        .unwrap()
+        .pages
+        .iter()
+        .map(|page| {
+            page.text_blocks()
+                .first()
+                .map(|block| block.text.clone())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// A 10pt text block `width` points wide, without recorded character
+/// advances.
+pub fn text_block(text: &str, x: f32, y: f32, width: f32) -> PageContent {
+    PageContent::Text(TextBlock {
+        width,
+        ..TextBlock::new(text.to_string(), x, y, 10.0, None)
+    })
+}
+
+/// An A4 page holding `contents`.
+pub fn page(contents: Vec<PageContent>) -> Page {
+    Page {
+        width: 595.0,
+        height: 842.0,
+        contents,
+        ..Default::default()
+    }
+}
+
+/// Wraps `tables` in an sfnt table directory. Checksums are left at zero.
+fn sfnt(tables: Vec<(&[u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let mut font = vec![0, 1, 0, 0];
+    font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    font.extend_from_slice(&[0; 6]);
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, table) in &tables {
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&[0; 4]);
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().div_ceil(4) * 4;
+    }
+    for (_, table) in tables {
+        font.extend_from_slice(&table);
+        font.resize(font.len().div_ceil(4) * 4, 0);
+    }
+    font
+}
+
+/// A TTC collection of `fonts`, whose table offsets are rebased onto the
+/// collection.
+pub fn font_collection(fonts: &[Vec<u8>]) -> Vec<u8> {
+    let mut collection = b"ttcf\0\x01\0\0".to_vec();
+    collection.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+    let mut base = 12 + fonts.len() * 4;
+    for font in fonts {
+        collection.extend_from_slice(&(base as u32).to_be_bytes());
+        base += font.len();
+    }
+    for font in fonts {
+        let base = collection.len() as u32;
+        let mut font = font.clone();
+        let count = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for record in (0..count).map(|i| 12 + i * 16) {
+            let offset = u32::from_be_bytes(font[record + 8..record + 12].try_into().unwrap());
+            font[record + 8..record + 12].copy_from_slice(&(offset + base).to_be_bytes());
+        }
+        collection.extend_from_slice(&font);
+    }
+    collection
+}
+
+/// A simple glyph with one closed contour through `points`.
+fn simple_glyph(points: &[(i16, i16)]) -> Vec<u8> {
+    let xs = points.iter().map(|p| p.0);
+    let ys = points.iter().map(|p| p.1);
+    let mut glyph = Vec::new();
+    for value in [
+        1,
+        xs.clone().min().unwrap(),
+        ys.clone().min().unwrap(),
+        xs.clone().max().unwrap(),
+        ys.clone().max().unwrap(),
+        points.len() as i16 - 1,
+        0,
+    ] {
+        glyph.extend_from_slice(&value.to_be_bytes());
+    }
+    glyph.extend(std::iter::repeat_n(0x01, points.len()));
+    for coordinates in [xs.collect::<Vec<_>>(), ys.collect()] {
+        let mut previous = 0;
+        for value in coordinates {
+            glyph.extend_from_slice(&(value - previous).to_be_bytes());
+            previous = value;
+        }
+    }
+    glyph
+}
+
+/// A minimal TrueType font named `TestSans` with 1000 units per em and
+/// glyphs for `A` (1, 600 wide), `あ` (2, 1000 wide), `Ж` (3, 700 wide, a
+/// composite of glyph 1) and `B` (4, 650 wide).
+pub fn test_font() -> Vec<u8> {
+    let square = simple_glyph(&[(50, 0), (550, 0), (550, 700), (50, 700)]);
+    let triangle = simple_glyph(&[(100, -100), (900, -100), (500, 800)]);
+    let mut composite = Vec::new();
+    // One component: glyph 1, with word-sized x/y offsets.
+    for value in [-1i16, 50, 0, 550, 700, 0x0003, 1, 0, 0] {
+        composite.extend_from_slice(&value.to_be_bytes());
+    }
+    let glyphs = [Vec::new(), square.clone(), triangle, composite, square];
+
+    let mut glyf = Vec::new();
+    let mut loca = Vec::new();
+    for glyph in &glyphs {
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        glyf.extend_from_slice(glyph);
+        glyf.resize(glyf.len().div_ceil(4) * 4, 0);
+    }
+    loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+    let mut head = Vec::new();
+    head.extend_from_slice(&[0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    head.extend_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+    head.extend_from_slice(&[0, 0]);
+    head.extend_from_slice(&1000u16.to_be_bytes());
+    head.extend_from_slice(&[0; 16]);
+    for value in [0i16, -100, 900, 800, 0, 8, 2, 1, 0] {
+        head.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut hhea = vec![0, 1, 0, 0];
+    for value in [800i16, -200, 0, 1000, 0, 0, 900, 1, 0, 0, 0, 0, 0, 0, 0] {
+        hhea.extend_from_slice(&value.to_be_bytes());
+    }
+    hhea.extend_from_slice(&(glyphs.len() as u16).to_be_bytes());
+
+    let mut maxp = vec![0, 0, 0x50, 0];
+    maxp.extend_from_slice(&(glyphs.len() as u16).to_be_bytes());
+
+    let mut hmtx = Vec::new();
+    for advance in [500u16, 600, 1000, 700, 650] {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&[0, 0]);
+    }
+
+    let groups: [(u32, u32); 4] = [(0x41, 1), (0x42, 4), (0x416, 3), (0x3042, 2)];
+    let mut cmap = Vec::new();
+    for value in [0u16, 1, 3, 10] {
+        cmap.extend_from_slice(&value.to_be_bytes());
+    }
+    cmap.extend_from_slice(&12u32.to_be_bytes());
+    cmap.extend_from_slice(&[0, 12, 0, 0]);
+    cmap.extend_from_slice(&(16 + groups.len() as u32 * 12).to_be_bytes());
+    cmap.extend_from_slice(&0u32.to_be_bytes());
+    cmap.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for (code, glyph) in groups {
+        for value in [code, code, glyph] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    let postscript_name: Vec<u8> = "TestSans"
+        .encode_utf16()
+        .flat_map(|u| u.to_be_bytes())
+        .collect();
+    let mut name = Vec::new();
+    for value in [0u16, 1, 18, 3, 1, 0x409, 6, postscript_name.len() as u16, 0] {
+        name.extend_from_slice(&value.to_be_bytes());
+    }
+    name.extend_from_slice(&postscript_name);
+
+    sfnt(vec![
+        (b"cmap", cmap),
+        (b"glyf", glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+        (b"name", name),
+    ])
+}